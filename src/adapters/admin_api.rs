@@ -0,0 +1,433 @@
+use std::sync::Arc;
+use ethers::providers::Provider;
+use serde::{Deserialize, Serialize};
+use serde_json::json;
+use warp::Filter;
+use std::time::SystemTime;
+use crate::adapters::auth::with_scope;
+use crate::adapters::rejections::IndexerUnavailable;
+use crate::config::{AppConfig, ConfigHandle};
+use crate::domain::entities::address::Address;
+use crate::domain::entities::api_key::{ApiKey, ApiScope, StoredApiKey};
+use crate::domain::entities::job::JobKind;
+use crate::domain::services::api_key_service::{ApiKeyService, ApiKeyServiceTrait};
+use crate::domain::services::data_storage_service::{DataStorageService, DataStorageTrait};
+use crate::domain::services::job_service::JobServiceTrait;
+use crate::domain::services::swap_compaction_service::{SwapCompactionService, SwapCompactionServiceTrait};
+use crate::domain::services::alert_service::{AlertService, AlertServiceTrait};
+use crate::domain::services::event_bus_service::{EventBusService, EventBusServiceTrait};
+use crate::domain::services::task_supervisor::TaskSupervisor;
+use crate::ports::chain::base::base_uniswap_v2_client_service::BaseUniswapV2ClientServiceTrait;
+use crate::ports::chain::base::base_uniswap_v2_indexer::BaseUniswapV2Indexer;
+use crate::ports::chain::base::base_uniswap_v2_job_runner::{BaseUniswapV2JobRunnerTrait, SYNCHRONIZER_PAIRS, SYNCHRONIZER_SWAPS};
+use crate::ports::chain::base::base_uniswap_v2_swap_sync::BaseUniswapV2SwapSynchronizerTrait;
+use crate::ports::chain::quorum::QuorumServiceTrait;
+use crate::ports::chain::rpc_pool::RpcPool;
+
+type Indexer = BaseUniswapV2Indexer<Provider<RpcPool>>;
+
+#[derive(Debug, Deserialize)]
+pub struct SwapBackfillRequest {
+    pub from_block: u64,
+    pub to_block: u64,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct ApiKeyRequest {
+    pub name: String,
+    pub key: String,
+    pub scopes: Vec<ApiScope>,
+    pub rate_limit_per_second: f64,
+    pub burst: u32,
+    pub daily_quota: Option<u64>,
+}
+
+// Keys added through the API are stored with the snapshots of processes running an indexer,
+// a serve process doesn't write snapshots and keeps them until restart only
+#[derive(Debug, Serialize)]
+pub struct ApiKeyCreated {
+    #[serde(flatten)]
+    pub api_key: ApiKey,
+    pub ephemeral: bool,
+}
+
+// A key with no refill or no capacity would reject every request
+fn validate_api_key_request(request: &ApiKeyRequest) -> Result<(), String> {
+    if request.name.trim().is_empty() {
+        return Err("name must not be empty".to_string());
+    }
+    if request.key.trim().is_empty() {
+        return Err("key must not be empty".to_string());
+    }
+    if request.scopes.is_empty() {
+        return Err("scopes must not be empty".to_string());
+    }
+    if !(request.rate_limit_per_second.is_finite() && request.rate_limit_per_second > 0.0) {
+        return Err("rate_limit_per_second must be greater than 0".to_string());
+    }
+    if request.burst == 0 {
+        return Err("burst must be greater than 0".to_string());
+    }
+    Ok(())
+}
+
+fn accepted(indexer: &Indexer, supervisor: &TaskSupervisor, kind: JobKind) -> warp::reply::WithStatus<warp::reply::Json> {
+    let job = indexer.job_runner.submit(supervisor, kind);
+    warp::reply::with_status(warp::reply::json(&job), warp::http::StatusCode::ACCEPTED)
+}
+
+fn error_reply(status: warp::http::StatusCode, error: String) -> warp::reply::WithStatus<warp::reply::Json> {
+    warp::reply::with_status(warp::reply::json(&json!({ "error": error })), status)
+}
+
+// Addresses in the path are accepted in any letter case
+fn parse_address(address: &str) -> Result<Address, warp::reply::WithStatus<warp::reply::Json>> {
+    address.parse().map_err(|e: String| error_reply(warp::http::StatusCode::BAD_REQUEST, e))
+}
+
+// Jobs are only created for synchronizers the indexer runs
+fn check_synchronizer(synchronizer: &str) -> Result<(), warp::reply::WithStatus<warp::reply::Json>> {
+    if synchronizer == SYNCHRONIZER_SWAPS || synchronizer == SYNCHRONIZER_PAIRS {
+        Ok(())
+    } else {
+        Err(error_reply(warp::http::StatusCode::NOT_FOUND, format!("Unknown synchronizer: {}", synchronizer)))
+    }
+}
+
+// The process' indexer, chain endpoints are unavailable without one
+fn with_indexer(indexer: Option<Indexer>) -> impl Filter<Extract = (Indexer,), Error = warp::Rejection> + Clone {
+    warp::any().and_then(move || {
+        let indexer = indexer.clone();
+        async move { indexer.ok_or_else(|| warp::reject::custom(IndexerUnavailable)) }
+    })
+}
+
+// Supervisor of the process' tasks, jobs run under it
+fn with_supervisor(supervisor: TaskSupervisor) -> impl Filter<Extract = (TaskSupervisor,), Error = std::convert::Infallible> + Clone {
+    warp::any().map(move || supervisor.clone())
+}
+
+// API keys the admin endpoints manage, the ones this API authorizes with
+fn with_api_keys(api_keys: ApiKeyService) -> impl Filter<Extract = (ApiKeyService,), Error = std::convert::Infallible> + Clone {
+    warp::any().map(move || api_keys.clone())
+}
+
+// Storage the API keys added at runtime are saved with, none when this process doesn't save snapshots
+fn with_key_storage(key_storage: Option<DataStorageService>) -> impl Filter<Extract = (Option<DataStorageService>,), Error = std::convert::Infallible> + Clone {
+    warp::any().map(move || key_storage.clone())
+}
+
+// Configuration in effect when the request arrives
+fn with_config(config: ConfigHandle) -> impl Filter<Extract = (Arc<AppConfig>,), Error = std::convert::Infallible> + Clone {
+    warp::any().map(move || config.get())
+}
+
+// Admin API of the process' storage, event bus and tasks, and of its indexer where one runs
+pub fn create_admin_rest_api(config: ConfigHandle, data_service: DataStorageService, api_keys: ApiKeyService,
+                             event_bus: EventBusService, supervisor: TaskSupervisor, indexer: Option<Indexer>) -> impl Filter<Extract = impl warp::Reply, Error = warp::Rejection> + Clone {
+    let compaction = indexer.as_ref().map(|indexer| indexer.compaction.clone());
+    let alerts = indexer.as_ref().map(|indexer| indexer.alerts.clone());
+    let key_storage = indexer.as_ref().map(|_| data_service.clone());
+
+    let jobs = warp::path!("admin"/"jobs")
+        .and(warp::get())
+        .and(with_scope(api_keys.clone(), ApiScope::Admin))
+        .and(with_indexer(indexer.clone()))
+        .and_then(move |indexer: Indexer| async move {
+            Ok::<_, warp::Rejection>(warp::reply::json(&indexer.jobs.get_jobs()))
+        });
+
+    let job = warp::path!("admin"/"jobs"/String)
+        .and(warp::get())
+        .and(with_scope(api_keys.clone(), ApiScope::Admin))
+        .and(with_indexer(indexer.clone()))
+        .and_then(move |id: String, indexer: Indexer| async move {
+            match indexer.jobs.get_job(id.clone()) {
+                Some(job) => Ok::<_, warp::Rejection>(warp::reply::with_status(
+                    warp::reply::json(&job),
+                    warp::http::StatusCode::OK,
+                )),
+                None => Ok::<_, warp::Rejection>(warp::reply::with_status(
+                    warp::reply::json(&json!({ "error": format!("Job {} not found", id) })),
+                    warp::http::StatusCode::NOT_FOUND,
+                )),
+            }
+        });
+
+    let swap_backfill = warp::path!("admin"/"jobs"/"swaps"/"backfill")
+        .and(warp::post())
+        .and(with_scope(api_keys.clone(), ApiScope::Admin))
+        .and(warp::body::json())
+        .and(with_indexer(indexer.clone()))
+        .and(with_supervisor(supervisor.clone()))
+        .and_then(move |request: SwapBackfillRequest, indexer: Indexer, supervisor: TaskSupervisor| async move {
+            if request.from_block > request.to_block {
+                return Ok::<_, warp::Rejection>(error_reply(warp::http::StatusCode::BAD_REQUEST,
+                    format!("Invalid block range {} - {}", request.from_block, request.to_block)));
+            }
+            Ok::<_, warp::Rejection>(accepted(&indexer, &supervisor, JobKind::SwapBackfill {
+                from_block: request.from_block,
+                to_block: request.to_block,
+            }))
+        });
+
+    let pair_refresh = warp::path!("admin"/"jobs"/"pairs"/String/"refresh")
+        .and(warp::post())
+        .and(with_scope(api_keys.clone(), ApiScope::Admin))
+        .and(with_indexer(indexer.clone()))
+        .and(with_supervisor(supervisor.clone()))
+        .and_then(move |pair_address: String, indexer: Indexer, supervisor: TaskSupervisor| async move {
+            let reply = parse_address(&pair_address)
+                .map(|pair_address| accepted(&indexer, &supervisor, JobKind::PairRefresh { pair_address }));
+            Ok::<_, warp::Rejection>(reply.unwrap_or_else(|error| error))
+        });
+
+    let token_refresh = warp::path!("admin"/"jobs"/"tokens"/String/"refresh")
+        .and(warp::post())
+        .and(with_scope(api_keys.clone(), ApiScope::Admin))
+        .and(with_indexer(indexer.clone()))
+        .and(with_supervisor(supervisor.clone()))
+        .and_then(move |token_address: String, indexer: Indexer, supervisor: TaskSupervisor| async move {
+            let reply = parse_address(&token_address)
+                .map(|token_address| accepted(&indexer, &supervisor, JobKind::TokenRefresh { token_address }));
+            Ok::<_, warp::Rejection>(reply.unwrap_or_else(|error| error))
+        });
+
+    let pair_discovery = warp::path!("admin"/"jobs"/"pairs"/"discovery")
+        .and(warp::post())
+        .and(with_scope(api_keys.clone(), ApiScope::Admin))
+        .and(with_indexer(indexer.clone()))
+        .and(with_supervisor(supervisor.clone()))
+        .and_then(move |indexer: Indexer, supervisor: TaskSupervisor| async move {
+            Ok::<_, warp::Rejection>(accepted(&indexer, &supervisor, JobKind::PairDiscovery))
+        });
+
+    let sync_pause = warp::path!("admin"/"jobs"/"sync"/String/"pause")
+        .and(warp::post())
+        .and(with_scope(api_keys.clone(), ApiScope::Admin))
+        .and(with_indexer(indexer.clone()))
+        .and(with_supervisor(supervisor.clone()))
+        .and_then(move |synchronizer: String, indexer: Indexer, supervisor: TaskSupervisor| async move {
+            let reply = check_synchronizer(&synchronizer)
+                .map(|_| accepted(&indexer, &supervisor, JobKind::SyncPause { synchronizer }));
+            Ok::<_, warp::Rejection>(reply.unwrap_or_else(|error| error))
+        });
+
+    let sync_resume = warp::path!("admin"/"jobs"/"sync"/String/"resume")
+        .and(warp::post())
+        .and(with_scope(api_keys.clone(), ApiScope::Admin))
+        .and(with_indexer(indexer.clone()))
+        .and(with_supervisor(supervisor.clone()))
+        .and_then(move |synchronizer: String, indexer: Indexer, supervisor: TaskSupervisor| async move {
+            let reply = check_synchronizer(&synchronizer)
+                .map(|_| accepted(&indexer, &supervisor, JobKind::SyncResume { synchronizer }));
+            Ok::<_, warp::Rejection>(reply.unwrap_or_else(|error| error))
+        });
+
+    let sync_status = warp::path!("admin"/"sync")
+        .and(warp::get())
+        .and(with_scope(api_keys.clone(), ApiScope::Admin))
+        .and(with_indexer(indexer.clone()))
+        .and_then(move |indexer: Indexer| async move {
+            let swap_sync = &indexer.swap_sync;
+            let client_service = &indexer.client_service;
+
+            let reply = warp::reply::json(&json!({
+                "swaps": {
+                    "paused": swap_sync.is_paused().await,
+                    "state": swap_sync.get_sync_state().get_state(),
+                    "last_processed_block": swap_sync.get_last_processed_block().await,
+                    "quarantined_swap_logs": indexer.data_service.get_quarantined_swap_logs_size(),
+                },
+                "pairs": {
+                    "paused": client_service.is_paused().await,
+                    "state": client_service.get_sync_state().get_state(),
+                },
+            }));
+            Ok::<_, warp::Rejection>(reply)
+        });
+
+    let rpc_endpoints = warp::path!("admin"/"rpc"/"endpoints")
+        .and(warp::get())
+        .and(with_scope(api_keys.clone(), ApiScope::Admin))
+        .and(with_indexer(indexer.clone()))
+        .and_then(move |indexer: Indexer| async move {
+            let pool: &RpcPool = indexer.provider.as_ref().as_ref();
+            Ok::<_, warp::Rejection>(warp::reply::json(&pool.get_status()))
+        });
+
+    let rpc_quorum = warp::path!("admin"/"rpc"/"quorum")
+        .and(warp::get())
+        .and(with_scope(api_keys.clone(), ApiScope::Admin))
+        .and(with_config(config.clone()))
+        .and(with_indexer(indexer.clone()))
+        .and_then(move |config: Arc<AppConfig>, indexer: Indexer| async move {
+            let reply = warp::reply::json(&json!({
+                "quorum_size": config.base_chain().rpc_quorum_size,
+                "quorum_threshold": config.base_chain().rpc_quorum_threshold,
+                "metrics": indexer.quorum.get_metrics(),
+            }));
+            Ok::<_, warp::Rejection>(reply)
+        });
+
+    let storage_status = warp::path!("admin"/"storage")
+        .and(warp::get())
+        .and(with_scope(api_keys.clone(), ApiScope::Admin))
+        .and(with_config(config.clone()))
+        .and(warp::any().map(move || data_service.clone()))
+        .and(warp::any().map(move || compaction.clone()))
+        .and_then(move |config: Arc<AppConfig>, data_service: DataStorageService, compaction: Option<SwapCompactionService>| async move {
+            let reply = warp::reply::json(&json!({
+                "memory": data_service.get_memory_usage(),
+                "retention": {
+                    "swap_log_retention_secs": config.storage.swap_log_retention_secs,
+                    "swap_log_retention_blocks": config.storage.swap_log_retention_blocks,
+                    "swap_log_retention_per_pair": config.storage.swap_log_retention_per_pair,
+                    "swap_aggregate_hourly_retention_secs": config.storage.swap_aggregate_hourly_retention_secs,
+                },
+                "last_compaction": compaction.and_then(|compaction| compaction.get_last_compaction()),
+            }));
+            Ok::<_, warp::Rejection>(reply)
+        });
+
+    let event_bus = warp::path!("admin"/"events")
+        .and(warp::get())
+        .and(with_scope(api_keys.clone(), ApiScope::Admin))
+        .and(warp::any().map(move || event_bus.clone()))
+        .and_then(move |event_bus: EventBusService| async move {
+            Ok::<_, warp::Rejection>(warp::reply::json(&event_bus.get_metrics()))
+        });
+
+    let alerts = warp::path!("admin"/"alerts")
+        .and(warp::get())
+        .and(with_scope(api_keys.clone(), ApiScope::Admin))
+        .and(warp::any().map(move || alerts.clone()))
+        .and_then(move |alerts: Option<AlertService>| async move {
+            let alerts = alerts.map(|alerts| alerts.get_alerts()).unwrap_or_default();
+            Ok::<_, warp::Rejection>(warp::reply::json(&alerts))
+        });
+
+    let tasks = warp::path!("admin"/"tasks")
+        .and(warp::get())
+        .and(with_scope(api_keys.clone(), ApiScope::Admin))
+        .and(with_supervisor(supervisor.clone()))
+        .and_then(move |supervisor: TaskSupervisor| async move {
+            Ok::<_, warp::Rejection>(warp::reply::json(&supervisor.get_tasks()))
+        });
+
+    let api_key_list = warp::path!("admin"/"api-keys")
+        .and(warp::get())
+        .and(with_scope(api_keys.clone(), ApiScope::Admin))
+        .and(with_api_keys(api_keys.clone()))
+        .and_then(move |api_key_service: ApiKeyService| async move {
+            Ok::<_, warp::Rejection>(warp::reply::json(&api_key_service.get_api_keys()))
+        });
+
+    let api_key_usage = warp::path!("admin"/"api-keys"/"usage")
+        .and(warp::get())
+        .and(with_scope(api_keys.clone(), ApiScope::Admin))
+        .and(with_api_keys(api_keys.clone()))
+        .and_then(move |api_key_service: ApiKeyService| async move {
+            Ok::<_, warp::Rejection>(warp::reply::json(&api_key_service.get_usage()))
+        });
+
+    let api_key_create = warp::path!("admin"/"api-keys")
+        .and(warp::post())
+        .and(with_scope(api_keys.clone(), ApiScope::Admin))
+        .and(warp::body::json())
+        .and(with_api_keys(api_keys.clone()))
+        .and(with_key_storage(key_storage.clone()))
+        .and_then(move |request: ApiKeyRequest, api_key_service: ApiKeyService, key_storage: Option<DataStorageService>| async move {
+            if let Err(e) = validate_api_key_request(&request) {
+                return Ok::<_, warp::Rejection>(error_reply(warp::http::StatusCode::BAD_REQUEST, e));
+            }
+            let api_key = ApiKey {
+                name: request.name,
+                key: request.key,
+                scopes: request.scopes,
+                rate_limit_per_second: request.rate_limit_per_second,
+                burst: request.burst,
+                daily_quota: request.daily_quota,
+                created_at: SystemTime::now(),
+            };
+            if let Err(e) = api_key_service.create_api_key(api_key.clone()) {
+                return Ok::<_, warp::Rejection>(error_reply(warp::http::StatusCode::CONFLICT, e));
+            }
+            if let Some(key_storage) = &key_storage {
+                key_storage.add_api_key(StoredApiKey::from(&api_key));
+            }
+            Ok::<_, warp::Rejection>(warp::reply::with_status(
+                warp::reply::json(&ApiKeyCreated { api_key, ephemeral: key_storage.is_none() }),
+                warp::http::StatusCode::CREATED,
+            ))
+        });
+
+    let config_status = warp::path!("admin"/"config")
+        .and(warp::get())
+        .and(with_scope(api_keys.clone(), ApiScope::Admin))
+        .and(with_config(config.clone()))
+        .and_then(move |config: Arc<AppConfig>| async move {
+            Ok::<_, warp::Rejection>(warp::reply::json(&config.redacted()))
+        });
+
+    let config_handle = config.clone();
+    let config_reload = warp::path!("admin"/"config"/"reload")
+        .and(warp::post())
+        .and(with_scope(api_keys.clone(), ApiScope::Admin))
+        .and(warp::any().map(move || config_handle.clone()))
+        .and_then(move |config: ConfigHandle| async move {
+            match config.reload().map_err(|e| e.to_string()) {
+                Ok(reload) => Ok::<_, warp::Rejection>(warp::reply::with_status(
+                    warp::reply::json(&reload),
+                    warp::http::StatusCode::OK,
+                )),
+                Err(e) => Ok::<_, warp::Rejection>(warp::reply::with_status(
+                    warp::reply::json(&json!({ "error": e })),
+                    warp::http::StatusCode::UNPROCESSABLE_ENTITY,
+                )),
+            }
+        });
+
+    let api_key_delete = warp::path!("admin"/"api-keys"/String)
+        .and(warp::delete())
+        .and(with_scope(api_keys.clone(), ApiScope::Admin))
+        .and(with_api_keys(api_keys.clone()))
+        .and(with_key_storage(key_storage))
+        .and_then(move |name: String, api_key_service: ApiKeyService, key_storage: Option<DataStorageService>| async move {
+            if let Some(key_storage) = &key_storage {
+                key_storage.remove_api_key(name.clone());
+            }
+            match api_key_service.remove_api_key(name.clone()) {
+                Some(api_key) => Ok::<_, warp::Rejection>(warp::reply::with_status(
+                    warp::reply::json(&api_key),
+                    warp::http::StatusCode::OK,
+                )),
+                None => Ok::<_, warp::Rejection>(warp::reply::with_status(
+                    warp::reply::json(&json!({ "error": format!("API key {} not found", name) })),
+                    warp::http::StatusCode::NOT_FOUND,
+                )),
+            }
+        });
+
+    jobs
+        .or(job)
+        .or(swap_backfill)
+        .or(pair_refresh)
+        .or(token_refresh)
+        .or(pair_discovery)
+        .or(sync_pause)
+        .or(sync_resume)
+        .or(sync_status)
+        .or(rpc_endpoints)
+        .or(rpc_quorum)
+        .or(storage_status)
+        .or(event_bus)
+        .or(alerts)
+        .or(tasks)
+        .or(config_status)
+        .or(config_reload)
+        .or(api_key_list)
+        .or(api_key_usage)
+        .or(api_key_create)
+        .or(api_key_delete)
+}
@@ -0,0 +1,24 @@
+use warp::Filter;
+use crate::adapters::rejections::ApiAccessRejection;
+use crate::domain::entities::api_key::{ApiKey, ApiScope};
+use crate::domain::services::api_key_service::{ApiKeyService, ApiKeyServiceTrait};
+
+// Authenticates the x-api-key header, checks its scope and applies rate limits and quotas
+pub fn with_api_key(api_keys: ApiKeyService, scope: ApiScope) -> impl Filter<Extract = (ApiKey,), Error = warp::Rejection> + Clone {
+    warp::header::optional::<String>("x-api-key")
+        .and_then(move |key: Option<String>| {
+            let api_keys = api_keys.clone();
+            async move {
+                api_keys
+                    .authorize(key, scope)
+                    .map_err(|denied| warp::reject::custom(ApiAccessRejection(denied)))
+            }
+        })
+}
+
+// Same checks as with_api_key for endpoints that don't use the key itself
+pub fn with_scope(api_keys: ApiKeyService, scope: ApiScope) -> impl Filter<Extract = (), Error = warp::Rejection> + Clone {
+    with_api_key(api_keys, scope)
+        .map(|_api_key: ApiKey| ())
+        .untuple_one()
+}
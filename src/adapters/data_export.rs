@@ -0,0 +1,563 @@
+use std::error::Error;
+use std::io::Write;
+use std::str::FromStr;
+use std::sync::Arc;
+use std::time::{SystemTime, UNIX_EPOCH};
+use parquet::basic::Compression;
+use parquet::data_type::{BoolType, ByteArray, ByteArrayType, Int64Type};
+use parquet::file::properties::WriterProperties;
+use parquet::file::writer::SerializedFileWriter;
+use parquet::schema::parser::parse_message_type;
+use crate::domain::entities::address::Address;
+use crate::domain::entities::swap_log::SwapLog;
+use crate::domain::entities::swap_aggregate::SwapAggregate;
+use crate::domain::entities::token::Token;
+use crate::domain::entities::token_pair::TokenPair;
+use crate::domain::services::data_storage_service::{DataStorage, DataStorageService, DataStorageTrait};
+
+const EXPORT_BATCH_SIZE: usize = 500;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum ExportDataset {
+    Tokens,
+    TokenPairs,
+    SwapLogs,
+    SwapAggregates,
+    UpdateLogs,
+}
+
+impl FromStr for ExportDataset {
+    type Err = String;
+
+    fn from_str(value: &str) -> Result<Self, Self::Err> {
+        match value {
+            "tokens" => Ok(ExportDataset::Tokens),
+            "token_pairs" => Ok(ExportDataset::TokenPairs),
+            "swap_logs" => Ok(ExportDataset::SwapLogs),
+            "swap_aggregates" => Ok(ExportDataset::SwapAggregates),
+            "update_logs" => Ok(ExportDataset::UpdateLogs),
+            _ => Err(format!("Unknown dataset {}, expected tokens, token_pairs, swap_logs, swap_aggregates or update_logs", value)),
+        }
+    }
+}
+
+impl ExportDataset {
+    pub fn as_str(&self) -> &str {
+        match self {
+            ExportDataset::Tokens => "tokens",
+            ExportDataset::TokenPairs => "token_pairs",
+            ExportDataset::SwapLogs => "swap_logs",
+            ExportDataset::SwapAggregates => "swap_aggregates",
+            ExportDataset::UpdateLogs => "update_logs",
+        }
+    }
+
+    fn columns(&self) -> Vec<ExportColumn> {
+        use ColumnType::{Boolean, Integer, Text};
+        let columns: &[(&'static str, ColumnType)] = match self {
+            ExportDataset::Tokens => &[
+                ("address", Text), ("protocol_id", Text), ("symbol", Text), ("name", Text),
+                ("decimals", Text), ("pairs", Integer), ("active_pairs", Integer), ("swaps", Integer),
+                ("high_risk", Boolean), ("retrieved_at", Integer), ("updated_at", Integer),
+            ],
+            ExportDataset::TokenPairs => &[
+                ("token_pair_address", Text), ("protocol_id", Text), ("base_address", Text),
+                ("base_reserve", Text), ("quote_address", Text), ("quote_reserve", Text),
+                ("reserve_block", Integer), ("swaps", Integer), ("retrieved_at", Integer), ("updated_at", Integer),
+            ],
+            ExportDataset::SwapLogs => &[
+                ("pair_address", Text), ("protocol_id", Text), ("block_number", Integer), ("block_hash", Text),
+                ("transaction_hash", Text), ("transaction_index", Integer), ("log_index", Integer),
+                ("sender", Text), ("to", Text), ("amount0_in", Text), ("amount1_in", Text),
+                ("amount0_out", Text), ("amount1_out", Text), ("removed", Boolean),
+            ],
+            ExportDataset::SwapAggregates => &[
+                ("pair_address", Text), ("protocol_id", Text), ("interval", Text), ("bucket_start", Integer),
+                ("first_block", Integer), ("last_block", Integer), ("swaps", Integer),
+                ("amount0_in", Text), ("amount1_in", Text), ("amount0_out", Text), ("amount1_out", Text),
+                ("unique_traders", Integer),
+            ],
+            ExportDataset::UpdateLogs => &[
+                ("protocol_id", Text), ("created_at", Integer), ("last_update_at", Integer),
+                ("start_block", Integer), ("end_block", Integer), ("pair_count", Integer),
+            ],
+        };
+        columns.iter()
+            .map(|(name, column_type)| ExportColumn { name, column_type: *column_type })
+            .collect()
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum ExportFormat {
+    Csv,
+    Ndjson,
+    Parquet,
+}
+
+impl FromStr for ExportFormat {
+    type Err = String;
+
+    fn from_str(value: &str) -> Result<Self, Self::Err> {
+        match value {
+            "csv" => Ok(ExportFormat::Csv),
+            "ndjson" => Ok(ExportFormat::Ndjson),
+            "parquet" => Ok(ExportFormat::Parquet),
+            _ => Err(format!("Unknown format {}, expected csv, ndjson or parquet", value)),
+        }
+    }
+}
+
+impl ExportFormat {
+    pub fn content_type(&self) -> &str {
+        match self {
+            ExportFormat::Csv => "text/csv",
+            ExportFormat::Ndjson => "application/x-ndjson",
+            ExportFormat::Parquet => "application/vnd.apache.parquet",
+        }
+    }
+
+    pub fn extension(&self) -> &str {
+        match self {
+            ExportFormat::Csv => "csv",
+            ExportFormat::Ndjson => "ndjson",
+            ExportFormat::Parquet => "parquet",
+        }
+    }
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct ExportFilter {
+    pub protocol: Option<String>,
+    pub from_block: Option<u64>,
+    pub to_block: Option<u64>,
+    pub token: Option<Address>,
+}
+
+impl ExportFilter {
+    fn matches_protocol(&self, protocol_id: &str) -> bool {
+        self.protocol.as_ref().is_none_or(|protocol| protocol.eq_ignore_ascii_case(protocol_id))
+    }
+
+    fn matches_block(&self, block: u64) -> bool {
+        self.from_block.is_none_or(|from_block| from_block <= block)
+            && self.to_block.is_none_or(|to_block| to_block >= block)
+    }
+
+    fn matches_token(&self, address: Address) -> bool {
+        match self.token {
+            Some(token) => token == address,
+            None => true,
+        }
+    }
+
+    // Pairs with the filter token on either side
+    fn matches_pair(&self, pair: &TokenPair) -> bool {
+        match self.token {
+            Some(token) => pair.base_address == token || pair.quote_address == token,
+            None => true,
+        }
+    }
+
+    fn matches_block_range(&self, start_block: u64, end_block: u64) -> bool {
+        self.from_block.is_none_or(|from_block| from_block <= end_block)
+            && self.to_block.is_none_or(|to_block| to_block >= start_block)
+    }
+}
+
+fn unix_seconds(time: SystemTime) -> ExportValue {
+    ExportValue::Integer(time.duration_since(UNIX_EPOCH).map(|d| d.as_secs() as i64).unwrap_or(0))
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum ColumnType {
+    Text,
+    Integer,
+    Boolean,
+}
+
+#[derive(Debug, Clone)]
+pub struct ExportColumn {
+    pub name: &'static str,
+    pub column_type: ColumnType,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum ExportValue {
+    Text(String),
+    Integer(i64),
+    Boolean(bool),
+    Null,
+}
+
+impl ExportValue {
+    fn to_csv_field(&self) -> String {
+        match self {
+            ExportValue::Text(value) => value.clone(),
+            ExportValue::Integer(value) => value.to_string(),
+            ExportValue::Boolean(value) => value.to_string(),
+            ExportValue::Null => String::new(),
+        }
+    }
+
+    fn to_json(&self) -> serde_json::Value {
+        match self {
+            ExportValue::Text(value) => serde_json::Value::from(value.clone()),
+            ExportValue::Integer(value) => serde_json::Value::from(*value),
+            ExportValue::Boolean(value) => serde_json::Value::from(*value),
+            ExportValue::Null => serde_json::Value::Null,
+        }
+    }
+}
+
+type ExportRow = Vec<ExportValue>;
+
+// Format specific writer, rows are pushed in batches so only one batch is kept in memory
+trait RowWriter {
+    fn write_batch(&mut self, rows: &[ExportRow]) -> Result<(), Box<dyn Error>>;
+    fn finish(self: Box<Self>) -> Result<(), Box<dyn Error>>;
+}
+
+struct CsvRowWriter<W: Write> {
+    writer: csv::Writer<W>,
+}
+
+impl<W: Write> CsvRowWriter<W> {
+    fn new(output: W, columns: &[ExportColumn]) -> Result<Self, Box<dyn Error>> {
+        let mut writer = csv::Writer::from_writer(output);
+        writer.write_record(columns.iter().map(|column| column.name))?;
+        Ok(CsvRowWriter { writer })
+    }
+}
+
+impl<W: Write> RowWriter for CsvRowWriter<W> {
+    fn write_batch(&mut self, rows: &[ExportRow]) -> Result<(), Box<dyn Error>> {
+        for row in rows {
+            self.writer.write_record(row.iter().map(|value| value.to_csv_field()))?;
+        }
+        self.writer.flush()?;
+        Ok(())
+    }
+
+    fn finish(mut self: Box<Self>) -> Result<(), Box<dyn Error>> {
+        self.writer.flush()?;
+        Ok(())
+    }
+}
+
+struct NdjsonRowWriter<W: Write> {
+    output: W,
+    columns: Vec<ExportColumn>,
+}
+
+impl<W: Write> RowWriter for NdjsonRowWriter<W> {
+    fn write_batch(&mut self, rows: &[ExportRow]) -> Result<(), Box<dyn Error>> {
+        for row in rows {
+            let object: serde_json::Map<String, serde_json::Value> = self.columns.iter()
+                .zip(row.iter())
+                .map(|(column, value)| (column.name.to_string(), value.to_json()))
+                .collect();
+            serde_json::to_writer(&mut self.output, &object)?;
+            self.output.write_all(b"\n")?;
+        }
+        self.output.flush()?;
+        Ok(())
+    }
+
+    fn finish(mut self: Box<Self>) -> Result<(), Box<dyn Error>> {
+        self.output.flush()?;
+        Ok(())
+    }
+}
+
+// Every batch becomes one parquet row group
+struct ParquetRowWriter<W: Write + Send> {
+    writer: SerializedFileWriter<W>,
+    columns: Vec<ExportColumn>,
+}
+
+impl<W: Write + Send> ParquetRowWriter<W> {
+    fn new(output: W, dataset: ExportDataset, columns: &[ExportColumn]) -> Result<Self, Box<dyn Error>> {
+        let fields: Vec<String> = columns.iter()
+            .map(|column| match column.column_type {
+                ColumnType::Text => format!("OPTIONAL BYTE_ARRAY {} (UTF8);", column.name),
+                ColumnType::Integer => format!("OPTIONAL INT64 {};", column.name),
+                ColumnType::Boolean => format!("OPTIONAL BOOLEAN {};", column.name),
+            })
+            .collect();
+        let schema = parse_message_type(&format!("message {} {{ {} }}", dataset.as_str(), fields.join(" ")))?;
+        let properties = WriterProperties::builder()
+            .set_compression(Compression::SNAPPY)
+            .build();
+        let writer = SerializedFileWriter::new(output, Arc::new(schema), Arc::new(properties))?;
+        Ok(ParquetRowWriter { writer, columns: columns.to_vec() })
+    }
+}
+
+impl<W: Write + Send> RowWriter for ParquetRowWriter<W> {
+    fn write_batch(&mut self, rows: &[ExportRow]) -> Result<(), Box<dyn Error>> {
+        if rows.is_empty() {
+            return Ok(());
+        }
+        let mut row_group = self.writer.next_row_group()?;
+        for (index, column) in self.columns.iter().enumerate() {
+            let mut column_writer = row_group.next_column()?.ok_or("Missing parquet column writer")?;
+            let definition_levels: Vec<i16> = rows.iter()
+                .map(|row| if row[index] == ExportValue::Null { 0 } else { 1 })
+                .collect();
+            match column.column_type {
+                ColumnType::Text => {
+                    let values: Vec<ByteArray> = rows.iter()
+                        .filter_map(|row| match &row[index] {
+                            ExportValue::Text(value) => Some(ByteArray::from(value.as_str())),
+                            _ => None,
+                        })
+                        .collect();
+                    column_writer.typed::<ByteArrayType>().write_batch(&values, Some(&definition_levels), None)?;
+                }
+                ColumnType::Integer => {
+                    let values: Vec<i64> = rows.iter()
+                        .filter_map(|row| match &row[index] {
+                            ExportValue::Integer(value) => Some(*value),
+                            _ => None,
+                        })
+                        .collect();
+                    column_writer.typed::<Int64Type>().write_batch(&values, Some(&definition_levels), None)?;
+                }
+                ColumnType::Boolean => {
+                    let values: Vec<bool> = rows.iter()
+                        .filter_map(|row| match &row[index] {
+                            ExportValue::Boolean(value) => Some(*value),
+                            _ => None,
+                        })
+                        .collect();
+                    column_writer.typed::<BoolType>().write_batch(&values, Some(&definition_levels), None)?;
+                }
+            }
+            column_writer.close()?;
+        }
+        row_group.close()?;
+        Ok(())
+    }
+
+    fn finish(self: Box<Self>) -> Result<(), Box<dyn Error>> {
+        self.writer.close()?;
+        Ok(())
+    }
+}
+
+// Exports of one storage
+pub struct DataExportService {
+    data_service: DataStorageService,
+}
+
+pub trait DataExportServiceTrait {
+    fn export<W: Write + Send + 'static>(&self, dataset: ExportDataset, format: ExportFormat,
+                                         filter: &ExportFilter, output: W) -> Result<u64, Box<dyn Error>>;
+}
+
+impl DataExportService {
+    pub fn new(data_service: DataStorageService) -> Self {
+        DataExportService { data_service }
+    }
+
+    fn token_rows(storage: &DataStorage, filter: &ExportFilter, writer: &mut dyn RowWriter)
+        -> Result<u64, Box<dyn Error>> {
+        let mut exported = 0;
+        let tokens: Vec<&Token> = storage.tokens.values().collect();
+        for batch in tokens.chunks(EXPORT_BATCH_SIZE) {
+            let rows: Vec<ExportRow> = batch.iter().map(|token| (*token).clone())
+                .filter(|token| filter.matches_protocol(&token.protocol_id))
+                .filter(|token| filter.matches_token(token.address))
+                .map(|token| vec![
+                    ExportValue::Text(token.address.to_string()),
+                    ExportValue::Text(token.protocol_id),
+                    ExportValue::Text(token.symbol),
+                    ExportValue::Text(token.name),
+                    ExportValue::Text(token.decimals),
+                    ExportValue::Integer(token.pairs.len() as i64),
+                    ExportValue::Integer(token.active_pairs.len() as i64),
+                    ExportValue::Integer(token.swaps),
+                    ExportValue::Boolean(token.high_risk),
+                    unix_seconds(token.retrieved_at),
+                    unix_seconds(token.updated_at),
+                ])
+                .collect();
+            exported += rows.len() as u64;
+            writer.write_batch(&rows)?;
+        }
+        Ok(exported)
+    }
+
+    fn token_pair_rows(storage: &DataStorage, filter: &ExportFilter, writer: &mut dyn RowWriter)
+        -> Result<u64, Box<dyn Error>> {
+        let mut exported = 0;
+        let token_pairs: Vec<&TokenPair> = storage.token_pairs.values().collect();
+        for batch in token_pairs.chunks(EXPORT_BATCH_SIZE) {
+            let rows: Vec<ExportRow> = batch.iter().map(|pair| (*pair).clone())
+                .filter(|pair| filter.matches_protocol(&pair.protocol_id))
+                .filter(|pair| filter.matches_pair(pair))
+                .map(|pair| vec![
+                    ExportValue::Text(pair.token_pair_address.to_string()),
+                    ExportValue::Text(pair.protocol_id),
+                    ExportValue::Text(pair.base_address.to_string()),
+                    ExportValue::Text(pair.base_reserve.to_string()),
+                    ExportValue::Text(pair.quote_address.to_string()),
+                    ExportValue::Text(pair.quote_reserve.to_string()),
+                    ExportValue::Integer(pair.reserve_block as i64),
+                    ExportValue::Integer(pair.swaps),
+                    unix_seconds(pair.retrieved_at),
+                    unix_seconds(pair.updated_at),
+                ])
+                .collect();
+            exported += rows.len() as u64;
+            writer.write_batch(&rows)?;
+        }
+        Ok(exported)
+    }
+
+    fn swap_log_row(swap_log: SwapLog, protocol_id: &str) -> ExportRow {
+        let decoded = swap_log.decode();
+        let text = |value: Option<String>| value.map(ExportValue::Text).unwrap_or(ExportValue::Null);
+        let integer = |value: Option<u64>| value.map(|v| ExportValue::Integer(v as i64)).unwrap_or(ExportValue::Null);
+        vec![
+            ExportValue::Text(swap_log.address.to_string()),
+            ExportValue::Text(protocol_id.to_string()),
+            integer(swap_log.block_number.map(|block| block.as_u64())),
+            text(swap_log.block_hash.map(|hash| format!("{:?}", hash))),
+            text(swap_log.transaction_hash.map(|hash| format!("{:?}", hash))),
+            integer(swap_log.transaction_index.map(|index| index.as_u64())),
+            integer(swap_log.log_index.map(|index| index.as_u64())),
+            text(decoded.as_ref().map(|swap| Address::from(swap.sender).to_string())),
+            text(decoded.as_ref().map(|swap| Address::from(swap.to).to_string())),
+            text(decoded.as_ref().map(|swap| swap.amount0_in.to_string())),
+            text(decoded.as_ref().map(|swap| swap.amount1_in.to_string())),
+            text(decoded.as_ref().map(|swap| swap.amount0_out.to_string())),
+            text(decoded.as_ref().map(|swap| swap.amount1_out.to_string())),
+            swap_log.removed.map(ExportValue::Boolean).unwrap_or(ExportValue::Null),
+        ]
+    }
+
+    fn swap_log_rows(storage: &DataStorage, filter: &ExportFilter, writer: &mut dyn RowWriter)
+        -> Result<u64, Box<dyn Error>> {
+        let mut exported = 0;
+        // Swap logs are grouped by pair, each pair is exported as one batch
+        for (key, swap_logs) in storage.swap_logs.iter() {
+            let token_pair = storage.token_pairs.get(key);
+            let pair_protocol_id = token_pair.as_ref().map(|pair| pair.protocol_id.clone()).unwrap_or_default();
+            //logs of unknown emitters involve no token
+            let involves_token = match token_pair {
+                Some(pair) => filter.matches_pair(pair),
+                None => filter.token.is_none(),
+            };
+            if !involves_token {
+                continue;
+            }
+
+            let rows: Vec<ExportRow> = swap_logs.iter()
+                .filter(|swap_log| match swap_log.block_number {
+                    Some(block) => filter.matches_block(block.as_u64()),
+                    None => filter.from_block.is_none() && filter.to_block.is_none(),
+                })
+                .cloned()
+                .map(|swap_log| {
+                    // logs stored before protocol attribution carry no protocol, the pair's applies
+                    let protocol_id = if swap_log.protocol_id.is_empty() {
+                        pair_protocol_id.clone()
+                    } else {
+                        swap_log.protocol_id.clone()
+                    };
+                    (swap_log, protocol_id)
+                })
+                .filter(|(_, protocol_id)| filter.matches_protocol(protocol_id))
+                .map(|(swap_log, protocol_id)| Self::swap_log_row(swap_log, &protocol_id))
+                .collect();
+            exported += rows.len() as u64;
+            writer.write_batch(&rows)?;
+        }
+        Ok(exported)
+    }
+
+    fn swap_aggregate_rows(storage: &DataStorage, filter: &ExportFilter, writer: &mut dyn RowWriter)
+        -> Result<u64, Box<dyn Error>> {
+        let mut aggregates: Vec<&SwapAggregate> = storage.swap_aggregates.values()
+            .filter(|aggregate| filter.matches_protocol(&aggregate.protocol_id))
+            .filter(|aggregate| filter.matches_block_range(aggregate.first_block, aggregate.last_block))
+            .filter(|aggregate| match storage.token_pairs.get(&aggregate.pair_address) {
+                Some(pair) => filter.matches_pair(pair),
+                None => filter.token.is_none(),
+            })
+            .collect();
+        aggregates.sort_by(|a, b| (&a.pair_address, a.interval.as_str(), a.bucket_start)
+            .cmp(&(&b.pair_address, b.interval.as_str(), b.bucket_start)));
+
+        let mut exported = 0;
+        for batch in aggregates.chunks(EXPORT_BATCH_SIZE) {
+            let rows: Vec<ExportRow> = batch.iter()
+                .map(|aggregate| vec![
+                    ExportValue::Text(aggregate.pair_address.to_string()),
+                    ExportValue::Text(aggregate.protocol_id.clone()),
+                    ExportValue::Text(aggregate.interval.as_str().to_string()),
+                    ExportValue::Integer(aggregate.bucket_start as i64),
+                    ExportValue::Integer(aggregate.first_block as i64),
+                    ExportValue::Integer(aggregate.last_block as i64),
+                    ExportValue::Integer(aggregate.swaps),
+                    ExportValue::Text(aggregate.amount0_in.to_string()),
+                    ExportValue::Text(aggregate.amount1_in.to_string()),
+                    ExportValue::Text(aggregate.amount0_out.to_string()),
+                    ExportValue::Text(aggregate.amount1_out.to_string()),
+                    ExportValue::Integer(aggregate.unique_traders() as i64),
+                ])
+                .collect();
+            exported += rows.len() as u64;
+            writer.write_batch(&rows)?;
+        }
+        Ok(exported)
+    }
+
+    fn update_log_rows(storage: &DataStorage, filter: &ExportFilter, writer: &mut dyn RowWriter)
+        -> Result<u64, Box<dyn Error>> {
+        let rows: Vec<ExportRow> = storage.update_logs.values()
+            .filter(|update_log| filter.matches_protocol(&update_log.protocol_id))
+            .filter(|update_log| filter.matches_block_range(update_log.start_block, update_log.end_block))
+            .cloned()
+            .map(|update_log| vec![
+                ExportValue::Text(update_log.protocol_id),
+                unix_seconds(update_log.created_at),
+                unix_seconds(update_log.last_update_at),
+                ExportValue::Integer(update_log.start_block as i64),
+                ExportValue::Integer(update_log.end_block as i64),
+                ExportValue::Integer(update_log.pair_count as i64),
+            ])
+            .collect();
+        writer.write_batch(&rows)?;
+        Ok(rows.len() as u64)
+    }
+}
+
+impl DataExportServiceTrait for DataExportService {
+    fn export<W: Write + Send + 'static>(&self, dataset: ExportDataset, format: ExportFormat,
+                                         filter: &ExportFilter, output: W) -> Result<u64, Box<dyn Error>> {
+        // One snapshot for the whole export, rows stay consistent while indexing goes on
+        let storage = self.data_service.get_snapshot();
+        let columns = dataset.columns();
+
+        let mut writer: Box<dyn RowWriter> = match format {
+            ExportFormat::Csv => Box::new(CsvRowWriter::new(output, &columns)?),
+            ExportFormat::Ndjson => Box::new(NdjsonRowWriter { output, columns }),
+            ExportFormat::Parquet => Box::new(ParquetRowWriter::new(output, dataset, &columns)?),
+        };
+
+        let exported = match dataset {
+            ExportDataset::Tokens => Self::token_rows(&storage, filter, writer.as_mut())?,
+            ExportDataset::TokenPairs => Self::token_pair_rows(&storage, filter, writer.as_mut())?,
+            ExportDataset::SwapLogs => Self::swap_log_rows(&storage, filter, writer.as_mut())?,
+            ExportDataset::SwapAggregates => Self::swap_aggregate_rows(&storage, filter, writer.as_mut())?,
+            ExportDataset::UpdateLogs => Self::update_log_rows(&storage, filter, writer.as_mut())?,
+        };
+        writer.finish()?;
+
+        log::info!("Exported {} {} rows as {}", exported, dataset.as_str(), format.extension());
+        Ok(exported)
+    }
+}
@@ -0,0 +1,49 @@
+use std::convert::Infallible;
+use futures_util::stream;
+use serde::Deserialize;
+use warp::sse::Event;
+use warp::Filter;
+use crate::adapters::auth::with_scope;
+use crate::domain::entities::api_key::ApiScope;
+use crate::domain::services::api_key_service::ApiKeyService;
+use crate::domain::services::event_bus_service::{receive_event, EventBusService, EventBusServiceTrait};
+use crate::domain::services::task_supervisor::ShutdownSignal;
+
+#[derive(Debug, Deserialize)]
+pub struct EventQuery {
+    // comma separated event types, all events when missing
+    pub types: Option<String>,
+}
+
+// Server-sent events stream of the domain event bus, streams end on shutdown so the server can close
+pub fn create_event_rest_api(event_bus: EventBusService, shutdown: ShutdownSignal, api_keys: ApiKeyService) -> impl Filter<Extract = impl warp::Reply, Error = warp::Rejection> + Clone {
+
+    warp::path!("events")
+        .and(warp::get())
+        .and(with_scope(api_keys, ApiScope::Read))
+        .and(warp::query::<EventQuery>())
+        .map(move |query: EventQuery| {
+            let types: Option<Vec<String>> = query.types
+                .map(|types| types.split(',').map(|event_type| event_type.trim().to_string()).collect());
+            let receiver = event_bus.subscribe();
+
+            let state = (event_bus.clone(), shutdown.clone(), receiver, types);
+            let events = stream::unfold(state, |(event_bus, shutdown, mut receiver, types)| async move {
+                loop {
+                    let event = tokio::select! {
+                        event = receive_event(&event_bus, &mut receiver) => event?,
+                        _ = shutdown.triggered() => return None,
+                    };
+                    if types.as_ref().is_some_and(|types| !types.iter().any(|event_type| event_type == event.event_type())) {
+                        continue;
+                    }
+                    let sse_event = Event::default()
+                        .event(event.event_type())
+                        .json_data(&event)
+                        .unwrap_or_else(|_| Event::default().comment("unserializable event"));
+                    return Some((Ok::<_, Infallible>(sse_event), (event_bus, shutdown, receiver, types)));
+                }
+            });
+            warp::sse::reply(warp::sse::keep_alive().stream(events))
+        })
+}
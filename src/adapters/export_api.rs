@@ -0,0 +1,137 @@
+use std::io::Write;
+use serde::Deserialize;
+use serde_json::json;
+use tokio::sync::mpsc;
+use warp::http::{header, HeaderValue, StatusCode};
+use warp::hyper::body::Bytes;
+use warp::hyper::Body;
+use warp::Filter;
+use crate::adapters::auth::with_scope;
+use crate::adapters::data_export::{DataExportService, DataExportServiceTrait, ExportDataset, ExportFilter, ExportFormat};
+use crate::domain::entities::address::Address;
+use crate::domain::entities::api_key::ApiScope;
+use crate::domain::services::api_key_service::ApiKeyService;
+use crate::domain::services::data_storage_service::DataStorageService;
+
+const STREAM_CHUNK_SIZE: usize = 64 * 1024;
+
+#[derive(Debug, Deserialize)]
+pub struct ExportQuery {
+    pub format: Option<String>,
+    pub protocol: Option<String>,
+    pub from_block: Option<u64>,
+    pub to_block: Option<u64>,
+    pub token: Option<String>,
+}
+
+// Blocking writer feeding the HTTP response body, the bounded channel keeps memory flat for slow clients
+struct ChannelWriter {
+    sender: mpsc::Sender<Result<Bytes, String>>,
+    buffer: Vec<u8>,
+}
+
+impl Write for ChannelWriter {
+    fn write(&mut self, data: &[u8]) -> std::io::Result<usize> {
+        self.buffer.extend_from_slice(data);
+        if self.buffer.len() >= STREAM_CHUNK_SIZE {
+            self.flush()?;
+        }
+        Ok(data.len())
+    }
+
+    fn flush(&mut self) -> std::io::Result<()> {
+        if self.buffer.is_empty() {
+            return Ok(());
+        }
+        let chunk = Bytes::from(std::mem::take(&mut self.buffer));
+        self.sender
+            .blocking_send(Ok(chunk))
+            .map_err(|_| std::io::Error::new(std::io::ErrorKind::BrokenPipe, "Export client disconnected"))
+    }
+}
+
+impl Drop for ChannelWriter {
+    fn drop(&mut self) {
+        let _ = self.flush();
+    }
+}
+
+fn error_response(status: StatusCode, message: String) -> warp::reply::Response {
+    let mut response = warp::reply::Response::new(Body::from(json!({ "error": message }).to_string()));
+    *response.status_mut() = status;
+    response.headers_mut().insert(header::CONTENT_TYPE, HeaderValue::from_static("application/json"));
+    response
+}
+
+fn stream_export(data_service: DataStorageService, dataset: ExportDataset, format: ExportFormat, filter: ExportFilter)
+    -> warp::reply::Response {
+    let (sender, mut receiver) = mpsc::channel::<Result<Bytes, String>>(16);
+    let (mut body_sender, body) = Body::channel();
+
+    tokio::task::spawn_blocking(move || {
+        let export_service = DataExportService::new(data_service);
+        let writer = ChannelWriter { sender: sender.clone(), buffer: Vec::with_capacity(STREAM_CHUNK_SIZE) };
+        if let Err(e) = export_service.export(dataset, format, &filter, writer) {
+            log::error!("Export of {} failed: {}", dataset.as_str(), e);
+            let _ = sender.blocking_send(Err(e.to_string()));
+        }
+    });
+
+    tokio::spawn(async move {
+        while let Some(chunk) = receiver.recv().await {
+            match chunk {
+                Ok(chunk) => {
+                    if body_sender.send_data(chunk).await.is_err() {
+                        break;
+                    }
+                }
+                Err(_) => {
+                    body_sender.abort();
+                    break;
+                }
+            }
+        }
+    });
+
+    let mut response = warp::reply::Response::new(body);
+    let headers = response.headers_mut();
+    if let Ok(value) = HeaderValue::from_str(format.content_type()) {
+        headers.insert(header::CONTENT_TYPE, value);
+    }
+    let disposition = format!("attachment; filename=\"{}.{}\"", dataset.as_str(), format.extension());
+    if let Ok(value) = HeaderValue::from_str(&disposition) {
+        headers.insert(header::CONTENT_DISPOSITION, value);
+    }
+    response
+}
+
+pub fn create_export_rest_api(data_service: DataStorageService, api_keys: ApiKeyService) -> impl Filter<Extract = impl warp::Reply, Error = warp::Rejection> + Clone {
+
+    warp::path!("export"/String)
+        .and(warp::get())
+        .and(with_scope(api_keys, ApiScope::Read))
+        .and(warp::query::<ExportQuery>())
+        .and(warp::any().map(move || data_service.clone()))
+        .and_then(move |dataset: String, query: ExportQuery, data_service: DataStorageService| async move {
+            let dataset = match dataset.parse::<ExportDataset>() {
+                Ok(dataset) => dataset,
+                Err(e) => return Ok::<_, warp::Rejection>(error_response(StatusCode::NOT_FOUND, e)),
+            };
+            let format = match query.format.as_deref().unwrap_or("ndjson").parse::<ExportFormat>() {
+                Ok(format) => format,
+                Err(e) => return Ok::<_, warp::Rejection>(error_response(StatusCode::BAD_REQUEST, e)),
+            };
+            let token = match query.token.as_deref().map(str::parse::<Address>).transpose() {
+                Ok(token) => token,
+                Err(e) => return Ok::<_, warp::Rejection>(error_response(StatusCode::BAD_REQUEST, e)),
+            };
+            let filter = ExportFilter {
+                protocol: query.protocol,
+                from_block: query.from_block,
+                to_block: query.to_block,
+                token,
+            };
+
+            Ok::<_, warp::Rejection>(stream_export(data_service, dataset, format, filter))
+        })
+}
@@ -1 +1,11 @@
-pub mod token_api;
\ No newline at end of file
+pub mod token_api;
+pub mod admin_api;
+pub mod rejections;
+pub mod auth;
+pub mod response_cache;
+pub mod data_export;
+pub mod export_api;
+pub mod event_api;
+pub mod token_list_api;
+pub mod quote_api;
+pub mod pair_api;
@@ -0,0 +1,73 @@
+use serde_json::json;
+use warp::http::StatusCode;
+use warp::Filter;
+use crate::adapters::auth::with_scope;
+use crate::adapters::response_cache::{with_cache_request, CacheRequest, ResponseCache};
+use crate::config::ConfigHandle;
+use crate::domain::entities::address::Address;
+use crate::domain::entities::api_key::ApiScope;
+use crate::domain::services::api_key_service::ApiKeyService;
+use crate::domain::services::data_storage_service::DataStorageService;
+use crate::domain::services::depth_service::{DepthService, DepthServiceTrait};
+use crate::domain::services::liquidity_service::{LiquidityService, LiquidityServiceTrait};
+
+fn error_body(message: String) -> Vec<u8> {
+    json!({ "error": message }).to_string().into_bytes()
+}
+
+pub fn create_pair_rest_api(config: ConfigHandle, data_service: DataStorageService, api_keys: ApiKeyService)
+    -> impl Filter<Extract = impl warp::Reply, Error = warp::Rejection> + Clone {
+    let cache = ResponseCache::new(config.clone(), data_service);
+    let depth_service = DepthService::new(config.clone());
+    let liquidity_service = LiquidityService::new(config);
+
+    let depth = warp::path!("pairs"/String/"depth")
+        .and(warp::get())
+        .and(with_scope(api_keys.clone(), ApiScope::Read))
+        .and(with_cache_request())
+        .and_then({
+            let cache = cache.clone();
+            move |pair_address: String, cache_request: CacheRequest| {
+                let cache = cache.clone();
+                let depth_service = depth_service.clone();
+                async move {
+                    let response = cache.json_response(cache_request, move |storage| {
+                        let pair_address: Address = match pair_address.parse() {
+                            Ok(pair_address) => pair_address,
+                            Err(e) => return (StatusCode::BAD_REQUEST, error_body(e)),
+                        };
+                        match storage.token_pairs.get(&pair_address).and_then(|pair| depth_service.pair_depth(pair)) {
+                            Some(depth) => (StatusCode::OK, serde_json::to_vec(&depth).unwrap_or_default()),
+                            None => (StatusCode::NOT_FOUND, error_body(format!("Pair {} not found", pair_address))),
+                        }
+                    }).await;
+                    Ok::<_, warp::Rejection>(response)
+                }
+            }
+        });
+
+    // Mint and Burn history, LP net flows and the rug-pull flag
+    let liquidity = warp::path!("pairs"/String/"liquidity")
+        .and(warp::get())
+        .and(with_scope(api_keys.clone(), ApiScope::Read))
+        .and(with_cache_request())
+        .and_then(move |pair_address: String, cache_request: CacheRequest| {
+            let cache = cache.clone();
+            let liquidity_service = liquidity_service.clone();
+            async move {
+                let response = cache.json_response(cache_request, move |storage| {
+                    let pair_address: Address = match pair_address.parse() {
+                        Ok(pair_address) => pair_address,
+                        Err(e) => return (StatusCode::BAD_REQUEST, error_body(e)),
+                    };
+                    match liquidity_service.history(storage, pair_address) {
+                        Some(history) => (StatusCode::OK, serde_json::to_vec(&history).unwrap_or_default()),
+                        None => (StatusCode::NOT_FOUND, error_body(format!("Pair {} not found", pair_address))),
+                    }
+                }).await;
+                Ok::<_, warp::Rejection>(response)
+            }
+        });
+
+    depth.or(liquidity)
+}
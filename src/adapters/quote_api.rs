@@ -0,0 +1,70 @@
+use ethers::types::U256;
+use serde::Deserialize;
+use serde_json::json;
+use warp::http::StatusCode;
+use warp::Filter;
+use crate::adapters::auth::with_scope;
+use crate::adapters::response_cache::{with_cache_request, CacheRequest, ResponseCache};
+use crate::config::ConfigHandle;
+use crate::domain::entities::address::Address;
+use crate::domain::entities::api_key::ApiScope;
+use crate::domain::services::api_key_service::ApiKeyService;
+use crate::domain::services::data_storage_service::DataStorageService;
+use crate::domain::services::quote_service::{QuoteService, QuoteServiceTrait};
+
+#[derive(Debug, Deserialize)]
+pub struct QuoteQuery {
+    #[serde(rename = "in")]
+    pub token_in: Option<String>,
+    #[serde(rename = "out")]
+    pub token_out: Option<String>,
+    // in token_in's smallest unit
+    pub amount: Option<String>,
+}
+
+fn parse_query(query: &QuoteQuery) -> Result<(Address, Address, U256), String> {
+    let token_in: Address = query.token_in.as_deref().ok_or("Missing in")?.parse()?;
+    let token_out: Address = query.token_out.as_deref().ok_or("Missing out")?.parse()?;
+    let amount = query.amount.as_deref().ok_or("Missing amount")?;
+    let amount_in = U256::from_dec_str(amount).map_err(|_| format!("Invalid amount: {}", amount))?;
+    if amount_in.is_zero() {
+        return Err("amount must be greater than 0".to_string());
+    }
+    if token_in == token_out {
+        return Err("in and out must be different tokens".to_string());
+    }
+    Ok((token_in, token_out, amount_in))
+}
+
+fn error_body(message: String) -> Vec<u8> {
+    json!({ "error": message }).to_string().into_bytes()
+}
+
+pub fn create_quote_rest_api(config: ConfigHandle, data_service: DataStorageService, api_keys: ApiKeyService)
+    -> impl Filter<Extract = impl warp::Reply, Error = warp::Rejection> + Clone {
+    let cache = ResponseCache::new(config.clone(), data_service);
+    let quotes = QuoteService::new(config);
+
+    warp::path!("quote")
+        .and(warp::get())
+        .and(with_scope(api_keys, ApiScope::Read))
+        .and(warp::query::<QuoteQuery>())
+        .and(with_cache_request())
+        .and_then(move |query: QuoteQuery, cache_request: CacheRequest| {
+            let cache = cache.clone();
+            let quotes = quotes.clone();
+            async move {
+                let response = cache.json_response(cache_request, move |storage| {
+                    let (token_in, token_out, amount_in) = match parse_query(&query) {
+                        Ok(parsed) => parsed,
+                        Err(e) => return (StatusCode::BAD_REQUEST, error_body(e)),
+                    };
+                    match quotes.quote(storage, token_in, token_out, amount_in) {
+                        Some(quote) => (StatusCode::OK, serde_json::to_vec(&quote).unwrap_or_default()),
+                        None => (StatusCode::NOT_FOUND, error_body(format!("No route from {} to {}", token_in, token_out))),
+                    }
+                }).await;
+                Ok::<_, warp::Rejection>(response)
+            }
+        })
+}
@@ -0,0 +1,57 @@
+use std::convert::Infallible;
+use serde_json::json;
+use warp::http::StatusCode;
+use warp::reject::Reject;
+use warp::Reply;
+use crate::domain::services::api_key_service::AccessDenied;
+
+#[derive(Debug)]
+pub struct ApiAccessRejection(pub AccessDenied);
+
+impl Reject for ApiAccessRejection {}
+
+// Chain endpoints of a process that runs no indexer (serve mode)
+#[derive(Debug)]
+pub struct IndexerUnavailable;
+
+impl Reject for IndexerUnavailable {}
+
+// Maps rejections raised by the API filters into JSON error responses
+pub async fn handle_rejection(err: warp::Rejection) -> Result<impl warp::Reply, Infallible> {
+    let mut retry_after = None;
+    let (status, message) = if err.is_not_found() {
+        (StatusCode::NOT_FOUND, "Not found".to_string())
+    } else if let Some(ApiAccessRejection(denied)) = err.find::<ApiAccessRejection>() {
+        match denied {
+            AccessDenied::MissingKey => (StatusCode::UNAUTHORIZED, "Missing x-api-key header".to_string()),
+            AccessDenied::InvalidKey => (StatusCode::UNAUTHORIZED, "Invalid API key".to_string()),
+            AccessDenied::InsufficientScope => (StatusCode::FORBIDDEN, "API key scope does not allow this endpoint".to_string()),
+            AccessDenied::RateLimited { retry_after_secs } => {
+                retry_after = Some(*retry_after_secs);
+                (StatusCode::TOO_MANY_REQUESTS, "Rate limit exceeded".to_string())
+            }
+            AccessDenied::QuotaExceeded { retry_after_secs } => {
+                retry_after = Some(*retry_after_secs);
+                (StatusCode::TOO_MANY_REQUESTS, "Daily quota exceeded".to_string())
+            }
+        }
+    } else if err.find::<IndexerUnavailable>().is_some() {
+        (StatusCode::SERVICE_UNAVAILABLE, "No indexer runs in this process".to_string())
+    } else if let Some(e) = err.find::<warp::filters::body::BodyDeserializeError>() {
+        (StatusCode::BAD_REQUEST, e.to_string())
+    } else if let Some(e) = err.find::<warp::reject::InvalidQuery>() {
+        (StatusCode::BAD_REQUEST, e.to_string())
+    } else if err.find::<warp::reject::MethodNotAllowed>().is_some() {
+        (StatusCode::METHOD_NOT_ALLOWED, "Method not allowed".to_string())
+    } else {
+        log::error!("Unhandled rejection: {:?}", err);
+        (StatusCode::INTERNAL_SERVER_ERROR, "Internal server error".to_string())
+    };
+
+    let reply = warp::reply::json(&json!({ "error": message }));
+    let mut response = warp::reply::with_status(reply, status).into_response();
+    if let Some(retry_after) = retry_after {
+        response.headers_mut().insert(warp::http::header::RETRY_AFTER, retry_after.into());
+    }
+    Ok(response)
+}
@@ -0,0 +1,235 @@
+use std::collections::HashMap;
+use std::hash::{DefaultHasher, Hash, Hasher};
+use std::sync::{Arc, Mutex};
+use std::time::{SystemTime, UNIX_EPOCH};
+use chrono::{DateTime, Utc};
+use warp::http::{header, HeaderValue, StatusCode};
+use warp::hyper::body::Bytes;
+use warp::Filter;
+use crate::config::ConfigHandle;
+use crate::domain::services::data_storage_service::{DataStorage, DataStorageService, DataStorageTrait, DataVersion};
+
+const MAX_CACHED_RESPONSES: usize = 1024;
+
+// Responses built from one storage and configuration, keyed by route and query
+#[derive(Clone)]
+pub struct ResponseCache {
+    config: ConfigHandle,
+    data_service: DataStorageService,
+    // creation time in the ETag, a restarted process never validates the tags of an earlier one
+    epoch: u64,
+    entries: Arc<Mutex<CacheEntries>>,
+}
+
+struct CacheEntries {
+    // configuration the responses were built with, a reload drops them
+    config_generation: u64,
+    config_changed_at: SystemTime,
+    responses: HashMap<String, CachedResponse>,
+    // incremented on every lookup, the least recently used response is evicted first
+    clock: u64,
+}
+
+#[derive(Clone)]
+pub struct CachedResponse {
+    // storage version the body was built from, a later one rebuilds it
+    pub version: u64,
+    // hash of the body, writes leaving it unchanged keep the ETag and Last-Modified
+    pub content_hash: u64,
+    pub etag: String,
+    pub last_modified: SystemTime,
+    pub status: StatusCode,
+    pub body: Bytes,
+    pub last_used: u64,
+}
+
+#[derive(Debug, Clone)]
+pub struct CacheRequest {
+    pub key: String,
+    pub if_none_match: Option<String>,
+    pub if_modified_since: Option<String>,
+}
+
+// Extracts the cache key (route and query) and the conditional request headers
+pub fn with_cache_request() -> impl Filter<Extract = (CacheRequest,), Error = warp::Rejection> + Clone {
+    warp::path::full()
+        .and(
+            warp::query::raw()
+                .or(warp::any().map(String::new))
+                .unify(),
+        )
+        .and(warp::header::optional::<String>("if-none-match"))
+        .and(warp::header::optional::<String>("if-modified-since"))
+        .map(|path: warp::path::FullPath, query: String, if_none_match, if_modified_since| {
+            let key = if query.is_empty() {
+                path.as_str().to_string()
+            } else {
+                format!("{}?{}", path.as_str(), query)
+            };
+            CacheRequest { key, if_none_match, if_modified_since }
+        })
+}
+
+fn etag(epoch: u64, config_generation: u64, content_hash: u64) -> String {
+    format!("\"{:x}-{:x}-{:x}\"", epoch, config_generation, content_hash)
+}
+
+fn content_hash(status: StatusCode, body: &[u8]) -> u64 {
+    let mut hasher = DefaultHasher::new();
+    status.as_u16().hash(&mut hasher);
+    body.hash(&mut hasher);
+    hasher.finish()
+}
+
+fn http_date(time: SystemTime) -> String {
+    DateTime::<Utc>::from(time).format("%a, %d %b %Y %H:%M:%S GMT").to_string()
+}
+
+fn is_not_modified(request: &CacheRequest, cached: &CachedResponse) -> bool {
+    // If-None-Match takes precedence over If-Modified-Since (RFC 9110)
+    if let Some(if_none_match) = &request.if_none_match {
+        return if_none_match
+            .split(',')
+            .map(|tag| tag.trim().trim_start_matches("W/"))
+            .any(|tag| tag == "*" || tag == cached.etag);
+    }
+    if let Some(if_modified_since) = &request.if_modified_since {
+        if let Ok(since) = DateTime::parse_from_rfc2822(if_modified_since) {
+            let last_modified = DateTime::<Utc>::from(cached.last_modified).timestamp();
+            return last_modified <= since.timestamp();
+        }
+    }
+    false
+}
+
+impl ResponseCache {
+    pub fn new(config: ConfigHandle, data_service: DataStorageService) -> Self {
+        let now = SystemTime::now();
+        let epoch = now.duration_since(UNIX_EPOCH).map(|duration| duration.as_nanos() as u64).unwrap_or_default();
+        let entries = CacheEntries {
+            config_generation: config.generation(),
+            config_changed_at: now,
+            responses: HashMap::new(),
+            clock: 0,
+        };
+        ResponseCache { config, data_service, epoch, entries: Arc::new(Mutex::new(entries)) }
+    }
+
+    // Configuration generation and since when it applies, responses of an earlier one are dropped
+    fn config_state(&self) -> (u64, SystemTime) {
+        let mut entries = self.entries.lock().unwrap();
+        let generation = self.config.generation();
+        if entries.config_generation != generation {
+            entries.config_generation = generation;
+            entries.config_changed_at = SystemTime::now();
+            entries.responses.clear();
+        }
+        (entries.config_generation, entries.config_changed_at)
+    }
+
+    // Response cached for the key, possibly built from an earlier storage version
+    fn lookup(&self, key: &str, config_generation: u64) -> Option<CachedResponse> {
+        let mut entries = self.entries.lock().unwrap();
+        if entries.config_generation != config_generation {
+            return None;
+        }
+        entries.clock += 1;
+        let clock = entries.clock;
+        let cached = entries.responses.get_mut(key)?;
+        cached.last_used = clock;
+        Some(cached.clone())
+    }
+
+    fn store(&self, key: String, mut cached: CachedResponse, config_generation: u64) {
+        let mut entries = self.entries.lock().unwrap();
+        //built with a configuration reloaded meanwhile
+        if entries.config_generation != config_generation {
+            return;
+        }
+        entries.clock += 1;
+        cached.last_used = entries.clock;
+        let cache = &mut entries.responses;
+        if cache.len() >= MAX_CACHED_RESPONSES && !cache.contains_key(&key) {
+            let least_recently_used = cache.iter()
+                .min_by_key(|(_, entry)| entry.last_used)
+                .map(|(key, _)| key.clone());
+            if let Some(least_recently_used) = least_recently_used {
+                cache.remove(&least_recently_used);
+            }
+        }
+        cache.insert(key, cached);
+    }
+
+    // Serves a JSON response from the cache while the storage data version and the configuration are unchanged.
+    // Otherwise the body is rebuilt on the blocking pool; validators only change when the body does,
+    // unchanged polls get 304 Not Modified.
+    pub async fn json_response<F>(&self, request: CacheRequest, build: F) -> warp::reply::Response
+    where
+        F: FnOnce(&DataStorage) -> (StatusCode, Vec<u8>) + Send + 'static,
+    {
+        // Body and validators come from the same snapshot, a write published meanwhile cannot mix in
+        let (config_generation, config_changed_at) = self.config_state();
+        let storage = self.data_service.get_snapshot();
+        let data_version = DataVersion {
+            version: storage.version,
+            last_modified: storage.last_modified,
+        };
+
+        let previous = self.lookup(&request.key, config_generation);
+        let cached = match previous {
+            Some(cached) if cached.version == data_version.version => cached,
+            previous => {
+                let built = tokio::task::spawn_blocking(move || build(&storage)).await;
+                let (status, body) = match built {
+                    Ok(built) => built,
+                    Err(e) => {
+                        log::error!("Response for {} not built: {}", request.key, e);
+                        let mut response = warp::reply::Response::new(warp::hyper::Body::empty());
+                        *response.status_mut() = StatusCode::INTERNAL_SERVER_ERROR;
+                        return response;
+                    }
+                };
+                let content_hash = content_hash(status, &body);
+                let last_modified = match previous {
+                    Some(previous) if previous.content_hash == content_hash => previous.last_modified,
+                    //a process start or reload changes responses of unchanged data as well
+                    _ => std::cmp::max(data_version.last_modified, config_changed_at),
+                };
+                let cached = CachedResponse {
+                    version: data_version.version,
+                    content_hash,
+                    etag: etag(self.epoch, config_generation, content_hash),
+                    last_modified,
+                    status,
+                    body: Bytes::from(body),
+                    last_used: 0,
+                };
+                self.store(request.key.clone(), cached.clone(), config_generation);
+                cached
+            }
+        };
+        let mut response = if cached.status.is_success() && is_not_modified(&request, &cached) {
+            let mut response = warp::reply::Response::new(warp::hyper::Body::empty());
+            *response.status_mut() = StatusCode::NOT_MODIFIED;
+            response
+        } else {
+            let mut response = warp::reply::Response::new(warp::hyper::Body::from(cached.body.clone()));
+            *response.status_mut() = cached.status;
+            response.headers_mut().insert(header::CONTENT_TYPE, HeaderValue::from_static("application/json"));
+            response
+        };
+
+        if cached.status.is_success() {
+            let headers = response.headers_mut();
+            if let Ok(value) = HeaderValue::from_str(&cached.etag) {
+                headers.insert(header::ETAG, value);
+            }
+            if let Ok(value) = HeaderValue::from_str(&http_date(cached.last_modified)) {
+                headers.insert(header::LAST_MODIFIED, value);
+            }
+            headers.insert(header::CACHE_CONTROL, HeaderValue::from_static("no-cache"));
+        }
+
+        response
+    }
+}
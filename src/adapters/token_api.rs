@@ -1,28 +1,69 @@
-use std::sync::Arc;
 use serde_json::json;
 use warp::Filter;
-use crate::domain::services::data_storage_service::{DataStorageService, DataStorageTrait};
+use crate::adapters::auth::with_scope;
+use crate::adapters::response_cache::{with_cache_request, CacheRequest, ResponseCache};
+use crate::config::ConfigHandle;
+use crate::domain::entities::address::Address;
+use crate::domain::entities::api_key::ApiScope;
+use crate::domain::services::api_key_service::ApiKeyService;
+use crate::domain::services::data_storage_service::DataStorageService;
+use crate::domain::services::depth_service::{DepthService, DepthServiceTrait};
 
-pub fn create_token_rest_api() -> impl Filter<Extract = impl warp::Reply, Error = warp::Rejection> + Clone {
+pub fn create_token_rest_api(config: ConfigHandle, data_service: DataStorageService, api_keys: ApiKeyService) -> impl Filter<Extract = impl warp::Reply, Error = warp::Rejection> + Clone {
+    let cache = ResponseCache::new(config.clone(), data_service);
+    let depth_service = DepthService::new(config);
 
-    let token_data = warp::path!("token"/"data")
-        .and_then( move || async move{
-            let data_service = Arc::new(DataStorageService);
+    let tokens = warp::path!("token"/"data")
+        .and(warp::get())
+        .and(with_scope(api_keys.clone(), ApiScope::Read))
+        .and(with_cache_request())
+        .and_then({
+            let cache = cache.clone();
+            move |cache_request: CacheRequest| {
+                let cache = cache.clone();
+                async move {
+                    let response = cache.json_response(cache_request, move |storage| {
+                        let tokens = &storage.tokens;
 
-            let tokens = data_service.get_tokens();
-
-            if !tokens.is_empty() {
-                let reply = warp::reply::json(&tokens);
-                Ok::<_, warp::Rejection>(warp::reply::with_status(reply, warp::http::StatusCode::OK))
-            } else {
-                let error_reply = warp::reply::json(&json!({ "error": "No tokens found" }));
-                Ok::<_, warp::Rejection>(warp::reply::with_status(
-                    error_reply,
-                    warp::http::StatusCode::NOT_FOUND,
-                ))
+                        if !tokens.is_empty() {
+                            (warp::http::StatusCode::OK, serde_json::to_vec(tokens).unwrap_or_default())
+                        } else {
+                            let error_reply = json!({ "error": "No tokens found" });
+                            (warp::http::StatusCode::NOT_FOUND, error_reply.to_string().into_bytes())
+                        }
+                    }).await;
+                    Ok::<_, warp::Rejection>(response)
+                }
             }
+        });
 
+    let token_depth = warp::path!("token"/String/"depth")
+        .and(warp::get())
+        .and(with_scope(api_keys.clone(), ApiScope::Read))
+        .and(with_cache_request())
+        .and_then(move |token_address: String, cache_request: CacheRequest| {
+            let cache = cache.clone();
+            let depth_service = depth_service.clone();
+            async move {
+                let response = cache.json_response(cache_request, move |storage| {
+                    let token_address: Address = match token_address.parse() {
+                        Ok(token_address) => token_address,
+                        Err(e) => {
+                            let error_reply = json!({ "error": e });
+                            return (warp::http::StatusCode::BAD_REQUEST, error_reply.to_string().into_bytes());
+                        }
+                    };
+                    match storage.tokens.get(&token_address) {
+                        Some(token) => (warp::http::StatusCode::OK, serde_json::to_vec(&depth_service.token_depth(storage, token)).unwrap_or_default()),
+                        None => {
+                            let error_reply = json!({ "error": format!("Token {} not found", token_address) });
+                            (warp::http::StatusCode::NOT_FOUND, error_reply.to_string().into_bytes())
+                        }
+                    }
+                }).await;
+                Ok::<_, warp::Rejection>(response)
+            }
         });
 
-    token_data
-}
\ No newline at end of file
+    tokens.or(token_depth)
+}
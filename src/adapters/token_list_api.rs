@@ -0,0 +1,45 @@
+use serde::Deserialize;
+use warp::Filter;
+use crate::adapters::auth::with_scope;
+use crate::adapters::response_cache::{with_cache_request, CacheRequest, ResponseCache};
+use crate::config::ConfigHandle;
+use crate::domain::entities::api_key::ApiScope;
+use crate::domain::services::api_key_service::ApiKeyService;
+use crate::domain::services::data_storage_service::DataStorageService;
+use crate::domain::services::token_list_service::{TokenListService, TokenListServiceTrait};
+
+// Overrides of the configured risk and liquidity filters
+#[derive(Debug, Deserialize)]
+pub struct TokenListQuery {
+    pub include_high_risk: Option<bool>,
+    pub min_active_pairs: Option<usize>,
+    pub verified: Option<bool>,
+}
+
+pub fn create_token_list_rest_api(config: ConfigHandle, data_service: DataStorageService, api_keys: ApiKeyService)
+    -> impl Filter<Extract = impl warp::Reply, Error = warp::Rejection> + Clone {
+    let cache = ResponseCache::new(config.clone(), data_service.clone());
+    let token_lists = TokenListService::new(config, data_service);
+
+    warp::path!("tokenlist.json")
+        .and(warp::get())
+        .and(with_scope(api_keys, ApiScope::Read))
+        .and(warp::query::<TokenListQuery>())
+        .and(with_cache_request())
+        .and_then(move |query: TokenListQuery, cache_request: CacheRequest| {
+            let mut filter = token_lists.default_filter();
+            filter.include_high_risk = query.include_high_risk.unwrap_or(filter.include_high_risk);
+            filter.min_active_pairs = query.min_active_pairs.unwrap_or(filter.min_active_pairs);
+            filter.verified_only = query.verified.unwrap_or(filter.verified_only);
+
+            let cache = cache.clone();
+            let token_lists = token_lists.clone();
+            async move {
+                let response = cache.json_response(cache_request, move |storage| {
+                    let token_list = token_lists.export(storage, &filter);
+                    (warp::http::StatusCode::OK, serde_json::to_vec(&token_list).unwrap_or_default())
+                }).await;
+                Ok::<_, warp::Rejection>(response)
+            }
+        })
+}
@@ -0,0 +1,118 @@
+use clap::{Args, Parser, Subcommand, ValueEnum};
+use crate::adapters::data_export::{ExportDataset, ExportFilter, ExportFormat};
+use crate::domain::entities::address::Address;
+
+#[derive(Debug, Parser)]
+#[command(name = "token_data_provider", about = "Token data provider for Uniswap V2 on Base network")]
+pub struct Cli {
+    #[command(subcommand)]
+    pub command: Option<Command>,
+}
+
+#[derive(Debug, Subcommand)]
+pub enum Command {
+    /// REST API only, serves data from the storage snapshot
+    Serve,
+    /// Chain synchronization only, saves the storage snapshot
+    Index,
+    /// Re-index swaps for a block range into the storage snapshot
+    Backfill(BackfillArgs),
+    /// Live on-chain lookup
+    #[command(subcommand)]
+    Inspect(InspectCommand),
+    /// Configuration tools
+    #[command(subcommand)]
+    Config(ConfigCommand),
+    /// Export tokens, token pairs, swap logs or update logs from the storage snapshot
+    Export(ExportArgs),
+}
+
+#[derive(Debug, Args)]
+pub struct BackfillArgs {
+    #[arg(long)]
+    pub from: u64,
+    #[arg(long)]
+    pub to: u64,
+}
+
+#[derive(Debug, Clone, Copy, ValueEnum)]
+pub enum OutputFormat {
+    Table,
+    Json,
+}
+
+#[derive(Debug, Subcommand)]
+pub enum InspectCommand {
+    /// Pair tokens and reserves
+    Pair {
+        address: Address,
+        #[arg(long, value_enum, default_value = "table")]
+        format: OutputFormat,
+    },
+    /// Token metadata, with pairs from the storage snapshot when available
+    Token {
+        address: Address,
+        #[arg(long, value_enum, default_value = "table")]
+        format: OutputFormat,
+    },
+}
+
+#[derive(Debug, Subcommand)]
+pub enum ConfigCommand {
+    /// Validate the configuration and reach the RPC endpoint
+    Check,
+}
+
+#[derive(Debug, Args)]
+pub struct ExportArgs {
+    /// tokens, token_pairs, swap_logs, swap_aggregates or update_logs
+    #[arg(long)]
+    pub dataset: ExportDataset,
+    /// csv, ndjson or parquet
+    #[arg(long, default_value = "csv")]
+    pub format: ExportFormat,
+    /// Output file
+    #[arg(long)]
+    pub output: String,
+    /// Storage snapshot to export from, defaults to storage_snapshot_path from config
+    #[arg(long)]
+    pub snapshot: Option<String>,
+    #[arg(long)]
+    pub protocol: Option<String>,
+    #[arg(long)]
+    pub from_block: Option<u64>,
+    #[arg(long)]
+    pub to_block: Option<u64>,
+    /// Token address, any letter case
+    #[arg(long)]
+    pub token: Option<Address>,
+}
+
+impl ExportArgs {
+    pub fn filter(&self) -> ExportFilter {
+        ExportFilter {
+            protocol: self.protocol.clone(),
+            from_block: self.from_block,
+            to_block: self.to_block,
+            token: self.token,
+        }
+    }
+}
+
+// Prints rows as an aligned two column table or as a JSON object
+pub fn print_output(format: OutputFormat, rows: &[(&str, String)]) {
+    match format {
+        OutputFormat::Table => {
+            let width = rows.iter().map(|(name, _)| name.len()).max().unwrap_or(0);
+            for (name, value) in rows {
+                println!("{:<width$}  {}", name, value, width = width);
+            }
+        }
+        OutputFormat::Json => {
+            let object: serde_json::Map<String, serde_json::Value> = rows.iter()
+                .map(|(name, value)| (name.to_string(), serde_json::Value::from(value.clone())))
+                .collect();
+            println!("{}", serde_json::to_string_pretty(&object).unwrap_or_default());
+        }
+    }
+}
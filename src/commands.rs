@@ -0,0 +1,186 @@
+use std::error::Error;
+use std::fs::File;
+use std::io::BufWriter;
+use std::sync::Arc;
+use ethers::providers::{Middleware, Provider};
+use crate::ports::chain::rpc_pool::{connect_provider, RpcPool, RpcPoolSettings};
+use crate::adapters::data_export::{DataExportService, DataExportServiceTrait};
+use crate::cli::{print_output, BackfillArgs, ExportArgs, OutputFormat};
+use crate::config::{load_config_from_env_or_file, validate_config, ConfigHandle};
+use crate::domain::entities::address::Address;
+use crate::domain::entities::job::JobKind;
+use crate::domain::services::data_storage_service::{DataStorageService, DataStorageTrait};
+use crate::domain::services::task_supervisor::{ShutdownSignal, TaskSupervisor};
+use crate::ports::chain::base::base_uniswap_v2_client_service::{BaseUniswapV2ClientServiceTrait, UniswapV2Factory, UniswapV2Pair, ERC20};
+use crate::ports::chain::base::base_uniswap_v2_indexer::BaseUniswapV2Indexer;
+use crate::ports::chain::base::base_uniswap_v2_job_runner::BaseUniswapV2JobRunnerTrait;
+use crate::ports::chain::base::base_uniswap_v2_swap_sync::PROTOCOL_ID;
+
+// A missing snapshot starts an empty storage, an unreadable one is an error so that it is never overwritten
+pub fn load_snapshot(data_service: &DataStorageService, path: &str) -> Result<bool, Box<dyn Error>> {
+    let loaded = data_service.load_snapshot(path)?;
+    if loaded {
+        log::info!("Storage snapshot loaded from {}", path);
+    } else {
+        log::warn!("Storage snapshot {} not found", path);
+    }
+    Ok(loaded)
+}
+
+pub async fn backfill(config: ConfigHandle, args: BackfillArgs) -> Result<(), Box<dyn Error>> {
+    let data_service = DataStorageService::new();
+    let path = config.get().storage.snapshot_path.clone();
+    load_snapshot(&data_service, &path)?;
+
+    let provider = connect_provider(&config.get()).await?;
+    // a backfill can run for long, its endpoints are health checked until it is done
+    let supervisor = TaskSupervisor::new(config.clone(), ShutdownSignal::new(), None);
+    provider.as_ref().as_ref().supervise_health_check(&supervisor, config.get().base_chain().rpc_health_check_interval);
+    let indexer = BaseUniswapV2Indexer::new(config, data_service.clone(), provider);
+    let result = indexer.job_runner
+        .execute(JobKind::SwapBackfill { from_block: args.from, to_block: args.to })
+        .await;
+    supervisor.shutdown().await;
+    log::info!("{}", result?);
+
+    data_service.save_snapshot(&path)?;
+    log::info!("Storage snapshot saved to {}", path);
+    Ok(())
+}
+
+async fn token_symbol(address: Address, provider: Arc<Provider<RpcPool>>) -> (String, u8) {
+    let token = ERC20::new(address, provider);
+    let symbol = token.symbol().call().await.unwrap_or_else(|_| "No data".to_string());
+    let decimals = token.decimals().call().await.unwrap_or(0);
+    (symbol, decimals)
+}
+
+pub async fn inspect_pair(config: ConfigHandle, pair_address: Address, format: OutputFormat) -> Result<(), Box<dyn Error>> {
+    let provider = connect_provider(&config.get()).await?;
+
+    let pair = UniswapV2Pair::new(pair_address, provider.clone());
+    let token0: Address = pair.token_0().call().await?.into();
+    let token1: Address = pair.token_1().call().await?.into();
+    let reserve = pair.get_reserves().call().await?;
+    let base = token_symbol(token0, provider.clone()).await;
+    let quote = token_symbol(token1, provider.clone()).await;
+
+    print_output(format, &[
+        ("pair_address", pair_address.to_string()),
+        ("base_address", token0.to_string()),
+        ("base_symbol", base.0),
+        ("base_decimals", base.1.to_string()),
+        ("base_reserve", reserve.0.to_string()),
+        ("quote_address", token1.to_string()),
+        ("quote_symbol", quote.0),
+        ("quote_decimals", quote.1.to_string()),
+        ("quote_reserve", reserve.1.to_string()),
+        ("reserve_block", reserve.2.to_string()),
+    ]);
+    Ok(())
+}
+
+pub async fn inspect_token(config: ConfigHandle, token_address: Address, format: OutputFormat) -> Result<(), Box<dyn Error>> {
+    let provider = connect_provider(&config.get()).await?;
+
+    // Pairs and swaps come from the snapshot, metadata is read live
+    let data_service = DataStorageService::new();
+    load_snapshot(&data_service, &config.get().storage.snapshot_path)?;
+
+    let indexer = BaseUniswapV2Indexer::new(config, data_service, provider);
+    let token = indexer.client_service.fetch_token_details(token_address, 0, 0).await?;
+
+    print_output(format, &[
+        ("address", token.address.to_string()),
+        ("protocol_id", token.protocol_id),
+        ("name", token.name),
+        ("symbol", token.symbol),
+        ("decimals", token.decimals),
+        ("pairs", token.pairs.len().to_string()),
+        ("active_pairs", token.active_pairs.len().to_string()),
+        ("swaps", token.swaps.to_string()),
+        ("high_risk", token.high_risk.to_string()),
+    ]);
+    Ok(())
+}
+
+pub async fn config_check() -> Result<(), Box<dyn Error>> {
+    let config = match load_config_from_env_or_file() {
+        Ok(config) => config,
+        Err(e) => {
+            println!("FAIL  configuration: {}", e);
+            return Err(e);
+        }
+    };
+
+    let errors = validate_config(&config);
+    if errors.is_empty() {
+        println!("OK    configuration");
+    } else {
+        for error in &errors {
+            println!("FAIL  configuration: {}", error);
+        }
+        return Err(format!("{} configuration errors", errors.len()).into());
+    }
+
+    let pool = match RpcPool::connect(&config.base_rpc_endpoints(), RpcPoolSettings::from_config(&config)).await {
+        Ok(pool) => pool,
+        Err(e) => {
+            println!("FAIL  rpc: {}", e);
+            return Err(e);
+        }
+    };
+    pool.check_health().await;
+    let status = pool.get_status();
+    for endpoint in &status {
+        match &endpoint.last_error {
+            None => println!("OK    rpc {} ({}): block {}, {:.0} ms", endpoint.url, endpoint.transport, endpoint.head_block, endpoint.latency_ms),
+            Some(e) => println!("FAIL  rpc {}: {}", endpoint.url, e),
+        }
+    }
+    if status.iter().all(|endpoint| endpoint.last_error.is_some()) {
+        return Err("no RPC endpoint reachable".into());
+    }
+
+    let provider = Arc::new(Provider::new(pool));
+    let chain_id = match provider.get_chainid().await {
+        Ok(chain_id) => chain_id,
+        Err(e) => {
+            println!("FAIL  rpc chain id: {}", e);
+            return Err(e.into());
+        }
+    };
+    println!("OK    rpc chain id {}", chain_id);
+
+    let factory_address: Address = config.protocol(PROTOCOL_ID).map(|protocol| protocol.factory.as_str()).unwrap_or_default().parse()?;
+    let factory = UniswapV2Factory::new(factory_address, provider);
+    match factory.all_pairs_length().call().await {
+        Ok(pairs) => println!("OK    uniswap v2 factory {}: {} pairs", factory_address, pairs),
+        Err(e) => {
+            println!("FAIL  uniswap v2 factory {}: {}", factory_address, e);
+            return Err(e.into());
+        }
+    }
+
+    Ok(())
+}
+
+pub async fn export(config: ConfigHandle, args: ExportArgs) -> Result<(), Box<dyn Error>> {
+    let snapshot = args.snapshot.clone().unwrap_or_else(|| config.get().storage.snapshot_path.clone());
+    log::info!("Exporting {} as {} from {} to {}", args.dataset.as_str(), args.format.extension(), snapshot, args.output);
+
+    let result = tokio::task::spawn_blocking(move || -> Result<u64, String> {
+        let data_service = DataStorageService::new();
+        if !data_service.load_snapshot(&snapshot).map_err(|e| e.to_string())? {
+            return Err(format!("Storage snapshot {} not found", snapshot));
+        }
+        let output = BufWriter::new(File::create(&args.output).map_err(|e| e.to_string())?);
+        DataExportService::new(data_service)
+            .export(args.dataset, args.format, &args.filter(), output)
+            .map_err(|e| e.to_string())
+    }).await?;
+
+    let exported = result?;
+    log::info!("Export finished: {} rows", exported);
+    Ok(())
+}
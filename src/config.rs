@@ -1,104 +1,760 @@
+use std::collections::{BTreeMap, HashSet};
 use std::env;
-use config::{Config, File};
-use serde::Deserialize;
 use std::error::Error;
 use std::sync::Arc;
-use once_cell::sync::Lazy;
+use std::sync::atomic::{AtomicU64, Ordering};
+use arc_swap::ArcSwap;
+use config::{Config, Environment, File};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use crate::domain::entities::address::Address;
+use crate::ports::chain::base::base_uniswap_v2_swap_sync::PROTOCOL_ID;
 
-#[derive(Debug, Deserialize)]
-pub struct DefaultConfig {
-    pub server_port_http: u16,
+pub const CONFIG_FILE: &str = "resources/config.toml";
+// Environment overrides of any key, sections separated by "__": TDP_SYNC__DATA_REFRESH_INTERVAL, TDP_CHAINS__BASE__RPC_URL
+pub const ENV_PREFIX: &str = "TDP";
+// Chain the indexer runs on
+pub const BASE_CHAIN: &str = "base";
+// Environment variable selecting a profile, resources/config.<profile>.toml is layered over config.toml
+pub const PROFILE_ENV_VAR: &str = "TDP_PROFILE";
+// Profile allowed to run with the sample API keys it ships
+pub const DEV_PROFILE: &str = "dev";
+// Keys of resources/config.dev.toml, public and never accepted for admin access outside that profile
+pub const SAMPLE_API_KEYS: [&str; 2] = ["local-read-key", "local-admin-key"];
+// Longest quote route, every hop multiplies the routes searched
+pub const MAX_QUOTE_HOPS: usize = 4;
+// More points would repeat impacts at basis point resolution
+pub const MAX_DEPTH_CURVE_POINTS: usize = 50;
+
+// Settings a running process keeps until restart, a reload only applies the others
+const STRUCTURAL_KEYS: [&str; 9] = [
+    "profile",
+    "api",
+    "chains",
+    "protocols",
+    "storage.snapshot_path",
+    "sync.logs_range_initial",
+    "sync.logs_range_min",
+    "sync.logs_range_max",
+    "token_lists.files",
+];
+
+// Variables of the former flat configuration, still read for existing deployments
+const LEGACY_ENV_VARS: [(&str, &str); 4] = [
+    ("SERVER_PORT_HTTP", "api.port_http"),
+    ("DATA_REFRESH_INTERVAL", "sync.data_refresh_interval"),
+    ("CHAIN_BASE_RPC_URL", "chains.base.rpc_url"),
+    ("STORAGE_SNAPSHOT_PATH", "storage.snapshot_path"),
+];
+
+// Former factory variables, set the factory of the [[protocols]] entry with the id
+const LEGACY_FACTORY_ENV_VARS: [(&str, &str); 2] = [
+    ("CHAIN_BASE_UNISWAP_V2_FACTORY_ADDRESS", "BASE_UNISWAP_V2"),
+    ("CHAIN_BASE_UNISWAP_V3_FACTORY_ADDRESS", "BASE_UNISWAP_V3"),
+];
+
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+#[serde(deny_unknown_fields)]
+pub struct ApiConfig {
+    pub port_http: u16,
+    #[serde(default)]
+    pub keys: Vec<ApiKeyConfig>,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+#[serde(deny_unknown_fields)]
+pub struct ApiKeyConfig {
+    pub name: String,
+    pub key: String,
+    pub scopes: Vec<String>,
+    pub rate_limit_per_second: f64,
+    pub burst: u32,
+    pub daily_quota: Option<u64>,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+#[serde(deny_unknown_fields)]
+pub struct SyncConfig {
     pub data_refresh_interval: u64,
-    pub chain_base_rpc_url: String,
-    pub chain_base_uniswap_v2_factory_address: String,
-    pub chain_base_uniswap_v3_factory_address: String,
+    pub logs_range_initial: u64,
+    pub logs_range_min: u64,
+    pub logs_range_max: u64,
+    pub logs_max_attempts: u32,
+    // seconds the pair sync waits for the swap backfill before counting swaps, 0 waits until it is done
+    pub swap_wait_timeout: u64,
+    pub alert_sync_lag_blocks: u64,
+    pub task_restart_backoff_initial: u64,
+    pub task_restart_backoff_max: u64,
+    pub shutdown_grace_period: u64,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+#[serde(deny_unknown_fields)]
+pub struct StorageConfig {
+    pub snapshot_path: String,
+    pub snapshot_interval: u64,
+    pub swap_log_retention_secs: u64,
+    pub swap_log_retention_blocks: u64,
+    pub swap_log_retention_per_pair: usize,
+    pub swap_aggregate_hourly_retention_secs: u64,
+    pub swap_compaction_interval: u64,
+}
+
+// Thresholds a token is flagged high risk below
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+#[serde(deny_unknown_fields)]
+pub struct RiskConfig {
+    // pairs with reserves
+    pub min_active_pairs: usize,
+    // share of the token's pairs that have reserves
+    pub min_active_pair_ratio: f64,
+}
+
+// Curated token lists imported on startup, and the list served at /tokenlist.json
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+#[serde(deny_unknown_fields)]
+pub struct TokenListsConfig {
+    // tokenlists.org JSON files, a token in several lists takes the metadata of the last one
+    #[serde(default)]
+    pub files: Vec<String>,
+    pub name: String,
+    // served tokens need this many pairs with reserves
+    pub min_active_pairs: usize,
+    pub include_high_risk: bool,
+}
+
+impl Default for TokenListsConfig {
+    fn default() -> Self {
+        TokenListsConfig {
+            files: vec![],
+            name: "Token Data Provider".to_string(),
+            min_active_pairs: 1,
+            include_high_risk: false,
+        }
+    }
+}
+
+// Swap quotes served at /quote
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+#[serde(deny_unknown_fields)]
+pub struct QuoteConfig {
+    // pairs a route may go through
+    pub max_hops: usize,
+}
+
+impl Default for QuoteConfig {
+    fn default() -> Self {
+        QuoteConfig { max_hops: 3 }
+    }
+}
+
+// Pairs flagged as rug pulls, their tokens are high risk
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+#[serde(deny_unknown_fields)]
+pub struct RugPullConfig {
+    // seconds liquidity removals are summed over
+    pub window_secs: u64,
+    // share of the liquidity removed within the window
+    pub max_liquidity_drop_share: f64,
+    // share of the liquidity one removal by the first liquidity provider may take
+    pub max_deployer_removal_share: f64,
+    // seconds after the flagged burn a flag is cleared unless a later burn raised it again, 0 keeps flags
+    pub flag_expiry_secs: u64,
+}
+
+impl Default for RugPullConfig {
+    fn default() -> Self {
+        RugPullConfig { window_secs: 3600, max_liquidity_drop_share: 0.5, max_deployer_removal_share: 0.1, flag_expiry_secs: 604800 }
+    }
+}
+
+// Liquidity depth served on the pair and token endpoints
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+#[serde(deny_unknown_fields)]
+pub struct DepthConfig {
+    // price impacts the tradeable amount is reported for, in basis points
+    pub impact_levels_bps: Vec<u32>,
+    // points of the slippage curve, log-spaced impacts between 0.1% and 50%
+    pub curve_points: usize,
+}
+
+impl Default for DepthConfig {
+    fn default() -> Self {
+        DepthConfig { impact_levels_bps: vec![100, 200, 500], curve_points: 10 }
+    }
 }
 
-enum EnvVar {
-    ServerPortHTTP,
-    DataRefreshInterval,
-    ChainBaseRPCURL,
-    ChainBaseUniswapV2FactoryAddress,
-    ChainBaseUniswapV3FactoryAddress
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+#[serde(deny_unknown_fields)]
+pub struct ChainConfig {
+    pub chain_id: u64,
+    pub rpc_url: String,
+    // when set, replaces rpc_url
+    #[serde(default)]
+    pub rpc_endpoints: Vec<RpcEndpointConfig>,
+    pub genesis_timestamp: u64,
+    pub block_time: u64,
+    pub rpc_health_check_interval: u64,
+    pub rpc_max_head_lag: u64,
+    pub rpc_max_head_age: u64,
+    pub rpc_quorum_size: usize,
+    pub rpc_quorum_threshold: usize,
+    // seconds a contract stays disputed after the last quorum disagreement on it, 0 keeps disputes
+    #[serde(default = "default_rpc_quorum_dispute_ttl")]
+    pub rpc_quorum_dispute_ttl: u64,
 }
 
-#[derive(Debug, Deserialize)]
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+#[serde(deny_unknown_fields)]
+pub struct RpcEndpointConfig {
+    pub url: String,
+    #[serde(default = "default_rpc_endpoint_weight")]
+    pub weight: u32,
+    // largest eth_getLogs block span the provider accepts
+    pub max_logs_range: Option<u64>,
+}
+
+fn default_rpc_quorum_dispute_ttl() -> u64 {
+    3600
+}
+
+fn default_rpc_endpoint_weight() -> u32 {
+    1
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq)]
+#[serde(rename_all = "snake_case")]
+pub enum ProtocolKind {
+    UniswapV2,
+    UniswapV3,
+}
+
+// Ingestion settings of a protocol: the [sync] values with the protocol's own overrides
+#[derive(Debug, Serialize, Clone, PartialEq)]
+pub struct IngestionConfig {
+    pub logs_range_initial: u64,
+    pub logs_range_min: u64,
+    pub logs_range_max: u64,
+    pub logs_max_attempts: u32,
+    pub swap_wait_timeout: u64,
+}
+
+// Per-protocol overrides of the [sync] ingestion settings, unset ones fall back to [sync]
+#[derive(Debug, Serialize, Deserialize, Clone, Default, PartialEq)]
+#[serde(deny_unknown_fields)]
+pub struct IngestionOverrides {
+    pub logs_range_initial: Option<u64>,
+    pub logs_range_min: Option<u64>,
+    pub logs_range_max: Option<u64>,
+    pub logs_max_attempts: Option<u32>,
+    pub swap_wait_timeout: Option<u64>,
+}
+
+impl IngestionOverrides {
+    pub fn is_empty(&self) -> bool {
+        *self == IngestionOverrides::default()
+    }
+}
+
+// DEX deployment on a chain. The indexer discovers the pairs of the BASE_UNISWAP_V2 factory, swaps of pairs
+// created by the other uniswap_v2 factories are attributed to their protocol
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+#[serde(deny_unknown_fields)]
+pub struct ProtocolConfig {
+    pub id: String,
+    pub chain: String,
+    pub dex_id: String,
+    pub kind: ProtocolKind,
+    pub factory: String,
+    // first block swaps are indexed from
+    #[serde(default)]
+    pub start_block: u64,
+    // swap fee in basis points
+    pub fee_bps: u32,
+    #[serde(default)]
+    pub ingestion: IngestionOverrides,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+#[serde(deny_unknown_fields)]
 pub struct AppConfig {
-    pub default: DefaultConfig,
+    // profile layered over config.toml, if any
+    #[serde(default)]
+    pub profile: Option<String>,
+    pub api: ApiConfig,
+    pub sync: SyncConfig,
+    pub storage: StorageConfig,
+    pub risk: RiskConfig,
+    #[serde(default)]
+    pub token_lists: TokenListsConfig,
+    #[serde(default)]
+    pub quote: QuoteConfig,
+    #[serde(default)]
+    pub depth: DepthConfig,
+    #[serde(default)]
+    pub rug_pull: RugPullConfig,
+    pub chains: BTreeMap<String, ChainConfig>,
+    #[serde(default)]
+    pub protocols: Vec<ProtocolConfig>,
 }
 
-// Lazy static configuration loading
-pub static CONFIG: Lazy<Arc<AppConfig>> = Lazy::new(|| {
-    log::info!("Configuration loading ...");
-    match load_config_from_env_or_file() {
-        Ok(config) => Arc::new(config),
-        Err(e) => {
-            log::error!("Failed to load configuration: {:?}", e);
-            panic!("Failed to load configuration: {:?}", e);
+impl AppConfig {
+    // Validated to exist on startup
+    pub fn base_chain(&self) -> &ChainConfig {
+        &self.chains[BASE_CHAIN]
+    }
+
+    // Configured RPC endpoint pool, a single rpc_url endpoint when no pool is configured
+    pub fn base_rpc_endpoints(&self) -> Vec<RpcEndpointConfig> {
+        let chain = self.base_chain();
+        if chain.rpc_endpoints.is_empty() {
+            vec![RpcEndpointConfig {
+                url: chain.rpc_url.clone(),
+                weight: default_rpc_endpoint_weight(),
+                max_logs_range: None,
+            }]
+        } else {
+            chain.rpc_endpoints.clone()
+        }
+    }
+
+    pub fn protocol(&self, id: &str) -> Option<&ProtocolConfig> {
+        self.protocols.iter().find(|protocol| protocol.id == id)
+    }
+
+    pub fn ingestion(&self, protocol_id: &str) -> IngestionConfig {
+        let overrides = self.protocol(protocol_id).map(|protocol| protocol.ingestion.clone()).unwrap_or_default();
+        IngestionConfig {
+            logs_range_initial: overrides.logs_range_initial.unwrap_or(self.sync.logs_range_initial),
+            logs_range_min: overrides.logs_range_min.unwrap_or(self.sync.logs_range_min),
+            logs_range_max: overrides.logs_range_max.unwrap_or(self.sync.logs_range_max),
+            logs_max_attempts: overrides.logs_max_attempts.unwrap_or(self.sync.logs_max_attempts),
+            swap_wait_timeout: overrides.swap_wait_timeout.unwrap_or(self.sync.swap_wait_timeout),
+        }
+    }
+
+    // Uniswap V2 forks on the chain whose pairs the indexer doesn't discover itself
+    pub fn other_uniswap_v2_protocols(&self, chain: &str) -> Vec<&ProtocolConfig> {
+        self.protocols.iter()
+            .filter(|protocol| protocol.chain == chain && protocol.kind == ProtocolKind::UniswapV2 && protocol.id != PROTOCOL_ID)
+            .collect()
+    }
+
+    // Configuration as served by the admin API, API keys are not shown
+    pub fn redacted(&self) -> Value {
+        let mut value = serde_json::to_value(self).unwrap_or_default();
+        if let Some(keys) = value.pointer_mut("/api/keys").and_then(|keys| keys.as_array_mut()) {
+            for key in keys {
+                key["key"] = Value::String("***".to_string());
+            }
+        }
+        value
+    }
+}
+
+// Outcome of a configuration reload
+#[derive(Debug, Serialize, Clone, Default)]
+pub struct ConfigReload {
+    // changed keys in effect from now on
+    pub applied: Vec<String>,
+    // changed keys that take effect on restart
+    pub restart_required: Vec<String>,
+}
+
+// Configuration of a running process, every service reads the current one through its clone.
+// Reloads swap in new values of the non-structural settings.
+#[derive(Clone)]
+pub struct ConfigHandle {
+    current: Arc<ArcSwap<AppConfig>>,
+    // number of reloads that applied changes
+    generation: Arc<AtomicU64>,
+}
+
+impl ConfigHandle {
+    pub fn new(config: AppConfig) -> Self {
+        ConfigHandle { current: Arc::new(ArcSwap::from_pointee(config)), generation: Arc::new(AtomicU64::new(0)) }
+    }
+
+    pub fn get(&self) -> Arc<AppConfig> {
+        self.current.load_full()
+    }
+
+    pub fn generation(&self) -> u64 {
+        self.generation.load(Ordering::SeqCst)
+    }
+
+    // Reads config.toml and the environment again, nothing changes when the result is invalid
+    pub fn reload(&self) -> Result<ConfigReload, Box<dyn Error>> {
+        let next = load_config_from_env_or_file()?;
+        let errors = validate_config(&next);
+        if !errors.is_empty() {
+            return Err(format!("configuration not reloaded: {}", errors.join("; ")).into());
+        }
+
+        let current = self.get();
+        let current_value = serde_json::to_value(current.as_ref())?;
+        let mut next_value = serde_json::to_value(&next)?;
+        let mut changed = vec![];
+        changed_keys("", &current_value, &next_value, &mut changed);
+
+        //structural settings keep their running values
+        for key in STRUCTURAL_KEYS {
+            let pointer = format!("/{}", key.replace('.', "/"));
+            if let (Some(running), Some(next)) = (current_value.pointer(&pointer), next_value.pointer_mut(&pointer)) {
+                *next = running.clone();
+            }
+        }
+        let (restart_required, applied): (Vec<String>, Vec<String>) = changed.into_iter().partition(|key| is_structural(key));
+        if !applied.is_empty() {
+            self.current.store(Arc::new(serde_json::from_value(next_value)?));
+            self.generation.fetch_add(1, Ordering::SeqCst);
+        }
+
+        for key in &applied {
+            log::info!("Config reload: {} applied", key);
+        }
+        for key in &restart_required {
+            log::warn!("Config reload: {} changed, takes effect on restart", key);
+        }
+        Ok(ConfigReload { applied, restart_required })
+    }
+}
+
+fn is_structural(key: &str) -> bool {
+    STRUCTURAL_KEYS.iter().any(|structural| key == *structural || key.starts_with(&format!("{}.", structural)))
+}
+
+// Dotted paths of the values that differ, lists are compared as a whole
+fn changed_keys(path: &str, current: &Value, next: &Value, changed: &mut Vec<String>) {
+    match (current, next) {
+        (Value::Object(current), Value::Object(next)) => {
+            let keys: std::collections::BTreeSet<&String> = current.keys().chain(next.keys()).collect();
+            for key in keys {
+                let key_path = if path.is_empty() { key.clone() } else { format!("{}.{}", path, key) };
+                changed_keys(&key_path, current.get(key).unwrap_or(&Value::Null), next.get(key).unwrap_or(&Value::Null), changed);
+            }
         }
+        (current, next) if current != next => changed.push(path.to_string()),
+        _ => {}
     }
-});
+}
 
-impl EnvVar {
-    // Returns the environment variable name as a &str
-    fn as_str(&self) -> &str {
-        match self {
-            EnvVar::ServerPortHTTP => "SERVER_PORT_HTTP",
-            EnvVar::DataRefreshInterval => "DATA_REFRESH_INTERVAL",
-            EnvVar::ChainBaseRPCURL => "CHAIN_BASE_RPC_URL",
-            EnvVar::ChainBaseUniswapV2FactoryAddress => "CHAIN_BASE_UNISWAP_V2_FACTORY_ADDRESS",
-            EnvVar::ChainBaseUniswapV3FactoryAddress => "CHAIN_BASE_UNISWAP_V3_FACTORY_ADDRESS"
+// Configuration loaded and validated at startup and handed to every service built from it
+pub fn load_app_config() -> Result<ConfigHandle, Box<dyn Error>> {
+    log::info!("Configuration loading ...");
+    let config = match load_config_from_env_or_file() {
+        Ok(config) => config,
+        Err(e) => {
+            log::error!("Failed to load configuration: {}", e);
+            return Err(e);
+        }
+    };
+    let errors = validate_config(&config);
+    if !errors.is_empty() {
+        for error in &errors {
+            log::error!("Invalid configuration: {}", error);
         }
+        return Err(format!("{} configuration errors, see `config check`", errors.len()).into());
     }
+    log_config_overrides(&config);
+    Ok(ConfigHandle::new(config))
+}
 
-    // Fetches the value from the environment, attempts to parse it into the desired type, or returns the default value
-    fn get_value<T: std::str::FromStr + Clone>(&self, default: &T) -> T
-    where
-        T::Err: std::fmt::Debug,
-    {
-        env::var(self.as_str())
-            .ok()
-            .and_then(|val| val.parse::<T>().ok()) // Try to parse the value to type T
-            .unwrap_or_else(|| default.clone()) // Clone the default value if parsing fails
+// Logs the values the profile and the environment change from config.toml, and the per-protocol ingestion settings
+fn log_config_overrides(config: &AppConfig) {
+    let (Ok(file), Ok(profiled)) = (load_config(), load_profile_config()) else {
+        return;
+    };
+    let file_value = file.redacted();
+    let profile_value = profiled.redacted();
+    let value = config.redacted();
+    let mut changed = vec![];
+    changed_keys("", &file_value, &value, &mut changed);
+    for key in changed.into_iter().filter(|key| key != "profile") {
+        let pointer = format!("/{}", key.replace('.', "/"));
+        let in_file = file_value.pointer(&pointer).unwrap_or(&Value::Null);
+        let in_effect = value.pointer(&pointer).unwrap_or(&Value::Null);
+        let source = match (&config.profile, profile_value.pointer(&pointer) == Some(in_effect)) {
+            (Some(profile), true) => format!("profile {}", profile),
+            _ => "environment".to_string(),
+        };
+        log::info!("Config {} = {} ({}, config.toml: {})", key, in_effect, source, in_file);
+    }
+    for protocol in config.protocols.iter().filter(|protocol| !protocol.ingestion.is_empty()) {
+        log::info!("Protocol {} ingestion: {:?}", protocol.id, config.ingestion(&protocol.id));
     }
 }
 
 // Loads the configuration from a file (config.toml)
 pub fn load_config() -> Result<AppConfig, Box<dyn Error>> {
     let mut settings = Config::default();
+    settings.merge(File::with_name(CONFIG_FILE))?;
+    let app_config: AppConfig = settings.try_into()?;
+    Ok(app_config)
+}
+
+// Loads config.toml with the selected profile layered over it
+pub fn load_profile_config() -> Result<AppConfig, Box<dyn Error>> {
+    let mut settings = Config::default();
+    settings.merge(File::with_name(CONFIG_FILE))?;
+    merge_profile(&mut settings)?;
+    Ok(settings.try_into()?)
+}
 
-    // Load configuration from file
-    settings.merge(File::with_name("resources/config.toml"))?;
+// Loads config.toml with the given profile layered over it
+pub fn load_config_with_profile(profile: &str) -> Result<AppConfig, Box<dyn Error>> {
+    let mut settings = Config::default();
+    settings.merge(File::with_name(CONFIG_FILE))?;
+    merge_profile_file(&mut settings, profile)?;
+    Ok(settings.try_into()?)
+}
 
-    // Parse the configuration into the AppConfig structure
-    let app_config: AppConfig = settings.try_into()?;
+// Profile from TDP_PROFILE, else the profile key of config.toml
+fn merge_profile(settings: &mut Config) -> Result<(), Box<dyn Error>> {
+    let profile = match env::var(PROFILE_ENV_VAR) {
+        Ok(profile) => Some(profile),
+        Err(_) => settings.get_str("profile").ok(),
+    };
+    match profile.filter(|profile| !profile.is_empty()) {
+        Some(profile) => merge_profile_file(settings, &profile),
+        None => Ok(()),
+    }
+}
 
-    Ok(app_config)
+fn merge_profile_file(settings: &mut Config, profile: &str) -> Result<(), Box<dyn Error>> {
+    let path = profile_file(profile);
+    if !std::path::Path::new(&path).exists() {
+        return Err(format!("profile {}: {} not found", profile, path).into());
+    }
+    settings.merge(File::with_name(&path))?;
+    settings.set("profile", profile)?;
+    Ok(())
+}
+
+pub fn profile_file(profile: &str) -> String {
+    CONFIG_FILE.replace(".toml", &format!(".{}.toml", profile))
 }
 
-// Load the configuration from environment variables, overriding values from the file if present
+// Loads config.toml and the profile, then overrides any key from TDP_ environment variables
 pub fn load_config_from_env_or_file() -> Result<AppConfig, Box<dyn Error>> {
-    // First, load the configuration from the file
-    let mut config = load_config()?;
+    let mut settings = Config::default();
+    settings.merge(File::with_name(CONFIG_FILE))?;
+    merge_profile(&mut settings)?;
+    for (variable, key) in LEGACY_ENV_VARS {
+        if let Ok(value) = env::var(variable) {
+            log::warn!("{} is deprecated, use {}_{}", variable, ENV_PREFIX, key.replace('.', "__").to_uppercase());
+            settings.set(key, value)?;
+        }
+    }
+    settings.merge(Environment::with_prefix(ENV_PREFIX).separator("__"))?;
+    let mut config: AppConfig = settings.try_into()?;
+    for (variable, id) in LEGACY_FACTORY_ENV_VARS {
+        if let Ok(value) = env::var(variable) {
+            match config.protocols.iter_mut().find(|protocol| protocol.id == id) {
+                Some(protocol) => {
+                    log::warn!("{} is deprecated, set factory of the {} protocol in {}", variable, id, CONFIG_FILE);
+                    protocol.factory = value;
+                }
+                None => log::error!("{} is ignored, no {} protocol is configured", variable, id),
+            }
+        }
+    }
 
-    // Override with environment variables if they exist
-    config.default.server_port_http = EnvVar::ServerPortHTTP
-        .get_value(&config.default.server_port_http); // u16 for server_port_http
+    log::info!("Config profile: {}", config.profile.as_deref().unwrap_or("none"));
+    log::info!("Config loaded: {:?} {:?} {:?} {:?}", config.api.port_http, config.sync, config.storage, config.risk);
+    log::info!("Chains configured: {:?}", config.chains.keys().collect::<Vec<_>>());
+    log::info!("RPC endpoints configured: {}", config.base_rpc_endpoints().len());
+    log::info!("Protocols configured: {:?}", config.protocols.iter().map(|protocol| &protocol.id).collect::<Vec<_>>());
+    log::info!("API keys configured: {}", config.api.keys.len());
 
-    config.default.data_refresh_interval = EnvVar::DataRefreshInterval
-        .get_value(&config.default.data_refresh_interval); // u16 refresh data interval in seconds
+    Ok(config)
+}
 
-    // Override with environment variables if they exist
-    config.default.chain_base_rpc_url = EnvVar::ChainBaseRPCURL
-        .get_value(&config.default.chain_base_rpc_url); // String for chain RPC URL
+// Checks values the application can't run with, returns one message per problem
+pub fn validate_config(config: &AppConfig) -> Vec<String> {
+    let mut errors = vec![];
 
-    config.default.chain_base_uniswap_v2_factory_address = EnvVar::ChainBaseUniswapV2FactoryAddress
-        .get_value(&config.default.chain_base_uniswap_v2_factory_address); // String for Factory Address
+    if config.api.port_http == 0 {
+        errors.push("api.port_http must be greater than 0".to_string());
+    }
+    let mut keys = HashSet::new();
+    for api_key in &config.api.keys {
+        if api_key.name.is_empty() || api_key.key.is_empty() {
+            errors.push("api.keys: name and key must not be empty".to_string());
+        }
+        if !keys.insert(api_key.key.clone()) {
+            errors.push(format!("api.keys.{}: key is used by another API key", api_key.name));
+        }
+        for scope in &api_key.scopes {
+            if scope != "read" && scope != "admin" {
+                errors.push(format!("api.keys.{}: unknown scope {}", api_key.name, scope));
+            }
+        }
+        if api_key.rate_limit_per_second <= 0.0 {
+            errors.push(format!("api.keys.{}: rate_limit_per_second must be greater than 0", api_key.name));
+        }
+        if api_key.burst == 0 {
+            errors.push(format!("api.keys.{}: burst must be greater than 0", api_key.name));
+        }
+        if api_key.scopes.iter().any(|scope| scope == "admin") && SAMPLE_API_KEYS.contains(&api_key.key.as_str())
+            && config.profile.as_deref() != Some(DEV_PROFILE) {
+            errors.push(format!("api.keys.{}: admin key is the public sample key of the {} profile", api_key.name, DEV_PROFILE));
+        }
+    }
 
-    config.default.chain_base_uniswap_v3_factory_address = EnvVar::ChainBaseUniswapV3FactoryAddress
-        .get_value(&config.default.chain_base_uniswap_v3_factory_address); // String for Factory V3 address
+    let sync = &config.sync;
+    if sync.data_refresh_interval == 0 {
+        errors.push("sync.data_refresh_interval must be greater than 0".to_string());
+    }
+    validate_ingestion("sync", &config.ingestion(""), &mut errors);
+    if sync.task_restart_backoff_initial == 0 || sync.task_restart_backoff_initial > sync.task_restart_backoff_max {
+        errors.push("sync.task_restart_backoff_initial must be between 1 and task_restart_backoff_max".to_string());
+    }
 
-    log::info!("Config loaded: {:?}",config);
+    let storage = &config.storage;
+    if storage.snapshot_path.is_empty() {
+        errors.push("storage.snapshot_path must not be empty".to_string());
+    }
+    if storage.snapshot_interval == 0 {
+        errors.push("storage.snapshot_interval must be greater than 0".to_string());
+    }
+    if storage.swap_compaction_interval == 0 {
+        errors.push("storage.swap_compaction_interval must be greater than 0".to_string());
+    }
+    if storage.swap_aggregate_hourly_retention_secs > 0
+        && storage.swap_aggregate_hourly_retention_secs < storage.swap_log_retention_secs {
+        errors.push("storage.swap_aggregate_hourly_retention_secs must not be shorter than swap_log_retention_secs".to_string());
+    }
 
-    Ok(config)
-}
\ No newline at end of file
+    if !(0.0..=1.0).contains(&config.risk.min_active_pair_ratio) {
+        errors.push("risk.min_active_pair_ratio must be between 0 and 1".to_string());
+    }
+
+    let token_lists = &config.token_lists;
+    for file in &token_lists.files {
+        if !std::path::Path::new(file).is_file() {
+            errors.push(format!("token_lists.files: {} not found", file));
+        }
+    }
+    //tokenlists.org schema limits
+    if token_lists.name.is_empty() || token_lists.name.chars().count() > 30 {
+        errors.push("token_lists.name must be 1 to 30 characters".to_string());
+    }
+
+    if !(1..=MAX_QUOTE_HOPS).contains(&config.quote.max_hops) {
+        errors.push(format!("quote.max_hops must be between 1 and {}", MAX_QUOTE_HOPS));
+    }
+    let depth = &config.depth;
+    if depth.impact_levels_bps.is_empty() || depth.impact_levels_bps.iter().any(|level| *level == 0 || *level >= 10000) {
+        errors.push("depth.impact_levels_bps must list impacts between 1 and 9999".to_string());
+    }
+    if !(2..=MAX_DEPTH_CURVE_POINTS).contains(&depth.curve_points) {
+        errors.push(format!("depth.curve_points must be between 2 and {}", MAX_DEPTH_CURVE_POINTS));
+    }
+
+    let rug_pull = &config.rug_pull;
+    if rug_pull.window_secs == 0 {
+        errors.push("rug_pull.window_secs must be greater than 0".to_string());
+    }
+    if !(rug_pull.max_liquidity_drop_share > 0.0 && rug_pull.max_liquidity_drop_share <= 1.0) {
+        errors.push("rug_pull.max_liquidity_drop_share must be greater than 0 and at most 1".to_string());
+    }
+    if !(rug_pull.max_deployer_removal_share > 0.0 && rug_pull.max_deployer_removal_share <= 1.0) {
+        errors.push("rug_pull.max_deployer_removal_share must be greater than 0 and at most 1".to_string());
+    }
+    if rug_pull.flag_expiry_secs > 0 && rug_pull.flag_expiry_secs < rug_pull.window_secs {
+        errors.push("rug_pull.flag_expiry_secs must not be shorter than window_secs".to_string());
+    }
+
+    if !config.chains.contains_key(BASE_CHAIN) {
+        errors.push(format!("chains.{} must be configured", BASE_CHAIN));
+    }
+    for (name, chain) in &config.chains {
+        if chain.chain_id == 0 {
+            errors.push(format!("chains.{}.chain_id must be greater than 0", name));
+        }
+        if chain.rpc_url.is_empty() && chain.rpc_endpoints.is_empty() {
+            errors.push(format!("chains.{}.rpc_url must be an http(s)://, ws(s):// URL or an IPC path", name));
+        }
+        for endpoint in &chain.rpc_endpoints {
+            if endpoint.url.is_empty() {
+                errors.push(format!("chains.{}.rpc_endpoints: url must not be empty", name));
+            }
+            if endpoint.weight == 0 {
+                errors.push(format!("chains.{}.rpc_endpoints.{}: weight must be greater than 0", name, endpoint.url));
+            }
+            if endpoint.max_logs_range == Some(0) {
+                errors.push(format!("chains.{}.rpc_endpoints.{}: max_logs_range must be greater than 0", name, endpoint.url));
+            }
+            //the endpoint caps the getLogs range of every protocol on the chain
+            for protocol in config.protocols.iter().filter(|protocol| protocol.chain == *name) {
+                let logs_range_min = config.ingestion(&protocol.id).logs_range_min;
+                if endpoint.max_logs_range.is_some_and(|max_logs_range| max_logs_range > 0 && max_logs_range < logs_range_min) {
+                    errors.push(format!("chains.{}.rpc_endpoints.{}: max_logs_range must not be below the logs_range_min {} of protocol {}",
+                        name, endpoint.url, logs_range_min, protocol.id));
+                }
+            }
+        }
+        if chain.block_time == 0 {
+            errors.push(format!("chains.{}.block_time must be greater than 0", name));
+        }
+        if chain.rpc_health_check_interval == 0 {
+            errors.push(format!("chains.{}.rpc_health_check_interval must be greater than 0", name));
+        }
+        if chain.rpc_max_head_age == 0 {
+            errors.push(format!("chains.{}.rpc_max_head_age must be greater than 0", name));
+        }
+        if chain.rpc_quorum_size > 0 {
+            let endpoints = std::cmp::max(chain.rpc_endpoints.len(), 1);
+            if chain.rpc_quorum_threshold == 0 || chain.rpc_quorum_threshold > chain.rpc_quorum_size {
+                errors.push(format!("chains.{}.rpc_quorum_threshold must be between 1 and rpc_quorum_size", name));
+            }
+            if chain.rpc_quorum_size > endpoints {
+                errors.push(format!("chains.{}.rpc_quorum_size {} exceeds the {} configured rpc_endpoints",
+                    name, chain.rpc_quorum_size, endpoints));
+            }
+        }
+    }
+
+    let mut protocol_ids = HashSet::new();
+    for protocol in &config.protocols {
+        if protocol.id.is_empty() || protocol.dex_id.is_empty() {
+            errors.push("protocols: id and dex_id must not be empty".to_string());
+        }
+        if !protocol_ids.insert(protocol.id.clone()) {
+            errors.push(format!("protocols.{}: id is used by another protocol", protocol.id));
+        }
+        if !config.chains.contains_key(&protocol.chain) {
+            errors.push(format!("protocols.{}: chain {} is not configured", protocol.id, protocol.chain));
+        }
+        if protocol.factory.parse::<Address>().is_err() {
+            errors.push(format!("protocols.{}: factory is not a valid address: {}", protocol.id, protocol.factory));
+        }
+        if protocol.fee_bps >= 10_000 {
+            errors.push(format!("protocols.{}: fee_bps must be below 10000", protocol.id));
+        }
+        if !protocol.ingestion.is_empty() {
+            validate_ingestion(&format!("protocols.{}.ingestion", protocol.id), &config.ingestion(&protocol.id), &mut errors);
+        }
+    }
+    match config.protocol(PROTOCOL_ID) {
+        Some(protocol) if protocol.chain != BASE_CHAIN || protocol.kind != ProtocolKind::UniswapV2 =>
+            errors.push(format!("protocols.{}: must be a uniswap_v2 protocol on chain {}", PROTOCOL_ID, BASE_CHAIN)),
+        Some(_) => {}
+        None => errors.push(format!("protocols.{} must be configured, its pairs are indexed", PROTOCOL_ID)),
+    }
+
+    errors
+}
+
+fn validate_ingestion(path: &str, ingestion: &IngestionConfig, errors: &mut Vec<String>) {
+    if ingestion.logs_range_min == 0 || ingestion.logs_range_min > ingestion.logs_range_max {
+        errors.push(format!("{}.logs_range_min must be between 1 and logs_range_max", path));
+    }
+    if ingestion.logs_range_initial < ingestion.logs_range_min || ingestion.logs_range_initial > ingestion.logs_range_max {
+        errors.push(format!("{}.logs_range_initial must be between logs_range_min and logs_range_max", path));
+    }
+    if ingestion.logs_max_attempts == 0 {
+        errors.push(format!("{}.logs_max_attempts must be greater than 0", path));
+    }
+}
@@ -0,0 +1,71 @@
+use std::fmt;
+use std::str::FromStr;
+use ethers::types::H160;
+use ethers::utils::to_checksum;
+use serde::{de, Deserialize, Deserializer, Serialize, Serializer};
+
+// Contract or account address, the storage key of tokens, pairs and swap logs.
+// Equal for every spelling of the same 20 bytes: parsed case-insensitively, written EIP-55 checksummed.
+#[derive(Clone, Copy, Default, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct Address(pub H160);
+
+impl Address {
+    pub fn as_h160(&self) -> H160 {
+        self.0
+    }
+
+    pub fn to_checksum(&self) -> String {
+        to_checksum(&self.0, None)
+    }
+}
+
+impl From<H160> for Address {
+    fn from(address: H160) -> Self {
+        Address(address)
+    }
+}
+
+impl From<Address> for H160 {
+    fn from(address: Address) -> Self {
+        address.0
+    }
+}
+
+// 40 hex digits with or without the 0x prefix, in any letter case, the checksum is not enforced
+impl FromStr for Address {
+    type Err = String;
+
+    fn from_str(value: &str) -> Result<Self, Self::Err> {
+        let trimmed = value.trim();
+        let hex = trimmed.strip_prefix("0x").or_else(|| trimmed.strip_prefix("0X")).unwrap_or(trimmed);
+        if hex.len() != 40 || !hex.chars().all(|c| c.is_ascii_hexdigit()) {
+            return Err(format!("Invalid address: {}", value));
+        }
+        H160::from_str(hex).map(Address).map_err(|e| format!("Invalid address {}: {}", value, e))
+    }
+}
+
+impl fmt::Display for Address {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.to_checksum())
+    }
+}
+
+impl fmt::Debug for Address {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.to_checksum())
+    }
+}
+
+impl Serialize for Address {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.collect_str(self)
+    }
+}
+
+impl<'de> Deserialize<'de> for Address {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let value = String::deserialize(deserializer)?;
+        value.parse().map_err(de::Error::custom)
+    }
+}
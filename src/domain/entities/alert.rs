@@ -0,0 +1,36 @@
+use std::fmt;
+use std::time::SystemTime;
+use serde::{Deserialize, Serialize};
+
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq)]
+#[serde(rename_all = "snake_case")]
+pub enum AlertSeverity {
+    Info,
+    Warning,
+    Critical,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct Alert {
+    pub id: u64,
+    pub severity: AlertSeverity,
+    pub kind: String,
+    // token, pair or synchronizer the alert is about
+    pub subject: String,
+    pub message: String,
+    pub raised_at: SystemTime,
+}
+
+impl fmt::Display for Alert {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "Alert {{ id: {}, severity: {:?}, kind: {}, subject: {}, message: {} }}",
+            self.id,
+            self.severity,
+            self.kind,
+            self.subject,
+            self.message
+        )
+    }
+}
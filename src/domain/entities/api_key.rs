@@ -0,0 +1,93 @@
+use std::time::SystemTime;
+use serde::{Deserialize, Serialize};
+
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Eq, Hash)]
+#[serde(rename_all = "snake_case")]
+pub enum ApiScope {
+    Read,
+    Admin,
+}
+
+impl ApiScope {
+    // Admin keys can also call read endpoints
+    pub fn allows(&self, required: ApiScope) -> bool {
+        *self == required || *self == ApiScope::Admin
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct ApiKey {
+    pub name: String,
+    #[serde(skip_serializing)]
+    pub key: String,
+    pub scopes: Vec<ApiScope>,
+    pub rate_limit_per_second: f64,
+    pub burst: u32,
+    pub daily_quota: Option<u64>,
+    pub created_at: SystemTime,
+}
+
+// API key added through the admin API, stored with its secret so that it survives restarts
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct StoredApiKey {
+    pub name: String,
+    pub key: String,
+    pub scopes: Vec<ApiScope>,
+    pub rate_limit_per_second: f64,
+    pub burst: u32,
+    pub daily_quota: Option<u64>,
+    pub created_at: SystemTime,
+}
+
+impl From<&ApiKey> for StoredApiKey {
+    fn from(api_key: &ApiKey) -> Self {
+        StoredApiKey {
+            name: api_key.name.clone(),
+            key: api_key.key.clone(),
+            scopes: api_key.scopes.clone(),
+            rate_limit_per_second: api_key.rate_limit_per_second,
+            burst: api_key.burst,
+            daily_quota: api_key.daily_quota,
+            created_at: api_key.created_at,
+        }
+    }
+}
+
+impl From<StoredApiKey> for ApiKey {
+    fn from(stored: StoredApiKey) -> Self {
+        ApiKey {
+            name: stored.name,
+            key: stored.key,
+            scopes: stored.scopes,
+            rate_limit_per_second: stored.rate_limit_per_second,
+            burst: stored.burst,
+            daily_quota: stored.daily_quota,
+            created_at: stored.created_at,
+        }
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct ApiKeyUsage {
+    pub name: String,
+    pub total_requests: u64,
+    pub rate_limited_requests: u64,
+    pub quota_rejected_requests: u64,
+    pub quota_day: String,
+    pub quota_used: u64,
+    pub last_used_at: Option<SystemTime>,
+}
+
+impl ApiKeyUsage {
+    pub fn new(name: String) -> Self {
+        ApiKeyUsage {
+            name,
+            total_requests: 0,
+            rate_limited_requests: 0,
+            quota_rejected_requests: 0,
+            quota_day: String::new(),
+            quota_used: 0,
+            last_used_at: None,
+        }
+    }
+}
@@ -0,0 +1,98 @@
+use std::fmt;
+use serde::Serialize;
+use crate::domain::entities::address::Address;
+use crate::domain::entities::liquidity_event::RugPullReason;
+use crate::domain::entities::sync_state::SyncPhase;
+
+// Changes published by the synchronizers, consumed by aggregators, streaming endpoints and alerting
+#[derive(Debug, Clone, Serialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum DomainEvent {
+    PairDiscovered {
+        pair_address: Address,
+        protocol_id: String,
+        base_address: Address,
+        quote_address: Address,
+    },
+    ReservesUpdated {
+        pair_address: Address,
+        base_reserve: u128,
+        quote_reserve: u128,
+        reserve_block: u32,
+    },
+    SwapIndexed {
+        pair_address: Address,
+        protocol_id: String,
+        block_number: Option<u64>,
+        transaction_hash: Option<String>,
+        log_index: Option<u64>,
+    },
+    TokenUpdated {
+        address: Address,
+        symbol: String,
+        active_pairs: usize,
+        swaps: i64,
+        high_risk: bool,
+    },
+    RiskChanged {
+        address: Address,
+        symbol: String,
+        high_risk: bool,
+    },
+    RugPullDetected {
+        pair_address: Address,
+        reason: RugPullReason,
+        share: f64,
+        block_number: u64,
+    },
+    SyncProgress {
+        synchronizer: String,
+        block: u64,
+        target_block: u64,
+    },
+    SyncStateChanged {
+        synchronizer: String,
+        phase: SyncPhase,
+        previous_phase: SyncPhase,
+        error: Option<String>,
+    },
+}
+
+impl DomainEvent {
+    // Name used in the serialized "type" field and for filtering subscriptions
+    pub fn event_type(&self) -> &str {
+        match self {
+            DomainEvent::PairDiscovered { .. } => "pair_discovered",
+            DomainEvent::ReservesUpdated { .. } => "reserves_updated",
+            DomainEvent::SwapIndexed { .. } => "swap_indexed",
+            DomainEvent::TokenUpdated { .. } => "token_updated",
+            DomainEvent::RiskChanged { .. } => "risk_changed",
+            DomainEvent::RugPullDetected { .. } => "rug_pull_detected",
+            DomainEvent::SyncProgress { .. } => "sync_progress",
+            DomainEvent::SyncStateChanged { .. } => "sync_state_changed",
+        }
+    }
+}
+
+impl fmt::Display for DomainEvent {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            DomainEvent::PairDiscovered { pair_address, protocol_id, .. } =>
+                write!(f, "PairDiscovered {{ pair_address: {}, protocol_id: {} }}", pair_address, protocol_id),
+            DomainEvent::ReservesUpdated { pair_address, base_reserve, quote_reserve, .. } =>
+                write!(f, "ReservesUpdated {{ pair_address: {}, reserves: {}:{} }}", pair_address, base_reserve, quote_reserve),
+            DomainEvent::SwapIndexed { pair_address, block_number, .. } =>
+                write!(f, "SwapIndexed {{ pair_address: {}, block_number: {:?} }}", pair_address, block_number),
+            DomainEvent::TokenUpdated { address, symbol, .. } =>
+                write!(f, "TokenUpdated {{ address: {}, symbol: {} }}", address, symbol),
+            DomainEvent::RiskChanged { address, symbol, high_risk } =>
+                write!(f, "RiskChanged {{ address: {}, symbol: {}, high_risk: {} }}", address, symbol, high_risk),
+            DomainEvent::RugPullDetected { pair_address, reason, share, .. } =>
+                write!(f, "RugPullDetected {{ pair_address: {}, reason: {:?}, share: {:.2} }}", pair_address, reason, share),
+            DomainEvent::SyncProgress { synchronizer, block, target_block } =>
+                write!(f, "SyncProgress {{ synchronizer: {}, block: {}, target_block: {} }}", synchronizer, block, target_block),
+            DomainEvent::SyncStateChanged { synchronizer, phase, previous_phase, .. } =>
+                write!(f, "SyncStateChanged {{ synchronizer: {}, phase: {} -> {} }}", synchronizer, previous_phase, phase),
+        }
+    }
+}
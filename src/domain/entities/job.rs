@@ -0,0 +1,48 @@
+use std::fmt;
+use std::time::SystemTime;
+use serde::{Deserialize, Serialize};
+use crate::domain::entities::address::Address;
+
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum JobKind {
+    SwapBackfill { from_block: u64, to_block: u64 },
+    PairRefresh { pair_address: Address },
+    TokenRefresh { token_address: Address },
+    PairDiscovery,
+    SyncPause { synchronizer: String },
+    SyncResume { synchronizer: String },
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq)]
+#[serde(rename_all = "snake_case")]
+pub enum JobStatus {
+    Pending,
+    Running,
+    Completed,
+    Failed,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct Job {
+    pub id: String,
+    pub kind: JobKind,
+    pub status: JobStatus,
+    pub message: Option<String>,
+    pub created_at: SystemTime,
+    pub started_at: Option<SystemTime>,
+    pub finished_at: Option<SystemTime>,
+}
+
+impl fmt::Display for Job {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "Job {{ id: {}, kind: {:?}, status: {:?}, message: {:?} }}",
+            self.id,
+            self.kind,
+            self.status,
+            self.message
+        )
+    }
+}
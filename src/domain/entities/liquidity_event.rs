@@ -0,0 +1,137 @@
+use std::fmt;
+use std::time::SystemTime;
+use ethers::types::{Log, H160, H256, U256};
+use serde::{Deserialize, Serialize};
+use crate::domain::entities::address::Address;
+
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum LiquidityEventKind {
+    Mint,
+    Burn,
+}
+
+// Liquidity added (Mint) or removed (Burn) by LPs of a V2 pair, amounts of token0 (base) and token1 (quote)
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+pub struct LiquidityEvent {
+    pub pair_address: Address,
+    pub kind: LiquidityEventKind,
+    // caller of mint/burn, usually the router
+    pub sender: Address,
+    // receiver of the removed tokens, burns only
+    pub to: Option<Address>,
+    // transaction sender, the liquidity provider behind the router
+    pub origin: Option<Address>,
+    pub amount0: u128,
+    pub amount1: u128,
+    pub block_number: u64,
+    pub transaction_hash: Option<H256>,
+    pub log_index: Option<U256>,
+}
+
+impl LiquidityEvent {
+    // Mint(address indexed sender, uint amount0, uint amount1)
+    // Burn(address indexed sender, uint amount0, uint amount1, address indexed to)
+    pub fn decode(kind: LiquidityEventKind, log: &Log) -> Option<LiquidityEvent> {
+        let topics = if kind == LiquidityEventKind::Burn { 3 } else { 2 };
+        if log.topics.len() < topics || log.data.len() < 64 {
+            return None;
+        }
+        let word = |index: usize| {
+            let amount = U256::from_big_endian(&log.data[index * 32..(index + 1) * 32]);
+            u128::try_from(amount).unwrap_or(u128::MAX)
+        };
+
+        Some(LiquidityEvent {
+            pair_address: log.address.into(),
+            kind,
+            sender: H160::from(log.topics[1]).into(),
+            to: (kind == LiquidityEventKind::Burn).then(|| H160::from(log.topics[2]).into()),
+            origin: None,
+            amount0: word(0),
+            amount1: word(1),
+            block_number: log.block_number.map(|block| block.as_u64()).unwrap_or_default(),
+            transaction_hash: log.transaction_hash,
+            log_index: log.log_index,
+        })
+    }
+
+    // Same log, a backfilled range is indexed again
+    pub fn is_same_log(&self, other: &LiquidityEvent) -> bool {
+        self.transaction_hash == other.transaction_hash && self.log_index == other.log_index
+    }
+}
+
+impl fmt::Display for LiquidityEvent {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "LiquidityEvent {{ pair_address: {}, kind: {:?}, amounts: {}:{}, block_number: {}, origin: {:?} }}",
+            self.pair_address,
+            self.kind,
+            self.amount0,
+            self.amount1,
+            self.block_number,
+            self.origin
+        )
+    }
+}
+
+// Liquidity events of a tracked pair pruned past the rug-pull window, folded into totals
+#[derive(Debug, Serialize, Deserialize, Clone, Default, PartialEq)]
+pub struct LiquidityTotals {
+    // origin of the pair's first Mint once it is pruned
+    pub deployer: Option<Address>,
+    pub mints: u64,
+    pub burns: u64,
+    pub minted0: u128,
+    pub minted1: u128,
+    pub burned0: u128,
+    pub burned1: u128,
+    // highest pruned block, later logs up to it are already counted
+    pub last_block: u64,
+}
+
+impl LiquidityTotals {
+    // Events are folded in block order, the first Mint sets the deployer
+    pub fn add(&mut self, event: &LiquidityEvent) {
+        match event.kind {
+            LiquidityEventKind::Mint => {
+                if self.mints == 0 {
+                    self.deployer = event.origin;
+                }
+                self.mints += 1;
+                self.minted0 = self.minted0.saturating_add(event.amount0);
+                self.minted1 = self.minted1.saturating_add(event.amount1);
+            }
+            LiquidityEventKind::Burn => {
+                self.burns += 1;
+                self.burned0 = self.burned0.saturating_add(event.amount0);
+                self.burned1 = self.burned1.saturating_add(event.amount1);
+            }
+        }
+        self.last_block = std::cmp::max(self.last_block, event.block_number);
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum RugPullReason {
+    // a share of the liquidity was removed within the detection window
+    LiquidityDrop,
+    // the first liquidity provider removed liquidity
+    DeployerRemoval,
+}
+
+// Pair flagged by the rug-pull detector until rug_pull.flag_expiry_secs after the flagged burn
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+pub struct RugPull {
+    pub pair_address: Address,
+    pub reason: RugPullReason,
+    // share of the pair's liquidity removed
+    pub share: f64,
+    // burn the detection ended at
+    pub block_number: u64,
+    pub transaction_hash: Option<H256>,
+    pub detected_at: SystemTime,
+}
@@ -3,3 +3,13 @@ pub mod token;
 pub mod token_pair;
 pub mod update_log;
 pub mod swap_log;
+pub mod swap_aggregate;
+pub mod domain_event;
+pub mod alert;
+pub mod job;
+pub mod api_key;
+pub mod supervised_task;
+pub mod sync_state;
+pub mod token_list;
+pub mod address;
+pub mod liquidity_event;
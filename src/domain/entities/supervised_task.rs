@@ -0,0 +1,48 @@
+use std::fmt;
+use std::time::SystemTime;
+use serde::{Deserialize, Serialize};
+
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq)]
+#[serde(rename_all = "snake_case")]
+pub enum TaskState {
+    Running,
+    // failed, waiting for the backoff before the next start
+    Restarting,
+    Finished,
+    Stopped,
+}
+
+// How a supervised task ends on shutdown
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq)]
+#[serde(rename_all = "snake_case")]
+pub enum ShutdownMode {
+    // watches the shutdown signal itself and returns once its in-flight work is done
+    Drain,
+    // dropped at its next await point
+    Cancel,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct SupervisedTask {
+    pub name: String,
+    pub state: TaskState,
+    pub shutdown_mode: ShutdownMode,
+    pub restarts: u64,
+    pub last_error: Option<String>,
+    pub last_failed_at: Option<SystemTime>,
+    pub started_at: SystemTime,
+    pub next_restart_at: Option<SystemTime>,
+}
+
+impl fmt::Display for SupervisedTask {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "SupervisedTask {{ name: {}, state: {:?}, restarts: {}, last_error: {:?} }}",
+            self.name,
+            self.state,
+            self.restarts,
+            self.last_error
+        )
+    }
+}
@@ -0,0 +1,106 @@
+use std::collections::BTreeSet;
+use std::fmt;
+use ethers::types::{H160, U256};
+use serde::{Deserialize, Serialize};
+use crate::domain::entities::address::Address;
+use crate::domain::entities::swap_log::SwapLog;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum AggregateInterval {
+    Hourly,
+    Daily,
+}
+
+impl AggregateInterval {
+    pub fn as_str(&self) -> &str {
+        match self {
+            AggregateInterval::Hourly => "hourly",
+            AggregateInterval::Daily => "daily",
+        }
+    }
+
+    pub fn seconds(&self) -> u64 {
+        match self {
+            AggregateInterval::Hourly => 3_600,
+            AggregateInterval::Daily => 86_400,
+        }
+    }
+
+    // Start of the UTC hour or day containing the timestamp
+    pub fn bucket_start(&self, timestamp: u64) -> u64 {
+        timestamp - timestamp % self.seconds()
+    }
+}
+
+// Swaps of one pair compacted into an hour or a day, amounts are raw token0/token1 units
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SwapAggregate {
+    pub pair_address: Address,
+    pub protocol_id: String,
+    pub interval: AggregateInterval,
+    pub bucket_start: u64,
+    pub first_block: u64,
+    pub last_block: u64,
+    pub swaps: i64,
+    pub amount0_in: U256,
+    pub amount1_in: U256,
+    pub amount0_out: U256,
+    pub amount1_out: U256,
+    // swap recipients, kept so later compactions of the same bucket count unique traders correctly
+    pub traders: BTreeSet<H160>,
+}
+
+impl SwapAggregate {
+    pub fn new(pair_address: Address, protocol_id: String, interval: AggregateInterval, bucket_start: u64) -> Self {
+        SwapAggregate {
+            pair_address,
+            protocol_id,
+            interval,
+            bucket_start,
+            first_block: u64::MAX,
+            last_block: 0,
+            swaps: 0,
+            amount0_in: U256::zero(),
+            amount1_in: U256::zero(),
+            amount0_out: U256::zero(),
+            amount1_out: U256::zero(),
+            traders: BTreeSet::new(),
+        }
+    }
+
+    pub fn key(pair_address: &Address, interval: AggregateInterval, bucket_start: u64) -> String {
+        format!("{}:{}:{}", pair_address, interval.as_str(), bucket_start)
+    }
+
+    pub fn add(&mut self, swap_log: &SwapLog, block: u64) {
+        self.swaps += 1;
+        self.first_block = std::cmp::min(self.first_block, block);
+        self.last_block = std::cmp::max(self.last_block, block);
+        if let Some(swap) = swap_log.decode() {
+            self.amount0_in = self.amount0_in.saturating_add(swap.amount0_in);
+            self.amount1_in = self.amount1_in.saturating_add(swap.amount1_in);
+            self.amount0_out = self.amount0_out.saturating_add(swap.amount0_out);
+            self.amount1_out = self.amount1_out.saturating_add(swap.amount1_out);
+            self.traders.insert(swap.to);
+        }
+    }
+
+    pub fn unique_traders(&self) -> usize {
+        self.traders.len()
+    }
+}
+
+impl fmt::Display for SwapAggregate {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "SwapAggregate {{ pair_address: {}, interval: {}, bucket_start: {}, swaps: {}, unique_traders: {} }}",
+            self.pair_address,
+            self.interval.as_str(),
+            self.bucket_start,
+            self.swaps,
+            self.unique_traders()
+        )
+    }
+}
@@ -1,10 +1,24 @@
 use std::fmt;
-use ethers::types::{Address, Bytes, H256, U256, U64};
+use ethers::types::{Bytes, Log, H160, H256, U256, U64};
 use serde::{Deserialize, Serialize};
+use crate::domain::entities::address::Address;
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct DecodedSwap {
+    pub sender: H160,
+    pub to: H160,
+    pub amount0_in: U256,
+    pub amount1_in: U256,
+    pub amount0_out: U256,
+    pub amount1_out: U256,
+}
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct SwapLog {
     pub address: Address,
+    // protocol of the factory that created the pair, empty while quarantined
+    #[serde(default)]
+    pub protocol_id: String,
     pub topics: Vec<H256>,
     pub data: Bytes,
     pub block_hash: Option<H256>,
@@ -15,6 +29,23 @@ pub struct SwapLog {
     pub removed: Option<bool>,
 }
 
+impl From<&Log> for SwapLog {
+    fn from(log: &Log) -> Self {
+        SwapLog {
+            address: log.address.into(),
+            protocol_id: String::new(),
+            topics: log.topics.clone(),
+            data: log.data.clone(),
+            block_hash: log.block_hash,
+            block_number: log.block_number,
+            transaction_hash: log.transaction_hash,
+            transaction_index: log.transaction_index,
+            log_index: log.log_index,
+            removed: log.removed,
+        }
+    }
+}
+
 impl fmt::Display for SwapLog {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         write!(
@@ -26,4 +57,28 @@ impl fmt::Display for SwapLog {
             self.log_index
         )
     }
-}
\ No newline at end of file
+}
+
+impl SwapLog {
+    // Same log, delivered again by an overlapping range or a resumed subscription
+    pub fn is_same_log(&self, other: &SwapLog) -> bool {
+        self.transaction_hash == other.transaction_hash && self.log_index == other.log_index
+    }
+
+    // Swap(address indexed sender, uint amount0In, uint amount1In, uint amount0Out, uint amount1Out, address indexed to)
+    pub fn decode(&self) -> Option<DecodedSwap> {
+        if self.topics.len() < 3 || self.data.len() < 128 {
+            return None;
+        }
+        let word = |index: usize| U256::from_big_endian(&self.data[index * 32..(index + 1) * 32]);
+
+        Some(DecodedSwap {
+            sender: H160::from(self.topics[1]),
+            to: H160::from(self.topics[2]),
+            amount0_in: word(0),
+            amount1_in: word(1),
+            amount0_out: word(2),
+            amount1_out: word(3),
+        })
+    }
+}
@@ -0,0 +1,98 @@
+use std::fmt;
+use std::time::SystemTime;
+use serde::{Deserialize, Serialize};
+
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq)]
+#[serde(rename_all = "snake_case")]
+pub enum SyncPhase {
+    // not started, paused or stopped
+    Idle,
+    // extracting block ranges up to a target block
+    Backfilling,
+    // caught up, following the chain head
+    Following,
+    // running on a fallback after an error, e.g. polling after a dropped subscription
+    Degraded,
+    // exited with an error, the supervisor restarts it
+    Failed,
+}
+
+impl SyncPhase {
+    // Allowed moves, every phase may go to Failed and a failed synchronizer only starts over
+    pub fn can_transition_to(&self, next: SyncPhase) -> bool {
+        match (self, next) {
+            (_, SyncPhase::Failed) => true,
+            (SyncPhase::Failed, next) => matches!(next, SyncPhase::Idle | SyncPhase::Backfilling),
+            (current, next) => *current != next,
+        }
+    }
+}
+
+impl fmt::Display for SyncPhase {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let phase = match self {
+            SyncPhase::Idle => "idle",
+            SyncPhase::Backfilling => "backfilling",
+            SyncPhase::Following => "following",
+            SyncPhase::Degraded => "degraded",
+            SyncPhase::Failed => "failed",
+        };
+        write!(f, "{}", phase)
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct SyncState {
+    pub synchronizer: String,
+    pub phase: SyncPhase,
+    // when the current phase was entered
+    pub since: SystemTime,
+    pub updated_at: SystemTime,
+    pub block: u64,
+    pub target_block: u64,
+    pub last_error: Option<String>,
+    pub last_error_at: Option<SystemTime>,
+    // last time the synchronizer reached its target block
+    pub caught_up_at: Option<SystemTime>,
+}
+
+impl SyncState {
+    pub fn new(synchronizer: &str) -> Self {
+        let now = SystemTime::now();
+        SyncState {
+            synchronizer: synchronizer.to_string(),
+            phase: SyncPhase::Idle,
+            since: now,
+            updated_at: now,
+            block: 0,
+            target_block: 0,
+            last_error: None,
+            last_error_at: None,
+            caught_up_at: None,
+        }
+    }
+
+    // Dependants may read the stored data: caught up with the head, or stopped by an error they can report.
+    // Idle has not extracted anything yet.
+    pub fn is_settled(&self) -> bool {
+        match self.phase {
+            SyncPhase::Following => true,
+            SyncPhase::Failed => self.last_error.is_some(),
+            _ => false,
+        }
+    }
+}
+
+impl fmt::Display for SyncState {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "SyncState {{ synchronizer: {}, phase: {}, block: {}, target_block: {}, last_error: {:?} }}",
+            self.synchronizer,
+            self.phase,
+            self.block,
+            self.target_block,
+            self.last_error
+        )
+    }
+}
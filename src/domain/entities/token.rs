@@ -1,27 +1,39 @@
 use std::collections::HashMap;
 use std::time::SystemTime;
 use serde::{Deserialize, Serialize};
+use crate::domain::entities::address::Address;
+use crate::domain::entities::token_list::TokenListEntry;
 use crate::domain::entities::token_pair::TokenPair;
 
-#[derive(Debug, Serialize, Deserialize,Clone)]
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
 pub struct Token{
-    pub address: String,
+    pub address: Address,
     pub protocol_id: String,
     pub symbol: String,
     pub name: String,
     pub decimals: String,
     pub retrieved_at: SystemTime,
     pub updated_at: SystemTime,
-    pub pairs: HashMap<String, TokenPair>,
-    pub active_pairs: HashMap<String, TokenPair>,
+    pub pairs: HashMap<Address, TokenPair>,
+    pub active_pairs: HashMap<Address, TokenPair>,
     pub swaps: i64,
-    pub high_risk: bool
+    pub high_risk: bool,
+    // RPC providers disagreed on this token's metadata in quorum mode
+    #[serde(default)]
+    pub quorum_disputed: bool,
+    // listed in an imported token list, name and symbol come from the list
+    #[serde(default)]
+    pub verified: bool,
+    #[serde(default)]
+    pub logo_uri: Option<String>,
+    #[serde(default)]
+    pub tags: Vec<String>,
 }
 
 impl Default for Token {
     fn default() -> Self {
         Token {
-            address: String::new(),
+            address: Address::default(),
             protocol_id: String::new(),
             symbol: String::new(),
             name: String::new(),
@@ -32,6 +44,20 @@ impl Default for Token {
             active_pairs: HashMap::new(),
             swaps: 0,
             high_risk: false,
+            quorum_disputed: false,
+            verified: false,
+            logo_uri: None,
+            tags: vec![],
         }
     }
+}
+
+impl Token {
+    pub fn apply_token_list_entry(&mut self, entry: &TokenListEntry) {
+        self.verified = true;
+        self.name = entry.name.clone();
+        self.symbol = entry.symbol.clone();
+        self.logo_uri = entry.logo_uri.clone();
+        self.tags = entry.tags.clone();
+    }
 }
\ No newline at end of file
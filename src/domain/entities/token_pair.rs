@@ -1,34 +1,43 @@
 use std::fmt;
 use std::time::SystemTime;
 use serde::{Deserialize, Serialize};
+use crate::domain::entities::address::Address;
 
-#[derive(Debug, Serialize, Deserialize,Clone)]
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
 pub struct TokenPair{
-    pub token_pair_address: String,
+    pub token_pair_address: Address,
     pub protocol_id: String,
-    pub base_address: String,
+    pub base_address: Address,
     pub base_reserve: u128,
-    pub quote_address: String,
+    pub quote_address: Address,
     pub quote_reserve: u128,
     pub reserve_block: u32,
+    // chain head the reserves were read at or after, 0 when unknown
+    #[serde(default)]
+    pub reserves_read_block: u64,
     pub swaps: i64,
     pub retrieved_at: SystemTime,
-    pub updated_at: SystemTime
+    pub updated_at: SystemTime,
+    // RPC providers disagreed on this pair's data in quorum mode
+    #[serde(default)]
+    pub quorum_disputed: bool
 }
 
 impl Default for TokenPair {
     fn default() -> Self {
         TokenPair {
-            token_pair_address: String::new(),
+            token_pair_address: Address::default(),
             protocol_id: String::new(),
-            base_address: String::new(),
+            base_address: Address::default(),
             base_reserve: 0,
-            quote_address: String::new(),
+            quote_address: Address::default(),
             quote_reserve: 0,
             reserve_block: 0,
+            reserves_read_block: 0,
             swaps: 0,
             retrieved_at: SystemTime::now(),
             updated_at: SystemTime::now(),
+            quorum_disputed: false,
         }
     }
 }
@@ -45,9 +54,11 @@ impl fmt::Display for TokenPair {
             quote_address: {},
             quote_reserve: {},
             reserve_block: {},
+            reserves_read_block: {},
             swaps: {},
             retrieved_at: {:?},
-            updated_at: {:?}
+            updated_at: {:?},
+            quorum_disputed: {}
         }}",
             self.token_pair_address,
             self.protocol_id,
@@ -56,9 +67,11 @@ impl fmt::Display for TokenPair {
             self.quote_address,
             self.quote_reserve,
             self.reserve_block,
+            self.reserves_read_block,
             self.swaps,
             self.retrieved_at,
-            self.updated_at
+            self.updated_at,
+            self.quorum_disputed
         )
     }
 }
\ No newline at end of file
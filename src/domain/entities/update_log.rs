@@ -9,7 +9,10 @@ pub struct UpdateLog{
     pub created_at: SystemTime,
     pub last_update_at: SystemTime,
     pub start_block: u64,
-    pub end_block: u64
+    pub end_block: u64,
+    // factory pairs discovered so far, the next refresh continues at this allPairs index
+    #[serde(default)]
+    pub pair_count: u64,
 }
 
 // Implementacja Default
@@ -21,6 +24,7 @@ impl Default for UpdateLog {
             last_update_at: SystemTime::now(),
             start_block: 0,
             end_block: 0,
+            pair_count: 0,
         }
     }
 }
@@ -32,12 +36,13 @@ impl fmt::Display for UpdateLog {
 
         write!(
             f,
-            "UpdateLog {{ protocol_id: {}, created_at: {}, last_update_at: {}, start_block: {}, end_block: {} }}",
+            "UpdateLog {{ protocol_id: {}, created_at: {}, last_update_at: {}, start_block: {}, end_block: {}, pair_count: {} }}",
             self.protocol_id,
             created_at.format("%Y-%m-%d %H:%M:%S"),
             last_update_at.format("%Y-%m-%d %H:%M:%S"),
             self.start_block,
             self.end_block,
+            self.pair_count,
         )
     }
 }
\ No newline at end of file
@@ -0,0 +1,111 @@
+use std::collections::{HashMap, VecDeque};
+use std::sync::{Arc, Mutex};
+use std::time::SystemTime;
+use async_trait::async_trait;
+use crate::config::ConfigHandle;
+use crate::domain::entities::alert::{Alert, AlertSeverity};
+use crate::domain::entities::domain_event::DomainEvent;
+use crate::domain::entities::sync_state::SyncPhase;
+use crate::domain::services::event_bus_service::{receive_event, EventBusService, EventBusServiceTrait};
+
+// Most recent alerts kept for the admin API
+const MAX_ALERTS: usize = 200;
+
+#[derive(Default)]
+pub struct AlertStorage {
+    pub alerts: VecDeque<Alert>,
+    pub sequence: u64,
+}
+
+#[derive(Clone)]
+pub struct AlertService {
+    config: ConfigHandle,
+    event_bus: EventBusService,
+    storage: Arc<Mutex<AlertStorage>>,
+}
+
+impl AlertService {
+    pub fn new(config: ConfigHandle, event_bus: EventBusService) -> Self {
+        AlertService {
+            config,
+            event_bus,
+            storage: Arc::new(Mutex::new(AlertStorage::default())),
+        }
+    }
+}
+
+#[async_trait]
+pub trait AlertServiceTrait: Send + Sync {
+    async fn run(&self);
+    fn raise(&self, severity: AlertSeverity, kind: &str, subject: String, message: String);
+    fn get_alerts(&self) -> Vec<Alert>;
+}
+
+#[async_trait]
+impl AlertServiceTrait for AlertService {
+    // Turns risk changes, rug pulls, sync lag and degraded synchronizers into alerts, lag is reported once when it starts and once when it ends
+    async fn run(&self) {
+        let mut receiver = self.event_bus.subscribe();
+        let mut lagging: HashMap<String, bool> = HashMap::new();
+        log::info!("Alerting subscribed to domain events");
+        while let Some(event) = receive_event(&self.event_bus, &mut receiver).await {
+            match event {
+                DomainEvent::RiskChanged { address, symbol, high_risk: true } => self.raise(
+                    AlertSeverity::Warning, "token_high_risk", address.to_string(),
+                    format!("Token {} became high risk", symbol)),
+                DomainEvent::RiskChanged { address, symbol, high_risk: false } => self.raise(
+                    AlertSeverity::Info, "token_risk_cleared", address.to_string(),
+                    format!("Token {} is no longer high risk", symbol)),
+                DomainEvent::RugPullDetected { pair_address, reason, share, block_number } => self.raise(
+                    AlertSeverity::Critical, "rug_pull", pair_address.to_string(),
+                    format!("Pair {} lost {:.0}% of its liquidity ({:?}) at block {}", pair_address, share * 100.0, reason, block_number)),
+                DomainEvent::SyncProgress { synchronizer, block, target_block } => {
+                    let lag = target_block.saturating_sub(block);
+                    let was_lagging = lagging.get(&synchronizer).copied().unwrap_or(false);
+                    let is_lagging = lag > self.config.get().sync.alert_sync_lag_blocks;
+                    if is_lagging && !was_lagging {
+                        self.raise(AlertSeverity::Warning, "sync_lagging", synchronizer.clone(),
+                            format!("{} sync is {} blocks behind block {}", synchronizer, lag, target_block));
+                    } else if !is_lagging && was_lagging {
+                        self.raise(AlertSeverity::Info, "sync_caught_up", synchronizer.clone(),
+                            format!("{} sync caught up at block {}", synchronizer, block));
+                    }
+                    lagging.insert(synchronizer, is_lagging);
+                }
+                //failures are raised by the task supervisor
+                DomainEvent::SyncStateChanged { synchronizer, phase: SyncPhase::Degraded, error, .. } => self.raise(
+                    AlertSeverity::Warning, "sync_degraded", synchronizer.clone(),
+                    format!("{} sync degraded: {}", synchronizer, error.unwrap_or_default())),
+                _ => {}
+            }
+        }
+    }
+
+    fn raise(&self, severity: AlertSeverity, kind: &str, subject: String, message: String) {
+        let mut storage = self.storage.lock().unwrap();
+        storage.sequence += 1;
+        let alert = Alert {
+            id: storage.sequence,
+            severity,
+            kind: kind.to_string(),
+            subject,
+            message,
+            raised_at: SystemTime::now(),
+        };
+        match severity {
+            AlertSeverity::Critical => log::error!("{}", alert),
+            AlertSeverity::Warning => log::warn!("{}", alert),
+            AlertSeverity::Info => log::info!("{}", alert),
+        }
+        if storage.alerts.len() >= MAX_ALERTS {
+            storage.alerts.pop_front();
+        }
+        storage.alerts.push_back(alert);
+    }
+
+    // Newest first
+    fn get_alerts(&self) -> Vec<Alert> {
+        let storage = self.storage.lock().unwrap();
+        storage.alerts.iter().rev().cloned().collect()
+    }
+}
@@ -0,0 +1,200 @@
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
+use std::time::{Instant, SystemTime};
+use chrono::{Duration, Utc};
+use crate::config::AppConfig;
+use crate::domain::entities::api_key::{ApiKey, ApiKeyUsage, ApiScope, StoredApiKey};
+
+pub struct TokenBucket {
+    pub tokens: f64,
+    pub last_refill: Instant,
+}
+
+#[derive(Default)]
+pub struct ApiKeyStorage {
+    pub keys: HashMap<String, ApiKey>,
+    pub buckets: HashMap<String, TokenBucket>,
+    pub usage: HashMap<String, ApiKeyUsage>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum AccessDenied {
+    MissingKey,
+    InvalidKey,
+    InsufficientScope,
+    RateLimited { retry_after_secs: u64 },
+    QuotaExceeded { retry_after_secs: u64 },
+}
+
+// API keys of one REST API with their rate limit buckets and usage, shared by its clones
+#[derive(Clone, Default)]
+pub struct ApiKeyService {
+    storage: Arc<Mutex<ApiKeyStorage>>,
+}
+
+impl ApiKeyService {
+    pub fn new() -> Self {
+        ApiKeyService::default()
+    }
+}
+
+fn insert_api_key(storage: &mut ApiKeyStorage, api_key: ApiKey) {
+    storage.buckets.insert(api_key.key.clone(), TokenBucket {
+        tokens: api_key.burst as f64,
+        last_refill: Instant::now(),
+    });
+    storage.usage
+        .entry(api_key.key.clone())
+        .or_insert_with(|| ApiKeyUsage::new(api_key.name.clone()));
+    storage.keys.insert(api_key.key.clone(), api_key);
+}
+
+#[allow(dead_code)]
+pub trait ApiKeyServiceTrait {
+    fn init(&self, config: &AppConfig);
+    fn add_api_key(&self, api_key: ApiKey);
+    fn create_api_key(&self, api_key: ApiKey) -> Result<(), String>;
+    fn restore_api_keys(&self, stored: Vec<StoredApiKey>);
+    fn remove_api_key(&self, name: String) -> Option<ApiKey>;
+    fn get_api_keys(&self) -> Vec<ApiKey>;
+    fn authorize(&self, key: Option<String>, scope: ApiScope) -> Result<ApiKey, AccessDenied>;
+    fn get_usage(&self) -> Vec<ApiKeyUsage>;
+}
+
+impl ApiKeyServiceTrait for ApiKeyService {
+    fn init(&self, config: &AppConfig) {
+        for key_config in &config.api.keys {
+            let scopes: Vec<ApiScope> = key_config.scopes.iter()
+                .filter_map(|scope| match scope.as_str() {
+                    "read" => Some(ApiScope::Read),
+                    "admin" => Some(ApiScope::Admin),
+                    _ => {
+                        log::warn!("API key {}: unknown scope {} ignored", key_config.name, scope);
+                        None
+                    }
+                })
+                .collect();
+
+            self.add_api_key(ApiKey {
+                name: key_config.name.clone(),
+                key: key_config.key.clone(),
+                scopes,
+                rate_limit_per_second: key_config.rate_limit_per_second,
+                burst: key_config.burst,
+                daily_quota: key_config.daily_quota,
+                created_at: SystemTime::now(),
+            });
+        }
+
+        log::info!("API keys initialized: {}", self.get_api_keys().len());
+    }
+
+    fn add_api_key(&self, api_key: ApiKey) {
+        insert_api_key(&mut self.storage.lock().unwrap(), api_key);
+    }
+
+    // Adds a key unless its name or key is taken, checked under the same lock
+    fn create_api_key(&self, api_key: ApiKey) -> Result<(), String> {
+        let mut storage = self.storage.lock().unwrap();
+        if storage.keys.values().any(|existing| existing.name == api_key.name) {
+            return Err(format!("API key {} already exists", api_key.name));
+        }
+        if storage.keys.contains_key(&api_key.key) {
+            return Err("key is used by another API key".to_string());
+        }
+        insert_api_key(&mut storage, api_key);
+        Ok(())
+    }
+
+    // Keys added through the admin API before the restart, the configured ones take precedence
+    fn restore_api_keys(&self, stored: Vec<StoredApiKey>) {
+        let count = stored.len();
+        for api_key in stored {
+            let name = api_key.name.clone();
+            if let Err(e) = self.create_api_key(api_key.into()) {
+                log::warn!("Stored API key {} not restored: {}", name, e);
+            }
+        }
+        log::info!("Stored API keys restored: {}", count);
+    }
+
+    fn remove_api_key(&self, name: String) -> Option<ApiKey> {
+        let mut storage = self.storage.lock().unwrap();
+        let key = storage.keys.values().find(|api_key| api_key.name == name)?.key.clone();
+        storage.buckets.remove(&key);
+        storage.usage.remove(&key);
+        storage.keys.remove(&key)
+    }
+
+    fn get_api_keys(&self) -> Vec<ApiKey> {
+        let storage = self.storage.lock().unwrap();
+        storage.keys.values().cloned().collect()
+    }
+
+    fn authorize(&self, key: Option<String>, scope: ApiScope) -> Result<ApiKey, AccessDenied> {
+        let key = key.ok_or(AccessDenied::MissingKey)?;
+        let mut storage = self.storage.lock().unwrap();
+        let storage = &mut *storage;
+
+        let api_key = storage.keys.get(&key).cloned().ok_or(AccessDenied::InvalidKey)?;
+        if !api_key.scopes.iter().any(|granted| granted.allows(scope)) {
+            return Err(AccessDenied::InsufficientScope);
+        }
+
+        let usage = storage.usage
+            .entry(key.clone())
+            .or_insert_with(|| ApiKeyUsage::new(api_key.name.clone()));
+        usage.total_requests += 1;
+        usage.last_used_at = Some(SystemTime::now());
+
+        // Daily quota, reset at UTC midnight
+        let now = Utc::now();
+        let today = now.format("%Y-%m-%d").to_string();
+        if usage.quota_day != today {
+            usage.quota_day = today;
+            usage.quota_used = 0;
+        }
+        if let Some(daily_quota) = api_key.daily_quota {
+            if usage.quota_used >= daily_quota {
+                usage.quota_rejected_requests += 1;
+                let next_day = (now.date_naive() + Duration::days(1))
+                    .and_hms_opt(0, 0, 0)
+                    .unwrap()
+                    .and_utc();
+                let retry_after_secs = (next_day - now).num_seconds().max(1) as u64;
+                return Err(AccessDenied::QuotaExceeded { retry_after_secs });
+            }
+        }
+
+        // Token bucket: refill proportionally to the elapsed time, capped by burst
+        let bucket = storage.buckets.entry(key).or_insert_with(|| TokenBucket {
+            tokens: api_key.burst as f64,
+            last_refill: Instant::now(),
+        });
+        let elapsed = bucket.last_refill.elapsed().as_secs_f64();
+        bucket.tokens = (bucket.tokens + elapsed * api_key.rate_limit_per_second).min(api_key.burst as f64);
+        bucket.last_refill = Instant::now();
+
+        if bucket.tokens < 1.0 {
+            usage.rate_limited_requests += 1;
+            let retry_after_secs = if api_key.rate_limit_per_second > 0.0 {
+                ((1.0 - bucket.tokens) / api_key.rate_limit_per_second).ceil().max(1.0) as u64
+            } else {
+                60
+            };
+            return Err(AccessDenied::RateLimited { retry_after_secs });
+        }
+
+        bucket.tokens -= 1.0;
+        usage.quota_used += 1;
+
+        Ok(api_key)
+    }
+
+    fn get_usage(&self) -> Vec<ApiKeyUsage> {
+        let storage = self.storage.lock().unwrap();
+        let mut usage: Vec<ApiKeyUsage> = storage.usage.values().cloned().collect();
+        usage.sort_by(|a, b| a.name.cmp(&b.name));
+        usage
+    }
+}
@@ -1,77 +1,327 @@
-use std::collections::HashMap;
+use std::collections::{HashMap, HashSet};
+use std::error::Error;
+use std::fs::{self, File};
+use std::io::{BufReader, BufWriter, Write};
+use std::path::Path;
 use std::sync::{Arc, Mutex};
-use once_cell::sync::Lazy;
+use arc_swap::ArcSwap;
+use std::time::SystemTime;
+use serde::{Deserialize, Serialize};
+use crate::domain::entities::address::Address;
+use crate::domain::entities::api_key::StoredApiKey;
+use crate::domain::entities::liquidity_event::{LiquidityEvent, LiquidityTotals, RugPull};
 use crate::domain::entities::protocol::Protocol;
 use crate::domain::entities::token::Token;
+use crate::domain::entities::token_list::TokenListEntry;
 use crate::domain::entities::token_pair::TokenPair;
 use crate::domain::entities::update_log::UpdateLog;
 use log;
 use crate::domain::entities::swap_log::SwapLog;
+use crate::domain::entities::swap_aggregate::{AggregateInterval, SwapAggregate};
+use ethers::types::{H160, H256, U256};
 
-pub static DATA_STORAGE: Lazy<Arc<Mutex<DataStorage>>> = Lazy::new(|| {
-    Arc::new(Mutex::new(DataStorage::default()))
-});
-
+// Persistent maps, a snapshot copy shares all unchanged entries with the previous one
+#[derive(Clone, Serialize, Deserialize)]
 pub struct DataStorage {
-    pub tokens: HashMap<String, Token>,
-    pub protocols: HashMap<String, Protocol>,
-    pub token_pairs: HashMap<String, TokenPair>,
-    pub update_logs: HashMap<String, UpdateLog>,
-    pub swap_logs: HashMap<String, Vec<SwapLog>>,
+    pub tokens: im::HashMap<Address, Token>,
+    pub protocols: im::HashMap<String, Protocol>,
+    pub token_pairs: im::HashMap<Address, TokenPair>,
+    pub update_logs: im::HashMap<String, UpdateLog>,
+    pub swap_logs: im::HashMap<Address, im::Vector<SwapLog>>,
+    // swap logs of addresses not known as pairs of a registered factory yet
+    #[serde(default)]
+    pub quarantined_swap_logs: im::HashMap<Address, im::Vector<SwapLog>>,
+    // pairs attributed to other registered factories, pair -> protocol id
+    #[serde(default)]
+    pub pair_protocols: im::HashMap<Address, String>,
+    // hourly and daily aggregates of compacted swap logs, see SwapAggregate::key
+    #[serde(default)]
+    pub swap_aggregates: im::HashMap<String, SwapAggregate>,
+    // (pair, interval) -> bucket start -> swap_aggregates key, rebuilt when a snapshot is loaded
+    #[serde(skip)]
+    pub swap_aggregate_index: im::HashMap<(Address, AggregateInterval), im::OrdMap<u64, String>>,
+    // pair -> last block whose swap logs were compacted, later logs up to it are already counted
+    #[serde(default)]
+    pub compacted_blocks: im::HashMap<Address, u64>,
+    // pair -> swaps folded into its daily aggregates
+    #[serde(default)]
+    pub compacted_swaps: im::HashMap<Address, i64>,
+    // token -> metadata of imported token lists, applied to the token whenever it is stored
+    #[serde(default)]
+    pub token_list_entries: im::HashMap<Address, TokenListEntry>,
+    // Mint and Burn events within the rug-pull window, see LiquidityService::prune_liquidity_events
+    #[serde(default)]
+    pub liquidity_events: im::HashMap<Address, im::Vector<LiquidityEvent>>,
+    // tracked pair -> liquidity events pruned past the rug-pull window
+    #[serde(default)]
+    pub liquidity_totals: im::HashMap<Address, LiquidityTotals>,
+    // pairs flagged by the rug-pull detector
+    #[serde(default)]
+    pub rug_pulls: im::HashMap<Address, RugPull>,
+    // name -> API key added through the admin API
+    #[serde(default)]
+    pub api_keys: im::HashMap<String, StoredApiKey>,
+    // protocol id -> last block whose swap logs are all stored, a restarted swap sync resumes after it
+    #[serde(default)]
+    pub swap_checkpoints: im::HashMap<String, u64>,
+    pub version: u64,
+    pub last_modified: SystemTime,
 }
 
 impl Default for DataStorage {
     fn default() -> Self {
         DataStorage {
-            tokens: HashMap::new(),
-            protocols: HashMap::new(),
-            token_pairs: HashMap::new(),
-            update_logs: HashMap::new(),
-            swap_logs: HashMap::new(),
+            tokens: im::HashMap::new(),
+            protocols: im::HashMap::new(),
+            token_pairs: im::HashMap::new(),
+            update_logs: im::HashMap::new(),
+            swap_logs: im::HashMap::new(),
+            quarantined_swap_logs: im::HashMap::new(),
+            pair_protocols: im::HashMap::new(),
+            swap_aggregates: im::HashMap::new(),
+            swap_aggregate_index: im::HashMap::new(),
+            compacted_blocks: im::HashMap::new(),
+            compacted_swaps: im::HashMap::new(),
+            token_list_entries: im::HashMap::new(),
+            liquidity_events: im::HashMap::new(),
+            liquidity_totals: im::HashMap::new(),
+            rug_pulls: im::HashMap::new(),
+            api_keys: im::HashMap::new(),
+            swap_checkpoints: im::HashMap::new(),
+            version: 0,
+            last_modified: SystemTime::now(),
         }
     }
 }
 
-pub struct DataStorageService;
+impl DataStorage {
+    // Every write moves the data version forward, readers use it to detect changes
+    pub fn touch(&mut self) {
+        self.version += 1;
+        self.last_modified = SystemTime::now();
+    }
+
+    // Aggregate of the pair's bucket, created empty and indexed when missing
+    pub fn swap_aggregate_mut(&mut self, pair_address: Address, protocol_id: &str, interval: AggregateInterval,
+                              bucket_start: u64) -> &mut SwapAggregate {
+        let key = SwapAggregate::key(&pair_address, interval, bucket_start);
+        self.swap_aggregate_index.entry((pair_address, interval)).or_default().insert(bucket_start, key.clone());
+        self.swap_aggregates.entry(key)
+            .or_insert_with(|| SwapAggregate::new(pair_address, protocol_id.to_string(), interval, bucket_start))
+    }
+
+    // Keeps the aggregates matching keep, returns how many were removed
+    pub fn retain_swap_aggregates(&mut self, keep: impl Fn(&SwapAggregate) -> bool) -> usize {
+        let removed: Vec<(String, SwapAggregate)> = self.swap_aggregates.iter()
+            .filter(|(_, aggregate)| !keep(aggregate))
+            .map(|(key, aggregate)| (key.clone(), aggregate.clone()))
+            .collect();
+        for (key, aggregate) in &removed {
+            self.swap_aggregates.remove(key);
+            let index_key = (aggregate.pair_address, aggregate.interval);
+            if let Some(buckets) = self.swap_aggregate_index.get_mut(&index_key) {
+                buckets.remove(&aggregate.bucket_start);
+                if buckets.is_empty() {
+                    self.swap_aggregate_index.remove(&index_key);
+                }
+            }
+        }
+        removed.len()
+    }
+
+    pub fn index_swap_aggregates(&mut self) {
+        let mut index: im::HashMap<(Address, AggregateInterval), im::OrdMap<u64, String>> = im::HashMap::new();
+        for (key, aggregate) in self.swap_aggregates.iter() {
+            index.entry((aggregate.pair_address, aggregate.interval)).or_default().insert(aggregate.bucket_start, key.clone());
+        }
+        self.swap_aggregate_index = index;
+    }
+
+    pub fn advance_swap_checkpoint(&mut self, protocol_id: &str, block: u64) {
+        let checkpoint = self.swap_checkpoints.entry(protocol_id.to_string()).or_default();
+        *checkpoint = std::cmp::max(*checkpoint, block);
+    }
+
+    // Stored swap logs of a pair plus the ones compacted into its aggregates
+    pub fn swap_count(&self, key: Address) -> i64 {
+        let compacted = self.compacted_swaps.get(&key).copied().unwrap_or_default();
+        self.swap_logs.get(&key).map_or(0, |logs| logs.len() as i64) + compacted
+    }
+}
+
+// Estimated heap use of the storage collections, entry counts and sizes in bytes
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct StorageMemoryUsage {
+    pub tokens: usize,
+    pub token_pairs: usize,
+    pub swap_logs: usize,
+    pub quarantined_swap_logs: usize,
+    pub swap_aggregates: usize,
+    pub liquidity_events: usize,
+    pub tokens_bytes: usize,
+    pub token_pairs_bytes: usize,
+    pub swap_logs_bytes: usize,
+    pub quarantined_swap_logs_bytes: usize,
+    pub swap_aggregates_bytes: usize,
+    pub liquidity_events_bytes: usize,
+    pub estimated_total_bytes: usize,
+    // resident set size of the whole process, where the platform reports it
+    pub process_rss_bytes: Option<u64>,
+}
+
+fn swap_log_bytes(swap_log: &SwapLog) -> usize {
+    std::mem::size_of::<Address>() + std::mem::size_of::<SwapLog>()
+        + swap_log.topics.len() * std::mem::size_of::<H256>()
+        + swap_log.data.len()
+        + swap_log.protocol_id.len()
+}
+
+fn process_rss_bytes() -> Option<u64> {
+    //"VmRSS:    123456 kB", independent of the page size
+    let status = fs::read_to_string("/proc/self/status").ok()?;
+    let kilobytes: u64 = status.lines()
+        .find_map(|line| line.strip_prefix("VmRSS:"))?
+        .split_whitespace()
+        .next()?
+        .parse()
+        .ok()?;
+    Some(kilobytes * 1024)
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct DataVersion {
+    pub version: u64,
+    pub last_modified: SystemTime,
+}
+
+struct StorageState {
+    // published read model: readers load the current snapshot without locking, writers publish a new one
+    snapshot: ArcSwap<DataStorage>,
+    // serializes writers, each applies its update to a copy of the latest snapshot
+    writer: Mutex<()>,
+}
+
+// Handle to one storage instance, clones share the same data
+#[derive(Clone)]
+pub struct DataStorageService {
+    state: Arc<StorageState>,
+}
+
+impl Default for DataStorageService {
+    fn default() -> Self {
+        Self::new()
+    }
+}
 
 #[allow(dead_code)]
 pub trait DataStorageTrait {
-    fn get_instance() -> Arc<Mutex<DataStorage>>;
+    fn get_snapshot(&self) -> Arc<DataStorage>;
+    fn write_batch<R>(&self, update: impl FnOnce(&mut DataStorage) -> R) -> R;
     fn init(&self);
+    fn get_data_version(&self) -> DataVersion;
+    fn save_snapshot(&self, path: &str) -> Result<(), Box<dyn Error>>;
+    fn load_snapshot(&self, path: &str) -> Result<bool, Box<dyn Error>>;
 
-    fn add_token(&self, key: String, token: Token);
-    fn get_token(&self, key: String) -> Option<Token>;
-    fn get_tokens(&self) -> HashMap<String, Token>;
+    fn add_token(&self, key: Address, token: Token);
+    fn get_token(&self, key: Address) -> Option<Token>;
+    fn get_tokens(&self) -> HashMap<Address, Token>;
     fn get_tokens_size(&self) -> i64;
+    fn get_token_keys(&self) -> Vec<Address>;
+    fn get_tokens_by_keys(&self, keys: &[Address]) -> Vec<Token>;
+    fn add_token_list_entries(&self, entries: Vec<(Address, TokenListEntry)>) -> i64;
+    fn get_token_list_entry(&self, key: Address) -> Option<TokenListEntry>;
 
     fn add_protocol(&self, key: String, protocol: Protocol);
     fn get_protocol(&self, key: String) -> Option<Protocol>;
     fn get_protocols(&self) -> HashMap<String, Protocol>;
     fn get_protocols_size(&self) -> i64;
 
-    fn add_token_pair(&self, key: String, token_pair: TokenPair);
-    fn get_token_pair(&self, key: String)-> Option<TokenPair>;
-    fn get_token_pairs(&self) -> HashMap<String, TokenPair>;
+    fn add_token_pair(&self, key: Address, token_pair: TokenPair);
+    fn get_token_pair(&self, key: Address)-> Option<TokenPair>;
+    fn contains_token_pair(&self, key: Address) -> bool;
+    fn get_token_pairs(&self) -> HashMap<Address, TokenPair>;
     fn get_token_pairs_size(&self) -> i64;
-    fn get_token_pairs_by_address(&self, address: String) -> HashMap<String, TokenPair>;
-    fn get_non_zero_reserve_token_pairs_by_address(&self, address: String) -> HashMap<String, TokenPair>;
-    fn get_total_swaps_for_address(&self, address: String) -> i64;
+    fn get_token_pair_keys(&self) -> Vec<Address>;
+    fn get_token_pairs_by_keys(&self, keys: &[Address]) -> Vec<TokenPair>;
+    fn get_token_pairs_by_address(&self, address: Address) -> HashMap<Address, TokenPair>;
+    fn get_non_zero_reserve_token_pairs_by_address(&self, address: Address) -> HashMap<Address, TokenPair>;
+    fn get_total_swaps_for_address(&self, address: Address) -> i64;
 
     fn add_update_log(&self, key: String, update_log: UpdateLog);
     fn get_update_log(&self, key: String) -> Option<UpdateLog>;
     fn get_update_logs(&self) -> HashMap<String, UpdateLog>;
     fn get_update_logs_size(&self) -> i64;
 
-    fn add_swap_log(&self, key: String, swap_log: SwapLog);
-    fn get_swap_logs(&self, key: String) -> Option<Vec<SwapLog>>;
-    fn get_all_swap_logs(&self) -> HashMap<String, Vec<SwapLog>>;
-    fn get_swap_logs_size(&self, key: String) -> i64;
+    fn add_swap_log(&self, key: Address, swap_log: SwapLog);
+    fn get_swap_logs(&self, key: Address) -> Option<Vec<SwapLog>>;
+    fn get_all_swap_logs(&self) -> HashMap<Address, Vec<SwapLog>>;
+    fn get_swap_log_keys(&self) -> Vec<Address>;
+    fn get_swap_logs_size(&self, key: Address) -> i64;
     fn get_total_swap_logs_size(&self) -> i64;
+    fn get_swap_count(&self, key: Address) -> i64;
+    fn get_swap_checkpoint(&self, protocol_id: String) -> u64;
+    fn get_swap_aggregates(&self, key: Address, interval: AggregateInterval) -> Vec<SwapAggregate>;
+    fn get_memory_usage(&self) -> StorageMemoryUsage;
+    fn remove_swap_logs_in_range(&self, from_block: u64, to_block: u64) -> HashMap<Address, i64>;
+    fn remove_swap_log(&self, key: Address, transaction_hash: Option<H256>, log_index: Option<U256>) -> bool;
+    fn set_quorum_disputed(&self, keys: Vec<Address>) -> i64;
+
+    fn add_quarantined_swap_log(&self, key: Address, swap_log: SwapLog);
+    fn get_quarantined_swap_log_addresses(&self) -> Vec<Address>;
+    fn get_quarantined_swap_logs_size(&self) -> i64;
+    fn release_quarantined_swap_logs(&self, key: Address, protocol_id: String) -> i64;
+    fn set_pair_protocol(&self, key: Address, protocol_id: String);
+    fn get_pair_protocol(&self, key: Address) -> Option<String>;
+
+    fn get_liquidity_events(&self, key: Address) -> Vec<LiquidityEvent>;
+    fn get_total_liquidity_events_size(&self) -> i64;
+    fn remove_liquidity_event(&self, key: Address, transaction_hash: Option<H256>, log_index: Option<U256>) -> bool;
+    fn add_rug_pull(&self, key: Address, rug_pull: RugPull);
+    fn get_rug_pull(&self, key: Address) -> Option<RugPull>;
+    fn remove_rug_pull(&self, key: Address) -> bool;
+
+    fn add_api_key(&self, api_key: StoredApiKey);
+    fn get_api_keys(&self) -> Vec<StoredApiKey>;
+    fn remove_api_key(&self, name: String) -> bool;
+}
+
+impl DataStorageService {
+    // Empty storage, independent of every other instance
+    pub fn new() -> Self {
+        DataStorageService {
+            state: Arc::new(StorageState {
+                snapshot: ArcSwap::from_pointee(DataStorage::default()),
+                writer: Mutex::new(()),
+            }),
+        }
+    }
+
+    fn load(&self) -> arc_swap::Guard<Arc<DataStorage>> {
+        self.state.snapshot.load()
+    }
+
+    fn write<R>(&self, update: impl FnOnce(&mut DataStorage) -> R) -> R {
+        let _writer = self.state.writer.lock().unwrap();
+        let mut storage = DataStorage::clone(&self.state.snapshot.load());
+        let result = update(&mut storage);
+        self.state.snapshot.store(Arc::new(storage));
+        result
+    }
 }
 
 impl DataStorageTrait for DataStorageService {
-    fn get_instance() -> Arc<Mutex<DataStorage>> {
-        Arc::clone(&DATA_STORAGE)
+    // Consistent view of the whole storage, unaffected by writes published later
+    fn get_snapshot(&self) -> Arc<DataStorage> {
+        self.state.snapshot.load_full()
+    }
+
+    // Applies several updates as one new snapshot and data version
+    fn write_batch<R>(&self, update: impl FnOnce(&mut DataStorage) -> R) -> R {
+        self.write(|storage| {
+            let result = update(storage);
+            storage.touch();
+            result
+        })
     }
 
     fn init(&self) {
@@ -80,87 +330,187 @@ impl DataStorageTrait for DataStorageService {
             chain_id: "BASE".to_string(),
             dex_id: "UNISWAP_V2".to_string(),
         };
-        Self.add_protocol(protocol.id.clone(), protocol);
+        self.add_protocol(protocol.id.clone(), protocol);
 
         log::info!("Data Storage initialized");
     }
 
+    fn get_data_version(&self) -> DataVersion {
+        let storage = self.load();
+        DataVersion {
+            version: storage.version,
+            last_modified: storage.last_modified,
+        }
+    }
+
+    // Writes the whole storage to a JSON file, the temporary file is renamed so readers never see a partial snapshot
+    fn save_snapshot(&self, path: &str) -> Result<(), Box<dyn Error>> {
+        if let Some(parent) = Path::new(path).parent() {
+            fs::create_dir_all(parent)?;
+        }
+        let tmp_path = format!("{}.tmp", path);
+        {
+            let mut writer = BufWriter::new(File::create(&tmp_path)?);
+            let storage = self.load();
+            serde_json::to_writer(&mut writer, storage.as_ref())?;
+            writer.flush()?;
+        }
+        fs::rename(&tmp_path, path)?;
+        Ok(())
+    }
+
+    fn load_snapshot(&self, path: &str) -> Result<bool, Box<dyn Error>> {
+        if !Path::new(path).exists() {
+            return Ok(false);
+        }
+        let mut snapshot: DataStorage = serde_json::from_reader(BufReader::new(File::open(path)?))?;
+        self.write(|storage| {
+            // Keep the version monotonic, snapshots may come from a process started later
+            snapshot.version = std::cmp::max(snapshot.version, storage.version);
+            //snapshots written before the per-pair counts only have the daily aggregates
+            if snapshot.compacted_swaps.is_empty() {
+                for aggregate in snapshot.swap_aggregates.values().filter(|aggregate| aggregate.interval == AggregateInterval::Daily) {
+                    *snapshot.compacted_swaps.entry(aggregate.pair_address).or_default() += aggregate.swaps;
+                }
+            }
+            snapshot.index_swap_aggregates();
+            *storage = snapshot;
+            storage.touch();
+            Ok(true)
+        })
+    }
+
     // TOKEN
-    fn add_token(&self, key: String, token: Token) {
-        let mut storage = DATA_STORAGE.lock().unwrap();
-        storage.tokens.insert(key, token);
+    fn add_token(&self, key: Address, mut token: Token) {
+        self.write(|storage| {
+            if let Some(entry) = storage.token_list_entries.get(&key) {
+                token.apply_token_list_entry(entry);
+            }
+            storage.tokens.insert(key, token);
+            storage.touch();
+        })
+    }
+
+    // Later entries of a token replace earlier ones, returns the number of stored tokens updated
+    fn add_token_list_entries(&self, entries: Vec<(Address, TokenListEntry)>) -> i64 {
+        self.write(|storage| {
+            let mut updated = 0;
+            for (key, entry) in entries {
+                if let Some(token) = storage.tokens.get_mut(&key) {
+                    token.apply_token_list_entry(&entry);
+                    updated += 1;
+                }
+                storage.token_list_entries.insert(key, entry);
+            }
+            storage.touch();
+            updated
+        })
     }
 
-    fn get_token(&self, key: String) -> Option<Token> {
-        let storage = DATA_STORAGE.lock().unwrap();
+    fn get_token_list_entry(&self, key: Address) -> Option<TokenListEntry> {
+        let storage = self.load();
+        storage.token_list_entries.get(&key).cloned()
+    }
+
+    fn get_token(&self, key: Address) -> Option<Token> {
+        let storage = self.load();
         storage.tokens.get(&key).cloned()
     }
 
-    fn get_tokens(&self) -> HashMap<String, Token> {
-        let storage = DATA_STORAGE.lock().unwrap();
-        storage.tokens.clone()
+    fn get_tokens(&self) -> HashMap<Address, Token> {
+        let storage = self.load();
+        storage.tokens.clone().into_iter().collect()
     }
 
     fn get_tokens_size(&self) -> i64 {
-        let storage = DATA_STORAGE.lock().unwrap();
+        let storage = self.load();
         storage.tokens.len() as i64
     }
 
+    fn get_token_keys(&self) -> Vec<Address> {
+        let storage = self.load();
+        storage.tokens.keys().cloned().collect()
+    }
+
+    fn get_tokens_by_keys(&self, keys: &[Address]) -> Vec<Token> {
+        let storage = self.load();
+        keys.iter().filter_map(|key| storage.tokens.get(key).cloned()).collect()
+    }
+
     // PROTOCOL
     fn add_protocol(&self, key: String, protocol: Protocol) {
-        let mut storage = DATA_STORAGE.lock().unwrap();
-        storage.protocols.insert(key, protocol);
+        self.write(|storage| {
+            storage.protocols.insert(key, protocol);
+            storage.touch();
+        })
     }
 
     fn get_protocol(&self, key: String) -> Option<Protocol> {
-        let storage = DATA_STORAGE.lock().unwrap();
+        let storage = self.load();
         storage.protocols.get(&key).cloned()
     }
 
 
     fn get_protocols(&self) -> HashMap<String, Protocol> {
-        let storage = DATA_STORAGE.lock().unwrap();
-        storage.protocols.clone()
+        let storage = self.load();
+        storage.protocols.clone().into_iter().collect()
     }
 
     fn get_protocols_size(&self) -> i64 {
-        let storage = DATA_STORAGE.lock().unwrap();
+        let storage = self.load();
         storage.protocols.len() as i64
     }
 
     // TOKEN PAIRS
-    fn add_token_pair(&self, key: String, token_pair: TokenPair) {
-        let mut storage = DATA_STORAGE.lock().unwrap();
-        storage.token_pairs.insert(key, token_pair);
+    fn add_token_pair(&self, key: Address, token_pair: TokenPair) {
+        self.write(|storage| {
+            storage.token_pairs.insert(key, token_pair);
+            storage.touch();
+        })
     }
 
-    fn get_token_pair(&self, key: String) -> Option<TokenPair> {
-        let storage = DATA_STORAGE.lock().unwrap();
+    fn get_token_pair(&self, key: Address) -> Option<TokenPair> {
+        let storage = self.load();
         storage.token_pairs.get(&key).cloned()
     }
 
-    fn get_token_pairs(&self) -> HashMap<String, TokenPair> {
-        let storage = DATA_STORAGE.lock().unwrap();
-        storage.token_pairs.clone()
+    fn contains_token_pair(&self, key: Address) -> bool {
+        let storage = self.load();
+        storage.token_pairs.contains_key(&key)
+    }
+
+    fn get_token_pairs(&self) -> HashMap<Address, TokenPair> {
+        let storage = self.load();
+        storage.token_pairs.clone().into_iter().collect()
     }
 
     fn get_token_pairs_size(&self) -> i64 {
-        let storage = DATA_STORAGE.lock().unwrap();
+        let storage = self.load();
         storage.token_pairs.len() as i64
     }
 
-    fn get_token_pairs_by_address(&self, address: String) -> HashMap<String, TokenPair> {
-        let storage = DATA_STORAGE.lock().unwrap();
+    fn get_token_pair_keys(&self) -> Vec<Address> {
+        let storage = self.load();
+        storage.token_pairs.keys().cloned().collect()
+    }
+
+    fn get_token_pairs_by_keys(&self, keys: &[Address]) -> Vec<TokenPair> {
+        let storage = self.load();
+        keys.iter().filter_map(|key| storage.token_pairs.get(key).cloned()).collect()
+    }
+
+    fn get_token_pairs_by_address(&self, address: Address) -> HashMap<Address, TokenPair> {
+        let storage = self.load();
         storage
             .token_pairs
             .iter()
             .filter(|(_, pair)| pair.base_address == address || pair.quote_address == address)
-            .map(|(key, pair)| (key.clone(), pair.clone()))
+            .map(|(key, pair)| (*key, pair.clone()))
             .collect()
     }
 
-    fn get_non_zero_reserve_token_pairs_by_address(&self, address: String) -> HashMap<String, TokenPair> {
-        let storage = DATA_STORAGE.lock().unwrap();
+    fn get_non_zero_reserve_token_pairs_by_address(&self, address: Address) -> HashMap<Address, TokenPair> {
+        let storage = self.load();
         storage
             .token_pairs
             .iter()
@@ -169,12 +519,12 @@ impl DataStorageTrait for DataStorageService {
                     && pair.base_reserve > 0
                     && pair.quote_reserve > 0
             })
-            .map(|(key, pair)| (key.clone(), pair.clone()))
+            .map(|(key, pair)| (*key, pair.clone()))
             .collect()
     }
 
-    fn get_total_swaps_for_address(&self, address: String) -> i64 {
-        let storage = DATA_STORAGE.lock().unwrap();
+    fn get_total_swaps_for_address(&self, address: Address) -> i64 {
+        let storage = self.load();
         storage
             .token_pairs
             .values()
@@ -186,48 +536,59 @@ impl DataStorageTrait for DataStorageService {
 
     // UPDATE LOGS
     fn add_update_log(&self, key: String, update_log: UpdateLog) {
-        let mut storage = DATA_STORAGE.lock().unwrap();
-        storage.update_logs.insert(key, update_log);
+        self.write(|storage| {
+            storage.update_logs.insert(key, update_log);
+            storage.touch();
+        })
     }
 
     fn get_update_log(&self, key: String) -> Option<UpdateLog> {
-        let storage = DATA_STORAGE.lock().unwrap();
+        let storage = self.load();
         storage.update_logs.get(&key).cloned()
     }
 
     fn get_update_logs(&self) -> HashMap<String, UpdateLog> {
-        let storage = DATA_STORAGE.lock().unwrap();
-        storage.update_logs.clone()
+        let storage = self.load();
+        storage.update_logs.clone().into_iter().collect()
     }
 
     fn get_update_logs_size(&self) -> i64 {
-        let storage = DATA_STORAGE.lock().unwrap();
+        let storage = self.load();
         storage.update_logs.len() as i64
     }
 
     //SWAP LOGS
 
-    fn add_swap_log(&self, key: String, swap_log: SwapLog) {
-        let mut storage = DATA_STORAGE.lock().unwrap();
-        storage
-            .swap_logs
-            .entry(key)
-            .or_insert_with(Vec::new)
-            .push(swap_log);
+    fn add_swap_log(&self, key: Address, swap_log: SwapLog) {
+        self.write(|storage| {
+            storage
+                .swap_logs
+                .entry(key)
+                .or_default()
+                .push_back(swap_log);
+            storage.touch();
+        })
     }
 
-    fn get_swap_logs(&self, key: String) -> Option<Vec<SwapLog>> {
-        let storage = DATA_STORAGE.lock().unwrap();
-        storage.swap_logs.get(&key).cloned()
+    fn get_swap_logs(&self, key: Address) -> Option<Vec<SwapLog>> {
+        let storage = self.load();
+        storage.swap_logs.get(&key).map(|logs| logs.iter().cloned().collect())
     }
 
-    fn get_all_swap_logs(&self) -> HashMap<String, Vec<SwapLog>> {
-        let storage = DATA_STORAGE.lock().unwrap();
-        storage.swap_logs.clone()
+    fn get_all_swap_logs(&self) -> HashMap<Address, Vec<SwapLog>> {
+        let storage = self.load();
+        storage.swap_logs.iter()
+            .map(|(key, logs)| (*key, logs.iter().cloned().collect()))
+            .collect()
     }
 
-    fn get_swap_logs_size(&self, key: String) -> i64 {
-        let storage = DATA_STORAGE.lock().unwrap();
+    fn get_swap_log_keys(&self) -> Vec<Address> {
+        let storage = self.load();
+        storage.swap_logs.keys().cloned().collect()
+    }
+
+    fn get_swap_logs_size(&self, key: Address) -> i64 {
+        let storage = self.load();
         storage
             .swap_logs
             .get(&key)
@@ -235,7 +596,254 @@ impl DataStorageTrait for DataStorageService {
     }
 
     fn get_total_swap_logs_size(&self) -> i64 {
-        let storage = DATA_STORAGE.lock().unwrap();
+        let storage = self.load();
         storage.swap_logs.values().map(|logs| logs.len() as i64).sum()
     }
-}
\ No newline at end of file
+
+    // Raw swap logs plus the swaps already compacted into daily aggregates
+    fn get_swap_count(&self, key: Address) -> i64 {
+        self.load().swap_count(key)
+    }
+
+    fn get_swap_checkpoint(&self, protocol_id: String) -> u64 {
+        self.load().swap_checkpoints.get(&protocol_id).copied().unwrap_or_default()
+    }
+
+    fn get_swap_aggregates(&self, key: Address, interval: AggregateInterval) -> Vec<SwapAggregate> {
+        let storage = self.load();
+        //buckets are indexed in start order
+        storage.swap_aggregate_index.get(&(key, interval))
+            .map(|buckets| buckets.values().filter_map(|key| storage.swap_aggregates.get(key)).cloned().collect())
+            .unwrap_or_default()
+    }
+
+    fn get_memory_usage(&self) -> StorageMemoryUsage {
+        let storage = self.load();
+        let mut usage = StorageMemoryUsage {
+            tokens: storage.tokens.len(),
+            token_pairs: storage.token_pairs.len(),
+            process_rss_bytes: process_rss_bytes(),
+            ..Default::default()
+        };
+        for token in storage.tokens.values() {
+            usage.tokens_bytes += std::mem::size_of::<Address>() + std::mem::size_of::<Token>()
+                + token.symbol.len() + token.name.len()
+                + token.pairs.len() * (std::mem::size_of::<Address>() + std::mem::size_of::<TokenPair>())
+                + token.active_pairs.len() * (std::mem::size_of::<Address>() + std::mem::size_of::<TokenPair>());
+        }
+        usage.token_pairs_bytes = storage.token_pairs.len() * (std::mem::size_of::<Address>() + std::mem::size_of::<TokenPair>());
+        for logs in storage.swap_logs.values() {
+            usage.swap_logs += logs.len();
+            usage.swap_logs_bytes += logs.iter().map(swap_log_bytes).sum::<usize>();
+        }
+        for logs in storage.quarantined_swap_logs.values() {
+            usage.quarantined_swap_logs += logs.len();
+            usage.quarantined_swap_logs_bytes += logs.iter().map(swap_log_bytes).sum::<usize>();
+        }
+        usage.swap_aggregates = storage.swap_aggregates.len();
+        usage.swap_aggregates_bytes = storage.swap_aggregates.iter()
+            .map(|(key, aggregate)| key.len() + std::mem::size_of::<SwapAggregate>()
+                + aggregate.protocol_id.len()
+                + aggregate.traders.len() * std::mem::size_of::<H160>())
+            .sum::<usize>()
+            + storage.swap_aggregate_index.values()
+                .flat_map(|buckets| buckets.values())
+                .map(|key| std::mem::size_of::<u64>() + key.len())
+                .sum::<usize>();
+        usage.liquidity_events = storage.liquidity_events.values().map(|events| events.len()).sum();
+        usage.liquidity_events_bytes = storage.liquidity_events.len() * std::mem::size_of::<Address>()
+            + usage.liquidity_events * std::mem::size_of::<LiquidityEvent>()
+            + storage.liquidity_totals.len() * (std::mem::size_of::<Address>() + std::mem::size_of::<LiquidityTotals>());
+        usage.estimated_total_bytes = usage.tokens_bytes + usage.token_pairs_bytes + usage.swap_logs_bytes
+            + usage.quarantined_swap_logs_bytes + usage.swap_aggregates_bytes + usage.liquidity_events_bytes;
+        usage
+    }
+
+    // Removed logs per emitter
+    fn remove_swap_logs_in_range(&self, from_block: u64, to_block: u64) -> HashMap<Address, i64> {
+        self.write(|storage| {
+            let mut removed: HashMap<Address, i64> = HashMap::new();
+            for (key, logs) in storage.swap_logs.iter_mut().chain(storage.quarantined_swap_logs.iter_mut()) {
+                let size = logs.len();
+                logs.retain(|log| match log.block_number {
+                    Some(block) => block.as_u64() < from_block || block.as_u64() > to_block,
+                    None => true,
+                });
+                if logs.len() < size {
+                    *removed.entry(*key).or_default() += (size - logs.len()) as i64;
+                }
+            }
+            storage.swap_logs.retain(|_, logs| !logs.is_empty());
+            storage.quarantined_swap_logs.retain(|_, logs| !logs.is_empty());
+            if !removed.is_empty() {
+                storage.touch();
+            }
+            removed
+        })
+    }
+
+    fn remove_swap_log(&self, key: Address, transaction_hash: Option<H256>, log_index: Option<U256>) -> bool {
+        self.write(|storage| {
+            let mut removed = false;
+            for swap_logs in [&mut storage.swap_logs, &mut storage.quarantined_swap_logs] {
+                if let Some(logs) = swap_logs.get_mut(&key) {
+                    let size = logs.len();
+                    logs.retain(|log| log.transaction_hash != transaction_hash || log.log_index != log_index);
+                    removed |= logs.len() != size;
+                    if logs.is_empty() {
+                        swap_logs.remove(&key);
+                    }
+                }
+            }
+            if removed {
+                storage.touch();
+            }
+            removed
+        })
+    }
+
+    // Flags the tokens and pairs of the keys as disputed and clears the others, returns the number changed
+    fn set_quorum_disputed(&self, keys: Vec<Address>) -> i64 {
+        self.write(|storage| {
+            let disputed: HashSet<Address> = keys.into_iter().collect();
+            let flagged: Vec<Address> = storage.tokens.iter().filter(|(_, token)| token.quorum_disputed).map(|(key, _)| *key)
+                .chain(storage.token_pairs.iter().filter(|(_, token_pair)| token_pair.quorum_disputed).map(|(key, _)| *key))
+                .collect();
+            let mut changed = 0;
+            for key in disputed.iter().chain(&flagged) {
+                let is_disputed = disputed.contains(key);
+                if let Some(token) = storage.tokens.get_mut(key).filter(|token| token.quorum_disputed != is_disputed) {
+                    token.quorum_disputed = is_disputed;
+                    changed += 1;
+                }
+                if let Some(token_pair) = storage.token_pairs.get_mut(key).filter(|token_pair| token_pair.quorum_disputed != is_disputed) {
+                    token_pair.quorum_disputed = is_disputed;
+                    changed += 1;
+                }
+            }
+            if changed > 0 {
+                storage.touch();
+            }
+            changed
+        })
+    }
+
+    fn add_quarantined_swap_log(&self, key: Address, swap_log: SwapLog) {
+        self.write(|storage| {
+            storage
+                .quarantined_swap_logs
+                .entry(key)
+                .or_default()
+                .push_back(swap_log);
+            storage.touch();
+        })
+    }
+
+    fn get_quarantined_swap_log_addresses(&self) -> Vec<Address> {
+        let storage = self.load();
+        storage.quarantined_swap_logs.keys().copied().collect()
+    }
+
+    fn get_quarantined_swap_logs_size(&self) -> i64 {
+        let storage = self.load();
+        storage.quarantined_swap_logs.values().map(|logs| logs.len() as i64).sum()
+    }
+
+    fn release_quarantined_swap_logs(&self, key: Address, protocol_id: String) -> i64 {
+        self.write(|storage| {
+            let Some(logs) = storage.quarantined_swap_logs.remove(&key) else {
+                return 0;
+            };
+            let released = logs.len() as i64;
+            storage.swap_logs.entry(key).or_default().extend(logs.into_iter().map(|mut log| {
+                log.protocol_id = protocol_id.clone();
+                log
+            }));
+            storage.touch();
+            released
+        })
+    }
+
+    fn set_pair_protocol(&self, key: Address, protocol_id: String) {
+        self.write(|storage| {
+            storage.pair_protocols.insert(key, protocol_id);
+            storage.touch();
+        })
+    }
+
+    fn get_pair_protocol(&self, key: Address) -> Option<String> {
+        let storage = self.load();
+        storage.pair_protocols.get(&key).cloned()
+    }
+
+    fn get_liquidity_events(&self, key: Address) -> Vec<LiquidityEvent> {
+        let storage = self.load();
+        storage.liquidity_events.get(&key).map(|events| events.iter().cloned().collect()).unwrap_or_default()
+    }
+
+    fn get_total_liquidity_events_size(&self) -> i64 {
+        let storage = self.load();
+        storage.liquidity_events.values().map(|events| events.len() as i64).sum()
+    }
+
+    fn remove_liquidity_event(&self, key: Address, transaction_hash: Option<H256>, log_index: Option<U256>) -> bool {
+        self.write(|storage| {
+            let Some(events) = storage.liquidity_events.get_mut(&key) else {
+                return false;
+            };
+            let size = events.len();
+            events.retain(|event| event.transaction_hash != transaction_hash || event.log_index != log_index);
+            let removed = events.len() != size;
+            if events.is_empty() {
+                storage.liquidity_events.remove(&key);
+            }
+            if removed {
+                storage.touch();
+            }
+            removed
+        })
+    }
+
+    fn add_rug_pull(&self, key: Address, rug_pull: RugPull) {
+        self.write(|storage| {
+            storage.rug_pulls.insert(key, rug_pull);
+            storage.touch();
+        })
+    }
+
+    fn get_rug_pull(&self, key: Address) -> Option<RugPull> {
+        let storage = self.load();
+        storage.rug_pulls.get(&key).cloned()
+    }
+
+    fn remove_rug_pull(&self, key: Address) -> bool {
+        self.write(|storage| {
+            let removed = storage.rug_pulls.remove(&key).is_some();
+            if removed {
+                storage.touch();
+            }
+            removed
+        })
+    }
+
+    fn add_api_key(&self, api_key: StoredApiKey) {
+        self.write(|storage| {
+            storage.api_keys.insert(api_key.name.clone(), api_key);
+            storage.touch();
+        });
+    }
+
+    fn get_api_keys(&self) -> Vec<StoredApiKey> {
+        self.load().api_keys.values().cloned().collect()
+    }
+
+    fn remove_api_key(&self, name: String) -> bool {
+        self.write(|storage| {
+            let removed = storage.api_keys.remove(&name).is_some();
+            if removed {
+                storage.touch();
+            }
+            removed
+        })
+    }
+}
@@ -0,0 +1,180 @@
+use ethers::types::U256;
+use serde::Serialize;
+use crate::config::ConfigHandle;
+use crate::domain::entities::address::Address;
+use crate::domain::entities::token::Token;
+use crate::domain::entities::token_pair::TokenPair;
+use crate::domain::services::data_storage_service::DataStorage;
+use crate::domain::services::quote_service::get_amount_out;
+
+const BPS: u64 = 10_000;
+const CURVE_MIN_IMPACT_BPS: f64 = 10.0;
+const CURVE_MAX_IMPACT_BPS: f64 = 5_000.0;
+
+// Swap filled impact_bps below the spot price, fees excluded like the quote's price impact
+#[derive(Debug, Clone, Serialize)]
+pub struct DepthLevel {
+    pub impact_bps: u32,
+    #[serde(serialize_with = "serialize_amount")]
+    pub amount_in: U256,
+    #[serde(serialize_with = "serialize_amount")]
+    pub amount_out: U256,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct DepthSide {
+    pub token_in: Address,
+    pub token_out: Address,
+    pub levels: Vec<DepthLevel>,
+    pub curve: Vec<DepthLevel>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct PairDepth {
+    pub pair_address: Address,
+    pub protocol_id: String,
+    pub fee_bps: u32,
+    pub reserve_block: u32,
+    pub sell_base: DepthSide,
+    pub sell_quote: DepthSide,
+}
+
+// Amount of the token itself, the other side differs between pairs
+#[derive(Debug, Clone, Serialize)]
+pub struct TokenDepthLevel {
+    pub impact_bps: u32,
+    #[serde(serialize_with = "serialize_amount")]
+    pub amount: U256,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct TokenDepthSide {
+    pub levels: Vec<TokenDepthLevel>,
+    pub curve: Vec<TokenDepthLevel>,
+}
+
+// A token's active pairs each traded up to the same impact, the best split of a larger order
+#[derive(Debug, Clone, Serialize)]
+pub struct TokenDepth {
+    pub address: Address,
+    // active pairs of a configured protocol
+    pub pairs: usize,
+    // oldest reserves the depth is based on
+    pub reserve_block: u32,
+    // token sold into the pairs
+    pub sell: TokenDepthSide,
+    // token bought out of the pairs
+    pub buy: TokenDepthSide,
+}
+
+fn serialize_amount<S: serde::Serializer>(amount: &U256, serializer: S) -> Result<S::Ok, S::Error> {
+    serializer.collect_str(amount)
+}
+
+// Selling a = x*p / ((1-p)*(1-fee)) into reserves x fills at p below the spot price (slippage), and buys p of the
+// other reserve. The pool's marginal price moves further than p.
+pub fn depth_at_impact(reserve_in: u128, reserve_out: u128, fee_bps: u32, impact_bps: u32) -> DepthLevel {
+    let amount_in = if impact_bps as u64 >= BPS || fee_bps as u64 >= BPS {
+        U256::zero()
+    } else {
+        U256::from(reserve_in) * U256::from(impact_bps) * U256::from(BPS)
+            / (U256::from(BPS - impact_bps as u64) * U256::from(BPS - fee_bps as u64))
+    };
+    DepthLevel {
+        impact_bps,
+        amount_in,
+        amount_out: get_amount_out(amount_in, U256::from(reserve_in), U256::from(reserve_out), fee_bps),
+    }
+}
+
+#[derive(Clone)]
+pub struct DepthService {
+    config: ConfigHandle,
+}
+
+pub trait DepthServiceTrait {
+    fn pair_depth(&self, pair: &TokenPair) -> Option<PairDepth>;
+    fn token_depth(&self, storage: &DataStorage, token: &Token) -> TokenDepth;
+}
+
+impl DepthService {
+    pub fn new(config: ConfigHandle) -> Self {
+        DepthService { config }
+    }
+
+    // Impacts from 0.1% to 50%, evenly spaced on a log scale
+    fn curve_impacts(&self) -> Vec<u32> {
+        let points = self.config.get().depth.curve_points.max(2);
+        let ratio = (CURVE_MAX_IMPACT_BPS / CURVE_MIN_IMPACT_BPS).powf(1.0 / (points - 1) as f64);
+        (0..points)
+            .map(|i| (CURVE_MIN_IMPACT_BPS * ratio.powi(i as i32)).round() as u32)
+            .collect()
+    }
+
+    fn side(&self, token_in: Address, token_out: Address, reserve_in: u128, reserve_out: u128, fee_bps: u32) -> DepthSide {
+        let levels = self.config.get().depth.impact_levels_bps.clone();
+        DepthSide {
+            token_in,
+            token_out,
+            levels: levels.into_iter().map(|impact| depth_at_impact(reserve_in, reserve_out, fee_bps, impact)).collect(),
+            curve: self.curve_impacts().into_iter().map(|impact| depth_at_impact(reserve_in, reserve_out, fee_bps, impact)).collect(),
+        }
+    }
+}
+
+impl DepthServiceTrait for DepthService {
+    // None when the pair's protocol isn't configured, its fee is unknown
+    fn pair_depth(&self, pair: &TokenPair) -> Option<PairDepth> {
+        let fee_bps = self.config.get().protocol(&pair.protocol_id)?.fee_bps;
+        Some(PairDepth {
+            pair_address: pair.token_pair_address,
+            protocol_id: pair.protocol_id.clone(),
+            fee_bps,
+            reserve_block: pair.reserve_block,
+            sell_base: self.side(pair.base_address, pair.quote_address, pair.base_reserve, pair.quote_reserve, fee_bps),
+            sell_quote: self.side(pair.quote_address, pair.base_address, pair.quote_reserve, pair.base_reserve, fee_bps),
+        })
+    }
+
+    // Pairs with reserves from the storage, the token's own pair lists are only recomputed on refresh
+    fn token_depth(&self, storage: &DataStorage, token: &Token) -> TokenDepth {
+        let pairs: Vec<PairDepth> = storage.token_pairs.values()
+            .filter(|pair| pair.base_address == token.address || pair.quote_address == token.address)
+            .filter(|pair| pair.base_reserve > 0 && pair.quote_reserve > 0)
+            .filter_map(|pair| self.pair_depth(pair))
+            .collect();
+        let (sell, buy): (Vec<&DepthSide>, Vec<&DepthSide>) = pairs.iter()
+            .map(|pair| if pair.sell_base.token_in == token.address {
+                (&pair.sell_base, &pair.sell_quote)
+            } else {
+                (&pair.sell_quote, &pair.sell_base)
+            })
+            .unzip();
+
+        let sum = |sides: &[&DepthSide], curve: bool, amount: fn(&DepthLevel) -> U256| -> Vec<TokenDepthLevel> {
+            let impacts = if curve { self.curve_impacts() } else { self.config.get().depth.impact_levels_bps.clone() };
+            impacts.into_iter().enumerate()
+                .map(|(i, impact_bps)| TokenDepthLevel {
+                    impact_bps,
+                    amount: sides.iter()
+                        .filter_map(|side| if curve { side.curve.get(i) } else { side.levels.get(i) })
+                        .fold(U256::zero(), |total, level| total.saturating_add(amount(level))),
+                })
+                .collect()
+        };
+
+        TokenDepth {
+            address: token.address,
+            pairs: pairs.len(),
+            reserve_block: pairs.iter().map(|pair| pair.reserve_block).min().unwrap_or_default(),
+            sell: TokenDepthSide {
+                levels: sum(&sell, false, |level| level.amount_in),
+                curve: sum(&sell, true, |level| level.amount_in),
+            },
+            buy: TokenDepthSide {
+                levels: sum(&buy, false, |level| level.amount_out),
+                curve: sum(&buy, true, |level| level.amount_out),
+            },
+        }
+    }
+}
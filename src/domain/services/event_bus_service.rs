@@ -0,0 +1,87 @@
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
+use serde::Serialize;
+use tokio::sync::broadcast;
+use crate::domain::entities::domain_event::DomainEvent;
+
+// Events a subscriber may fall behind by before it misses some
+const EVENT_BUS_CAPACITY: usize = 4096;
+
+#[derive(Debug, Default, Clone, Serialize)]
+pub struct EventBusMetrics {
+    pub published: HashMap<String, u64>,
+    pub lagged: u64,
+    pub subscribers: usize,
+}
+
+// Handle to one event bus, clones publish to and subscribe on the same channel
+#[derive(Clone)]
+pub struct EventBusService {
+    sender: broadcast::Sender<DomainEvent>,
+    metrics: Arc<Mutex<EventBusMetrics>>,
+}
+
+impl Default for EventBusService {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl EventBusService {
+    pub fn new() -> Self {
+        EventBusService {
+            sender: broadcast::channel(EVENT_BUS_CAPACITY).0,
+            metrics: Arc::new(Mutex::new(EventBusMetrics::default())),
+        }
+    }
+}
+
+// Next event for a subscriber, skipping over lag; None once the bus is closed
+pub async fn receive_event(event_bus: &EventBusService, receiver: &mut broadcast::Receiver<DomainEvent>) -> Option<DomainEvent> {
+    loop {
+        match receiver.recv().await {
+            Ok(event) => return Some(event),
+            Err(broadcast::error::RecvError::Lagged(skipped)) => event_bus.record_lagged(skipped),
+            Err(broadcast::error::RecvError::Closed) => return None,
+        }
+    }
+}
+
+pub trait EventBusServiceTrait {
+    fn publish(&self, event: DomainEvent);
+    fn publish_all(&self, events: Vec<DomainEvent>);
+    fn subscribe(&self) -> broadcast::Receiver<DomainEvent>;
+    fn record_lagged(&self, skipped: u64);
+    fn get_metrics(&self) -> EventBusMetrics;
+}
+
+impl EventBusServiceTrait for EventBusService {
+    // Never blocks the publisher, events without subscribers are dropped
+    fn publish(&self, event: DomainEvent) {
+        log::debug!("Event: {}", event);
+        *self.metrics.lock().unwrap().published.entry(event.event_type().to_string()).or_default() += 1;
+        let _ = self.sender.send(event);
+    }
+
+    fn publish_all(&self, events: Vec<DomainEvent>) {
+        for event in events {
+            self.publish(event);
+        }
+    }
+
+    fn subscribe(&self) -> broadcast::Receiver<DomainEvent> {
+        self.sender.subscribe()
+    }
+
+    // A subscriber fell more than EVENT_BUS_CAPACITY events behind
+    fn record_lagged(&self, skipped: u64) {
+        log::warn!("Event subscriber lagged, {} events skipped", skipped);
+        self.metrics.lock().unwrap().lagged += skipped;
+    }
+
+    fn get_metrics(&self) -> EventBusMetrics {
+        let mut metrics = self.metrics.lock().unwrap().clone();
+        metrics.subscribers = self.sender.receiver_count();
+        metrics
+    }
+}
@@ -0,0 +1,109 @@
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
+use std::time::SystemTime;
+use crate::domain::entities::job::{Job, JobKind, JobStatus};
+
+// Finished jobs kept for the admin API, the oldest are dropped first
+pub const MAX_FINISHED_JOBS: usize = 1000;
+
+#[derive(Default)]
+pub struct JobStorage {
+    pub jobs: HashMap<String, Job>,
+    pub sequence: u64,
+}
+
+// Jobs of one indexer, shared by its clones
+#[derive(Clone, Default)]
+pub struct JobService {
+    storage: Arc<Mutex<JobStorage>>,
+}
+
+impl JobService {
+    pub fn new() -> Self {
+        JobService::default()
+    }
+}
+
+fn prune_finished_jobs(storage: &mut JobStorage) {
+    let mut finished: Vec<(SystemTime, String)> = storage.jobs.values()
+        .filter(|job| matches!(job.status, JobStatus::Completed | JobStatus::Failed))
+        .map(|job| (job.finished_at.unwrap_or(job.created_at), job.id.clone()))
+        .collect();
+    if finished.len() <= MAX_FINISHED_JOBS {
+        return;
+    }
+    finished.sort();
+    for (_, id) in finished.iter().take(finished.len() - MAX_FINISHED_JOBS) {
+        storage.jobs.remove(id);
+    }
+}
+
+#[allow(dead_code)]
+pub trait JobServiceTrait {
+    fn create_job(&self, kind: JobKind) -> Job;
+    fn start_job(&self, id: String);
+    fn complete_job(&self, id: String, message: String);
+    fn fail_job(&self, id: String, error: String);
+    fn get_job(&self, id: String) -> Option<Job>;
+    fn get_jobs(&self) -> Vec<Job>;
+}
+
+impl JobServiceTrait for JobService {
+    fn create_job(&self, kind: JobKind) -> Job {
+        let mut storage = self.storage.lock().unwrap();
+        storage.sequence += 1;
+        let job = Job {
+            id: format!("job-{}", storage.sequence),
+            kind,
+            status: JobStatus::Pending,
+            message: None,
+            created_at: SystemTime::now(),
+            started_at: None,
+            finished_at: None,
+        };
+        storage.jobs.insert(job.id.clone(), job.clone());
+        prune_finished_jobs(&mut storage);
+        log::info!("Job created: {}", job);
+        job
+    }
+
+    fn start_job(&self, id: String) {
+        let mut storage = self.storage.lock().unwrap();
+        if let Some(job) = storage.jobs.get_mut(&id) {
+            job.status = JobStatus::Running;
+            job.started_at = Some(SystemTime::now());
+        }
+    }
+
+    fn complete_job(&self, id: String, message: String) {
+        let mut storage = self.storage.lock().unwrap();
+        if let Some(job) = storage.jobs.get_mut(&id) {
+            job.status = JobStatus::Completed;
+            job.message = Some(message);
+            job.finished_at = Some(SystemTime::now());
+            log::info!("Job completed: {}", job);
+        }
+    }
+
+    fn fail_job(&self, id: String, error: String) {
+        let mut storage = self.storage.lock().unwrap();
+        if let Some(job) = storage.jobs.get_mut(&id) {
+            job.status = JobStatus::Failed;
+            job.message = Some(error);
+            job.finished_at = Some(SystemTime::now());
+            log::error!("Job failed: {}", job);
+        }
+    }
+
+    fn get_job(&self, id: String) -> Option<Job> {
+        let storage = self.storage.lock().unwrap();
+        storage.jobs.get(&id).cloned()
+    }
+
+    fn get_jobs(&self) -> Vec<Job> {
+        let storage = self.storage.lock().unwrap();
+        let mut jobs: Vec<Job> = storage.jobs.values().cloned().collect();
+        jobs.sort_by_key(|job| job.created_at);
+        jobs
+    }
+}
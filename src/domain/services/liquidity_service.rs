@@ -0,0 +1,201 @@
+use std::collections::VecDeque;
+use std::time::SystemTime;
+use serde::Serialize;
+use crate::config::ConfigHandle;
+use crate::domain::entities::address::Address;
+use crate::domain::entities::liquidity_event::{LiquidityEvent, LiquidityEventKind, LiquidityTotals, RugPull, RugPullReason};
+use crate::domain::services::data_storage_service::DataStorage;
+
+// Liquidity history of a pair served at /pairs/{address}/liquidity, amounts of token0 (base) and token1 (quote)
+#[derive(Debug, Clone, Serialize)]
+pub struct PairLiquidity {
+    pub pair_address: Address,
+    // first liquidity provider, origin of the pair's first indexed Mint
+    pub deployer: Option<Address>,
+    pub minted0: u128,
+    pub minted1: u128,
+    pub burned0: u128,
+    pub burned1: u128,
+    // LP net flows, minted minus burned
+    pub net0: i128,
+    pub net1: i128,
+    pub rug_pull: Option<RugPull>,
+    // events within the rug-pull window, older ones only count in the totals
+    pub events: Vec<LiquidityEvent>,
+}
+
+// First liquidity provider of a pair
+pub fn deployer(events: &[LiquidityEvent]) -> Option<Address> {
+    events.iter()
+        .filter(|event| event.kind == LiquidityEventKind::Mint)
+        .min_by_key(|event| (event.block_number, event.log_index))
+        .and_then(|event| event.origin)
+}
+
+// Deployer of a pair whose first Mint may already be pruned
+fn pair_deployer(storage: &DataStorage, pair_address: Address, events: &[LiquidityEvent]) -> Option<Address> {
+    match storage.liquidity_totals.get(&pair_address) {
+        Some(totals) if totals.mints > 0 => totals.deployer,
+        _ => deployer(events),
+    }
+}
+
+fn sorted_events(storage: &DataStorage, pair_address: Address) -> Vec<LiquidityEvent> {
+    let mut events: Vec<LiquidityEvent> = storage.liquidity_events.get(&pair_address)
+        .map(|events| events.iter().cloned().collect())
+        .unwrap_or_default();
+    //backfilled ranges are appended after later blocks
+    events.sort_by_key(|event| (event.block_number, event.log_index));
+    events
+}
+
+#[derive(Clone)]
+pub struct LiquidityService {
+    config: ConfigHandle,
+}
+
+pub trait LiquidityServiceTrait {
+    fn history(&self, storage: &DataStorage, pair_address: Address) -> Option<PairLiquidity>;
+    fn detect_rug_pull(&self, storage: &DataStorage, pair_address: Address) -> Option<RugPull>;
+    fn prune_liquidity_events(&self, storage: &mut DataStorage, head_block: u64) -> i64;
+}
+
+impl LiquidityService {
+    pub fn new(config: ConfigHandle) -> Self {
+        LiquidityService { config }
+    }
+
+    fn window_blocks(&self) -> u64 {
+        let config = self.config.get();
+        std::cmp::max(1, config.rug_pull.window_secs / config.base_chain().block_time)
+    }
+}
+
+impl LiquidityServiceTrait for LiquidityService {
+    fn history(&self, storage: &DataStorage, pair_address: Address) -> Option<PairLiquidity> {
+        let events = sorted_events(storage, pair_address);
+        if events.is_empty() && !storage.token_pairs.contains_key(&pair_address) {
+            return None;
+        }
+        let mut totals = storage.liquidity_totals.get(&pair_address).cloned().unwrap_or_default();
+        events.iter().for_each(|event| totals.add(event));
+        let LiquidityTotals { minted0, minted1, burned0, burned1, .. } = totals;
+        let net = |minted: u128, burned: u128| (minted as i128).saturating_sub(burned as i128);
+
+        Some(PairLiquidity {
+            pair_address,
+            deployer: pair_deployer(storage, pair_address, &events),
+            minted0,
+            minted1,
+            burned0,
+            burned1,
+            net0: net(minted0, burned0),
+            net1: net(minted1, burned1),
+            rug_pull: storage.rug_pulls.get(&pair_address).cloned(),
+            events,
+        })
+    }
+
+    // The liquidity before an event is the current reserves plus what was removed since, minus what was added.
+    // Swaps since are ignored, the smaller share of both tokens counts. Needs reserves read after the last event.
+    fn detect_rug_pull(&self, storage: &DataStorage, pair_address: Address) -> Option<RugPull> {
+        let pair = storage.token_pairs.get(&pair_address)?;
+        let events = sorted_events(storage, pair_address);
+        if pair.reserves_read_block < events.last()?.block_number {
+            return None;
+        }
+        let rug_pull = self.config.get().rug_pull.clone();
+        let window_blocks = self.window_blocks();
+        let deployer = pair_deployer(storage, pair_address, &events);
+
+        //liquidity before event i, per token, from the current reserves back
+        let mut liquidity_before = vec![[pair.base_reserve as f64, pair.quote_reserve as f64]; events.len() + 1];
+        for i in (0..events.len()).rev() {
+            let sign = if events[i].kind == LiquidityEventKind::Burn { 1.0 } else { -1.0 };
+            let after = liquidity_before[i + 1];
+            liquidity_before[i] = [after[0] + sign * events[i].amount0 as f64, after[1] + sign * events[i].amount1 as f64];
+        }
+        let share = |removed: [f64; 2], before: [f64; 2]| -> f64 {
+            let token_share = |token: usize| if before[token] > 0.0 { (removed[token] / before[token]).clamp(0.0, 1.0) } else { 0.0 };
+            token_share(0).min(token_share(1))
+        };
+
+        //per token, indexes of the window's liquidity peaks in decreasing order
+        let mut peaks: [VecDeque<usize>; 2] = [VecDeque::new(), VecDeque::new()];
+        let mut window_start = 0;
+        for (i, event) in events.iter().enumerate() {
+            for (token, peaks) in peaks.iter_mut().enumerate() {
+                while peaks.back().is_some_and(|&peak| liquidity_before[peak][token] <= liquidity_before[i][token]) {
+                    peaks.pop_back();
+                }
+                peaks.push_back(i);
+            }
+            if event.kind != LiquidityEventKind::Burn {
+                continue;
+            }
+            while events[window_start].block_number + window_blocks < event.block_number {
+                window_start += 1;
+            }
+            for peaks in peaks.iter_mut() {
+                while peaks.front().is_some_and(|&peak| peak < window_start) {
+                    peaks.pop_front();
+                }
+            }
+            let flag = |reason: RugPullReason, share: f64| RugPull {
+                pair_address,
+                reason,
+                share,
+                block_number: event.block_number,
+                transaction_hash: event.transaction_hash,
+                detected_at: SystemTime::now(),
+            };
+
+            let by_deployer = deployer.is_some() && (event.origin == deployer || event.to == deployer);
+            let removed = share([event.amount0 as f64, event.amount1 as f64], liquidity_before[i]);
+            if by_deployer && removed >= rug_pull.max_deployer_removal_share {
+                return Some(flag(RugPullReason::DeployerRemoval, removed));
+            }
+            //measured from the window's peak, a mint early in the window doesn't hide the drop
+            let peak = [liquidity_before[peaks[0][0]][0], liquidity_before[peaks[1][0]][1]];
+            let after = liquidity_before[i + 1];
+            let dropped = share([peak[0] - after[0], peak[1] - after[1]], peak);
+            if dropped >= rug_pull.max_liquidity_drop_share {
+                return Some(flag(RugPullReason::LiquidityDrop, dropped));
+            }
+        }
+        None
+    }
+
+    // Events past the window no longer change the detection: those of tracked pairs are folded into the pair's
+    // totals, those of emitters still unknown are dropped. Returns the number of dropped events.
+    fn prune_liquidity_events(&self, storage: &mut DataStorage, head_block: u64) -> i64 {
+        let Some(through) = head_block.checked_sub(self.window_blocks() + 1) else {
+            return 0;
+        };
+        let pairs: Vec<Address> = storage.liquidity_events.iter()
+            .filter(|(_, events)| events.iter().any(|event| event.block_number <= through))
+            .map(|(pair_address, _)| *pair_address)
+            .collect();
+        let mut dropped = 0;
+        for pair_address in pairs {
+            let Some(events) = storage.liquidity_events.get(&pair_address).cloned() else {
+                continue;
+            };
+            let (mut pruned, kept): (Vec<LiquidityEvent>, Vec<LiquidityEvent>) = events.into_iter()
+                .partition(|event| event.block_number <= through);
+            if storage.token_pairs.contains_key(&pair_address) {
+                pruned.sort_by_key(|event| (event.block_number, event.log_index));
+                let totals = storage.liquidity_totals.entry(pair_address).or_default();
+                pruned.iter().for_each(|event| totals.add(event));
+            } else {
+                dropped += pruned.len() as i64;
+            }
+            if kept.is_empty() {
+                storage.liquidity_events.remove(&pair_address);
+            } else {
+                storage.liquidity_events.insert(pair_address, im::Vector::from(kept));
+            }
+        }
+        dropped
+    }
+}
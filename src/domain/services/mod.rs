@@ -1 +1,13 @@
-pub mod data_storage_service;
\ No newline at end of file
+pub mod data_storage_service;
+pub mod job_service;
+pub mod api_key_service;
+pub mod swap_compaction_service;
+pub mod event_bus_service;
+pub mod pair_activity_aggregator;
+pub mod alert_service;
+pub mod task_supervisor;
+pub mod sync_state_machine;
+pub mod token_list_service;
+pub mod quote_service;
+pub mod depth_service;
+pub mod liquidity_service;
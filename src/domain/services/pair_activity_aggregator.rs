@@ -0,0 +1,70 @@
+use std::collections::HashSet;
+use std::time::SystemTime;
+use async_trait::async_trait;
+use tokio::sync::broadcast::error::TryRecvError;
+use crate::domain::entities::address::Address;
+use crate::domain::entities::domain_event::DomainEvent;
+use crate::domain::services::data_storage_service::{DataStorageService, DataStorageTrait};
+use crate::domain::services::event_bus_service::{receive_event, EventBusService, EventBusServiceTrait};
+
+// Swap events folded into one storage write at most
+const MAX_BATCH_EVENTS: usize = 1024;
+
+// Keeps pair swap counts current from SwapIndexed events instead of waiting for the next pair refresh
+pub struct PairActivityAggregator {
+    data_service: DataStorageService,
+    event_bus: EventBusService,
+}
+
+#[async_trait]
+pub trait PairActivityAggregatorTrait: Send + Sync {
+    async fn run(&self);
+}
+
+impl PairActivityAggregator {
+    pub fn new(data_service: DataStorageService, event_bus: EventBusService) -> Self {
+        PairActivityAggregator { data_service, event_bus }
+    }
+
+    // Recounts the pairs from storage, backfills and pair refreshes setting the counts meanwhile are not added twice
+    fn apply(&self, pairs: HashSet<Address>) {
+        self.data_service.write_batch(|storage| {
+            for key in pairs {
+                let swaps = storage.swap_count(key);
+                if let Some(token_pair) = storage.token_pairs.get_mut(&key) {
+                    token_pair.swaps = swaps;
+                    token_pair.updated_at = SystemTime::now();
+                }
+            }
+        });
+    }
+}
+
+#[async_trait]
+impl PairActivityAggregatorTrait for PairActivityAggregator {
+    async fn run(&self) {
+        let mut receiver = self.event_bus.subscribe();
+        log::info!("Pair activity aggregator subscribed to domain events");
+        while let Some(event) = receive_event(&self.event_bus, &mut receiver).await {
+            //drain what is already queued so a busy getLogs response is one write
+            let mut events = vec![event];
+            while events.len() < MAX_BATCH_EVENTS {
+                match receiver.try_recv() {
+                    Ok(event) => events.push(event),
+                    Err(TryRecvError::Lagged(skipped)) => self.event_bus.record_lagged(skipped),
+                    Err(_) => break,
+                }
+            }
+
+            let pairs: HashSet<Address> = events.into_iter()
+                .filter_map(|event| match event {
+                    DomainEvent::SwapIndexed { pair_address, .. } => Some(pair_address),
+                    _ => None,
+                })
+                .collect();
+            if !pairs.is_empty() {
+                self.apply(pairs);
+            }
+        }
+    }
+}
@@ -0,0 +1,172 @@
+use std::collections::HashMap;
+use ethers::types::U256;
+use serde::Serialize;
+use crate::config::ConfigHandle;
+use crate::domain::entities::address::Address;
+use crate::domain::entities::token_pair::TokenPair;
+use crate::domain::services::data_storage_service::DataStorage;
+
+const FEE_DENOMINATOR: u64 = 10_000;
+
+// One pair of a quoted route
+#[derive(Debug, Clone, Serialize)]
+pub struct QuoteHop {
+    pub pair_address: Address,
+    pub protocol_id: String,
+    pub token_in: Address,
+    pub token_out: Address,
+    #[serde(serialize_with = "serialize_amount")]
+    pub amount_in: U256,
+    #[serde(serialize_with = "serialize_amount")]
+    pub amount_out: U256,
+    pub reserve_in: u128,
+    pub reserve_out: u128,
+    pub fee_bps: u32,
+    pub reserve_block: u32,
+}
+
+// Best route for swapping amount_in of token_in, amounts in the tokens' smallest units
+#[derive(Debug, Clone, Serialize)]
+pub struct Quote {
+    pub token_in: Address,
+    pub token_out: Address,
+    #[serde(serialize_with = "serialize_amount")]
+    pub amount_in: U256,
+    #[serde(serialize_with = "serialize_amount")]
+    pub amount_out: U256,
+    // share of the output lost to moving the reserves, fees excluded
+    pub price_impact: f64,
+    // oldest reserves the quote is based on
+    pub reserve_block: u32,
+    pub route: Vec<QuoteHop>,
+}
+
+// Amounts exceed the integer range of JSON numbers
+fn serialize_amount<S: serde::Serializer>(amount: &U256, serializer: S) -> Result<S::Ok, S::Error> {
+    serializer.collect_str(amount)
+}
+
+// UniswapV2Library.getAmountOut with the fee in basis points
+pub fn get_amount_out(amount_in: U256, reserve_in: U256, reserve_out: U256, fee_bps: u32) -> U256 {
+    if amount_in.is_zero() || reserve_in.is_zero() || reserve_out.is_zero() || fee_bps as u64 >= FEE_DENOMINATOR {
+        return U256::zero();
+    }
+    let amount_in_with_fee = amount_in.saturating_mul(U256::from(FEE_DENOMINATOR - fee_bps as u64));
+    let numerator = amount_in_with_fee.saturating_mul(reserve_out);
+    let denominator = reserve_in.saturating_mul(U256::from(FEE_DENOMINATOR)).saturating_add(amount_in_with_fee);
+    numerator / denominator
+}
+
+#[derive(Clone)]
+pub struct QuoteService {
+    config: ConfigHandle,
+}
+
+pub trait QuoteServiceTrait {
+    fn quote(&self, storage: &DataStorage, token_in: Address, token_out: Address, amount_in: U256) -> Option<Quote>;
+}
+
+impl QuoteService {
+    pub fn new(config: ConfigHandle) -> Self {
+        QuoteService { config }
+    }
+
+    // token -> pairs with reserves it can be swapped through, with the pair's fee
+    fn liquidity_graph<'a>(&self, storage: &'a DataStorage) -> HashMap<Address, Vec<(&'a TokenPair, u32)>> {
+        let config = self.config.get();
+        let mut graph: HashMap<Address, Vec<(&TokenPair, u32)>> = HashMap::new();
+        for pair in storage.token_pairs.values() {
+            //reserves of disputed pairs can't be trusted for a price
+            if pair.base_reserve == 0 || pair.quote_reserve == 0 || pair.quorum_disputed {
+                continue;
+            }
+            let fee_bps = match config.protocol(&pair.protocol_id) {
+                Some(protocol) => protocol.fee_bps,
+                None => continue,
+            };
+            graph.entry(pair.base_address).or_default().push((pair, fee_bps));
+            graph.entry(pair.quote_address).or_default().push((pair, fee_bps));
+        }
+        graph
+    }
+}
+
+impl QuoteServiceTrait for QuoteService {
+    // Walks the graph one hop at a time keeping the best amount reached per token, so every hop costs one pass
+    // over the pairs instead of enumerating all routes. Routes don't revisit a token.
+    fn quote(&self, storage: &DataStorage, token_in: Address, token_out: Address, amount_in: U256) -> Option<Quote> {
+        if token_in == token_out || amount_in.is_zero() {
+            return None;
+        }
+        let max_hops = self.config.get().quote.max_hops;
+        let graph = self.liquidity_graph(storage);
+
+        let mut best: Option<Vec<QuoteHop>> = None;
+        let mut frontier: HashMap<Address, (U256, Vec<QuoteHop>)> = HashMap::from([(token_in, (amount_in, vec![]))]);
+        for hop in 0..max_hops {
+            let mut next: HashMap<Address, (U256, Vec<QuoteHop>)> = HashMap::new();
+            for (token, (amount, route)) in &frontier {
+                for (pair, fee_bps) in graph.get(token).map(Vec::as_slice).unwrap_or_default() {
+                    let (next_token, reserve_in, reserve_out) = if pair.base_address == *token {
+                        (pair.quote_address, pair.base_reserve, pair.quote_reserve)
+                    } else {
+                        (pair.base_address, pair.quote_reserve, pair.base_reserve)
+                    };
+                    if next_token == token_in || route.iter().any(|step| step.token_in == next_token) {
+                        continue;
+                    }
+                    let amount_out = get_amount_out(*amount, U256::from(reserve_in), U256::from(reserve_out), *fee_bps);
+                    if amount_out.is_zero() {
+                        continue;
+                    }
+                    let step = QuoteHop {
+                        pair_address: pair.token_pair_address,
+                        protocol_id: pair.protocol_id.clone(),
+                        token_in: *token,
+                        token_out: next_token,
+                        amount_in: *amount,
+                        amount_out,
+                        reserve_in,
+                        reserve_out,
+                        fee_bps: *fee_bps,
+                        reserve_block: pair.reserve_block,
+                    };
+
+                    if next_token == token_out {
+                        let best_amount = best.as_ref().and_then(|route| route.last()).map(|step| step.amount_out);
+                        if best_amount.is_none_or(|best_amount| amount_out > best_amount) {
+                            best = Some(route.iter().cloned().chain([step]).collect());
+                        }
+                    } else if hop + 1 < max_hops
+                        && next.get(&next_token).is_none_or(|(reached, _)| amount_out > *reached) {
+                        next.insert(next_token, (amount_out, route.iter().cloned().chain([step]).collect()));
+                    }
+                }
+            }
+            frontier = next;
+        }
+
+        let route = best?;
+        let amount_out = route.last()?.amount_out;
+        // output at the mid prices of the route's pairs after fees
+        let spot_out = route.iter().fold(u256_to_f64(amount_in), |amount, step| {
+            amount * step.reserve_out as f64 / step.reserve_in as f64
+                * (FEE_DENOMINATOR - step.fee_bps as u64) as f64 / FEE_DENOMINATOR as f64
+        });
+        let price_impact = if spot_out > 0.0 { (1.0 - u256_to_f64(amount_out) / spot_out).max(0.0) } else { 0.0 };
+
+        Some(Quote {
+            token_in,
+            token_out,
+            amount_in,
+            amount_out,
+            price_impact,
+            reserve_block: route.iter().map(|step| step.reserve_block).min().unwrap_or_default(),
+            route,
+        })
+    }
+}
+
+fn u256_to_f64(value: U256) -> f64 {
+    value.to_string().parse().unwrap_or(f64::MAX)
+}
@@ -0,0 +1,186 @@
+use std::sync::{Arc, Mutex};
+use std::time::{SystemTime, UNIX_EPOCH};
+use serde::Serialize;
+use crate::config::ConfigHandle;
+use crate::domain::entities::address::Address;
+use crate::domain::entities::swap_aggregate::AggregateInterval;
+use crate::domain::entities::swap_log::SwapLog;
+use crate::domain::services::data_storage_service::{DataStorage, DataStorageService, DataStorageTrait};
+
+#[derive(Debug, Clone, Serialize)]
+pub struct CompactionResult {
+    pub compacted_swap_logs: i64,
+    pub compacted_pairs: i64,
+    pub dropped_quarantined_swap_logs: i64,
+    pub dropped_hourly_aggregates: i64,
+    pub min_block: u64,
+    pub finished_at: SystemTime,
+}
+
+// Raw swap logs are kept while they are within every configured limit
+#[derive(Debug, Clone, Copy)]
+pub struct RetentionPolicy {
+    // logs below this block are compacted, 0 when neither time nor depth retention is set
+    pub min_block: u64,
+    pub per_pair: usize,
+    // hourly aggregates of buckets ending before this timestamp are dropped, 0 keeps them
+    pub hourly_min_timestamp: u64,
+}
+
+#[derive(Clone)]
+pub struct SwapCompactionService {
+    config: ConfigHandle,
+    data_service: DataStorageService,
+    last_compaction: Arc<Mutex<Option<CompactionResult>>>,
+}
+
+pub trait SwapCompactionServiceTrait {
+    fn block_timestamp(&self, block: u64) -> u64;
+    fn retention_policy(&self, head_block: u64) -> RetentionPolicy;
+    fn compact_swap_logs(&self, head_block: u64) -> CompactionResult;
+    fn get_last_compaction(&self) -> Option<CompactionResult>;
+}
+
+impl SwapCompactionService {
+    pub fn new(config: ConfigHandle, data_service: DataStorageService) -> Self {
+        SwapCompactionService { config, data_service, last_compaction: Arc::new(Mutex::new(None)) }
+    }
+
+    fn now() -> u64 {
+        SystemTime::now().duration_since(UNIX_EPOCH).map(|duration| duration.as_secs()).unwrap_or_default()
+    }
+
+    fn block_at(&self, timestamp: u64) -> u64 {
+        timestamp.saturating_sub(self.config.get().base_chain().genesis_timestamp) / self.config.get().base_chain().block_time
+    }
+
+    // Last block to compact for one pair: everything below the policy's min block, and for pairs over the count
+    // limit the oldest blocks until at most per_pair logs remain
+    fn compact_through(logs: &im::Vector<SwapLog>, policy: &RetentionPolicy) -> Option<u64> {
+        let mut through = policy.min_block.checked_sub(1);
+        if policy.per_pair > 0 && logs.len() > policy.per_pair {
+            let mut blocks: Vec<u64> = logs.iter()
+                .filter_map(|log| log.block_number.map(|block| block.as_u64()))
+                .collect();
+            blocks.sort_unstable_by(|a, b| b.cmp(a));
+            //whole blocks are compacted, so the watermark never splits a block
+            if let Some(block) = blocks.get(policy.per_pair) {
+                through = std::cmp::max(through, Some(*block));
+            }
+        }
+        through.filter(|through| logs.iter().any(|log| log.block_number.is_some_and(|block| block.as_u64() <= *through)))
+    }
+
+    fn aggregate(&self, storage: &mut DataStorage, policy: &RetentionPolicy, key: Address, swap_log: &SwapLog, block: u64) {
+        let timestamp = self.block_timestamp(block);
+        let protocol_id = if swap_log.protocol_id.is_empty() {
+            storage.token_pairs.get(&key).map(|pair| pair.protocol_id.clone()).unwrap_or_default()
+        } else {
+            swap_log.protocol_id.clone()
+        };
+        for interval in [AggregateInterval::Hourly, AggregateInterval::Daily] {
+            let bucket_start = interval.bucket_start(timestamp);
+            //hourly buckets already past their retention only get the daily aggregate
+            if interval == AggregateInterval::Hourly && bucket_start + interval.seconds() <= policy.hourly_min_timestamp {
+                continue;
+            }
+            storage.swap_aggregate_mut(key, &protocol_id, interval, bucket_start).add(swap_log, block);
+        }
+    }
+}
+
+impl SwapCompactionServiceTrait for SwapCompactionService {
+    // Base produces a block every chain_base_block_time seconds since genesis
+    fn block_timestamp(&self, block: u64) -> u64 {
+        self.config.get().base_chain().genesis_timestamp + block * self.config.get().base_chain().block_time
+    }
+
+    fn retention_policy(&self, head_block: u64) -> RetentionPolicy {
+        let now = Self::now();
+        let mut min_block = 0;
+        if self.config.get().storage.swap_log_retention_secs > 0 {
+            min_block = self.block_at(now.saturating_sub(self.config.get().storage.swap_log_retention_secs));
+        }
+        if self.config.get().storage.swap_log_retention_blocks > 0 && head_block > 0 {
+            min_block = std::cmp::max(min_block, head_block.saturating_sub(self.config.get().storage.swap_log_retention_blocks));
+        }
+        let hourly_min_timestamp = match self.config.get().storage.swap_aggregate_hourly_retention_secs {
+            0 => 0,
+            retention => now.saturating_sub(retention),
+        };
+        RetentionPolicy { min_block, per_pair: self.config.get().storage.swap_log_retention_per_pair, hourly_min_timestamp }
+    }
+
+    // Folds raw swap logs outside the retention policy into hourly and daily aggregates and evicts them,
+    // published as one snapshot
+    fn compact_swap_logs(&self, head_block: u64) -> CompactionResult {
+        let policy = self.retention_policy(head_block);
+        let result = self.data_service.write_batch(|storage| {
+            let mut result = CompactionResult {
+                compacted_swap_logs: 0,
+                compacted_pairs: 0,
+                dropped_quarantined_swap_logs: 0,
+                dropped_hourly_aggregates: 0,
+                min_block: policy.min_block,
+                finished_at: SystemTime::now(),
+            };
+
+            let pairs: Vec<(Address, u64)> = storage.swap_logs.iter()
+                .filter_map(|(key, logs)| Self::compact_through(logs, &policy).map(|through| (*key, through)))
+                .collect();
+            for (key, through) in pairs {
+                let Some(logs) = storage.swap_logs.get(&key).cloned() else {
+                    continue;
+                };
+                let (evicted, kept): (Vec<SwapLog>, Vec<SwapLog>) = logs.into_iter()
+                    .partition(|log| log.block_number.is_some_and(|block| block.as_u64() <= through));
+                if evicted.is_empty() {
+                    continue;
+                }
+                let mut aggregated = 0;
+                for swap_log in evicted.iter().filter(|log| log.removed != Some(true)) {
+                    let block = swap_log.block_number.map(|block| block.as_u64()).unwrap_or_default();
+                    self.aggregate(storage, &policy, key, swap_log, block);
+                    aggregated += 1;
+                }
+                *storage.compacted_swaps.entry(key).or_default() += aggregated;
+                result.compacted_swap_logs += evicted.len() as i64;
+                result.compacted_pairs += 1;
+                if kept.is_empty() {
+                    storage.swap_logs.remove(&key);
+                } else {
+                    storage.swap_logs.insert(key, im::Vector::from(kept));
+                }
+                //highest compacted block, not the cutoff: older logs indexed later still get aggregated
+                let last_block = evicted.iter()
+                    .filter_map(|log| log.block_number.map(|block| block.as_u64()))
+                    .max()
+                    .unwrap_or_default();
+                let watermark = storage.compacted_blocks.entry(key).or_default();
+                *watermark = std::cmp::max(*watermark, last_block);
+            }
+
+            //emitters still unknown after the retention window are not worth keeping
+            if let Some(through) = policy.min_block.checked_sub(1) {
+                for logs in storage.quarantined_swap_logs.iter_mut().map(|(_, logs)| logs) {
+                    let size = logs.len();
+                    logs.retain(|log| log.block_number.is_none_or(|block| block.as_u64() > through));
+                    result.dropped_quarantined_swap_logs += (size - logs.len()) as i64;
+                }
+                storage.quarantined_swap_logs.retain(|_, logs| !logs.is_empty());
+            }
+
+            if policy.hourly_min_timestamp > 0 {
+                result.dropped_hourly_aggregates = storage.retain_swap_aggregates(|aggregate| aggregate.interval != AggregateInterval::Hourly
+                    || aggregate.bucket_start + aggregate.interval.seconds() > policy.hourly_min_timestamp) as i64;
+            }
+            result
+        });
+        *self.last_compaction.lock().unwrap() = Some(result.clone());
+        result
+    }
+
+    fn get_last_compaction(&self) -> Option<CompactionResult> {
+        self.last_compaction.lock().unwrap().clone()
+    }
+}
@@ -0,0 +1,124 @@
+use std::sync::Arc;
+use std::time::SystemTime;
+use tokio::sync::watch;
+use crate::domain::entities::domain_event::DomainEvent;
+use crate::domain::entities::sync_state::{SyncPhase, SyncState};
+use crate::domain::services::event_bus_service::{EventBusService, EventBusServiceTrait};
+
+// Phase of one synchronizer, shared by its clones. Dependants await phase changes instead of polling,
+// every change is published on the event bus.
+#[derive(Clone)]
+pub struct SyncStateMachine {
+    sender: Arc<watch::Sender<SyncState>>,
+    event_bus: EventBusService,
+}
+
+impl SyncStateMachine {
+    pub fn new(synchronizer: &str, event_bus: EventBusService) -> Self {
+        SyncStateMachine {
+            sender: Arc::new(watch::channel(SyncState::new(synchronizer)).0),
+            event_bus,
+        }
+    }
+
+    pub fn get_state(&self) -> SyncState {
+        self.sender.borrow().clone()
+    }
+
+    pub fn phase(&self) -> SyncPhase {
+        self.sender.borrow().phase
+    }
+
+    // Moves to phase, false when already there or the move is not allowed
+    pub fn transition(&self, phase: SyncPhase) -> bool {
+        self.apply(phase, None).is_some()
+    }
+
+    // Moves to Backfilling, returns the phase to go back to when done
+    pub fn begin_backfill(&self, target_block: u64) -> SyncPhase {
+        let previous = self.phase();
+        self.transition(SyncPhase::Backfilling);
+        self.sender.send_modify(|state| state.target_block = std::cmp::max(state.target_block, target_block));
+        previous
+    }
+
+    // Running on a fallback, the error stays visible until the next one
+    pub fn degrade(&self, error: String) {
+        if self.apply(SyncPhase::Degraded, Some(error.clone())).is_none() {
+            self.record_error(error);
+        }
+    }
+
+    pub fn fail(&self, error: String) {
+        self.apply(SyncPhase::Failed, Some(error));
+    }
+
+    // Error the synchronizer recovered from by itself, e.g. a retried range
+    pub fn record_error(&self, error: String) {
+        self.sender.send_modify(|state| {
+            state.last_error = Some(error);
+            state.last_error_at = Some(SystemTime::now());
+            state.updated_at = SystemTime::now();
+        });
+    }
+
+    pub fn progress(&self, block: u64, target_block: u64) {
+        self.sender.send_modify(|state| {
+            let now = SystemTime::now();
+            state.block = std::cmp::max(state.block, block);
+            state.target_block = std::cmp::max(state.target_block, target_block);
+            if state.block >= state.target_block {
+                state.caught_up_at = Some(now);
+            }
+            state.updated_at = now;
+        });
+    }
+
+    // Completes once the synchronizer follows the head, or failed
+    pub async fn wait_until_settled(&self) -> SyncState {
+        let mut receiver = self.sender.subscribe();
+        let state = receiver.wait_for(|state| state.is_settled()).await
+            .map(|state| state.clone());
+        //the sender lives as long as self
+        state.unwrap_or_else(|_| self.get_state())
+    }
+
+    pub fn subscribe(&self) -> watch::Receiver<SyncState> {
+        self.sender.subscribe()
+    }
+
+    fn apply(&self, phase: SyncPhase, error: Option<String>) -> Option<SyncPhase> {
+        let mut previous = None;
+        let mut refused = false;
+        self.sender.send_if_modified(|state| {
+            if !state.phase.can_transition_to(phase) {
+                refused = state.phase != phase;
+                return false;
+            }
+            let now = SystemTime::now();
+            previous = Some(state.phase);
+            state.phase = phase;
+            state.since = now;
+            state.updated_at = now;
+            if let Some(error) = &error {
+                state.last_error = Some(error.clone());
+                state.last_error_at = Some(now);
+            }
+            true
+        });
+
+        let state = self.get_state();
+        if refused {
+            log::warn!("Sync {}: transition {} -> {} not allowed", state.synchronizer, state.phase, phase);
+        }
+        let previous_phase = previous?;
+        log::info!("Sync {}: {} -> {}", state.synchronizer, previous_phase, phase);
+        self.event_bus.publish(DomainEvent::SyncStateChanged {
+            synchronizer: state.synchronizer,
+            phase,
+            previous_phase,
+            error,
+        });
+        Some(previous_phase)
+    }
+}
@@ -0,0 +1,243 @@
+use std::collections::HashMap;
+use std::any::Any;
+use std::future::Future;
+use std::panic::AssertUnwindSafe;
+use std::sync::{Arc, Mutex};
+use std::time::SystemTime;
+use futures_util::FutureExt;
+use tokio::sync::watch;
+use tokio::task::JoinHandle;
+use tokio::time::{sleep, timeout, Duration, Instant};
+use crate::config::ConfigHandle;
+use crate::domain::entities::alert::AlertSeverity;
+use crate::domain::entities::supervised_task::{ShutdownMode, SupervisedTask, TaskState};
+use crate::domain::services::alert_service::{AlertService, AlertServiceTrait};
+
+// Shutdown request shared by the supervisor and the tasks that drain themselves
+#[derive(Clone)]
+pub struct ShutdownSignal {
+    sender: Arc<watch::Sender<bool>>,
+}
+
+impl ShutdownSignal {
+    pub fn new() -> Self {
+        ShutdownSignal { sender: Arc::new(watch::channel(false).0) }
+    }
+
+    pub fn trigger(&self) {
+        self.sender.send_replace(true);
+    }
+
+    pub fn is_triggered(&self) -> bool {
+        *self.sender.borrow()
+    }
+
+    // Completes once shutdown is requested
+    pub async fn triggered(&self) {
+        let mut receiver = self.sender.subscribe();
+        let _ = receiver.wait_for(|triggered| *triggered).await;
+    }
+
+    // Sleeps for duration or until shutdown is requested, false when interrupted
+    pub async fn sleep(&self, duration: Duration) -> bool {
+        tokio::select! {
+            _ = sleep(duration) => true,
+            _ = self.triggered() => false,
+        }
+    }
+}
+
+impl Default for ShutdownSignal {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+// Supervisor loop of each task, awaited on shutdown
+type TaskHandles = Arc<Mutex<Vec<(String, JoinHandle<()>)>>>;
+
+// Runs the long-lived tasks of a process, restarts failed ones with backoff and stops all of them on shutdown
+#[derive(Clone)]
+pub struct TaskSupervisor {
+    config: ConfigHandle,
+    shutdown: ShutdownSignal,
+    alerts: Option<AlertService>,
+    tasks: Arc<Mutex<HashMap<String, SupervisedTask>>>,
+    handles: TaskHandles,
+}
+
+impl TaskSupervisor {
+    // Failures are raised as alerts when the process has an alert service
+    pub fn new(config: ConfigHandle, shutdown: ShutdownSignal, alerts: Option<AlertService>) -> Self {
+        TaskSupervisor {
+            config,
+            shutdown,
+            alerts,
+            tasks: Arc::new(Mutex::new(HashMap::new())),
+            handles: Arc::new(Mutex::new(vec![])),
+        }
+    }
+
+    pub fn shutdown_signal(&self) -> ShutdownSignal {
+        self.shutdown.clone()
+    }
+
+    // Starts the task built by start and starts it again after an error or panic, until it finishes or shutdown
+    pub fn supervise<F, Fut>(&self, name: &str, shutdown_mode: ShutdownMode, start: F)
+    where
+        F: Fn() -> Fut + Send + 'static,
+        Fut: Future<Output = Result<(), String>> + Send + 'static,
+    {
+        self.tasks.lock().unwrap().insert(name.to_string(), SupervisedTask {
+            name: name.to_string(),
+            state: TaskState::Running,
+            shutdown_mode,
+            restarts: 0,
+            last_error: None,
+            last_failed_at: None,
+            started_at: SystemTime::now(),
+            next_restart_at: None,
+        });
+
+        let supervisor = self.clone();
+        let task_name = name.to_string();
+        let handle = tokio::spawn(async move {
+            let initial_backoff = Duration::from_secs(supervisor.config.get().sync.task_restart_backoff_initial);
+            let max_backoff = Duration::from_secs(supervisor.config.get().sync.task_restart_backoff_max);
+            let mut backoff = initial_backoff;
+            loop {
+                let started_at = Instant::now();
+                //a panic is a failure like an error, aborting this loop drops the task with it
+                let run = AssertUnwindSafe(start()).catch_unwind();
+                let result = match shutdown_mode {
+                    ShutdownMode::Drain => run.await,
+                    ShutdownMode::Cancel => tokio::select! {
+                        result = run => result,
+                        _ = supervisor.shutdown.triggered() => Ok(Ok(())),
+                    },
+                };
+                let error = match result {
+                    Ok(Ok(())) => None,
+                    Ok(Err(e)) => Some(e),
+                    Err(panic) => Some(format!("task panicked: {}", panic_message(panic.as_ref()))),
+                };
+
+                if supervisor.shutdown.is_triggered() {
+                    if let Some(e) = &error {
+                        log::warn!("Task {} failed during shutdown: {}", task_name, e);
+                    }
+                    supervisor.update(&task_name, |task| task.state = TaskState::Stopped);
+                    return;
+                }
+                let Some(error) = error else {
+                    log::info!("Task {} finished", task_name);
+                    supervisor.update(&task_name, |task| task.state = TaskState::Finished);
+                    return;
+                };
+
+                //a run that lasted longer than the longest delay is no failure loop, start over
+                if started_at.elapsed() > max_backoff {
+                    backoff = initial_backoff;
+                }
+                log::error!("Task {} failed: {}. Restarting in {} s", task_name, error, backoff.as_secs());
+                supervisor.update(&task_name, |task| {
+                    task.state = TaskState::Restarting;
+                    task.last_error = Some(error.clone());
+                    task.last_failed_at = Some(SystemTime::now());
+                    task.next_restart_at = Some(SystemTime::now() + backoff);
+                });
+                if let Some(alerts) = &supervisor.alerts {
+                    alerts.raise(AlertSeverity::Critical, "task_failed", task_name.clone(),
+                        format!("Task {} failed and restarts in {} s: {}", task_name, backoff.as_secs(), error));
+                }
+
+                if !supervisor.shutdown.sleep(backoff).await {
+                    supervisor.update(&task_name, |task| task.state = TaskState::Stopped);
+                    return;
+                }
+                backoff = std::cmp::min(backoff * 2, max_backoff);
+                supervisor.update(&task_name, |task| {
+                    task.state = TaskState::Running;
+                    task.restarts += 1;
+                    task.started_at = SystemTime::now();
+                    task.next_restart_at = None;
+                });
+            }
+        });
+        let mut handles = self.handles.lock().unwrap();
+        //one-off tasks end long before shutdown
+        handles.retain(|(_, handle)| !handle.is_finished());
+        handles.push((name.to_string(), handle));
+    }
+
+    // Forgets the finished tasks whose name starts with prefix, one-off tasks like jobs would pile up otherwise
+    pub fn remove_finished(&self, prefix: &str) {
+        self.tasks.lock().unwrap()
+            .retain(|name, task| !(name.starts_with(prefix) && task.state == TaskState::Finished));
+    }
+
+    fn update(&self, name: &str, update: impl FnOnce(&mut SupervisedTask)) {
+        if let Some(task) = self.tasks.lock().unwrap().get_mut(name) {
+            update(task);
+        }
+    }
+
+    pub fn get_tasks(&self) -> Vec<SupervisedTask> {
+        let mut tasks: Vec<SupervisedTask> = self.tasks.lock().unwrap().values().cloned().collect();
+        tasks.sort_by(|a, b| a.name.cmp(&b.name));
+        tasks
+    }
+
+    // Waits for SIGTERM or SIGINT, then shuts the tasks down
+    pub async fn run_until_signal(&self) {
+        wait_for_signal().await;
+        self.shutdown().await;
+    }
+
+    // Requests shutdown and waits up to the grace period for the tasks, the rest is aborted
+    pub async fn shutdown(&self) {
+        let grace_period = Duration::from_secs(self.config.get().sync.shutdown_grace_period);
+        log::info!("Shutting down, waiting up to {} s for running tasks ...", grace_period.as_secs());
+        self.shutdown.trigger();
+
+        let deadline = Instant::now() + grace_period;
+        let handles: Vec<(String, JoinHandle<()>)> = self.handles.lock().unwrap().drain(..).collect();
+        for (name, mut handle) in handles {
+            if timeout(deadline.saturating_duration_since(Instant::now()), &mut handle).await.is_err() {
+                log::warn!("Task {} did not stop within the grace period, aborting it", name);
+                handle.abort();
+                self.update(&name, |task| task.state = TaskState::Stopped);
+            }
+        }
+        log::info!("All tasks stopped");
+    }
+}
+
+fn panic_message(panic: &(dyn Any + Send)) -> String {
+    panic.downcast_ref::<&str>().map(|message| message.to_string())
+        .or_else(|| panic.downcast_ref::<String>().cloned())
+        .unwrap_or_else(|| "unknown panic".to_string())
+}
+
+#[cfg(unix)]
+async fn wait_for_signal() {
+    use tokio::signal::unix::{signal, SignalKind};
+    let mut terminate = match signal(SignalKind::terminate()) {
+        Ok(terminate) => terminate,
+        Err(e) => {
+            log::error!("SIGTERM handler not installed: {}", e);
+            let _ = tokio::signal::ctrl_c().await;
+            return;
+        }
+    };
+    tokio::select! {
+        _ = terminate.recv() => log::info!("SIGTERM received"),
+        _ = tokio::signal::ctrl_c() => log::info!("SIGINT received"),
+    }
+}
+
+#[cfg(not(unix))]
+async fn wait_for_signal() {
+    let _ = tokio::signal::ctrl_c().await;
+    log::info!("Ctrl-C received");
+}
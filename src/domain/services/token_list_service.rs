@@ -0,0 +1,153 @@
+use std::error::Error;
+use std::fs::File;
+use std::io::BufReader;
+use chrono::{DateTime, SecondsFormat, Utc};
+use serde::Serialize;
+use crate::config::ConfigHandle;
+use crate::domain::entities::address::Address;
+use crate::domain::entities::token::Token;
+use crate::domain::entities::token_list::{TokenInfo, TokenList, TokenListEntry, TokenListVersion};
+use crate::domain::services::data_storage_service::{DataStorage, DataStorageService, DataStorageTrait};
+
+// Outcome of one imported list
+#[derive(Debug, Clone, Serialize)]
+pub struct TokenListImport {
+    pub list: String,
+    // entries of the indexed chain
+    pub entries: usize,
+    // entries of other chains or with an invalid address
+    pub skipped: usize,
+    // stored tokens that took the list's metadata
+    pub updated_tokens: i64,
+}
+
+// Tokens served at /tokenlist.json
+#[derive(Debug, Clone, Copy)]
+pub struct TokenListFilter {
+    pub include_high_risk: bool,
+    pub min_active_pairs: usize,
+    pub verified_only: bool,
+}
+
+#[derive(Clone)]
+pub struct TokenListService {
+    config: ConfigHandle,
+    data_service: DataStorageService,
+}
+
+pub trait TokenListServiceTrait {
+    fn import(&self, list: &TokenList) -> TokenListImport;
+    fn import_file(&self, path: &str) -> Result<TokenListImport, Box<dyn Error>>;
+    fn import_configured_files(&self) -> Vec<TokenListImport>;
+    fn default_filter(&self) -> TokenListFilter;
+    fn export(&self, storage: &DataStorage, filter: &TokenListFilter) -> TokenList;
+}
+
+impl TokenListService {
+    pub fn new(config: ConfigHandle, data_service: DataStorageService) -> Self {
+        TokenListService { config, data_service }
+    }
+
+    // Token as a list entry, none when its metadata can't satisfy the schema
+    fn token_info(chain_id: u64, token: &Token) -> Option<TokenInfo> {
+        let decimals: u8 = token.decimals.parse().ok()?;
+        let name = token.name.trim();
+        let symbol = token.symbol.trim();
+        //"No data" stands for metadata calls that reverted
+        if token.name == "No data" || name.is_empty() || name.chars().count() > 40
+            || symbol.is_empty() || symbol.chars().count() > 20 {
+            return None;
+        }
+        Some(TokenInfo {
+            chain_id,
+            address: token.address.to_string(),
+            name: name.to_string(),
+            symbol: symbol.to_string(),
+            decimals,
+            logo_uri: token.logo_uri.clone(),
+            tags: token.tags.clone(),
+            extensions: None,
+        })
+    }
+}
+
+impl TokenListServiceTrait for TokenListService {
+    fn import(&self, list: &TokenList) -> TokenListImport {
+        let chain_id = self.config.get().base_chain().chain_id;
+        let mut entries = vec![];
+        let mut skipped = 0;
+        for token in &list.tokens {
+            let address = match token.address.parse::<Address>() {
+                Ok(address) if token.chain_id == chain_id => address,
+                _ => {
+                    skipped += 1;
+                    continue;
+                }
+            };
+            entries.push((address, TokenListEntry {
+                list: list.name.clone(),
+                name: token.name.clone(),
+                symbol: token.symbol.clone(),
+                logo_uri: token.logo_uri.clone(),
+                tags: token.tags.clone(),
+            }));
+        }
+        let imported = entries.len();
+        let updated_tokens = self.data_service.add_token_list_entries(entries);
+        TokenListImport { list: list.name.clone(), entries: imported, skipped, updated_tokens }
+    }
+
+    fn import_file(&self, path: &str) -> Result<TokenListImport, Box<dyn Error>> {
+        let list: TokenList = serde_json::from_reader(BufReader::new(File::open(path)?))
+            .map_err(|e| format!("{}: {}", path, e))?;
+        Ok(self.import(&list))
+    }
+
+    // A list that can't be read is logged and skipped
+    fn import_configured_files(&self) -> Vec<TokenListImport> {
+        let mut imports = vec![];
+        for path in &self.config.get().token_lists.files {
+            match self.import_file(path) {
+                Ok(import) => {
+                    log::info!("Token list {} imported from {}: {} tokens, {} skipped, {} stored tokens updated",
+                        import.list, path, import.entries, import.skipped, import.updated_tokens);
+                    imports.push(import);
+                }
+                Err(e) => log::error!("Token list import failed: {}", e),
+            }
+        }
+        imports
+    }
+
+    fn default_filter(&self) -> TokenListFilter {
+        let token_lists = &self.config.get().token_lists;
+        TokenListFilter {
+            include_high_risk: token_lists.include_high_risk,
+            min_active_pairs: token_lists.min_active_pairs,
+            verified_only: false,
+        }
+    }
+
+    // The storage version is the patch version, it moves with every change of the served data
+    fn export(&self, storage: &DataStorage, filter: &TokenListFilter) -> TokenList {
+        let config = self.config.get();
+        let chain_id = config.base_chain().chain_id;
+        let mut tokens: Vec<TokenInfo> = storage.tokens.values()
+            .filter(|token| filter.include_high_risk || !token.high_risk)
+            .filter(|token| token.active_pairs.len() >= filter.min_active_pairs)
+            .filter(|token| !filter.verified_only || token.verified)
+            .filter_map(|token| Self::token_info(chain_id, token))
+            .collect();
+        tokens.sort_by(|a, b| a.symbol.to_lowercase().cmp(&b.symbol.to_lowercase()).then(a.address.cmp(&b.address)));
+
+        TokenList {
+            name: config.token_lists.name.clone(),
+            timestamp: DateTime::<Utc>::from(storage.last_modified).to_rfc3339_opts(SecondsFormat::Millis, true),
+            version: TokenListVersion { major: 1, minor: 0, patch: storage.version },
+            tokens,
+            logo_uri: None,
+            keywords: vec![],
+            tags: None,
+        }
+    }
+}
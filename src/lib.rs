@@ -0,0 +1,7 @@
+// Services of the token data provider, the binary wires them into commands and the tests run them against a mock RPC
+pub mod domain;
+pub mod config;
+pub mod ports;
+pub mod adapters;
+pub mod cli;
+pub mod commands;
@@ -1,14 +1,36 @@
-mod domain;
-mod config;
-mod ports;
-mod adapters;
-
 use std::env;
 use std::sync::Arc;
-use crate::config::CONFIG;
-use crate::ports::chain::base::base_uniswap_v2_client_service::{BaseUniswapV2ClientService, BaseUniswapV2ClientServiceTrait};
-use crate::adapters::token_api::create_token_rest_api;
-use crate::ports::chain::base::base_uniswap_v2_swap_sync::{BaseUniswapV2SwapSynchronizer, BaseUniswapV2SwapSynchronizerTrait};
+use std::time::SystemTime;
+use clap::Parser;
+use token_data_provider::commands;
+use ethers::providers::Provider;
+use tokio::time::{sleep, Duration};
+use token_data_provider::config::{load_app_config, ConfigHandle};
+use token_data_provider::ports::chain::base::base_uniswap_v2_client_service::BaseUniswapV2ClientServiceTrait;
+use token_data_provider::ports::chain::base::base_uniswap_v2_indexer::BaseUniswapV2Indexer;
+use token_data_provider::ports::chain::rpc_pool::{connect_provider, RpcPool};
+use token_data_provider::adapters::token_api::create_token_rest_api;
+use token_data_provider::adapters::admin_api::create_admin_rest_api;
+use token_data_provider::adapters::rejections::handle_rejection;
+use token_data_provider::adapters::export_api::create_export_rest_api;
+use token_data_provider::adapters::event_api::create_event_rest_api;
+use token_data_provider::adapters::token_list_api::create_token_list_rest_api;
+use token_data_provider::adapters::quote_api::create_quote_rest_api;
+use token_data_provider::adapters::pair_api::create_pair_rest_api;
+use token_data_provider::cli::{Cli, Command, ConfigCommand, InspectCommand};
+use token_data_provider::domain::entities::supervised_task::ShutdownMode;
+use token_data_provider::domain::services::data_storage_service::{DataStorageService, DataStorageTrait};
+use token_data_provider::domain::services::event_bus_service::EventBusService;
+use token_data_provider::domain::services::task_supervisor::{ShutdownSignal, TaskSupervisor};
+use warp::Filter;
+use token_data_provider::domain::services::api_key_service::{ApiKeyService, ApiKeyServiceTrait};
+use token_data_provider::ports::chain::base::base_uniswap_v2_swap_sync::BaseUniswapV2SwapSynchronizerTrait;
+use token_data_provider::domain::services::swap_compaction_service::SwapCompactionServiceTrait;
+use token_data_provider::domain::services::pair_activity_aggregator::PairActivityAggregatorTrait;
+use token_data_provider::domain::services::alert_service::AlertServiceTrait;
+use token_data_provider::domain::services::token_list_service::{TokenListService, TokenListServiceTrait};
+
+type Indexer = BaseUniswapV2Indexer<Provider<RpcPool>>;
 
 #[tokio::main]
 async fn main() -> Result<(), Box<dyn std::error::Error>> {
@@ -17,37 +39,279 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
         env::set_var("RUST_LOG", "info");
     }
     env_logger::init();
+
+    //config check reports configuration errors itself, every other command needs a valid one
+    let cli = Cli::parse();
+    match cli.command {
+        None => run_all(load_app_config()?).await,
+        Some(Command::Serve) => serve(load_app_config()?).await,
+        Some(Command::Index) => index(load_app_config()?).await,
+        Some(Command::Backfill(args)) => commands::backfill(load_app_config()?, args).await,
+        Some(Command::Inspect(InspectCommand::Pair { address, format })) => commands::inspect_pair(load_app_config()?, address, format).await,
+        Some(Command::Inspect(InspectCommand::Token { address, format })) => commands::inspect_token(load_app_config()?, address, format).await,
+        Some(Command::Config(ConfigCommand::Check)) => commands::config_check().await,
+        Some(Command::Export(args)) => commands::export(load_app_config()?, args).await,
+    }
+}
+
+// Indexer over the last storage snapshot and the configured RPC endpoint pool.
+// Loaded before any task starts, the snapshot tasks would otherwise replace it with a partial storage.
+async fn connect_indexer(config: ConfigHandle) -> Result<Indexer, Box<dyn std::error::Error>> {
+    let data_service = DataStorageService::new();
+    let path = config.get().storage.snapshot_path.clone();
+    commands::load_snapshot(&data_service, &path)
+        .map_err(|e| format!("Storage snapshot {} cannot be read, not starting over it: {}", path, e))?;
+    let provider = connect_provider(&config.get()).await?;
+    Ok(BaseUniswapV2Indexer::new(config, data_service, provider))
+}
+
+// Sync, snapshots and REST API in one process
+async fn run_all(config: ConfigHandle) -> Result<(), Box<dyn std::error::Error>> {
     log::info!("Starting application...");
+    let indexer = connect_indexer(config.clone()).await?;
+    TokenListService::new(config.clone(), indexer.data_service.clone()).import_configured_files();
+    let supervisor = TaskSupervisor::new(config.clone(), indexer.shutdown.clone(), Some(indexer.alerts.clone()));
+
+    spawn_rpc_health_check(&supervisor, &indexer);
+    spawn_event_subscribers(&supervisor, &indexer);
+    spawn_chain_data_sync(&supervisor, &indexer);
+    spawn_swap_sync(&supervisor, &indexer);
+    spawn_swap_compaction(&supervisor, &indexer);
+    spawn_storage_snapshot(&supervisor, config.clone(), indexer.data_service.clone());
+    spawn_config_reload(&supervisor, config.clone());
+    spawn_rest_api(&supervisor, config.clone(), indexer.data_service.clone(), indexer.event_bus.clone(), Some(indexer.clone()));
+
+    supervisor.run_until_signal().await;
+    flush_storage_snapshot(&config, &indexer.data_service).await
+}
+
+// Serves the snapshots of a separate index process, there is no indexer and no events in this one
+async fn serve(config: ConfigHandle) -> Result<(), Box<dyn std::error::Error>> {
+    log::info!("Starting application in serve mode...");
+    let data_service = DataStorageService::new();
+    let supervisor = TaskSupervisor::new(config.clone(), ShutdownSignal::new(), None);
+
+    spawn_snapshot_reload(&supervisor, config.clone(), data_service.clone());
+    spawn_config_reload(&supervisor, config.clone());
+    spawn_rest_api(&supervisor, config, data_service, EventBusService::new(), None);
+
+    supervisor.run_until_signal().await;
+    Ok(())
+}
 
+async fn index(config: ConfigHandle) -> Result<(), Box<dyn std::error::Error>> {
+    log::info!("Starting application in index mode...");
+    let indexer = connect_indexer(config.clone()).await?;
+    TokenListService::new(config.clone(), indexer.data_service.clone()).import_configured_files();
+    let supervisor = TaskSupervisor::new(config.clone(), indexer.shutdown.clone(), Some(indexer.alerts.clone()));
 
-    //base_uniswap_v2_client_service.init_chain_data_sync();
+    spawn_rpc_health_check(&supervisor, &indexer);
+    spawn_event_subscribers(&supervisor, &indexer);
+    spawn_chain_data_sync(&supervisor, &indexer);
+    spawn_swap_sync(&supervisor, &indexer);
+    spawn_swap_compaction(&supervisor, &indexer);
+    spawn_storage_snapshot(&supervisor, config.clone(), indexer.data_service.clone());
+    spawn_config_reload(&supervisor, config.clone());
 
-    //log::info!("HTTP SERVER PORT::{}",CONFIG.default.server_port_http);
+    supervisor.run_until_signal().await;
+    flush_storage_snapshot(&config, &indexer.data_service).await
+}
 
-    let base_uniswap_v2_swap_sync_handle = tokio::spawn(async{
-        let base_uniswap_v2_swap_sync = Arc::new(BaseUniswapV2SwapSynchronizer);
-        let _ = base_uniswap_v2_swap_sync.synchronize_swaps().await;
+fn spawn_rpc_health_check(supervisor: &TaskSupervisor, indexer: &Indexer) {
+    let pool: &RpcPool = indexer.provider.as_ref().as_ref();
+    pool.supervise_health_check(supervisor, indexer.config.get().base_chain().rpc_health_check_interval);
+}
+
+// Consumers of the domain events published by the synchronizers, they only return when the bus is gone
+fn spawn_event_subscribers(supervisor: &TaskSupervisor, indexer: &Indexer) {
+    let aggregator = Arc::new(indexer.pair_activity_aggregator());
+    supervisor.supervise("pair_activity_aggregator", ShutdownMode::Cancel, move || {
+        let aggregator = aggregator.clone();
+        async move {
+            aggregator.run().await;
+            Err("event bus closed".to_string())
+        }
+    });
+    let alerts = indexer.alerts.clone();
+    supervisor.supervise("alerts", ShutdownMode::Cancel, move || {
+        let alerts = alerts.clone();
+        async move {
+            alerts.run().await;
+            Err("event bus closed".to_string())
+        }
     });
+}
 
-    let base_uniswap_v2_handle = tokio::spawn(async {
-        log::info!("Starting Base Newtork Uniswap V2 Sync service ...");
-        let base_uniswap_v2_client_service = Arc::new(BaseUniswapV2ClientService);
-        let _ = base_uniswap_v2_client_service.init_chain_data_sync().await;
+fn spawn_swap_sync(supervisor: &TaskSupervisor, indexer: &Indexer) {
+    let base_uniswap_v2_swap_sync = indexer.swap_sync.clone();
+    supervisor.supervise("swap_sync", ShutdownMode::Drain, move || {
+        let base_uniswap_v2_swap_sync = base_uniswap_v2_swap_sync.clone();
+        async move {
+            base_uniswap_v2_swap_sync.synchronize_swaps().await.map_err(|e| e.to_string())
+        }
     });
+}
 
-    let rest_api_handle = tokio::spawn(async {
-        log::info!("Starting REST API server on port {} ...", CONFIG.default.server_port_http);
-        let api = create_token_rest_api();
-        warp::serve(api).run(([0, 0, 0, 0], CONFIG.default.server_port_http)).await;
+// Keeps raw swap logs within the retention policy, older ones live on as hourly/daily aggregates
+fn spawn_swap_compaction(supervisor: &TaskSupervisor, indexer: &Indexer) {
+    let indexer = indexer.clone();
+    supervisor.supervise("swap_compaction", ShutdownMode::Cancel, move || {
+        let indexer = indexer.clone();
+        async move {
+            loop {
+                sleep(Duration::from_secs(indexer.config.get().storage.swap_compaction_interval)).await;
+                let head_block = indexer.swap_sync.get_last_processed_block().await;
+                let compaction = indexer.compaction.clone();
+                let result = tokio::task::spawn_blocking(move || {
+                    compaction.compact_swap_logs(head_block)
+                }).await.map_err(|e| format!("Swap log compaction task failed: {:?}", e))?;
+                let usage = indexer.data_service.get_memory_usage();
+                log::info!("Swap logs compacted: {} logs of {} pairs (retention min block {}), {} quarantined and {} hourly aggregates dropped; \
+                    storage ~{} KiB ({} swap logs, {} aggregates), process RSS {:?} KiB",
+                    result.compacted_swap_logs, result.compacted_pairs, result.min_block,
+                    result.dropped_quarantined_swap_logs, result.dropped_hourly_aggregates,
+                    usage.estimated_total_bytes / 1024, usage.swap_logs, usage.swap_aggregates,
+                    usage.process_rss_bytes.map(|bytes| bytes / 1024));
+            }
+        }
     });
+}
 
-    if let Err(e) = tokio::try_join!(
-        base_uniswap_v2_handle,
-        base_uniswap_v2_swap_sync_handle,
-        rest_api_handle
-    ) {
-        log::error!("Error occurred while joining tasks: {:?}", e);
-    }
+fn spawn_chain_data_sync(supervisor: &TaskSupervisor, indexer: &Indexer) {
+    let base_uniswap_v2_client_service = indexer.client_service.clone();
+    supervisor.supervise("chain_data_sync", ShutdownMode::Drain, move || {
+        let base_uniswap_v2_client_service = base_uniswap_v2_client_service.clone();
+        async move {
+            log::info!("Starting Base Newtork Uniswap V2 Sync service ...");
+            base_uniswap_v2_client_service.init_chain_data_sync().await.map_err(|e| e.to_string())
+        }
+    });
+}
 
+// Saves the storage every storage_snapshot_interval, the last snapshot is written once all tasks stopped
+fn spawn_storage_snapshot(supervisor: &TaskSupervisor, config: ConfigHandle, data_service: DataStorageService) {
+    let shutdown = supervisor.shutdown_signal();
+    supervisor.supervise("storage_snapshot", ShutdownMode::Drain, move || {
+        let config = config.clone();
+        let data_service = data_service.clone();
+        let shutdown = shutdown.clone();
+        async move {
+            let path = config.get().storage.snapshot_path.clone();
+            while shutdown.sleep(Duration::from_secs(config.get().storage.snapshot_interval)).await {
+                let snapshot_service = data_service.clone();
+                let snapshot_path = path.clone();
+                let result = tokio::task::spawn_blocking(move || {
+                    snapshot_service.save_snapshot(&snapshot_path).map_err(|e| e.to_string())
+                }).await;
+                match result {
+                    Ok(Ok(())) => log::info!("Storage snapshot saved to {}", path),
+                    Ok(Err(e)) => log::error!("Storage snapshot failed: {}", e),
+                    Err(e) => log::error!("Storage snapshot task failed: {:?}", e),
+                }
+            }
+            Ok(())
+        }
+    });
+}
+
+// Checkpoint of everything the stopped synchronizers stored
+async fn flush_storage_snapshot(config: &ConfigHandle, data_service: &DataStorageService) -> Result<(), Box<dyn std::error::Error>> {
+    let path = config.get().storage.snapshot_path.clone();
+    let snapshot_service = data_service.clone();
+    let snapshot_path = path.clone();
+    tokio::task::spawn_blocking(move || {
+        snapshot_service.save_snapshot(&snapshot_path).map_err(|e| e.to_string())
+    }).await??;
+    log::info!("Storage snapshot saved to {}, shutdown complete", path);
     Ok(())
 }
+
+// Serve mode picks up snapshots written by a separate index process
+fn spawn_snapshot_reload(supervisor: &TaskSupervisor, config: ConfigHandle, data_service: DataStorageService) {
+    supervisor.supervise("snapshot_reload", ShutdownMode::Cancel, move || {
+        let config = config.clone();
+        let data_service = data_service.clone();
+        async move {
+            let path = config.get().storage.snapshot_path.clone();
+            let mut loaded_at: Option<SystemTime> = None;
+            loop {
+                let modified_at = std::fs::metadata(&path).and_then(|metadata| metadata.modified()).ok();
+                match modified_at {
+                    Some(modified_at) if loaded_at.is_none_or(|loaded_at| modified_at > loaded_at) => {
+                        let snapshot_service = data_service.clone();
+                        let snapshot_path = path.clone();
+                        let result = tokio::task::spawn_blocking(move || {
+                            snapshot_service.load_snapshot(&snapshot_path).map_err(|e| e.to_string())
+                        }).await;
+                        match result {
+                            Ok(Ok(_)) => {
+                                loaded_at = Some(modified_at);
+                                log::info!("Storage snapshot loaded from {}", path);
+                            }
+                            Ok(Err(e)) => log::error!("Storage snapshot load failed: {}", e),
+                            Err(e) => log::error!("Storage snapshot load task failed: {:?}", e),
+                        }
+                    }
+                    Some(_) => {}
+                    None => log::warn!("Storage snapshot {} not found", path),
+                }
+                sleep(Duration::from_secs(config.get().storage.snapshot_interval)).await;
+            }
+        }
+    });
+}
+
+// Applies changed settings of config.toml and the environment on SIGHUP
+#[cfg(unix)]
+fn spawn_config_reload(supervisor: &TaskSupervisor, config: ConfigHandle) {
+    use tokio::signal::unix::{signal, SignalKind};
+    supervisor.supervise("config_reload", ShutdownMode::Cancel, move || {
+        let config = config.clone();
+        async move {
+            let mut hangup = signal(SignalKind::hangup()).map_err(|e| e.to_string())?;
+            while hangup.recv().await.is_some() {
+                log::info!("SIGHUP received, reloading configuration");
+                if let Err(e) = config.reload().map_err(|e| e.to_string()) {
+                    log::error!("{}", e);
+                }
+            }
+            Ok(())
+        }
+    });
+}
+
+// Reload through the admin API only
+#[cfg(not(unix))]
+fn spawn_config_reload(_supervisor: &TaskSupervisor, _config: ConfigHandle) {}
+
+// Stops accepting connections on shutdown and finishes the requests in flight
+fn spawn_rest_api(supervisor: &TaskSupervisor, config: ConfigHandle, data_service: DataStorageService,
+                  event_bus: EventBusService, indexer: Option<Indexer>) {
+    //keys outlive restarts of the API task
+    let api_keys = ApiKeyService::new();
+    api_keys.init(&config.get());
+    api_keys.restore_api_keys(data_service.get_api_keys());
+    let api_supervisor = supervisor.clone();
+    supervisor.supervise("rest_api", ShutdownMode::Drain, move || {
+        let shutdown = api_supervisor.shutdown_signal();
+        let api = create_token_rest_api(config.clone(), data_service.clone(), api_keys.clone())
+            .or(create_pair_rest_api(config.clone(), data_service.clone(), api_keys.clone()))
+            .or(create_token_list_rest_api(config.clone(), data_service.clone(), api_keys.clone()))
+            .or(create_quote_rest_api(config.clone(), data_service.clone(), api_keys.clone()))
+            .or(create_export_rest_api(data_service.clone(), api_keys.clone()))
+            .or(create_event_rest_api(event_bus.clone(), shutdown.clone(), api_keys.clone()))
+            .or(create_admin_rest_api(config.clone(), data_service.clone(), api_keys.clone(), event_bus.clone(),
+                                      api_supervisor.clone(), indexer.clone()))
+            .recover(handle_rejection);
+        let port = config.get().api.port_http;
+        async move {
+            log::info!("Starting REST API server on port {} ...", port);
+            let (_, server) = warp::serve(api)
+                .try_bind_with_graceful_shutdown(([0, 0, 0, 0], port), async move { shutdown.triggered().await })
+                .map_err(|e| e.to_string())?;
+            server.await;
+            log::info!("REST API server stopped");
+            Ok(())
+        }
+    });
+}
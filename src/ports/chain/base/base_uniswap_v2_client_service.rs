@@ -1,18 +1,30 @@
-use std::collections::HashMap;
+use std::collections::{HashMap, HashSet};
 use std::error::Error;
 use std::sync::Arc;
 use std::time::SystemTime;
 use ethers::contract::abigen;
-use ethers::providers::{Http, Middleware, Provider};
+use ethers::providers::Middleware;
+use crate::ports::chain::chain_transport::{ChainMiddleware, SubscriptionTransport};
+use crate::ports::chain::quorum::{QuorumService, QuorumServiceTrait};
 use async_trait::async_trait;
-use ethers::types::{Address, Filter, Log, U256};
-use crate::config::CONFIG;
+use ethers::types::U256;
+use crate::config::{ConfigHandle, BASE_CHAIN};
 use log;
-use tokio::time::{sleep, Duration};
+use tokio::sync::Mutex;
+use futures_util::StreamExt;
+use tokio::time::{sleep_until, timeout_at, Duration, Instant};
+use crate::domain::entities::address::Address;
+use crate::domain::entities::protocol::Protocol;
 use crate::domain::entities::token::Token;
 use crate::domain::entities::token_pair::TokenPair;
 use crate::domain::entities::update_log::UpdateLog;
-use crate::domain::services::data_storage_service::{DataStorageService, DataStorageTrait};
+use crate::domain::entities::domain_event::DomainEvent;
+use crate::domain::entities::sync_state::SyncPhase;
+use crate::domain::services::data_storage_service::{DataStorage, DataStorageService, DataStorageTrait};
+use crate::domain::services::liquidity_service::{LiquidityService, LiquidityServiceTrait};
+use crate::domain::services::event_bus_service::{EventBusService, EventBusServiceTrait};
+use crate::domain::services::sync_state_machine::SyncStateMachine;
+use crate::domain::services::task_supervisor::ShutdownSignal;
 use crate::ports::chain::base::base_uniswap_v2_swap_sync::{BaseUniswapV2SwapSynchronizer, BaseUniswapV2SwapSynchronizerTrait};
 
 abigen!(
@@ -26,6 +38,7 @@ abigen!(
 abigen!(
     UniswapV2Pair,
     r#"[
+        function factory() external view returns (address)
         function token0() external view returns (address)
         function token1() external view returns (address)
         function getReserves() external view returns (uint112 _reserve0, uint112 _reserve1, uint32 _blockTimestampLast)
@@ -55,75 +68,403 @@ abigen!(
 
 pub static PROTOCOL_ID: &str = "BASE_UNISWAP_V2";
 
-#[derive(Debug)]
-pub struct BaseUniswapV2ClientService;
+#[derive(Debug, Default)]
+struct ClientServiceState {
+    paused: Mutex<bool>,
+    // quarantined emitters whose factory is not registered, not asked again
+    unknown_factory_pairs: Mutex<HashSet<Address>>,
+}
 
-#[async_trait]
-pub trait BaseUniswapV2ClientServiceTrait: Send + Sync{
-    async fn init_chain_data_sync(&self) -> Result<(), Box<dyn std::error::Error>>;
-    async fn init_token_list(&self, data_service: Arc<DataStorageService>,
-                             swap_sync: Arc<BaseUniswapV2SwapSynchronizer>,
-                             provider: Arc<Provider<Http>>, from_block: u64, to_block: u64)
-        -> Result<(), Box<dyn std::error::Error>>;
-    async fn fetch_token_details(
-        address: Address, data_service: Arc<DataStorageService>, provider: Arc<Provider<Http>>, from_block: u64, to_block: u64)
-        -> Result<Token, Box<dyn std::error::Error>>;
-    // async fn count_transfer_events(
-    //     token_address: Address, provider: Arc<Provider<Http>>, from_block: u64, to_block: u64)
-    //     -> Result<usize, Box<dyn std::error::Error>>;
-    async fn update_sync_log(&self, data_service: Arc<DataStorageService>, provider: Arc<Provider<Http>>)
-         -> Result<UpdateLog, Box<dyn std::error::Error>>;
+pub struct BaseUniswapV2ClientService<M> {
+    config: ConfigHandle,
+    data_service: DataStorageService,
+    event_bus: EventBusService,
+    provider: Arc<M>,
+    quorum: QuorumService,
+    swap_sync: BaseUniswapV2SwapSynchronizer<M>,
+    shutdown: ShutdownSignal,
+    state: Arc<ClientServiceState>,
+    sync_state: SyncStateMachine,
 }
 
-#[async_trait]
-impl BaseUniswapV2ClientServiceTrait for BaseUniswapV2ClientService {
-    async fn init_chain_data_sync(&self) -> Result<(), Box<dyn std::error::Error>> {
+impl<M> Clone for BaseUniswapV2ClientService<M> {
+    fn clone(&self) -> Self {
+        BaseUniswapV2ClientService {
+            config: self.config.clone(),
+            data_service: self.data_service.clone(),
+            event_bus: self.event_bus.clone(),
+            provider: self.provider.clone(),
+            quorum: self.quorum.clone(),
+            swap_sync: self.swap_sync.clone(),
+            shutdown: self.shutdown.clone(),
+            state: self.state.clone(),
+            sync_state: self.sync_state.clone(),
+        }
+    }
+}
 
-        let data_service = Arc::new(DataStorageService);
-        let swap_sync = Arc::new(BaseUniswapV2SwapSynchronizer);
+impl<M: ChainMiddleware> BaseUniswapV2ClientService<M> {
+    // Pair discovery waits for the given swap synchronizer before counting swaps, stops between pairs on shutdown
+    pub fn new(config: ConfigHandle, data_service: DataStorageService, event_bus: EventBusService, provider: Arc<M>,
+               quorum: QuorumService, swap_sync: BaseUniswapV2SwapSynchronizer<M>, shutdown: ShutdownSignal) -> Self {
+        let sync_state = SyncStateMachine::new("pairs", event_bus.clone());
+        BaseUniswapV2ClientService {
+            config,
+            data_service,
+            event_bus,
+            provider,
+            quorum,
+            swap_sync,
+            shutdown,
+            state: Arc::new(ClientServiceState::default()),
+            sync_state,
+        }
+    }
 
-        let rpc_url = CONFIG.default.chain_base_rpc_url.clone();
-        let provider = Arc::new(Provider::<Http>::try_from(rpc_url)?);
+    // Pair and token sync loop, init_chain_data_sync records how it ended
+    async fn run_chain_data_sync(&self) -> Result<(), Box<dyn Error>> {
+        let data_service = &self.data_service;
+        //a restart after a failure starts over, Failed only moves on to Idle or Backfilling
+        self.sync_state.transition(SyncPhase::Idle);
 
         log::info!("Start Base Network connection ...");
-        log::info!("RPC Endpoint: {}",CONFIG.default.chain_base_rpc_url);
+        let config = self.config.get();
+        log::info!("RPC Endpoints: {}",config.base_rpc_endpoints().len());
 
         data_service.init();
-        let update_log = self.update_sync_log(data_service.clone(),provider.clone()).await.unwrap();
+        for protocol in config.protocols.iter().filter(|protocol| protocol.chain == BASE_CHAIN) {
+            data_service.add_protocol(protocol.id.clone(), Protocol {
+                id: protocol.id.clone(),
+                chain_id: protocol.chain.to_uppercase(),
+                dex_id: protocol.dex_id.clone(),
+            });
+        }
+        let update_log = self.update_sync_log().await?;
         log::info!("UpdateLog: {}",update_log);
         loop{
+            if self.shutdown.is_triggered() {
+                log::info!("Base Network UniswapV2 - data refresh stopped");
+                return Ok(());
+            }
+            if self.is_paused().await {
+                log::info!("Base Network UniswapV2 - data refresh paused");
+                self.sync_state.transition(SyncPhase::Idle);
+                self.shutdown.sleep(Duration::from_secs(self.config.get().sync.data_refresh_interval)).await;
+                continue;
+            }
             log::info!("Base Network UniswapV2 - data refresh");
 
             if data_service.get_tokens_size() == 0{
-                let _ = Self::init_token_list(&self,data_service.clone(),swap_sync.clone(), provider.clone(),
-                                              update_log.start_block,update_log.end_block).await;
+                self.sync_state.begin_backfill(update_log.end_block);
+                let loaded = self.init_token_list(update_log.start_block,update_log.end_block)
+                    .await
+                    .map_err(|e| e.to_string());
+                match loaded {
+                    Ok(()) => { self.sync_state.transition(SyncPhase::Following); }
+                    //discovery starts over with the next refresh
+                    Err(e) => self.sync_state.degrade(format!("token list load failed: {}", e)),
+                }
             }
             else{
-                log::info!("Token list size: {}", data_service.get_tokens_size())
+                let refreshed = self.refresh_chain_data()
+                    .await
+                    .map_err(|e| e.to_string());
+                match refreshed {
+                    Ok(()) => if self.sync_state.phase() != SyncPhase::Degraded {
+                        self.sync_state.transition(SyncPhase::Following);
+                    },
+                    //continues at the stored pair count with the next refresh
+                    Err(e) => self.sync_state.degrade(format!("refresh failed: {}", e)),
+                }
             }
-            //let update_log = self.update_sync_log(data_service.clone(),provider.clone()).await.unwrap();
-            //log::info!("UpdateLog: {}",update_log);
-            sleep(Duration::from_secs(CONFIG.default.data_refresh_interval.clone())).await;
+            //quorum disagreements still within rpc_quorum_dispute_ttl, also those from other synchronizers' reads
+            let changed = data_service.set_quorum_disputed(self.quorum.disputed_keys());
+            if changed > 0 {
+                log::warn!("Quorum: disputed flag of {} stored tokens and pairs changed", changed);
+            }
+            let next_refresh = Instant::now() + Duration::from_secs(self.config.get().sync.data_refresh_interval);
+            if self.provider.provider().as_ref().supports_subscriptions() {
+                let followed = self.follow_new_heads(next_refresh)
+                    .await
+                    .map_err(|e| e.to_string());
+                match followed {
+                    Ok(()) => { self.sync_state.transition(SyncPhase::Following); }
+                    Err(e) => {
+                        log::warn!("newHeads subscription dropped: {}. Falling back to polling", e);
+                        self.sync_state.degrade(format!("newHeads subscription dropped: {}", e));
+                    }
+                }
+            }
+            tokio::select! {
+                _ = sleep_until(next_refresh) => {}
+                _ = self.shutdown.triggered() => {}
+            }
+        }
+    }
+
+    // Factory of the indexed protocol, its pairs are discovered
+    fn factory_address(&self) -> Result<Address, Box<dyn Error>> {
+        let config = self.config.get();
+        let protocol = config.protocol(PROTOCOL_ID).ok_or_else(|| format!("protocol {} not configured", PROTOCOL_ID))?;
+        Ok(protocol.factory.parse()?)
+    }
+
+    // Pair with its token addresses, swaps not counted yet
+    async fn read_token_pair(&self, pair_address: Address) -> Result<(TokenPair, Address, Address), Box<dyn Error>> {
+        let pair = UniswapV2Pair::new(pair_address, self.provider.clone());
+
+        let token0: Address = pair.token_0().call().await?.into();
+        let token1: Address = pair.token_1().call().await?.into();
+        //reserves read after this head include every liquidity event up to it
+        let reserves_read_block = self.provider.get_block_number().await?.as_u64();
+        let reserve = pair.get_reserves().call().await?;
+
+        let token_pair = TokenPair {
+            token_pair_address: pair_address,
+            protocol_id: PROTOCOL_ID.to_string(),
+            base_address: token0,
+            base_reserve: reserve.0,
+            quote_address: token1,
+            quote_reserve: reserve.1,
+            reserve_block: reserve.2,
+            reserves_read_block,
+            swaps: 0,
+            retrieved_at: SystemTime::now(),
+            updated_at: SystemTime::now(),
+            quorum_disputed: self.quorum.is_disputed(pair_address)
+        };
+        Ok((token_pair, token0, token1))
+    }
+
+    // Stores the pair unless reserves, swaps and flags are unchanged, true when stored
+    fn store_token_pair_if_changed(&self, mut token_pair: TokenPair) -> bool {
+        if let Some(existing) = self.data_service.get_token_pair(token_pair.token_pair_address) {
+            if existing.base_reserve == token_pair.base_reserve
+                && existing.quote_reserve == token_pair.quote_reserve
+                && existing.reserve_block == token_pair.reserve_block
+                && existing.swaps == token_pair.swaps
+                && existing.quorum_disputed == token_pair.quorum_disputed {
+                return false;
+            }
+            token_pair.retrieved_at = existing.retrieved_at;
+        }
+        self.store_token_pair(token_pair);
+        true
+    }
+
+    // Pairs, active pairs, swaps and the risk flag of a token from the stored pairs, a rug pull of any pair flags it
+    fn token_pair_stats(&self, address: Address) -> (HashMap<Address, TokenPair>, HashMap<Address, TokenPair>, i64, bool) {
+        let data_service = &self.data_service;
+        let pairs = data_service.get_token_pairs_by_address(address);
+        let active_pairs = data_service.get_non_zero_reserve_token_pairs_by_address(address);
+        let swaps = data_service.get_total_swaps_for_address(address);
+
+        let risk = &self.config.get().risk;
+        let min_active_pairs = std::cmp::max(risk.min_active_pairs, (pairs.len() as f64 * risk.min_active_pair_ratio).floor() as usize);
+        let high_risk = active_pairs.len() < min_active_pairs
+            || pairs.keys().any(|pair_address| data_service.get_rug_pull(*pair_address).is_some());
+        (pairs, active_pairs, swaps, high_risk)
+    }
+
+    // Recomputes a stored token from its pairs, metadata is fetched for new tokens only. True when stored.
+    async fn recompute_token(&self, address: Address) -> Result<bool, Box<dyn Error>> {
+        let Some(existing) = self.data_service.get_token(address) else {
+            let token = self.fetch_token_details(address, 0, 0).await?;
+            self.store_token(token);
+            return Ok(true);
+        };
+        let (pairs, active_pairs, swaps, high_risk) = self.token_pair_stats(address);
+        let quorum_disputed = self.quorum.is_disputed(address);
+        if existing.pairs == pairs && existing.active_pairs == active_pairs && existing.swaps == swaps
+            && existing.high_risk == high_risk && existing.quorum_disputed == quorum_disputed {
+            return Ok(false);
+        }
+        self.store_token(Token {
+            updated_at: SystemTime::now(),
+            pairs,
+            active_pairs,
+            swaps,
+            high_risk,
+            quorum_disputed,
+            ..existing
+        });
+        Ok(true)
+    }
+
+    // Mint or Burn indexed after the stored reserves were read
+    fn has_unread_liquidity_change(&self, storage: &DataStorage, pair: &TokenPair) -> bool {
+        storage.liquidity_events.get(&pair.token_pair_address)
+            .and_then(|events| events.iter().map(|event| event.block_number).max())
+            .is_some_and(|block| block > pair.reserves_read_block)
+    }
+
+    // Swap indexed after the stored reserves were read, also when compacted into the swap aggregates since
+    fn has_unread_swaps(&self, storage: &DataStorage, pair: &TokenPair) -> bool {
+        let address = pair.token_pair_address;
+        storage.swap_logs.get(&address).into_iter()
+            .flatten()
+            .filter_map(|swap_log| swap_log.block_number.map(|block| block.as_u64()))
+            .chain(storage.compacted_blocks.get(&address).copied())
+            .any(|block| block > pair.reserves_read_block)
+    }
+
+    // Flags the pairs the rug-pull detector catches and clears expired flags, a later burn flagged again extends a flag.
+    // Returns the tokens of pairs whose flag changed.
+    fn detect_rug_pulls(&self, head_block: u64) -> Vec<Address> {
+        let storage = self.data_service.get_snapshot();
+        let liquidity_service = LiquidityService::new(self.config.clone());
+        let config = self.config.get();
+        let expiry_blocks = config.rug_pull.flag_expiry_secs / config.base_chain().block_time;
+        let mut tokens = vec![];
+        let mut pair_addresses: Vec<Address> = storage.liquidity_events.keys().chain(storage.rug_pulls.keys()).copied().collect();
+        pair_addresses.sort();
+        pair_addresses.dedup();
+        for pair_address in &pair_addresses {
+            let flagged = storage.rug_pulls.get(pair_address);
+            let Some(rug_pull) = liquidity_service.detect_rug_pull(&storage, *pair_address)
+                .filter(|rug_pull| flagged.is_none_or(|flagged| rug_pull.block_number > flagged.block_number)) else {
+                let expired = flagged.is_some_and(|flagged| expiry_blocks > 0 && flagged.block_number + expiry_blocks < head_block);
+                if expired && self.data_service.remove_rug_pull(*pair_address) {
+                    log::info!("Rug pull: flag of pair {} expired at block {}", pair_address, head_block);
+                    if let Some(pair) = storage.token_pairs.get(pair_address) {
+                        tokens.extend([pair.base_address, pair.quote_address]);
+                    }
+                }
+                continue;
+            };
+            log::warn!("Rug pull: pair {} lost {:.2} of its liquidity ({:?}) at block {}",
+                pair_address, rug_pull.share, rug_pull.reason, rug_pull.block_number);
+            if let Some(pair) = storage.token_pairs.get(pair_address) {
+                tokens.extend([pair.base_address, pair.quote_address]);
+            }
+            self.event_bus.publish(DomainEvent::RugPullDetected {
+                pair_address: *pair_address,
+                reason: rug_pull.reason,
+                share: rug_pull.share,
+                block_number: rug_pull.block_number,
+            });
+            self.data_service.add_rug_pull(*pair_address, rug_pull);
+        }
+        tokens
+    }
+
+    // Records how many factory pairs are discovered, the next refresh continues there
+    fn set_pair_count(&self, pair_count: u64) {
+        if let Some(mut update_log) = self.data_service.get_update_log(PROTOCOL_ID.to_string()) {
+            update_log.pair_count = pair_count;
+            self.data_service.add_update_log(PROTOCOL_ID.to_string(), update_log);
+        }
+    }
+
+    // Stores the pair, then publishes a new pair or changed reserves
+    fn store_token_pair(&self, token_pair: TokenPair) {
+        let data_service = &self.data_service;
+        let key = token_pair.token_pair_address;
+        let event = match data_service.get_token_pair(key) {
+            None => Some(DomainEvent::PairDiscovered {
+                pair_address: key,
+                protocol_id: token_pair.protocol_id.clone(),
+                base_address: token_pair.base_address,
+                quote_address: token_pair.quote_address,
+            }),
+            Some(existing) if existing.base_reserve != token_pair.base_reserve
+                || existing.quote_reserve != token_pair.quote_reserve => Some(DomainEvent::ReservesUpdated {
+                pair_address: key,
+                base_reserve: token_pair.base_reserve,
+                quote_reserve: token_pair.quote_reserve,
+                reserve_block: token_pair.reserve_block,
+            }),
+            Some(_) => None,
+        };
+        data_service.add_token_pair(key, token_pair);
+        if let Some(event) = event {
+            self.event_bus.publish(event);
+        }
+    }
+
+    // Stores the token with its token list metadata, then publishes the update and a risk flag change of a known token
+    fn store_token(&self, mut token: Token) {
+        let data_service = &self.data_service;
+        if let Some(entry) = data_service.get_token_list_entry(token.address) {
+            token.apply_token_list_entry(&entry);
+        }
+        let previous_risk = data_service.get_token(token.address).map(|existing| existing.high_risk);
+        let mut events = vec![DomainEvent::TokenUpdated {
+            address: token.address,
+            symbol: token.symbol.clone(),
+            active_pairs: token.active_pairs.len(),
+            swaps: token.swaps,
+            high_risk: token.high_risk,
+        }];
+        if previous_risk.is_some_and(|high_risk| high_risk != token.high_risk) {
+            events.push(DomainEvent::RiskChanged {
+                address: token.address,
+                symbol: token.symbol.clone(),
+                high_risk: token.high_risk,
+            });
+        }
+        data_service.add_token(token.address, token);
+        self.event_bus.publish_all(events);
+    }
+}
+
+#[async_trait]
+pub trait BaseUniswapV2ClientServiceTrait: Send + Sync{
+    async fn init_chain_data_sync(&self) -> Result<(), Box<dyn std::error::Error>>;
+    async fn init_token_list(&self, from_block: u64, to_block: u64) -> Result<(), Box<dyn std::error::Error>>;
+    async fn refresh_chain_data(&self) -> Result<(), Box<dyn Error>>;
+    async fn fetch_token_details(&self, address: Address, from_block: u64, to_block: u64)
+        -> Result<Token, Box<dyn std::error::Error>>;
+    // async fn count_transfer_events(
+    //     &self, token_address: Address, from_block: u64, to_block: u64)
+    //     -> Result<usize, Box<dyn std::error::Error>>;
+    async fn update_sync_log(&self) -> Result<UpdateLog, Box<dyn std::error::Error>>;
+    async fn follow_new_heads(&self, until: Instant) -> Result<(), Box<dyn Error>>;
+    async fn recheck_quarantined_swap_logs(&self) -> Result<i64, Box<dyn Error>>;
+    async fn fetch_token_pair(&self, pair_address: Address) -> Result<TokenPair, Box<dyn std::error::Error>>;
+    async fn refresh_token_pair(&self, pair_address: Address) -> Result<TokenPair, Box<dyn std::error::Error>>;
+    async fn refresh_token(&self, token_address: Address) -> Result<Token, Box<dyn std::error::Error>>;
+    async fn pause(&self);
+    async fn resume(&self);
+    async fn is_paused(&self) -> bool;
+    fn get_sync_state(&self) -> SyncStateMachine;
+}
+
+#[async_trait]
+impl<M: ChainMiddleware> BaseUniswapV2ClientServiceTrait for BaseUniswapV2ClientService<M> {
+    async fn init_chain_data_sync(&self) -> Result<(), Box<dyn std::error::Error>> {
+        let result = self.run_chain_data_sync().await;
+        match &result {
+            Ok(()) => { self.sync_state.transition(SyncPhase::Idle); }
+            Err(e) => self.sync_state.fail(e.to_string()),
         }
+        result
     }
 
-    async fn init_token_list(&self, data_service: Arc<DataStorageService>, swap_sync: Arc<BaseUniswapV2SwapSynchronizer>, provider: Arc<Provider<Http>>, from_block: u64, to_block: u64) -> Result<(), Box<dyn std::error::Error>>{
+    async fn init_token_list(&self, from_block: u64, to_block: u64) -> Result<(), Box<dyn std::error::Error>>{
         log::info!("Initial full token load");
-        let factory_address: Address = CONFIG.default.chain_base_uniswap_v2_factory_address.clone().parse()?;
+        let data_service = &self.data_service;
+        let factory_address = self.factory_address()?;
 
-        let factory = UniswapV2Factory::new(factory_address, provider.clone());
+        let factory = UniswapV2Factory::new(factory_address, self.provider.clone());
 
         let pair_count: U256 = factory.all_pairs_length().call().await?;
         log::info!("Full pair list size: {}", pair_count);
 
         let mut token_addresses = vec![];
+        let reserves_read_block = self.provider.get_block_number().await?.as_u64();
         //for i in 0..100 {
         for i in 0..pair_count.as_u64() {
-            let pair_address: Address = factory.all_pairs(U256::from(i)).call().await?;
-            let pair = UniswapV2Pair::new(pair_address, provider.clone());
+            //token list stays empty, discovery starts over with the next run
+            if self.shutdown.is_triggered() {
+                log::info!("Pair discovery stopped after {} of {} pairs", i, pair_count);
+                return Ok(());
+            }
+            let pair_address: Address = factory.all_pairs(U256::from(i)).call().await?.into();
+            let pair = UniswapV2Pair::new(pair_address, self.provider.clone());
 
-            let token0: Address = pair.token_0().call().await?;
-            let token1: Address = pair.token_1().call().await?;
+            let token0: Address = pair.token_0().call().await?.into();
+            let token1: Address = pair.token_1().call().await?.into();
             let reserve = pair.get_reserves().call().await?;
 
             token_addresses.push(token0);
@@ -134,63 +475,172 @@ impl BaseUniswapV2ClientServiceTrait for BaseUniswapV2ClientService {
             //let swaps = Self::count_swap_events(pair_address, provider.clone(),from_block,to_block).await?;
             //log::info!("SWAPS: {}",swaps);
             let token_pair = TokenPair {
-                token_pair_address: pair_address.to_string(),
+                token_pair_address: pair_address,
                 protocol_id: PROTOCOL_ID.to_string(),
-                base_address: token0.to_string(),
+                base_address: token0,
                 base_reserve: reserve.0,
-                quote_address: token1.to_string(),
+                quote_address: token1,
                 quote_reserve: reserve.1,
                 reserve_block: reserve.2,
+                reserves_read_block,
                 swaps: 0,
                 retrieved_at: SystemTime::now(),
-                updated_at: SystemTime::now()
+                updated_at: SystemTime::now(),
+                quorum_disputed: self.quorum.is_disputed(pair_address)
             };
             //log::info!("Pair {}",token_pair);
-            data_service.add_token_pair(pair_address.to_string(),token_pair);
+            self.store_token_pair(token_pair);
         }
 
         token_addresses.sort();
         token_addresses.dedup();
 
+        if let Err(e) = self.recheck_quarantined_swap_logs().await {
+            log::warn!("Quarantine recheck failed: {}", e);
+        }
+
         //For each pair check swap events
-        //but first wait for swap events upload.
-        loop{
-            if swap_sync.get_processing_status().await {
-                log::info!("Swap events not ready");
-                sleep(Duration::from_secs(15)).await
+        //but first wait for swap events upload, a failed swap sync leaves the stored counts
+        let swap_sync_state = self.swap_sync.get_sync_state();
+        //a stopped or paused synchronizer extracts nothing to wait for
+        let swap_sync_active = self.swap_sync.is_running().await && !self.swap_sync.is_paused().await;
+        if swap_sync_active && !swap_sync_state.get_state().is_settled() {
+            log::info!("Swap events not ready, waiting for swap sync");
+        }
+        let swap_wait_timeout = self.config.get().ingestion(PROTOCOL_ID).swap_wait_timeout;
+        let swap_wait = async {
+            if !swap_sync_active {
+                return Some(swap_sync_state.get_state());
             }
-            else{
-                break;
+            if swap_wait_timeout == 0 {
+                return Some(swap_sync_state.wait_until_settled().await);
             }
+            tokio::time::timeout(Duration::from_secs(swap_wait_timeout), swap_sync_state.wait_until_settled()).await.ok()
+        };
+        let swap_state = tokio::select! {
+            state = swap_wait => state,
+            _ = self.shutdown.triggered() => return Ok(()),
+        };
+        match swap_state {
+            Some(state) if state.phase == SyncPhase::Failed =>
+                log::warn!("Swap sync failed ({}), counting stored swaps", state.last_error.unwrap_or_default()),
+            Some(_) => {}
+            None => log::warn!("Swap sync still backfilling after {}s, counting stored swaps", swap_wait_timeout),
         }
 
         for token_pair in data_service.get_token_pairs(){
-            let swaps = data_service.get_total_swaps_for_address(token_pair.clone().1.token_pair_address);
-            let mut updated_token_pair = token_pair.clone().1;
+            let swaps = data_service.get_swap_count(token_pair.1.token_pair_address);
+            let mut updated_token_pair = token_pair.1;
             updated_token_pair.swaps = swaps;
-            data_service.add_token_pair(updated_token_pair.token_pair_address.clone(), updated_token_pair);
+            data_service.add_token_pair(updated_token_pair.token_pair_address, updated_token_pair);
         }
 
         log::info!("Swaps events in token pairs");
+        self.detect_rug_pulls(to_block);
 
 
         log::info!("\nUnique tokens on BASE Network:");
 
         for token_address in token_addresses{
-            match Self::fetch_token_details(token_address, data_service.clone(), provider.clone(),
-                                            from_block,to_block).await {
+            if self.shutdown.is_triggered() {
+                log::info!("Token load stopped by shutdown");
+                return Ok(());
+            }
+            match self.fetch_token_details(token_address, from_block, to_block).await {
                 Ok(token) => {
-                    data_service.add_token(token.address.clone(),token);
+                    self.store_token(token);
                 }
                 Err(e) => log::error!("Fetch error for token {}: {:?}", token_address, e),
             }
         }
+        self.set_pair_count(pair_count.as_u64());
         Ok(())
     }
 
-    async fn fetch_token_details(address: Address, data_service: Arc<DataStorageService>, provider: Arc<Provider<Http>>,
-        from_block: u64, to_block: u64) -> Result<Token, Box<dyn std::error::Error>> {
-        let token = ERC20::new(address, provider.clone());
+    async fn refresh_chain_data(&self) -> Result<(), Box<dyn Error>> {
+        let data_service = &self.data_service;
+        let update_log = self.update_sync_log().await?;
+        let factory = UniswapV2Factory::new(self.factory_address()?, self.provider.clone());
+        let pair_count = factory.all_pairs_length().call().await?.as_u64();
+
+        //pairs created since the last refresh
+        let mut changed_tokens = vec![];
+        let mut new_pairs = 0;
+        for i in update_log.pair_count..pair_count {
+            if self.shutdown.is_triggered() {
+                self.set_pair_count(i);
+                return Ok(());
+            }
+            let discovered = async {
+                let pair_address: Address = factory.all_pairs(U256::from(i)).call().await?.into();
+                self.read_token_pair(pair_address).await
+            };
+            let (mut token_pair, token0, token1) = match discovered.await {
+                Ok(discovered) => discovered,
+                Err(e) => {
+                    self.set_pair_count(i);
+                    return Err(e);
+                }
+            };
+            token_pair.swaps = data_service.get_swap_count(token_pair.token_pair_address);
+            if self.store_token_pair_if_changed(token_pair) {
+                changed_tokens.extend([token0, token1]);
+            }
+            new_pairs += 1;
+        }
+        self.set_pair_count(pair_count);
+
+        if let Err(e) = self.recheck_quarantined_swap_logs().await {
+            log::warn!("Quarantine recheck failed: {}", e);
+        }
+
+        //pairs with new swaps or liquidity changes, their reserves moved
+        let mut updated_pairs = 0;
+        let storage = data_service.get_snapshot();
+        for (pair_address, pair) in data_service.get_token_pairs() {
+            if self.shutdown.is_triggered() {
+                break;
+            }
+            if !self.has_unread_swaps(&storage, &pair) && !self.has_unread_liquidity_change(&storage, &pair) {
+                continue;
+            }
+            let swaps = data_service.get_swap_count(pair_address);
+            match self.read_token_pair(pair_address).await {
+                Ok((mut token_pair, token0, token1)) => {
+                    token_pair.swaps = swaps;
+                    if self.store_token_pair_if_changed(token_pair) {
+                        changed_tokens.extend([token0, token1]);
+                        updated_pairs += 1;
+                    }
+                }
+                Err(e) => log::warn!("Refresh: pair {} read failed: {}", pair_address, e),
+            }
+        }
+
+        //tokens of new and changed pairs and of rug pulls
+        changed_tokens.extend(self.detect_rug_pulls(self.swap_sync.get_last_processed_block().await));
+        changed_tokens.sort();
+        changed_tokens.dedup();
+        let mut updated_tokens = 0;
+        for token_address in changed_tokens {
+            if self.shutdown.is_triggered() {
+                break;
+            }
+            match self.recompute_token(token_address).await {
+                Ok(true) => updated_tokens += 1,
+                Ok(false) => {}
+                Err(e) => log::error!("Fetch error for token {}: {:?}", token_address, e),
+            }
+        }
+
+        log::info!("Refresh to block {}: {} new pairs, {} pairs and {} tokens updated, token list size: {}",
+            update_log.end_block, new_pairs, updated_pairs, updated_tokens, data_service.get_tokens_size());
+        Ok(())
+    }
+
+    async fn fetch_token_details(&self, address: Address, _from_block: u64, _to_block: u64)
+        -> Result<Token, Box<dyn std::error::Error>> {
+        let token = ERC20::new(address, self.provider.clone());
 
         let name: String = token.name().call().await.unwrap_or_else(|_| "No data".to_string());
         let symbol: String = token.symbol().call().await.unwrap_or_else(|_| "No data".to_string());
@@ -201,22 +651,15 @@ impl BaseUniswapV2ClientServiceTrait for BaseUniswapV2ClientService {
         //let events = Self::count_transfer_events(address.clone(),provider.clone(),from_block,to_block).await.unwrap();
 
         //log::info!("Transfers {}",events);
-        let pairs =data_service.get_token_pairs_by_address(address.to_string());
-        let active_pairs = data_service.get_non_zero_reserve_token_pairs_by_address(address.to_string());
-        let swaps = data_service.get_total_swaps_for_address(address.to_string());
+        let (pairs, active_pairs, swaps, high_risk) = self.token_pair_stats(address);
 
         if swaps > 0 {
             log::info!("Existing swaps for token {} ",symbol);
         }
 
-        let mut high_risk = false;
-        if active_pairs.is_empty() || (active_pairs.len() < (pairs.len() / 2)) {
-            high_risk = true;
-        }
-
 
         let token_object = Token {
-            address: format!("{:?}", address),
+            address,
             protocol_id: PROTOCOL_ID.to_string(),
             symbol,
             name,
@@ -227,6 +670,10 @@ impl BaseUniswapV2ClientServiceTrait for BaseUniswapV2ClientService {
             active_pairs,
             swaps,
             high_risk,
+            quorum_disputed: self.quorum.is_disputed(address),
+            verified: false,
+            logo_uri: None,
+            tags: vec![],
         };
 
         Ok(token_object)
@@ -235,8 +682,8 @@ impl BaseUniswapV2ClientServiceTrait for BaseUniswapV2ClientService {
     /*
 
     async fn count_transfer_events(
+        &self,
         token_address: Address,
-        provider: Arc<Provider<Http>>,
         from_block: u64,
         to_block: u64,
     ) -> Result<usize, Box<dyn std::error::Error>> {
@@ -248,20 +695,21 @@ impl BaseUniswapV2ClientServiceTrait for BaseUniswapV2ClientService {
             .from_block(from_block)
             .to_block(to_block);
 
-        let logs: Vec<Log> = provider.get_logs(&filter).await?;
+        let logs: Vec<Log> = self.provider.get_logs(&filter).await?;
 
         Ok(logs.len())
     }
     */
 
-    async fn update_sync_log(&self, data_service: Arc<DataStorageService>, provider: Arc<Provider<Http>>) -> Result<UpdateLog, Box<dyn Error>> {
+    async fn update_sync_log(&self) -> Result<UpdateLog, Box<dyn Error>> {
+        let data_service = &self.data_service;
 
         let mut update_log = UpdateLog {
             protocol_id: PROTOCOL_ID.to_string(),
             ..Default::default()
         };
 
-        let current_block = provider.get_block_number().await?.as_u64();
+        let current_block = self.provider.get_block_number().await?.as_u64();
 
         if let Some(existing_log) = data_service.get_update_log(PROTOCOL_ID.to_string()) {
             let mut updated_log = existing_log.clone();
@@ -282,4 +730,130 @@ impl BaseUniswapV2ClientServiceTrait for BaseUniswapV2ClientService {
             Ok(update_log)
         }
     }
-}
+
+    async fn follow_new_heads(&self, until: Instant) -> Result<(), Box<dyn Error>> {
+        let data_service = &self.data_service;
+        let mut stream = self.provider.subscribe_blocks().await?;
+        loop {
+            let head = tokio::select! {
+                next = timeout_at(until, stream.next()) => match next {
+                    Ok(Some(head)) => head,
+                    Ok(None) => return Err("subscription stream closed".into()),
+                    Err(_) => return Ok(()),
+                },
+                _ = self.shutdown.triggered() => return Ok(()),
+            };
+            let Some(block) = head.number else {
+                continue;
+            };
+            //advance the update log with every new head instead of once per refresh
+            if let Some(mut update_log) = data_service.get_update_log(PROTOCOL_ID.to_string()) {
+                update_log.last_update_at = SystemTime::now();
+                update_log.end_block = std::cmp::max(update_log.end_block, block.as_u64());
+                data_service.add_update_log(PROTOCOL_ID.to_string(), update_log);
+            }
+            self.sync_state.progress(block.as_u64(), block.as_u64());
+            self.event_bus.publish(DomainEvent::SyncProgress {
+                synchronizer: "pairs".to_string(),
+                block: block.as_u64(),
+                target_block: block.as_u64(),
+            });
+        }
+    }
+
+    async fn recheck_quarantined_swap_logs(&self) -> Result<i64, Box<dyn Error>> {
+        let data_service = &self.data_service;
+        let factory_address = self.factory_address()?;
+        let mut released = 0;
+
+        for pair_address in data_service.get_quarantined_swap_log_addresses() {
+            //discovered by now
+            if data_service.contains_token_pair(pair_address) {
+                released += data_service.release_quarantined_swap_logs(pair_address, PROTOCOL_ID.to_string());
+                continue;
+            }
+            if self.state.unknown_factory_pairs.lock().await.contains(&pair_address) {
+                continue;
+            }
+
+            //not a V2 pair when factory() reverts
+            let factory = UniswapV2Pair::new(pair_address, self.provider.clone()).factory().call().await.ok().map(Address::from);
+            if factory == Some(factory_address) {
+                //created after the last discovery
+                match self.refresh_token_pair(pair_address).await {
+                    Ok(_) => released += data_service.release_quarantined_swap_logs(pair_address, PROTOCOL_ID.to_string()),
+                    Err(e) => log::warn!("Quarantine: pair {} refresh failed: {}", pair_address, e),
+                }
+                continue;
+            }
+            let config = self.config.get();
+            let registered = config.other_uniswap_v2_protocols(BASE_CHAIN).into_iter()
+                .find(|registered| registered.factory.parse::<Address>().ok() == factory && factory.is_some());
+            match registered {
+                Some(registered) => {
+                    data_service.set_pair_protocol(pair_address, registered.id.clone());
+                    released += data_service.release_quarantined_swap_logs(pair_address, registered.id.clone());
+                }
+                None => {
+                    log::debug!("Quarantine: {} created by unregistered factory {:?}", pair_address, factory);
+                    self.state.unknown_factory_pairs.lock().await.insert(pair_address);
+                }
+            }
+        }
+
+        log::info!("Quarantine: released {} swap logs, {} still quarantined",
+            released, data_service.get_quarantined_swap_logs_size());
+        Ok(released)
+    }
+
+    async fn fetch_token_pair(&self, pair_address: Address) -> Result<TokenPair, Box<dyn Error>> {
+        Ok(self.read_token_pair(pair_address).await?.0)
+    }
+
+    async fn refresh_token_pair(&self, pair_address: Address) -> Result<TokenPair, Box<dyn Error>> {
+        let data_service = &self.data_service;
+        let mut token_pair = self.fetch_token_pair(pair_address).await?;
+
+        if let Some(existing_pair) = data_service.get_token_pair(pair_address) {
+            token_pair.retrieved_at = existing_pair.retrieved_at;
+        }
+        token_pair.swaps = data_service.get_swap_count(pair_address);
+
+        self.store_token_pair(token_pair.clone());
+        log::info!("Pair refreshed: {}", token_pair);
+
+        Ok(token_pair)
+    }
+
+    async fn refresh_token(&self, token_address: Address) -> Result<Token, Box<dyn Error>> {
+        let update_log = self.update_sync_log().await?;
+        let mut token = self.fetch_token_details(token_address, update_log.start_block, update_log.end_block).await?;
+
+        if let Some(existing_token) = self.data_service.get_token(token.address) {
+            token.retrieved_at = existing_token.retrieved_at;
+        }
+
+        self.store_token(token.clone());
+
+        Ok(token)
+    }
+
+    async fn pause(&self) {
+        let mut paused = self.state.paused.lock().await;
+        *paused = true;
+    }
+
+    async fn resume(&self) {
+        let mut paused = self.state.paused.lock().await;
+        *paused = false;
+    }
+
+    async fn is_paused(&self) -> bool {
+        let paused = self.state.paused.lock().await;
+        *paused
+    }
+
+    fn get_sync_state(&self) -> SyncStateMachine {
+        self.sync_state.clone()
+    }
+}
\ No newline at end of file
@@ -0,0 +1,87 @@
+use std::sync::Arc;
+use crate::config::ConfigHandle;
+use crate::domain::services::alert_service::AlertService;
+use crate::domain::services::data_storage_service::DataStorageService;
+use crate::domain::services::event_bus_service::EventBusService;
+use crate::domain::services::job_service::JobService;
+use crate::domain::services::pair_activity_aggregator::PairActivityAggregator;
+use crate::domain::services::swap_compaction_service::SwapCompactionService;
+use crate::domain::services::task_supervisor::ShutdownSignal;
+use crate::ports::chain::base::base_uniswap_v2_client_service::BaseUniswapV2ClientService;
+use crate::ports::chain::base::base_uniswap_v2_job_runner::BaseUniswapV2JobRunner;
+use crate::ports::chain::base::base_uniswap_v2_swap_sync::BaseUniswapV2SwapSynchronizer;
+use crate::ports::chain::chain_transport::{ChainMiddleware, SubscriptionTransport};
+use crate::ports::chain::quorum::QuorumService;
+
+// One Base Uniswap V2 indexer: the services of a storage, its event bus and a provider.
+// Instances share no state, several can run in one process.
+pub struct BaseUniswapV2Indexer<M> {
+    pub config: ConfigHandle,
+    pub data_service: DataStorageService,
+    pub event_bus: EventBusService,
+    pub provider: Arc<M>,
+    // quorum reads of the provider and the contracts they disagreed on
+    pub quorum: QuorumService,
+    pub jobs: JobService,
+    // stops the synchronizers after their in-flight batches
+    pub shutdown: ShutdownSignal,
+    pub swap_sync: BaseUniswapV2SwapSynchronizer<M>,
+    pub client_service: BaseUniswapV2ClientService<M>,
+    pub job_runner: BaseUniswapV2JobRunner<M>,
+    pub compaction: SwapCompactionService,
+    pub alerts: AlertService,
+}
+
+impl<M> Clone for BaseUniswapV2Indexer<M> {
+    fn clone(&self) -> Self {
+        BaseUniswapV2Indexer {
+            config: self.config.clone(),
+            data_service: self.data_service.clone(),
+            event_bus: self.event_bus.clone(),
+            provider: self.provider.clone(),
+            quorum: self.quorum.clone(),
+            jobs: self.jobs.clone(),
+            shutdown: self.shutdown.clone(),
+            swap_sync: self.swap_sync.clone(),
+            client_service: self.client_service.clone(),
+            job_runner: self.job_runner.clone(),
+            compaction: self.compaction.clone(),
+            alerts: self.alerts.clone(),
+        }
+    }
+}
+
+impl<M: ChainMiddleware> BaseUniswapV2Indexer<M> {
+    pub fn new(config: ConfigHandle, data_service: DataStorageService, provider: Arc<M>) -> Self {
+        let event_bus = EventBusService::new();
+        let shutdown = ShutdownSignal::new();
+        let quorum = provider.provider().as_ref().quorum();
+        let jobs = JobService::new();
+        let swap_sync = BaseUniswapV2SwapSynchronizer::new(
+            config.clone(), data_service.clone(), event_bus.clone(), provider.clone(), shutdown.clone());
+        let client_service = BaseUniswapV2ClientService::new(
+            config.clone(), data_service.clone(), event_bus.clone(), provider.clone(), quorum.clone(), swap_sync.clone(), shutdown.clone());
+        let job_runner = BaseUniswapV2JobRunner::new(data_service.clone(), jobs.clone(), client_service.clone(), swap_sync.clone());
+        let compaction = SwapCompactionService::new(config.clone(), data_service.clone());
+        let alerts = AlertService::new(config.clone(), event_bus.clone());
+
+        BaseUniswapV2Indexer {
+            config,
+            data_service,
+            event_bus,
+            provider,
+            quorum,
+            jobs,
+            shutdown,
+            swap_sync,
+            client_service,
+            job_runner,
+            compaction,
+            alerts,
+        }
+    }
+
+    pub fn pair_activity_aggregator(&self) -> PairActivityAggregator {
+        PairActivityAggregator::new(self.data_service.clone(), self.event_bus.clone())
+    }
+}
@@ -0,0 +1,140 @@
+use std::error::Error;
+use async_trait::async_trait;
+use crate::ports::chain::chain_transport::ChainMiddleware;
+use crate::domain::entities::job::{Job, JobKind};
+use crate::domain::entities::supervised_task::ShutdownMode;
+use crate::domain::services::data_storage_service::{DataStorageService, DataStorageTrait};
+use crate::domain::services::job_service::{JobService, JobServiceTrait};
+use crate::domain::services::task_supervisor::TaskSupervisor;
+use crate::ports::chain::base::base_uniswap_v2_client_service::{BaseUniswapV2ClientService, BaseUniswapV2ClientServiceTrait};
+use crate::ports::chain::base::base_uniswap_v2_swap_sync::{BaseUniswapV2SwapSynchronizer, BaseUniswapV2SwapSynchronizerTrait};
+
+pub static SYNCHRONIZER_SWAPS: &str = "swaps";
+pub static SYNCHRONIZER_PAIRS: &str = "pairs";
+// supervised task of a job, followed by the job id
+pub static JOB_TASK_PREFIX: &str = "job:";
+
+pub struct BaseUniswapV2JobRunner<M> {
+    data_service: DataStorageService,
+    job_service: JobService,
+    client_service: BaseUniswapV2ClientService<M>,
+    swap_sync: BaseUniswapV2SwapSynchronizer<M>,
+}
+
+impl<M> Clone for BaseUniswapV2JobRunner<M> {
+    fn clone(&self) -> Self {
+        BaseUniswapV2JobRunner {
+            data_service: self.data_service.clone(),
+            job_service: self.job_service.clone(),
+            client_service: self.client_service.clone(),
+            swap_sync: self.swap_sync.clone(),
+        }
+    }
+}
+
+impl<M: ChainMiddleware> BaseUniswapV2JobRunner<M> {
+    // Jobs run against the storage and synchronizers of one indexer instance
+    pub fn new(data_service: DataStorageService, job_service: JobService, client_service: BaseUniswapV2ClientService<M>,
+               swap_sync: BaseUniswapV2SwapSynchronizer<M>) -> Self {
+        BaseUniswapV2JobRunner { data_service, job_service, client_service, swap_sync }
+    }
+}
+
+#[async_trait]
+pub trait BaseUniswapV2JobRunnerTrait: Send + Sync{
+    fn submit(&self, supervisor: &TaskSupervisor, kind: JobKind) -> Job;
+    async fn run_job(&self, job: Job);
+    async fn execute(&self, kind: JobKind) -> Result<String, Box<dyn Error>>;
+}
+
+#[async_trait]
+impl<M: ChainMiddleware> BaseUniswapV2JobRunnerTrait for BaseUniswapV2JobRunner<M> {
+    // Runs under the process' supervisor, shutdown waits for the job: a backfill stops after its range in flight and fails
+    fn submit(&self, supervisor: &TaskSupervisor, kind: JobKind) -> Job {
+        let job = self.job_service.create_job(kind);
+        supervisor.remove_finished(JOB_TASK_PREFIX);
+        let spawned_job = job.clone();
+        let job_runner = self.clone();
+        supervisor.supervise(&format!("{}{}", JOB_TASK_PREFIX, job.id), ShutdownMode::Drain, move || {
+            let job_runner = job_runner.clone();
+            let job = spawned_job.clone();
+            //the outcome is the job's status, a failed job is not started again
+            async move {
+                job_runner.run_job(job).await;
+                Ok(())
+            }
+        });
+        job
+    }
+
+    async fn run_job(&self, job: Job) {
+        let job_service = &self.job_service;
+        job_service.start_job(job.id.clone());
+
+        let result = self.execute(job.kind.clone()).await.map_err(|e| e.to_string());
+        match result {
+            Ok(message) => job_service.complete_job(job.id, message),
+            Err(e) => job_service.fail_job(job.id, e),
+        }
+    }
+
+    async fn execute(&self, kind: JobKind) -> Result<String, Box<dyn Error>> {
+        let data_service = &self.data_service;
+        let client_service = &self.client_service;
+        let swap_sync = &self.swap_sync;
+
+        match kind {
+            JobKind::SwapBackfill { from_block, to_block } => {
+                if from_block > to_block {
+                    return Err(format!("Invalid block range {} - {}", from_block, to_block).into());
+                }
+                let backfill = swap_sync.backfill_swaps(from_block, to_block).await?;
+                //only the pairs whose logs the backfill replaced, counted under the writer lock
+                data_service.write_batch(|storage| {
+                    for key in &backfill.pairs {
+                        let swaps = storage.swap_count(*key);
+                        if let Some(token_pair) = storage.token_pairs.get_mut(key) {
+                            token_pair.swaps = swaps;
+                        }
+                    }
+                });
+                Ok(format!("Indexed {} swap logs of {} pairs between blocks {} and {}",
+                           backfill.indexed, backfill.pairs.len(), from_block, to_block))
+            }
+            JobKind::PairRefresh { pair_address } => {
+                let token_pair = client_service.refresh_token_pair(pair_address).await?;
+                Ok(format!("Pair {} refreshed at reserve block {}", token_pair.token_pair_address, token_pair.reserve_block))
+            }
+            JobKind::TokenRefresh { token_address } => {
+                let token = client_service.refresh_token(token_address).await?;
+                Ok(format!("Token {} ({}) refreshed", token.symbol, token.address))
+            }
+            JobKind::PairDiscovery => {
+                let update_log = client_service.update_sync_log().await?;
+                client_service.init_token_list(update_log.start_block, update_log.end_block).await?;
+                Ok(format!("Discovered {} pairs and {} tokens",
+                           data_service.get_token_pairs_size(), data_service.get_tokens_size()))
+            }
+            JobKind::SyncPause { synchronizer } => {
+                if synchronizer == SYNCHRONIZER_SWAPS {
+                    swap_sync.pause().await;
+                } else if synchronizer == SYNCHRONIZER_PAIRS {
+                    client_service.pause().await;
+                } else {
+                    return Err(format!("Unknown synchronizer: {}", synchronizer).into());
+                }
+                Ok(format!("Synchronizer {} paused", synchronizer))
+            }
+            JobKind::SyncResume { synchronizer } => {
+                if synchronizer == SYNCHRONIZER_SWAPS {
+                    swap_sync.resume().await;
+                } else if synchronizer == SYNCHRONIZER_PAIRS {
+                    client_service.resume().await;
+                } else {
+                    return Err(format!("Unknown synchronizer: {}", synchronizer).into());
+                }
+                Ok(format!("Synchronizer {} resumed", synchronizer))
+            }
+        }
+    }
+}
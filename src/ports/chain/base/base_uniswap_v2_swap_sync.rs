@@ -1,124 +1,285 @@
+use std::collections::{HashMap, HashSet};
 use std::error::Error;
 use std::sync::Arc;
 use std::time::Duration;
 use async_trait::async_trait;
 use ethers::middleware::Middleware;
-use ethers::prelude::{Filter, Http, Provider, H256};
-use once_cell::sync::Lazy;
+use ethers::prelude::{Filter, Log, H256};
+use crate::ports::chain::chain_transport::{ChainMiddleware, SubscriptionTransport};
+use crate::ports::chain::log_range::{is_range_limit_error, AdaptiveLogRange};
 use tokio::sync::Mutex;
-use tokio::time::sleep;
-use crate::config::CONFIG;
+use futures_util::StreamExt;
+use tokio::time::timeout;
+use crate::config::ConfigHandle;
+use crate::domain::entities::address::Address;
+use crate::domain::entities::domain_event::DomainEvent;
+use crate::domain::entities::liquidity_event::{LiquidityEvent, LiquidityEventKind};
 use crate::domain::entities::swap_log::SwapLog;
-use crate::domain::services::data_storage_service::{DataStorageService, DataStorageTrait};
+use crate::domain::entities::sync_state::SyncPhase;
+use crate::domain::services::data_storage_service::{DataStorage, DataStorageService, DataStorageTrait};
+use crate::domain::services::event_bus_service::{EventBusService, EventBusServiceTrait};
+use crate::domain::services::liquidity_service::{LiquidityService, LiquidityServiceTrait};
+use crate::domain::services::sync_state_machine::SyncStateMachine;
+use crate::domain::services::task_supervisor::ShutdownSignal;
 
 pub static PROTOCOL_ID: &str = "BASE_UNISWAP_V2";
-pub static SWAP_SYNC_IN_PROGRESS: Lazy<Mutex<bool>> = Lazy::new(|| Mutex::new(false));
-pub static LAST_PROCESSED_BLOCK: Lazy<Mutex<u64>> = Lazy::new(|| Mutex::new(0));
+static SWAP_EVENT_SIGNATURE: &str = "0xd78ad95fa46c994b6551d0da85fc275fe613ce37657fb8d5e3d130840159d822";
+static MINT_EVENT_SIGNATURE: &str = "0x4c209b5fc8ad50758f13e2e1088ba56a560dff690a1c6fef26394f4c03821c4f";
+static BURN_EVENT_SIGNATURE: &str = "0xdccd412f0b1252819cb1fd330b93224ca42612892bb3f4f789976e6d81936496";
+// eth_getTransactionByHash requests in flight per range of liquidity logs
+const TRANSACTION_LOOKUPS: usize = 8;
+
+// Swap, Mint and Burn logs are extracted together, one getLogs request per block range
+fn indexed_topics() -> Vec<H256> {
+    [SWAP_EVENT_SIGNATURE, MINT_EVENT_SIGNATURE, BURN_EVENT_SIGNATURE].iter()
+        .map(|signature| signature.parse().unwrap())
+        .collect()
+}
+
+fn liquidity_event_kind(log: &Log) -> Option<LiquidityEventKind> {
+    let topic0 = format!("{:?}", log.topics.first()?);
+    if topic0 == MINT_EVENT_SIGNATURE {
+        Some(LiquidityEventKind::Mint)
+    } else if topic0 == BURN_EVENT_SIGNATURE {
+        Some(LiquidityEventKind::Burn)
+    } else {
+        None
+    }
+}
+
+// Swap logs a backfill stored and the pairs whose logs it removed or stored
+#[derive(Debug, Clone, Default)]
+pub struct SwapBackfill {
+    pub indexed: i64,
+    pub pairs: HashSet<Address>,
+}
+
+// Progress and controls of one synchronizer, shared by its clones
 #[derive(Debug)]
-pub struct BaseUniswapV2SwapSynchronizer;
+struct SwapSyncState {
+    // held while block ranges are extracted, the refresh loop and backfills don't write the same range
+    extraction: Mutex<()>,
+    last_processed_block: Mutex<u64>,
+    // synchronize_swaps is running, dependants only wait for a running synchronizer
+    running: Mutex<bool>,
+    paused: Mutex<bool>,
+    logs_range: Mutex<AdaptiveLogRange>,
+}
 
-#[async_trait]
-pub trait BaseUniswapV2SwapSynchronizerTrait: Send + Sync{
-    async fn synchronize_swaps(&self) -> Result<(), Box<dyn std::error::Error>>;
-    async fn extract_swap_events(
-        data_service: Arc<DataStorageService>,
-        provider: Arc<Provider<Http>>,
-        from_block: u64,
-        to_block: u64, step: usize
-    ) -> Result<u64, Box<dyn Error>>;
-    async fn get_processing_status(&self) -> bool;
-    async fn get_last_processed_block(&self) -> u64;
-    async fn is_block_processed_or_newer(&self, block: u64) -> bool;
+pub struct BaseUniswapV2SwapSynchronizer<M> {
+    config: ConfigHandle,
+    data_service: DataStorageService,
+    event_bus: EventBusService,
+    provider: Arc<M>,
+    shutdown: ShutdownSignal,
+    state: Arc<SwapSyncState>,
+    sync_state: SyncStateMachine,
 }
 
-#[async_trait]
-impl BaseUniswapV2SwapSynchronizerTrait for BaseUniswapV2SwapSynchronizer{
-    async fn synchronize_swaps(&self) -> Result<(), Box<dyn std::error::Error>> {
+impl<M> Clone for BaseUniswapV2SwapSynchronizer<M> {
+    fn clone(&self) -> Self {
+        BaseUniswapV2SwapSynchronizer {
+            config: self.config.clone(),
+            data_service: self.data_service.clone(),
+            event_bus: self.event_bus.clone(),
+            provider: self.provider.clone(),
+            shutdown: self.shutdown.clone(),
+            state: self.state.clone(),
+            sync_state: self.sync_state.clone(),
+        }
+    }
+}
 
-        let rpc_url = CONFIG.default.chain_base_rpc_url.clone();
-        let data_service = Arc::new(DataStorageService);
-        let provider = Arc::new(Provider::<Http>::try_from(rpc_url)?);
-        let step = 1000;
-        let mut from_block = 22800000;
+impl<M: ChainMiddleware> BaseUniswapV2SwapSynchronizer<M> {
+    // Stops taking new block ranges once shutdown is signalled, the range in flight is still stored
+    pub fn new(config: ConfigHandle, data_service: DataStorageService, event_bus: EventBusService, provider: Arc<M>,
+               shutdown: ShutdownSignal) -> Self {
+        let logs_range = AdaptiveLogRange::from_config(&config.get(), PROTOCOL_ID);
+        let sync_state = SyncStateMachine::new("swaps", event_bus.clone());
+        //restored from the loaded snapshot
+        let last_processed_block = data_service.get_swap_checkpoint(PROTOCOL_ID.to_string());
+        BaseUniswapV2SwapSynchronizer {
+            config,
+            data_service,
+            event_bus,
+            provider,
+            shutdown,
+            state: Arc::new(SwapSyncState {
+                extraction: Mutex::new(()),
+                last_processed_block: Mutex::new(last_processed_block),
+                running: Mutex::new(false),
+                paused: Mutex::new(false),
+                logs_range: Mutex::new(logs_range),
+            }),
+            sync_state,
+        }
+    }
 
-        loop{
-            log::info!("Swap event refresh ....  from block: {}",from_block);
-            {
-                let mut in_progress = SWAP_SYNC_IN_PROGRESS.lock().await;
-                if *in_progress {
-                    log::warn!("Synchronization already in progress. Skipping...");
-                    sleep(Duration::from_secs(CONFIG.default.data_refresh_interval.clone())).await;
-                    continue;
+    // Blocks produced between two polls, a longer gap is extracted as a backfill
+    fn poll_window(&self) -> u64 {
+        let config = self.config.get();
+        std::cmp::max(1, config.sync.data_refresh_interval / std::cmp::max(1, config.base_chain().block_time))
+    }
+
+    // Enters Backfilling for gaps longer than one poll window, returns the phase to go back to when done
+    fn begin_catch_up(&self, last_block: u64, target_block: u64) -> SyncPhase {
+        if target_block - last_block > self.poll_window() {
+            self.sync_state.begin_backfill(target_block)
+        } else {
+            self.sync_state.phase()
+        }
+    }
+
+    // Extracts swaps after last_block up to the chain head, returns the last extracted block.
+    // Caller holds the extraction lock.
+    async fn refresh_to_head(&self, last_block: u64) -> Result<u64, Box<dyn Error>> {
+        let latest_block = self.provider.get_block_number().await?.as_u64();
+        log::info!("Swap event refresh to block {}",latest_block);
+        if latest_block > last_block{
+            let previous = self.begin_catch_up(last_block, latest_block);
+            //short of latest_block when stopped by shutdown
+            let extracted = self.extract_swap_events(last_block + 1, latest_block).await?;
+            //still degraded until the subscription is back
+            self.sync_state.transition(if previous == SyncPhase::Degraded { SyncPhase::Degraded } else { SyncPhase::Following });
+            Ok(extracted)
+        }
+        else{
+            log::info!("No new blocks to process");
+            if self.sync_state.phase() != SyncPhase::Degraded {
+                self.sync_state.transition(SyncPhase::Following);
+            }
+            Ok(last_block)
+        }
+    }
+
+    // Records block as processed once all of its logs are stored, a restart resumes after it
+    async fn advance_checkpoint(&self, block: u64) {
+        let advanced = {
+            let mut last_block = self.state.last_processed_block.lock().await;
+            let advanced = block > *last_block;
+            *last_block = std::cmp::max(*last_block, block);
+            advanced
+        };
+        if advanced {
+            self.data_service.write_batch(|storage| storage.advance_swap_checkpoint(PROTOCOL_ID, block));
+            self.sync_state.progress(block, block);
+            self.event_bus.publish(DomainEvent::SyncProgress {
+                synchronizer: "swaps".to_string(),
+                block,
+                target_block: block,
+            });
+        }
+    }
+
+    // Decodes the Mint and Burn logs, with the transaction sender of burns and of the first mint of a pair.
+    // Each transaction is looked up once, concurrently. An unanswered lookup leaves the origin empty.
+    async fn read_liquidity_events(&self, logs: &[Log]) -> Vec<LiquidityEvent> {
+        let storage = self.data_service.get_snapshot();
+        let mut events: Vec<LiquidityEvent> = vec![];
+        let mut lookups: Vec<H256> = vec![];
+        for log in logs {
+            let Some(event) = liquidity_event_kind(log).and_then(|kind| LiquidityEvent::decode(kind, log)) else {
+                continue;
+            };
+            let minted_before = |event: &LiquidityEvent| events.iter()
+                .chain(storage.liquidity_events.get(&event.pair_address).into_iter().flatten())
+                .any(|other| other.pair_address == event.pair_address && other.kind == LiquidityEventKind::Mint)
+                || storage.liquidity_totals.get(&event.pair_address).is_some_and(|totals| totals.mints > 0);
+            if event.kind == LiquidityEventKind::Burn || !minted_before(&event) {
+                lookups.extend(event.transaction_hash);
+            }
+            events.push(event);
+        }
+        lookups.sort();
+        lookups.dedup();
+
+        let origins: HashMap<H256, Address> = futures_util::stream::iter(lookups)
+            .map(|hash| async move { (hash, self.provider.get_transaction(hash).await) })
+            .buffer_unordered(TRANSACTION_LOOKUPS)
+            .filter_map(|(hash, transaction)| async move {
+                match transaction {
+                    Ok(transaction) => transaction.map(|transaction| (hash, transaction.from.into())),
+                    Err(e) => {
+                        log::warn!("Liquidity sync: transaction {:?} lookup failed: {}", hash, e);
+                        None
+                    }
                 }
-                *in_progress = true;
-            }
-            //set in progress flag
-            let latest_block = provider.get_block_number().await?.as_u64();
-            log::info!("Swap event refresh to block {}",latest_block);
-            if latest_block > from_block{
-                Self::extract_swap_events(data_service.clone(),provider.clone(),from_block,latest_block,step).await?;
-                from_block = latest_block;
-            }
-            else{
-                log::info!("No new blocks to process");
-                sleep(Duration::from_secs(CONFIG.default.data_refresh_interval.clone())).await;
-            }
-            {
-                let mut in_progress = SWAP_SYNC_IN_PROGRESS.lock().await;
-                *in_progress = false;
-            }
-            sleep(Duration::from_secs(CONFIG.default.data_refresh_interval.clone())).await;
-        }
-    }
-
-    async fn extract_swap_events(
-        data_service: Arc<DataStorageService>,
-        provider: Arc<Provider<Http>>,
-        from_block: u64,
-        to_block: u64,
-        step: usize,
-    ) -> Result<u64, Box<dyn Error>> {
-        let swap_event_signature: H256 = "0xd78ad95fa46c994b6551d0da85fc275fe613ce37657fb8d5e3d130840159d822"
-            .parse()
-            .unwrap();
-
-        for block_start in (from_block..=to_block).step_by(step) {
-            let block_end = std::cmp::min(block_start as usize + step - 1, to_block as usize);
-            let mut attempts = 0;
-            let max_attempts = 5;
-
-            loop {
-                attempts += 1;
-
-                match provider.get_logs(
-                    &Filter::new()
-                        .topic0(swap_event_signature)
-                        .from_block(block_start)
-                        .to_block(block_end),
-                ).await {
-                    Ok(logs) => {
-                        for log in logs {
-                            let swap_log = SwapLog {
-                                address: log.address,
-                                topics: log.topics.clone(),
-                                data: log.data.clone(),
-                                block_hash: log.block_hash,
-                                block_number: log.block_number,
-                                transaction_hash: log.transaction_hash,
-                                transaction_index: log.transaction_index,
-                                log_index: log.log_index,
-                                removed: log.removed,
-                            };
-                            data_service.add_swap_log(swap_log.address.to_string(), swap_log);
+            })
+            .collect()
+            .await;
+        for event in events.iter_mut() {
+            event.origin = event.transaction_hash.and_then(|hash| origins.get(&hash).copied());
+        }
+        events
+    }
+
+    // Extracts from_block to to_block range by range, adding the pairs whose swaps were stored.
+    // Returns the last stored block, short of to_block when stopped by shutdown.
+    async fn extract_ranges(&self, from_block: u64, to_block: u64, pairs: &mut HashSet<Address>) -> Result<u64, Box<dyn Error>> {
+        let max_attempts = self.config.get().ingestion(PROTOCOL_ID).logs_max_attempts;
+        let mut attempts = 0;
+        let mut block_start = from_block;
+
+        while block_start <= to_block {
+            //no new range after shutdown, returns the last stored block
+            if self.shutdown.is_triggered() {
+                log::info!("Swap sync: shutdown, stopping before block {}", block_start);
+                return Ok(block_start.saturating_sub(1));
+            }
+            let range = self.state.logs_range.lock().await.size();
+            let block_end = std::cmp::min(block_start + range - 1, to_block);
+
+            match self.provider.get_logs(
+                &Filter::new()
+                    .topic0(indexed_topics())
+                    .from_block(block_start)
+                    .to_block(block_end),
+            ).await {
+                Ok(logs) => {
+                    let liquidity_events = self.read_liquidity_events(&logs).await;
+                    //the whole response is published as one snapshot
+                    let liquidity_service = LiquidityService::new(self.config.clone());
+                    let events: Vec<DomainEvent> = self.data_service.write_batch(|storage| {
+                        for event in liquidity_events {
+                            Self::store_liquidity_event(storage, event);
                         }
-                        let mut last_block = LAST_PROCESSED_BLOCK.lock().await;
-                        *last_block = block_end as u64;
-                        log::info!("Swap sync: processed blocks: {} from {} : data set size: {} ",
-                            block_end, to_block, data_service.get_all_swap_logs().len() );
-                        break;
+                        liquidity_service.prune_liquidity_events(storage, block_end);
+                        storage.advance_swap_checkpoint(PROTOCOL_ID, block_end);
+                        logs.iter()
+                            .filter(|log| liquidity_event_kind(log).is_none())
+                            .filter_map(|log| Self::store_swap_log(storage, SwapLog::from(log)))
+                            .collect()
+                    });
+                    pairs.extend(events.iter().filter_map(|event| match event {
+                        DomainEvent::SwapIndexed { pair_address, .. } => Some(*pair_address),
+                        _ => None,
+                    }));
+                    self.event_bus.publish_all(events);
+                    {
+                        let mut last_block = self.state.last_processed_block.lock().await;
+                        *last_block = std::cmp::max(*last_block, block_end);
                     }
-                    Err(e) if attempts < max_attempts => {
-                        log::warn!(
+                    self.sync_state.progress(block_end, to_block);
+                    self.event_bus.publish(DomainEvent::SyncProgress {
+                        synchronizer: "swaps".to_string(),
+                        block: block_end,
+                        target_block: to_block,
+                    });
+                    log::info!("Swap sync: processed blocks: {} from {} (range {}) : data set size: {} quarantined: {}",
+                        block_end, to_block, range, self.data_service.get_swap_log_keys().len(),
+                        self.data_service.get_quarantined_swap_logs_size());
+                    self.state.logs_range.lock().await.grow();
+                    attempts = 0;
+                    block_start = block_end + 1;
+                }
+                //provider rejected the span or result count, retry the same start with a smaller range
+                Err(e) if is_range_limit_error(&e.to_string()) && self.state.logs_range.lock().await.shrink() => {
+                    log::info!("Swap sync: range {} - {} rejected ({}), reducing range to {}",
+                        block_start, block_end, e, self.state.logs_range.lock().await.size());
+                }
+                Err(e) if attempts + 1 < max_attempts => {
+                    attempts += 1;
+                    log::warn!(
                         "Error fetching logs between blocks {} and {}: {}. Retrying... ({}/{})",
                         block_start,
                         block_end,
@@ -126,18 +287,18 @@ impl BaseUniswapV2SwapSynchronizerTrait for BaseUniswapV2SwapSynchronizer{
                         attempts,
                         max_attempts
                     );
-                        sleep(Duration::from_secs(2_u64.pow(attempts))).await;
-                    }
-                    Err(e) => {
-                        log::error!(
+                    self.sync_state.record_error(e.to_string());
+                    self.shutdown.sleep(Duration::from_secs(2_u64.pow(attempts))).await;
+                }
+                Err(e) => {
+                    log::error!(
                         "Failed to fetch logs between blocks {} and {} after {} attempts: {}",
                         block_start,
                         block_end,
-                        attempts,
+                        attempts + 1,
                         e
                     );
-                        return Err(e.into());
-                    }
+                    return Err(e.into());
                 }
             }
         }
@@ -145,18 +306,288 @@ impl BaseUniswapV2SwapSynchronizerTrait for BaseUniswapV2SwapSynchronizer{
         Ok(to_block)
     }
 
-    async fn get_processing_status(&self) -> bool {
-        let in_progress = SWAP_SYNC_IN_PROGRESS.lock().await;
-        *in_progress
+    // Swap sync loop, synchronize_swaps records how it ended
+    async fn run_swap_sync(&self) -> Result<(), Box<dyn Error>> {
+        //a restart after a failure starts over
+        self.sync_state.transition(SyncPhase::Idle);
+        //a restarted synchronizer resumes after the last stored range, start_block itself is extracted
+        let start_block = self.config.get().protocol(PROTOCOL_ID).map(|protocol| protocol.start_block).unwrap_or_default();
+        let mut last_block = std::cmp::max(start_block.saturating_sub(1), self.get_last_processed_block().await);
+
+        loop{
+            if self.shutdown.is_triggered() {
+                log::info!("Swap sync stopped at block {}", self.get_last_processed_block().await);
+                return Ok(());
+            }
+            if self.is_paused().await {
+                log::info!("Swap sync paused");
+                self.sync_state.transition(SyncPhase::Idle);
+                self.shutdown.sleep(Duration::from_secs(self.config.get().sync.data_refresh_interval)).await;
+                continue;
+            }
+            log::info!("Swap event refresh ({}) ....  after block: {}",PROTOCOL_ID,last_block);
+            let refreshed = {
+                let Ok(_extraction) = self.state.extraction.try_lock() else {
+                    log::warn!("Synchronization already in progress. Skipping...");
+                    self.shutdown.sleep(Duration::from_secs(self.config.get().sync.data_refresh_interval)).await;
+                    continue;
+                };
+                self.refresh_to_head(last_block)
+                    .await
+                    .map_err(|e| e.to_string())
+            };
+            last_block = refreshed?;
+            if self.shutdown.is_triggered() {
+                continue;
+            }
+            //follow the head over eth_subscribe, back to polling when the subscription drops
+            if self.provider.provider().as_ref().supports_subscriptions() {
+                let followed = self.follow_swap_logs(last_block)
+                    .await
+                    .map_err(|e| e.to_string());
+                if let Err(e) = followed {
+                    log::warn!("Swap log subscription dropped: {}. Falling back to polling", e);
+                    self.sync_state.degrade(format!("swap log subscription dropped: {}", e));
+                    self.shutdown.sleep(Duration::from_secs(self.config.get().sync.data_refresh_interval)).await;
+                }
+                last_block = std::cmp::max(last_block, self.get_last_processed_block().await);
+                continue;
+            }
+            self.shutdown.sleep(Duration::from_secs(self.config.get().sync.data_refresh_interval)).await;
+        }
+    }
+}
+
+#[allow(dead_code)]
+#[async_trait]
+pub trait BaseUniswapV2SwapSynchronizerTrait: Send + Sync{
+    async fn synchronize_swaps(&self) -> Result<(), Box<dyn std::error::Error>>;
+    async fn extract_swap_events(&self, from_block: u64, to_block: u64) -> Result<u64, Box<dyn Error>>;
+    fn store_swap_log(storage: &mut DataStorage, swap_log: SwapLog) -> Option<DomainEvent>;
+    fn store_liquidity_event(storage: &mut DataStorage, event: LiquidityEvent) -> bool;
+    async fn follow_swap_logs(&self, from_block: u64) -> Result<(), Box<dyn Error>>;
+    async fn backfill_swaps(&self, from_block: u64, to_block: u64) -> Result<SwapBackfill, Box<dyn Error>>;
+    fn get_sync_state(&self) -> SyncStateMachine;
+    async fn get_last_processed_block(&self) -> u64;
+    async fn is_block_processed_or_newer(&self, block: u64) -> bool;
+    async fn pause(&self);
+    async fn resume(&self);
+    async fn is_paused(&self) -> bool;
+    async fn is_running(&self) -> bool;
+}
+
+#[async_trait]
+impl<M: ChainMiddleware> BaseUniswapV2SwapSynchronizerTrait for BaseUniswapV2SwapSynchronizer<M> {
+    async fn synchronize_swaps(&self) -> Result<(), Box<dyn std::error::Error>> {
+        *self.state.running.lock().await = true;
+        let result = self.run_swap_sync().await.map_err(|e| e.to_string());
+        *self.state.running.lock().await = false;
+        match &result {
+            Ok(()) => {
+                self.sync_state.transition(SyncPhase::Idle);
+            }
+            Err(e) => self.sync_state.fail(e.clone()),
+        }
+        Ok(result?)
+    }
+
+    async fn extract_swap_events(&self, from_block: u64, to_block: u64) -> Result<u64, Box<dyn Error>> {
+        self.extract_ranges(from_block, to_block, &mut HashSet::new()).await
+    }
+
+    // Only pairs of the configured factory are indexed as ours, other registered factories keep their protocol,
+    // unknown emitters wait in quarantine until pair discovery
+    // Returns the SwapIndexed event to publish once the batch is stored, none for quarantined logs
+    fn store_swap_log(storage: &mut DataStorage, mut swap_log: SwapLog) -> Option<DomainEvent> {
+        let key = swap_log.address;
+        //already counted in the pair's swap aggregates
+        let block = swap_log.block_number.map(|block| block.as_u64());
+        if block.is_some_and(|block| storage.compacted_blocks.get(&key).is_some_and(|compacted| block <= *compacted)) {
+            return None;
+        }
+        //stored before, e.g. the open block of a subscription extracted again after a restart
+        let stored = storage.swap_logs.get(&key).into_iter()
+            .chain(storage.quarantined_swap_logs.get(&key))
+            .flatten()
+            .any(|stored| stored.is_same_log(&swap_log));
+        if stored {
+            return None;
+        }
+        let protocol_id = if storage.token_pairs.contains_key(&key) {
+            Some(PROTOCOL_ID.to_string())
+        } else {
+            storage.pair_protocols.get(&key).cloned()
+        };
+        match protocol_id {
+            Some(protocol_id) => {
+                let event = DomainEvent::SwapIndexed {
+                    pair_address: key,
+                    protocol_id: protocol_id.clone(),
+                    block_number: block,
+                    transaction_hash: swap_log.transaction_hash.map(|hash| format!("{:?}", hash)),
+                    log_index: swap_log.log_index.map(|index| index.as_u64()),
+                };
+                swap_log.protocol_id = protocol_id;
+                storage.swap_logs.entry(key).or_default().push_back(swap_log);
+                Some(event)
+            }
+            None => {
+                storage.quarantined_swap_logs.entry(key).or_default().push_back(swap_log);
+                None
+            }
+        }
+    }
+
+    // Kept for every emitter until pruned past the rug-pull window, the first Mint of a pair usually comes before
+    // the pair is discovered. False for a log stored or pruned before.
+    fn store_liquidity_event(storage: &mut DataStorage, event: LiquidityEvent) -> bool {
+        if storage.liquidity_totals.get(&event.pair_address).is_some_and(|totals| event.block_number <= totals.last_block) {
+            return false;
+        }
+        let events = storage.liquidity_events.entry(event.pair_address).or_default();
+        if events.iter().any(|stored| stored.is_same_log(&event)) {
+            return false;
+        }
+        events.push_back(event);
+        true
+    }
+
+    async fn follow_swap_logs(&self, from_block: u64) -> Result<(), Box<dyn Error>> {
+        let mut stream = self.provider.subscribe_logs(&Filter::new().topic0(indexed_topics())).await?;
+        log::info!("Swap sync: subscribed to swap logs ({})", PROTOCOL_ID);
+
+        //close the gap between the last poll and the subscription start, stream delivers everything after head
+        let head = self.provider.get_block_number().await?.as_u64();
+        if head > from_block {
+            let _extraction = self.state.extraction.lock().await;
+            self.begin_catch_up(from_block, head);
+            self.extract_swap_events(from_block + 1, head)
+                .await
+                .map_err(|e| e.to_string())?;
+        }
+        self.sync_state.transition(SyncPhase::Following);
+
+        //block of the last received log, more of its logs may follow, the checkpoint stays below it
+        let mut open_block = head;
+        loop {
+            if self.is_paused().await || self.shutdown.is_triggered() {
+                log::info!("Swap sync paused or stopping, leaving log subscription");
+                return Ok(());
+            }
+            let log = tokio::select! {
+                next = timeout(Duration::from_secs(self.config.get().sync.data_refresh_interval), stream.next()) => match next {
+                    Ok(Some(log)) => log,
+                    Ok(None) => return Err("subscription stream closed".into()),
+                    //the head moved past the open block, all of its logs were delivered
+                    Err(_) => {
+                        if self.provider.get_block_number().await?.as_u64() > open_block {
+                            self.advance_checkpoint(open_block).await;
+                        }
+                        continue;
+                    }
+                },
+                _ = self.shutdown.triggered() => continue,
+            };
+            let block = log.block_number.map(|block| block.as_u64()).unwrap_or_default();
+            if block <= head {
+                continue;
+            }
+            if block > open_block {
+                self.advance_checkpoint(block - 1).await;
+                open_block = block;
+            }
+            //an admin backfill removes and re-extracts ranges under the same lock
+            let _extraction = self.state.extraction.lock().await;
+            if let Some(kind) = liquidity_event_kind(&log) {
+                if log.removed == Some(true) {
+                    self.data_service.remove_liquidity_event(log.address.into(), log.transaction_hash, log.log_index);
+                } else if let Some(event) = self.read_liquidity_events(&[log]).await.pop() {
+                    log::info!("Liquidity sync: {:?} of pair {} at block {}", kind, event.pair_address, block);
+                    let liquidity_service = LiquidityService::new(self.config.clone());
+                    self.data_service.write_batch(|storage| {
+                        Self::store_liquidity_event(storage, event);
+                        liquidity_service.prune_liquidity_events(storage, block)
+                    });
+                }
+                continue;
+            }
+            let swap_log = SwapLog::from(&log);
+            if swap_log.removed == Some(true) {
+                //reorged out
+                self.data_service.remove_swap_log(swap_log.address, swap_log.transaction_hash, swap_log.log_index);
+                log::info!("Swap sync: removed reorged log at block {}", block);
+                continue;
+            }
+            if let Some(event) = self.data_service.write_batch(|storage| Self::store_swap_log(storage, swap_log)) {
+                self.event_bus.publish(event);
+            }
+        }
+    }
+
+    async fn backfill_swaps(&self, from_block: u64, to_block: u64) -> Result<SwapBackfill, Box<dyn Error>> {
+        //wait for the running refresh, the previous phase is restored when done
+        let _extraction = match self.state.extraction.try_lock() {
+            Ok(extraction) => extraction,
+            Err(_) => {
+                log::info!("Swap backfill waiting for running synchronization");
+                self.state.extraction.lock().await
+            }
+        };
+        let previous = self.sync_state.begin_backfill(to_block);
+
+        let removed = self.data_service.remove_swap_logs_in_range(from_block, to_block);
+        log::info!("Swap backfill {} - {}: removed {} existing swap logs",from_block,to_block,removed.values().sum::<i64>());
+        let mut pairs: HashSet<Address> = removed.into_keys().collect();
+        let before = self.data_service.get_total_swap_logs_size();
+        //stopped by shutdown, the rest of the range is not indexed
+        let result = self.extract_ranges(from_block, to_block, &mut pairs)
+            .await
+            .map_err(|e| e.to_string())
+            .and_then(|extracted| if extracted < to_block {
+                Err(format!("stopped by shutdown after block {}", extracted))
+            } else {
+                Ok(extracted)
+            });
+        if let Err(e) = &result {
+            self.sync_state.record_error(format!("backfill {} - {} failed: {}", from_block, to_block, e));
+        }
+        self.sync_state.transition(previous);
+
+        result?;
+        Ok(SwapBackfill { indexed: self.data_service.get_total_swap_logs_size() - before, pairs })
+    }
+
+    fn get_sync_state(&self) -> SyncStateMachine {
+        self.sync_state.clone()
     }
 
     async fn get_last_processed_block(&self) -> u64 {
-        let last_block = LAST_PROCESSED_BLOCK.lock().await;
+        let last_block = self.state.last_processed_block.lock().await;
         *last_block
     }
 
     async fn is_block_processed_or_newer(&self, block: u64) -> bool {
-        let last_block = LAST_PROCESSED_BLOCK.lock().await;
+        let last_block = self.state.last_processed_block.lock().await;
         *last_block >= block
     }
+
+    async fn pause(&self) {
+        let mut paused = self.state.paused.lock().await;
+        *paused = true;
+    }
+
+    async fn resume(&self) {
+        let mut paused = self.state.paused.lock().await;
+        *paused = false;
+    }
+
+    async fn is_paused(&self) -> bool {
+        let paused = self.state.paused.lock().await;
+        *paused
+    }
+
+    async fn is_running(&self) -> bool {
+        let running = self.state.running.lock().await;
+        *running
+    }
 }
\ No newline at end of file
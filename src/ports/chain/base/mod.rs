@@ -1,2 +1,4 @@
 pub mod base_uniswap_v2_client_service;
-pub mod base_uniswap_v2_swap_sync;
\ No newline at end of file
+pub mod base_uniswap_v2_swap_sync;
+pub mod base_uniswap_v2_job_runner;
+pub mod base_uniswap_v2_indexer;
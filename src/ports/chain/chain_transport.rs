@@ -0,0 +1,99 @@
+use std::error::Error;
+use std::fmt::Debug;
+use std::pin::Pin;
+use std::str::FromStr;
+use async_trait::async_trait;
+use ethers::providers::{Http, Ipc, JsonRpcClient, Middleware, ProviderError, PubsubClient, Ws};
+use ethers::types::U256;
+use futures_util::stream::Stream;
+use serde::de::DeserializeOwned;
+use serde::Serialize;
+use serde_json::value::RawValue;
+use crate::ports::chain::quorum::QuorumService;
+
+// Transport able to carry eth_subscribe, whether it can depends on the connection it currently uses
+pub trait SubscriptionTransport: PubsubClient {
+    fn supports_subscriptions(&self) -> bool;
+
+    // Quorum reads over this transport, none where every read goes to a single endpoint
+    fn quorum(&self) -> QuorumService {
+        QuorumService::default()
+    }
+}
+
+// Middleware stack the chain services run on, the provider is followed over subscriptions where it allows them
+pub trait ChainMiddleware: Middleware<Provider: SubscriptionTransport> + 'static {}
+
+impl<M> ChainMiddleware for M where M: Middleware<Provider: SubscriptionTransport> + 'static {}
+
+// Transport picked from the RPC URL scheme: http(s)://, ws(s):// or an IPC socket path (ipc:// prefix optional)
+#[derive(Debug)]
+pub enum ChainTransport {
+    Http(Http),
+    Ws(Ws),
+    Ipc(Ipc),
+}
+
+impl ChainTransport {
+    pub async fn connect(url: &str) -> Result<Self, Box<dyn Error>> {
+        if url.starts_with("http://") || url.starts_with("https://") {
+            Ok(ChainTransport::Http(Http::from_str(url)?))
+        } else if url.starts_with("ws://") || url.starts_with("wss://") {
+            Ok(ChainTransport::Ws(Ws::connect(url).await?))
+        } else {
+            Ok(ChainTransport::Ipc(Ipc::connect(url.trim_start_matches("ipc://")).await?))
+        }
+    }
+
+    pub fn kind(&self) -> &str {
+        match self {
+            ChainTransport::Http(_) => "http",
+            ChainTransport::Ws(_) => "ws",
+            ChainTransport::Ipc(_) => "ipc",
+        }
+    }
+}
+
+impl SubscriptionTransport for ChainTransport {
+    // eth_subscribe needs a persistent connection
+    fn supports_subscriptions(&self) -> bool {
+        !matches!(self, ChainTransport::Http(_))
+    }
+}
+
+#[async_trait]
+impl JsonRpcClient for ChainTransport {
+    type Error = ProviderError;
+
+    async fn request<T, R>(&self, method: &str, params: T) -> Result<R, Self::Error>
+    where
+        T: Debug + Serialize + Send + Sync,
+        R: DeserializeOwned + Send,
+    {
+        match self {
+            ChainTransport::Http(client) => client.request(method, params).await.map_err(Into::into),
+            ChainTransport::Ws(client) => client.request(method, params).await.map_err(Into::into),
+            ChainTransport::Ipc(client) => client.request(method, params).await.map_err(Into::into),
+        }
+    }
+}
+
+impl PubsubClient for ChainTransport {
+    type NotificationStream = Pin<Box<dyn Stream<Item = Box<RawValue>> + Send>>;
+
+    fn subscribe<T: Into<U256>>(&self, id: T) -> Result<Self::NotificationStream, Self::Error> {
+        match self {
+            ChainTransport::Http(_) => Err(ProviderError::UnsupportedRPC),
+            ChainTransport::Ws(client) => Ok(Box::pin(PubsubClient::subscribe(client, id).map_err(ProviderError::from)?)),
+            ChainTransport::Ipc(client) => Ok(Box::pin(PubsubClient::subscribe(client, id).map_err(ProviderError::from)?)),
+        }
+    }
+
+    fn unsubscribe<T: Into<U256>>(&self, id: T) -> Result<(), Self::Error> {
+        match self {
+            ChainTransport::Http(_) => Err(ProviderError::UnsupportedRPC),
+            ChainTransport::Ws(client) => PubsubClient::unsubscribe(client, id).map_err(ProviderError::from),
+            ChainTransport::Ipc(client) => PubsubClient::unsubscribe(client, id).map_err(ProviderError::from),
+        }
+    }
+}
@@ -0,0 +1,70 @@
+use crate::config::AppConfig;
+
+// Provider messages for getLogs requests rejected by block span or result count
+const RANGE_LIMIT_MESSAGES: [&str; 9] = [
+    "query returned more than",
+    "query exceeds max results",
+    "too many results",
+    "response size exceeded",
+    "block range is too wide",
+    "block range too large",
+    "exceed maximum block range",
+    "exceeds max block range",
+    "is limited to a",
+];
+
+pub fn is_range_limit_error(message: &str) -> bool {
+    let message = message.to_lowercase();
+    RANGE_LIMIT_MESSAGES.iter().any(|limit| message.contains(limit))
+}
+
+// Successful requests in a row before the range is doubled, keeps it from bouncing on a provider limit
+const GROW_AFTER_SUCCESSES: u32 = 8;
+
+// eth_getLogs block span: halved when a provider rejects the range, doubled again after successful requests
+#[derive(Debug, Clone)]
+pub struct AdaptiveLogRange {
+    size: u64,
+    min: u64,
+    max: u64,
+    successes: u32,
+}
+
+impl AdaptiveLogRange {
+    // A max below min wins, the provider limit can't be exceeded
+    pub fn new(initial: u64, min: u64, max: u64) -> Self {
+        let min = std::cmp::min(min, max);
+        AdaptiveLogRange { size: initial.clamp(min, max), min, max, successes: 0 }
+    }
+
+    // Limits configured for the protocol, capped by the smallest max_logs_range of the RPC endpoints
+    pub fn from_config(config: &AppConfig, protocol_id: &str) -> Self {
+        let ingestion = config.ingestion(protocol_id);
+        let max = config.base_rpc_endpoints().iter()
+            .filter_map(|endpoint| endpoint.max_logs_range)
+            .fold(ingestion.logs_range_max, std::cmp::min);
+        Self::new(ingestion.logs_range_initial, ingestion.logs_range_min, max)
+    }
+
+    pub fn size(&self) -> u64 {
+        self.size
+    }
+
+    // false when the range is already at its minimum
+    pub fn shrink(&mut self) -> bool {
+        self.successes = 0;
+        if self.size <= self.min {
+            return false;
+        }
+        self.size = std::cmp::max(self.size / 2, self.min);
+        true
+    }
+
+    pub fn grow(&mut self) {
+        self.successes += 1;
+        if self.successes >= GROW_AFTER_SUCCESSES {
+            self.successes = 0;
+            self.size = std::cmp::min(self.size.saturating_mul(2), self.max);
+        }
+    }
+}
@@ -1 +1,5 @@
-pub mod base;
\ No newline at end of file
+pub mod base;
+pub mod chain_transport;
+pub mod log_range;
+pub mod quorum;
+pub mod rpc_pool;
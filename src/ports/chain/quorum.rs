@@ -0,0 +1,171 @@
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, SystemTime};
+use ethers::types::{Bytes, Log};
+use serde::de::DeserializeOwned;
+use serde::Serialize;
+use serde_json::Value;
+use crate::domain::entities::address::Address;
+
+// Reads compared across providers in quorum mode: logs, reserves and ERC-20 metadata
+pub const QUORUM_METHODS: [&str; 2] = ["eth_getLogs", "eth_call"];
+
+#[derive(Debug, Clone, Serialize)]
+pub struct QuorumDispute {
+    pub address: Address,
+    pub method: String,
+    pub count: u64,
+    pub last_seen_at: SystemTime,
+}
+
+#[derive(Debug, Default, Clone, Serialize)]
+pub struct QuorumMetrics {
+    pub requests: u64,
+    pub agreed: u64,
+    pub disagreements: u64,
+    pub failures: u64,
+    pub disputes: HashMap<Address, QuorumDispute>,
+}
+
+// Quorum reads of one RPC pool and the contracts they disagreed on, shared by its clones
+#[derive(Debug, Clone, Default)]
+pub struct QuorumService {
+    metrics: Arc<Mutex<QuorumMetrics>>,
+    // a contract stays disputed this long after its last disagreement, zero keeps disputes
+    dispute_ttl: Duration,
+}
+
+impl QuorumService {
+    pub fn new(dispute_ttl_secs: u64) -> Self {
+        QuorumService { metrics: Arc::new(Mutex::new(QuorumMetrics::default())), dispute_ttl: Duration::from_secs(dispute_ttl_secs) }
+    }
+
+    fn expire_disputes(&self, metrics: &mut QuorumMetrics) {
+        if self.dispute_ttl.is_zero() {
+            return;
+        }
+        metrics.disputes.retain(|_, dispute| dispute.last_seen_at.elapsed().map_or(true, |age| age < self.dispute_ttl));
+    }
+}
+
+pub trait QuorumServiceTrait {
+    fn record_agreement(&self);
+    fn record_failure(&self);
+    fn record_disagreement(&self, method: &str, addresses: &[Address]);
+    fn disputed_keys(&self) -> Vec<Address>;
+    fn is_disputed(&self, address: Address) -> bool;
+    fn get_metrics(&self) -> QuorumMetrics;
+}
+
+impl QuorumServiceTrait for QuorumService {
+    fn record_agreement(&self) {
+        let mut metrics = self.metrics.lock().unwrap();
+        metrics.requests += 1;
+        metrics.agreed += 1;
+    }
+
+    fn record_failure(&self) {
+        let mut metrics = self.metrics.lock().unwrap();
+        metrics.requests += 1;
+        metrics.failures += 1;
+    }
+
+    // Counts a disagreement and records the contracts it touched
+    fn record_disagreement(&self, method: &str, addresses: &[Address]) {
+        let mut metrics = self.metrics.lock().unwrap();
+        metrics.disagreements += 1;
+        for address in addresses {
+            let dispute = metrics.disputes.entry(*address).or_insert_with(|| QuorumDispute {
+                address: *address,
+                method: method.to_string(),
+                count: 0,
+                last_seen_at: SystemTime::now(),
+            });
+            dispute.method = method.to_string();
+            dispute.count += 1;
+            dispute.last_seen_at = SystemTime::now();
+        }
+    }
+
+    // Storage keys of the disputed contracts, tokens and pairs alike
+    fn disputed_keys(&self) -> Vec<Address> {
+        let mut metrics = self.metrics.lock().unwrap();
+        self.expire_disputes(&mut metrics);
+        metrics.disputes.keys().copied().collect()
+    }
+
+    fn is_disputed(&self, address: Address) -> bool {
+        let mut metrics = self.metrics.lock().unwrap();
+        self.expire_disputes(&mut metrics);
+        metrics.disputes.contains_key(&address)
+    }
+
+    fn get_metrics(&self) -> QuorumMetrics {
+        let mut metrics = self.metrics.lock().unwrap();
+        self.expire_disputes(&mut metrics);
+        metrics.clone()
+    }
+}
+
+fn canonical<T: DeserializeOwned + Serialize>(response: &Value) -> Option<Value> {
+    serde_json::from_value::<T>(response.clone()).ok().and_then(|decoded| serde_json::to_value(decoded).ok())
+}
+
+// Response decoded and encoded again, providers writing the same result with other hex case, leading zeros, field order
+// or extra fields compare equal. Responses that don't decode are compared as returned.
+pub fn normalize_response(method: &str, response: &Value) -> Value {
+    let normalized = match method {
+        "eth_getLogs" => canonical::<Vec<Log>>(response),
+        "eth_call" => canonical::<Bytes>(response),
+        _ => None,
+    };
+    normalized.unwrap_or_else(|| response.clone())
+}
+
+// Logs of a getLogs response up to a block, for comparing answers of providers at different heads
+pub fn logs_up_to(response: &Value, block: u64) -> Value {
+    match response.as_array() {
+        Some(logs) => Value::Array(logs.iter()
+            .filter(|log| log.get("blockNumber")
+                .and_then(Value::as_str)
+                .and_then(|number| u64::from_str_radix(number.trim_start_matches("0x"), 16).ok())
+                .is_none_or(|number| number <= block))
+            .cloned()
+            .collect()),
+        None => response.clone(),
+    }
+}
+
+// Index of a response returned by at least threshold providers
+pub fn find_quorum(responses: &[Value], threshold: usize) -> Option<usize> {
+    (0..responses.len()).find(|&index| {
+        responses.iter().filter(|response| **response == responses[index]).count() >= threshold
+    })
+}
+
+// Contracts whose data differed: the eth_call target, or the emitters of logs missing from some responses
+pub fn disputed_addresses(method: &str, params: &Value, responses: &[Value]) -> Vec<Address> {
+    let mut addresses: Vec<Address> = match method {
+        "eth_call" => params.get(0)
+            .and_then(|call| call.get("to"))
+            .and_then(|to| serde_json::from_value(to.clone()).ok())
+            .into_iter()
+            .collect(),
+        _ => {
+            let logs: Vec<&Value> = responses.iter()
+                .filter_map(|response| response.as_array())
+                .flatten()
+                .collect();
+            logs.iter()
+                .filter(|log| !responses.iter().all(|response| {
+                    response.as_array().is_some_and(|items| items.contains(log))
+                }))
+                .filter_map(|log| log.get("address"))
+                .filter_map(|address| serde_json::from_value(address.clone()).ok())
+                .collect()
+        }
+    };
+    addresses.sort();
+    addresses.dedup();
+    addresses
+}
@@ -0,0 +1,584 @@
+use std::collections::HashMap;
+use std::error::Error;
+use std::fmt::Debug;
+use std::sync::{Arc, RwLock};
+use std::time::{Instant, SystemTime};
+use async_trait::async_trait;
+use futures_util::future::join_all;
+use ethers::providers::{JsonRpcClient, Provider, ProviderError, PubsubClient, RpcError};
+use ethers::types::U256;
+use serde::de::DeserializeOwned;
+use serde::Serialize;
+use serde_json::Value;
+use tokio::time::{sleep, timeout, Duration};
+use crate::config::{AppConfig, RpcEndpointConfig};
+use crate::domain::entities::supervised_task::ShutdownMode;
+use crate::domain::services::task_supervisor::TaskSupervisor;
+use crate::ports::chain::quorum::{disputed_addresses, find_quorum, logs_up_to, normalize_response, QuorumService, QuorumServiceTrait, QUORUM_METHODS};
+use crate::ports::chain::chain_transport::{ChainTransport, SubscriptionTransport};
+use crate::ports::chain::log_range::is_range_limit_error;
+
+// Weight of the newest sample in the moving error rate and latency
+const SAMPLE_WEIGHT: f64 = 0.2;
+// Error responses meaning the endpoint is throttling us, these fail over like transport errors
+const RATE_LIMIT_CODES: [i64; 2] = [429, -32005];
+
+#[derive(Debug, Clone, Serialize)]
+pub struct RpcEndpointStatus {
+    pub url: String,
+    pub transport: String,
+    pub weight: u32,
+    pub healthy: bool,
+    pub connected: bool,
+    pub head_block: u64,
+    pub head_lag: u64,
+    pub head_age_secs: Option<u64>,
+    pub latency_ms: f64,
+    pub error_rate: f64,
+    pub requests: u64,
+    pub errors: u64,
+    pub quorum_dissents: u64,
+    pub score: f64,
+    pub last_error: Option<String>,
+    pub last_checked_at: Option<SystemTime>,
+}
+
+#[derive(Debug, Default)]
+struct EndpointStats {
+    head_block: u64,
+    head_seen_at: Option<Instant>,
+    latency_ms: f64,
+    error_rate: f64,
+    requests: u64,
+    errors: u64,
+    quorum_dissents: u64,
+    last_error: Option<String>,
+    last_checked_at: Option<SystemTime>,
+    // smooth weighted round-robin credit, healthy endpoints take turns in proportion to their score
+    selection_credit: f64,
+}
+
+#[derive(Debug)]
+struct RpcEndpoint {
+    url: String,
+    weight: u32,
+    transport: RwLock<Option<Arc<ChainTransport>>>,
+    stats: std::sync::Mutex<EndpointStats>,
+}
+
+impl RpcEndpoint {
+    fn transport(&self) -> Option<Arc<ChainTransport>> {
+        self.transport.read().unwrap().clone()
+    }
+
+    fn record_success(&self, latency_ms: f64) {
+        let mut stats = self.stats.lock().unwrap();
+        stats.requests += 1;
+        stats.latency_ms = if stats.requests == 1 {
+            latency_ms
+        } else {
+            stats.latency_ms * (1.0 - SAMPLE_WEIGHT) + latency_ms * SAMPLE_WEIGHT
+        };
+        stats.error_rate *= 1.0 - SAMPLE_WEIGHT;
+    }
+
+    fn record_failure(&self, error: String) {
+        let mut stats = self.stats.lock().unwrap();
+        stats.requests += 1;
+        stats.errors += 1;
+        stats.error_rate = stats.error_rate * (1.0 - SAMPLE_WEIGHT) + SAMPLE_WEIGHT;
+        stats.last_error = Some(error);
+    }
+
+    fn record_head(&self, block: u64) {
+        let mut stats = self.stats.lock().unwrap();
+        if block > stats.head_block || stats.head_seen_at.is_none() {
+            stats.head_block = block;
+            stats.head_seen_at = Some(Instant::now());
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct RpcPoolSettings {
+    pub max_head_lag: u64,
+    pub max_head_age: u64,
+    // quorum mode: reads go to quorum_size endpoints and need quorum_threshold equal answers, 0 disables it
+    pub quorum_size: usize,
+    pub quorum_threshold: usize,
+    pub quorum_dispute_ttl: u64,
+}
+
+impl RpcPoolSettings {
+    pub fn from_config(config: &AppConfig) -> Self {
+        RpcPoolSettings {
+            max_head_lag: config.base_chain().rpc_max_head_lag,
+            max_head_age: config.base_chain().rpc_max_head_age,
+            quorum_size: config.base_chain().rpc_quorum_size,
+            quorum_threshold: config.base_chain().rpc_quorum_threshold,
+            quorum_dispute_ttl: config.base_chain().rpc_quorum_dispute_ttl,
+        }
+    }
+}
+
+#[derive(Debug)]
+struct RpcPoolInner {
+    endpoints: Vec<RpcEndpoint>,
+    settings: RpcPoolSettings,
+    // subscription id -> endpoint that created it, notifications come from the same connection
+    subscriptions: std::sync::Mutex<HashMap<U256, usize>>,
+    quorum: QuorumService,
+}
+
+// JSON-RPC client over several endpoints, calls are spread over healthy endpoints by score and fail over on errors
+#[derive(Debug, Clone)]
+pub struct RpcPool {
+    inner: Arc<RpcPoolInner>,
+}
+
+impl RpcPool {
+    pub async fn connect(endpoints: &[RpcEndpointConfig], settings: RpcPoolSettings) -> Result<Self, Box<dyn Error>> {
+        let mut pool_endpoints = vec![];
+        for endpoint in endpoints {
+            let transport = match ChainTransport::connect(&endpoint.url).await {
+                Ok(transport) => {
+                    log::info!("RPC endpoint: {} ({}, weight {})", endpoint.url, transport.kind(), endpoint.weight);
+                    Some(Arc::new(transport))
+                }
+                Err(e) => {
+                    log::warn!("RPC endpoint {} not connected: {}", endpoint.url, e);
+                    None
+                }
+            };
+            pool_endpoints.push(RpcEndpoint {
+                url: endpoint.url.clone(),
+                weight: endpoint.weight,
+                transport: RwLock::new(transport),
+                stats: std::sync::Mutex::new(EndpointStats::default()),
+            });
+        }
+        if pool_endpoints.iter().all(|endpoint| endpoint.transport().is_none()) {
+            return Err("no RPC endpoint could be connected".into());
+        }
+
+        let quorum = QuorumService::new(settings.quorum_dispute_ttl);
+        Ok(RpcPool {
+            inner: Arc::new(RpcPoolInner {
+                endpoints: pool_endpoints,
+                settings,
+                subscriptions: std::sync::Mutex::new(HashMap::new()),
+                quorum,
+            }),
+        })
+    }
+
+    pub fn get_status(&self) -> Vec<RpcEndpointStatus> {
+        let best_head = self.best_head();
+        self.inner.endpoints.iter()
+            .map(|endpoint| {
+                let transport = endpoint.transport();
+                let stats = endpoint.stats.lock().unwrap();
+                RpcEndpointStatus {
+                    url: endpoint.url.clone(),
+                    transport: transport.as_ref().map(|transport| transport.kind().to_string()).unwrap_or_default(),
+                    weight: endpoint.weight,
+                    healthy: self.is_healthy(transport.is_some(), &stats, best_head),
+                    connected: transport.is_some(),
+                    head_block: stats.head_block,
+                    head_lag: best_head.saturating_sub(stats.head_block),
+                    head_age_secs: stats.head_seen_at.map(|seen_at| seen_at.elapsed().as_secs()),
+                    latency_ms: stats.latency_ms,
+                    error_rate: stats.error_rate,
+                    requests: stats.requests,
+                    errors: stats.errors,
+                    quorum_dissents: stats.quorum_dissents,
+                    score: self.score(endpoint.weight, &stats),
+                    last_error: stats.last_error.clone(),
+                    last_checked_at: stats.last_checked_at,
+                }
+            })
+            .collect()
+    }
+
+    // Polls eth_blockNumber on every endpoint, reconnecting endpoints whose connection failed
+    pub async fn check_health(&self) {
+        for (index, endpoint) in self.inner.endpoints.iter().enumerate() {
+            let result = match endpoint.transport() {
+                Some(transport) => {
+                    let started = Instant::now();
+                    match timeout(Duration::from_secs(10), transport.request::<_, U256>("eth_blockNumber", ())).await {
+                        Ok(Ok(block)) => Ok((block.as_u64(), started.elapsed().as_secs_f64() * 1000.0)),
+                        Ok(Err(e)) => Err((e.as_error_response().is_none(), e.to_string())),
+                        Err(_) => Err((true, "health check timed out".to_string())),
+                    }
+                }
+                None => Err((true, "not connected".to_string())),
+            };
+
+            match result {
+                Ok((block, latency_ms)) => {
+                    endpoint.record_success(latency_ms);
+                    endpoint.record_head(block);
+                    endpoint.stats.lock().unwrap().last_error = None;
+                }
+                Err((reconnect, error)) => {
+                    endpoint.record_failure(error);
+                    //connection level failure, a fresh connection is used from the next call on
+                    if reconnect {
+                        match ChainTransport::connect(&endpoint.url).await.map_err(|e| e.to_string()) {
+                            Ok(transport) => {
+                                *endpoint.transport.write().unwrap() = Some(Arc::new(transport));
+                                self.forget_subscriptions(index);
+                            }
+                            Err(e) => log::warn!("RPC endpoint {} reconnect failed: {}", endpoint.url, e),
+                        }
+                    }
+                }
+            }
+            endpoint.stats.lock().unwrap().last_checked_at = Some(SystemTime::now());
+        }
+
+        for status in self.get_status().iter().filter(|status| !status.healthy) {
+            log::warn!("RPC endpoint {} unhealthy: lag {} blocks, error rate {:.2}, last error {:?}",
+                status.url, status.head_lag, status.error_rate, status.last_error);
+        }
+    }
+
+    // Health checks every interval seconds as a supervised task, cancelled on shutdown
+    pub fn supervise_health_check(&self, supervisor: &TaskSupervisor, interval: u64) {
+        let pool = self.clone();
+        supervisor.supervise("rpc_health_check", ShutdownMode::Cancel, move || {
+            let pool = pool.clone();
+            async move {
+                loop {
+                    pool.check_health().await;
+                    sleep(Duration::from_secs(interval)).await;
+                }
+            }
+        });
+    }
+
+    fn best_head(&self) -> u64 {
+        self.inner.endpoints.iter()
+            .map(|endpoint| endpoint.stats.lock().unwrap().head_block)
+            .max()
+            .unwrap_or_default()
+    }
+
+    fn is_healthy(&self, connected: bool, stats: &EndpointStats, best_head: u64) -> bool {
+        let head_fresh = stats.head_seen_at
+            .is_none_or(|seen_at| seen_at.elapsed().as_secs() <= self.inner.settings.max_head_age);
+        connected
+            && stats.error_rate < 0.5
+            && best_head.saturating_sub(stats.head_block) <= self.inner.settings.max_head_lag
+            && head_fresh
+    }
+
+    fn score(&self, weight: u32, stats: &EndpointStats) -> f64 {
+        weight as f64 * (1.0 - stats.error_rate) / (1.0 + stats.latency_ms / 100.0)
+    }
+
+    // Healthy endpoint whose turn it is, each is picked in proportion to its score
+    fn next_healthy(&self, candidates: &[(usize, bool, f64)]) -> Option<usize> {
+        let total: f64 = candidates.iter().filter(|(_, healthy, _)| *healthy).map(|(_, _, score)| score).sum();
+        if total <= 0.0 {
+            return None;
+        }
+        let mut picked: Option<(usize, f64)> = None;
+        for (index, _, score) in candidates.iter().filter(|(_, healthy, _)| *healthy) {
+            let mut stats = self.inner.endpoints[*index].stats.lock().unwrap();
+            stats.selection_credit += score;
+            if picked.is_none_or(|(_, credit)| stats.selection_credit > credit) {
+                picked = Some((*index, stats.selection_credit));
+            }
+        }
+        let (index, _) = picked?;
+        self.inner.endpoints[index].stats.lock().unwrap().selection_credit -= total;
+        Some(index)
+    }
+
+    // Endpoint indexes to try in order: the healthy endpoint whose turn it is, the other healthy ones by score,
+    // then the unhealthy ones
+    fn ranked(&self, subscriptions_only: bool) -> Vec<usize> {
+        let best_head = self.best_head();
+        let mut ranked: Vec<(usize, bool, f64)> = self.inner.endpoints.iter()
+            .enumerate()
+            .filter_map(|(index, endpoint)| {
+                let transport = endpoint.transport()?;
+                if subscriptions_only && !transport.supports_subscriptions() {
+                    return None;
+                }
+                let stats = endpoint.stats.lock().unwrap();
+                Some((index, self.is_healthy(true, &stats, best_head), self.score(endpoint.weight, &stats)))
+            })
+            .collect();
+        ranked.sort_by(|a, b| b.1.cmp(&a.1).then(b.2.total_cmp(&a.2)));
+        if let Some(next) = self.next_healthy(&ranked) {
+            let position = ranked.iter().position(|(index, _, _)| *index == next).unwrap_or_default();
+            ranked[..=position].rotate_right(1);
+        }
+        ranked.into_iter().map(|(index, _, _)| index).collect()
+    }
+
+    // Subscriptions of a closed connection end with it
+    fn forget_subscriptions(&self, endpoint_index: usize) {
+        self.inner.subscriptions.lock().unwrap().retain(|_, index| *index != endpoint_index);
+    }
+
+    fn subscription_endpoint(&self, id: U256) -> Result<Arc<ChainTransport>, ProviderError> {
+        let index = self.inner.subscriptions.lock().unwrap().get(&id).copied();
+        index
+            .and_then(|index| self.inner.endpoints[index].transport())
+            .ok_or_else(|| ProviderError::CustomError(format!("unknown subscription {}", id)))
+    }
+}
+
+impl RpcPool {
+    fn quorum_endpoints(&self) -> Vec<(usize, Arc<ChainTransport>)> {
+        self.ranked(false).into_iter()
+            .filter_map(|index| self.inner.endpoints[index].transport().map(|transport| (index, transport)))
+            .take(self.inner.settings.quorum_size)
+            .collect()
+    }
+
+    // Lowest head of the quorum endpoints, reads up to it can be answered by all of them
+    async fn quorum_head(&self) -> Result<U256, ProviderError> {
+        let requests = self.quorum_endpoints().into_iter().map(|(index, transport)| async move {
+            let started = Instant::now();
+            let result = transport.request::<_, U256>("eth_blockNumber", ()).await;
+            (index, started.elapsed().as_secs_f64() * 1000.0, result)
+        });
+        let mut heads = vec![];
+        for (index, latency_ms, result) in join_all(requests).await {
+            let endpoint = &self.inner.endpoints[index];
+            match result {
+                Ok(head) => {
+                    endpoint.record_success(latency_ms);
+                    endpoint.record_head(head.as_u64());
+                    heads.push(head);
+                }
+                Err(e) => endpoint.record_failure(e.to_string()),
+            }
+        }
+        if heads.len() < self.inner.settings.quorum_threshold {
+            return Err(ProviderError::CustomError(format!(
+                "quorum not reached for eth_blockNumber: {} answers, {} needed", heads.len(), self.inner.settings.quorum_threshold
+            )));
+        }
+        Ok(heads.into_iter().min().unwrap_or_default())
+    }
+
+    // Sends the read to the best quorum_size endpoints and returns the answer quorum_threshold of them agree on
+    async fn quorum_request<R: DeserializeOwned + Send>(&self, method: &str, mut params: Value) -> Result<R, ProviderError> {
+        let settings = &self.inner.settings;
+        let selected = self.quorum_endpoints();
+        let common_head = selected.iter()
+            .map(|(index, _)| self.inner.endpoints[*index].stats.lock().unwrap().head_block)
+            .min()
+            .unwrap_or_default();
+
+        //"latest" differs between providers a block apart, pin reads to a head all selected endpoints have
+        let mut compared_up_to = None;
+        if common_head > 0 {
+            let head = Value::String(format!("0x{:x}", common_head));
+            match method {
+                "eth_call" if params.get(1).and_then(Value::as_str) == Some("latest") => params[1] = head,
+                "eth_getLogs" if params[0].get("blockHash").is_none() => {
+                    let to_block = params[0].get("toBlock").and_then(Value::as_str).map(str::to_string);
+                    match to_block.as_deref() {
+                        None | Some("latest") => params[0]["toBlock"] = head,
+                        //logs above the common head are missing from lagging endpoints, only the rest is compared
+                        Some(to_block) if u64::from_str_radix(to_block.trim_start_matches("0x"), 16).is_ok_and(|to_block| to_block > common_head) =>
+                            compared_up_to = Some(common_head),
+                        Some(_) => {}
+                    }
+                }
+                _ => {}
+            }
+        }
+
+        let requests = selected.iter().map(|(index, transport)| {
+            let params = params.clone();
+            async move {
+                let started = Instant::now();
+                let result = transport.request::<_, Value>(method, params).await;
+                (*index, started.elapsed().as_secs_f64() * 1000.0, result)
+            }
+        });
+        let mut answered = vec![];
+        let mut responses = vec![];
+        let mut range_error = None;
+        for (index, latency_ms, result) in join_all(requests).await {
+            let endpoint = &self.inner.endpoints[index];
+            match result {
+                Ok(response) => {
+                    endpoint.record_success(latency_ms);
+                    answered.push(index);
+                    responses.push(response);
+                }
+                //the caller splits the range
+                Err(e) if is_range_limit_error(&e.to_string()) => {
+                    endpoint.record_success(latency_ms);
+                    range_error = Some(e);
+                }
+                Err(e) => endpoint.record_failure(e.to_string()),
+            }
+        }
+
+        let compared: Vec<Value> = responses.iter()
+            .map(|response| normalize_response(method, response))
+            .map(|response| match compared_up_to {
+                Some(block) => logs_up_to(&response, block),
+                None => response,
+            })
+            .collect();
+        let winner = find_quorum(&compared, settings.quorum_threshold);
+        if compared.iter().any(|response| *response != compared[0]) {
+            let addresses = disputed_addresses(method, &params, &compared);
+            if let Some(winner) = winner {
+                for (index, response) in answered.iter().zip(&compared) {
+                    if *response != compared[winner] {
+                        self.inner.endpoints[*index].stats.lock().unwrap().quorum_dissents += 1;
+                        log::warn!("RPC endpoint {} disagrees with the quorum on {}", self.inner.endpoints[*index].url, method);
+                    }
+                }
+            }
+            log::warn!("RPC quorum disagreement on {} ({} answers), affected contracts: {:?}", method, responses.len(), addresses);
+            self.inner.quorum.record_disagreement(method, &addresses);
+        }
+
+        match winner {
+            Some(winner) => {
+                self.inner.quorum.record_agreement();
+                //of the agreeing answers, the one reaching furthest above the common head
+                let answer = (0..responses.len())
+                    .filter(|&index| compared[index] == compared[winner])
+                    .max_by_key(|&index| responses[index].as_array().map_or(0, Vec::len))
+                    .unwrap_or(winner);
+                Ok(serde_json::from_value(responses.swap_remove(answer))?)
+            }
+            None => {
+                self.inner.quorum.record_failure();
+                Err(range_error.unwrap_or_else(|| ProviderError::CustomError(format!(
+                    "quorum not reached for {}: {} answers, {} needed", method, responses.len(), settings.quorum_threshold
+                ))))
+            }
+        }
+    }
+
+    async fn subscribe_on<T, R>(&self, index: usize, transport: &ChainTransport, params: &T) -> Result<R, ProviderError>
+    where
+        T: Debug + Serialize + Send + Sync,
+        R: DeserializeOwned + Send,
+    {
+        let value: serde_json::Value = transport.request("eth_subscribe", params).await?;
+        let id: U256 = serde_json::from_value(value.clone())?;
+        self.inner.subscriptions.lock().unwrap().insert(id, index);
+        Ok(serde_json::from_value(value)?)
+    }
+}
+
+fn fails_over(error: &ProviderError) -> bool {
+    //a range another endpoint would reject as well, the caller splits it
+    if is_range_limit_error(&error.to_string()) {
+        return false;
+    }
+    match error.as_error_response() {
+        Some(response) => RATE_LIMIT_CODES.contains(&response.code),
+        None => !matches!(error, ProviderError::SerdeJson(_)),
+    }
+}
+
+#[async_trait]
+impl JsonRpcClient for RpcPool {
+    type Error = ProviderError;
+
+    async fn request<T, R>(&self, method: &str, params: T) -> Result<R, Self::Error>
+    where
+        T: Debug + Serialize + Send + Sync,
+        R: DeserializeOwned + Send,
+    {
+        if self.inner.settings.quorum_size > 0 && QUORUM_METHODS.contains(&method) {
+            return self.quorum_request(method, serde_json::to_value(&params)?).await;
+        }
+        //ranges are read up to a head every quorum endpoint has, normal head lag is no disagreement
+        if self.inner.settings.quorum_size > 0 && method == "eth_blockNumber" {
+            return Ok(serde_json::from_value(serde_json::to_value(self.quorum_head().await?)?)?);
+        }
+
+        let subscribe = method == "eth_subscribe";
+        let mut last_error = None;
+
+        for index in self.ranked(subscribe) {
+            let endpoint = &self.inner.endpoints[index];
+            let Some(transport) = endpoint.transport() else {
+                continue;
+            };
+            let started = Instant::now();
+            let result = if subscribe {
+                self.subscribe_on(index, &transport, &params).await
+            } else {
+                transport.request::<_, R>(method, &params).await
+            };
+
+            match result {
+                Ok(response) => {
+                    endpoint.record_success(started.elapsed().as_secs_f64() * 1000.0);
+                    return Ok(response);
+                }
+                Err(e) if fails_over(&e) => {
+                    log::warn!("RPC endpoint {} failed {}: {}, failing over", endpoint.url, method, e);
+                    endpoint.record_failure(e.to_string());
+                    last_error = Some(e);
+                }
+                Err(e) => {
+                    endpoint.record_success(started.elapsed().as_secs_f64() * 1000.0);
+                    return Err(e);
+                }
+            }
+        }
+
+        Err(last_error.unwrap_or_else(|| ProviderError::CustomError("no RPC endpoint available".to_string())))
+    }
+}
+
+impl SubscriptionTransport for RpcPool {
+    // eth_subscribe needs at least one persistent connection
+    fn supports_subscriptions(&self) -> bool {
+        self.inner.endpoints.iter()
+            .filter_map(|endpoint| endpoint.transport())
+            .any(|transport| transport.supports_subscriptions())
+    }
+
+    fn quorum(&self) -> QuorumService {
+        self.inner.quorum.clone()
+    }
+}
+
+impl PubsubClient for RpcPool {
+    type NotificationStream = <ChainTransport as PubsubClient>::NotificationStream;
+
+    fn subscribe<T: Into<U256>>(&self, id: T) -> Result<Self::NotificationStream, Self::Error> {
+        let id = id.into();
+        let stream = self.subscription_endpoint(id).and_then(|transport| transport.subscribe(id));
+        //no stream is dropped for it, nothing unsubscribes later
+        if stream.is_err() {
+            self.inner.subscriptions.lock().unwrap().remove(&id);
+        }
+        stream
+    }
+
+    // Called when the subscription stream is dropped, the entry goes even when its connection is gone
+    fn unsubscribe<T: Into<U256>>(&self, id: T) -> Result<(), Self::Error> {
+        let id = id.into();
+        let transport = self.subscription_endpoint(id);
+        self.inner.subscriptions.lock().unwrap().remove(&id);
+        transport?.unsubscribe(id)
+    }
+}
+
+// Pool for the configured Base endpoints, long running callers supervise its health check
+pub async fn connect_provider(config: &AppConfig) -> Result<Arc<Provider<RpcPool>>, Box<dyn Error>> {
+    let pool = RpcPool::connect(&config.base_rpc_endpoints(), RpcPoolSettings::from_config(config)).await?;
+    Ok(Arc::new(Provider::new(pool)))
+}
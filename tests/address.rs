@@ -0,0 +1,59 @@
+use token_data_provider::domain::entities::address::Address;
+use token_data_provider::domain::entities::token::Token;
+use token_data_provider::domain::entities::token_pair::TokenPair;
+use token_data_provider::domain::services::data_storage_service::{DataStorageService, DataStorageTrait};
+
+const USDC: &str = "0x833589fCD6eDb6E08f4c7C32D4f71b54bdA02913";
+const PAIR: &str = "0x175176ddf9971c155909bcda18f8061b93109083";
+
+#[test]
+fn parses_any_letter_case_and_prints_checksum() {
+    let usdc: Address = USDC.parse().unwrap();
+    assert_eq!(USDC.to_lowercase().parse::<Address>().unwrap(), usdc);
+    assert_eq!(USDC.to_uppercase().replace("0X", "0x").parse::<Address>().unwrap(), usdc);
+    assert_eq!(USDC.trim_start_matches("0x").parse::<Address>().unwrap(), usdc);
+    assert_eq!(usdc.to_string(), USDC);
+    assert_eq!(format!("{:?}", usdc), USDC);
+
+    //the abbreviated H160 Display form is no address
+    assert!("0x8335…2913".parse::<Address>().is_err());
+    assert!("0x833589fcd6edb6e08f4c7c32d4f71b54bda029".parse::<Address>().is_err());
+    assert!("0x833589fcd6edb6e08f4c7c32d4f71b54bda0291g".parse::<Address>().is_err());
+}
+
+#[test]
+fn serializes_checksummed_and_deserializes_any_case() {
+    let usdc: Address = USDC.parse().unwrap();
+    assert_eq!(serde_json::to_string(&usdc).unwrap(), format!("\"{}\"", USDC));
+    let lowercase: Address = serde_json::from_str(&format!("\"{}\"", USDC.to_lowercase())).unwrap();
+    assert_eq!(lowercase, usdc);
+    assert!(serde_json::from_str::<Address>("\"0x8335…2913\"").is_err());
+}
+
+#[test]
+fn storage_keys_match_every_spelling() {
+    let data_service = DataStorageService::new();
+    let usdc: Address = USDC.to_lowercase().parse().unwrap();
+    let pair: Address = PAIR.parse().unwrap();
+    data_service.add_token_pair(pair, TokenPair {
+        token_pair_address: pair,
+        base_address: usdc,
+        ..TokenPair::default()
+    });
+    data_service.add_token(usdc, Token {
+        address: usdc,
+        pairs: data_service.get_token_pairs_by_address(usdc),
+        ..Token::default()
+    });
+
+    let upper: Address = USDC.to_uppercase().replace("0X", "0x").parse().unwrap();
+    assert_eq!(data_service.get_token(upper).unwrap().pairs.len(), 1);
+    assert!(data_service.contains_token_pair(PAIR.to_uppercase().replace("0X", "0x").parse().unwrap()));
+
+    //map keys and address fields come out checksummed
+    let json = serde_json::to_value(&data_service.get_snapshot().tokens).unwrap();
+    let token = &json[USDC];
+    assert_eq!(token["address"], USDC);
+    assert_eq!(token["pairs"][pair.to_string()]["base_address"], USDC);
+    assert_ne!(pair.to_string(), PAIR);
+}
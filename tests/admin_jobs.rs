@@ -0,0 +1,134 @@
+mod common;
+
+use std::sync::Arc;
+use ethers::providers::Provider;
+use serde_json::{json, Value};
+use token_data_provider::adapters::admin_api::create_admin_rest_api;
+use token_data_provider::config::{load_config, load_config_with_profile, ConfigHandle, BASE_CHAIN, DEV_PROFILE};
+use token_data_provider::domain::entities::address::Address;
+use token_data_provider::domain::entities::job::{JobKind, JobStatus};
+use token_data_provider::domain::entities::supervised_task::ShutdownMode;
+use token_data_provider::domain::services::api_key_service::{ApiKeyService, ApiKeyServiceTrait};
+use token_data_provider::domain::services::data_storage_service::{DataStorageService, DataStorageTrait};
+use token_data_provider::domain::services::job_service::{JobService, JobServiceTrait, MAX_FINISHED_JOBS};
+use token_data_provider::domain::services::task_supervisor::{ShutdownSignal, TaskSupervisor};
+use token_data_provider::ports::chain::base::base_uniswap_v2_client_service::BaseUniswapV2ClientServiceTrait;
+use token_data_provider::ports::chain::base::base_uniswap_v2_indexer::BaseUniswapV2Indexer;
+use token_data_provider::ports::chain::base::base_uniswap_v2_job_runner::{BaseUniswapV2JobRunnerTrait, JOB_TASK_PREFIX};
+use token_data_provider::ports::chain::base::base_uniswap_v2_swap_sync::BaseUniswapV2SwapSynchronizerTrait;
+use token_data_provider::ports::chain::rpc_pool::{RpcPool, RpcPoolSettings};
+use common::mock_rpc::MockRpcServer;
+
+// Addresses of tests/fixtures/base_uniswap_v2.json
+const PAIR_WETH_USDC: &str = "0x175176ddf9971c155909bcda18f8061b93109083";
+// pair without swaps
+const PAIR_USDC_ANON: &str = "0x6adb06345c327df2029907fb4d72c521e7ec39ff";
+const HEAD_BLOCK: u64 = 22800100;
+
+type Indexer = BaseUniswapV2Indexer<Provider<RpcPool>>;
+
+fn address(address: &str) -> Address {
+    address.parse().unwrap()
+}
+
+async fn connect_indexer(mock: &MockRpcServer) -> Indexer {
+    let mut config = load_config().unwrap();
+    config.chains.get_mut(BASE_CHAIN).unwrap().rpc_url = mock.url();
+    config.sync.logs_range_initial = 100;
+    config.sync.logs_max_attempts = 1;
+    let pool = RpcPool::connect(&config.base_rpc_endpoints(), RpcPoolSettings::from_config(&config)).await.unwrap();
+    BaseUniswapV2Indexer::new(ConfigHandle::new(config), DataStorageService::new(), Arc::new(Provider::new(pool)))
+}
+
+#[tokio::test]
+async fn job_requests_are_validated_before_submission() {
+    let mock = MockRpcServer::from_fixture("base_uniswap_v2").await;
+    let indexer = connect_indexer(&mock).await;
+    let api_keys = ApiKeyService::new();
+    api_keys.init(&load_config_with_profile(DEV_PROFILE).unwrap());
+    let supervisor = TaskSupervisor::new(indexer.config.clone(), ShutdownSignal::new(), None);
+    let admin_api = create_admin_rest_api(indexer.config.clone(), indexer.data_service.clone(), api_keys,
+                                          indexer.event_bus.clone(), supervisor, Some(indexer.clone()));
+
+    let post = |path: &str| warp::test::request().method("POST").path(path).header("x-api-key", "local-admin-key");
+    let response = post("/admin/jobs/swaps/backfill").json(&json!({ "from_block": 20, "to_block": 10 })).reply(&admin_api).await;
+    assert_eq!(response.status(), 400);
+    let response = post("/admin/jobs/sync/prices/pause").reply(&admin_api).await;
+    assert_eq!(response.status(), 404);
+    let response = post("/admin/jobs/sync/prices/resume").reply(&admin_api).await;
+    assert_eq!(response.status(), 404);
+    assert!(indexer.jobs.get_jobs().is_empty());
+
+    let response = post("/admin/jobs/sync/swaps/pause").reply(&admin_api).await;
+    assert_eq!(response.status(), 202);
+    let job: Value = serde_json::from_slice(response.body()).unwrap();
+    assert_eq!(job["kind"]["synchronizer"], "swaps");
+}
+
+#[test]
+fn finished_jobs_are_capped() {
+    let jobs = JobService::new();
+    let running = jobs.create_job(JobKind::PairDiscovery);
+    jobs.start_job(running.id.clone());
+    for _ in 0..MAX_FINISHED_JOBS + 10 {
+        let job = jobs.create_job(JobKind::PairDiscovery);
+        jobs.complete_job(job.id, "done".to_string());
+    }
+    let last = jobs.create_job(JobKind::PairDiscovery);
+
+    assert_eq!(jobs.get_jobs().len(), MAX_FINISHED_JOBS + 2);
+    //unfinished jobs are never dropped
+    assert!(jobs.get_job(running.id).is_some());
+    assert!(jobs.get_job(last.id).is_some());
+    assert!(jobs.get_job("job-2".to_string()).is_none());
+}
+
+#[tokio::test]
+async fn swap_backfill_recounts_only_its_pairs() {
+    let mock = MockRpcServer::from_fixture("base_uniswap_v2").await;
+    let indexer = connect_indexer(&mock).await;
+    let data_service = &indexer.data_service;
+    indexer.client_service.update_sync_log().await.unwrap();
+    indexer.swap_sync.extract_swap_events(22800000, HEAD_BLOCK).await.unwrap();
+    indexer.client_service.init_token_list(0, HEAD_BLOCK).await.unwrap();
+    let swaps = data_service.get_swap_count(address(PAIR_WETH_USDC));
+    assert!(swaps > 0);
+    //a pair without swaps in the range keeps its count
+    let mut untouched = data_service.get_token_pair(address(PAIR_USDC_ANON)).unwrap();
+    untouched.swaps = 99;
+    data_service.add_token_pair(address(PAIR_USDC_ANON), untouched);
+
+    let message = indexer.job_runner.execute(JobKind::SwapBackfill { from_block: 22800000, to_block: HEAD_BLOCK }).await.unwrap();
+    assert!(message.contains("pairs"), "{}", message);
+    assert_eq!(data_service.get_token_pair(address(PAIR_WETH_USDC)).unwrap().swaps, swaps);
+    assert_eq!(data_service.get_swap_count(address(PAIR_WETH_USDC)), swaps);
+    assert_eq!(data_service.get_token_pair(address(PAIR_USDC_ANON)).unwrap().swaps, 99);
+}
+
+#[tokio::test]
+async fn jobs_run_under_the_supervisor_and_backfills_fail_on_shutdown() {
+    let mock = MockRpcServer::from_fixture("base_uniswap_v2").await;
+    let indexer = connect_indexer(&mock).await;
+    let supervisor = TaskSupervisor::new(indexer.config.clone(), indexer.shutdown.clone(), None);
+
+    let job = indexer.job_runner.submit(&supervisor, JobKind::SyncPause { synchronizer: "swaps".to_string() });
+    let task = supervisor.get_tasks().into_iter().find(|task| task.name == format!("{}{}", JOB_TASK_PREFIX, job.id)).unwrap();
+    assert_eq!(task.shutdown_mode, ShutdownMode::Drain);
+    for _ in 0..50 {
+        if indexer.jobs.get_job(job.id.clone()).unwrap().status == JobStatus::Completed {
+            break;
+        }
+        tokio::time::sleep(std::time::Duration::from_millis(20)).await;
+    }
+
+    //shutdown waits for the backfill, stopped before its range it is no success
+    indexer.shutdown.trigger();
+    let backfill = indexer.job_runner.submit(&supervisor, JobKind::SwapBackfill { from_block: 22800000, to_block: HEAD_BLOCK });
+    supervisor.shutdown().await;
+    let backfill = indexer.jobs.get_job(backfill.id).unwrap();
+    assert_eq!(backfill.status, JobStatus::Failed);
+    assert!(backfill.message.as_deref().is_some_and(|message| message.contains("shutdown")), "{:?}", backfill.message);
+    //finished job tasks are dropped on the next submission
+    indexer.job_runner.submit(&supervisor, JobKind::SyncResume { synchronizer: "swaps".to_string() });
+    assert!(supervisor.get_tasks().iter().all(|task| task.name != format!("{}{}", JOB_TASK_PREFIX, job.id)));
+}
@@ -0,0 +1,121 @@
+mod common;
+
+use std::sync::Arc;
+use std::time::SystemTime;
+use ethers::providers::Provider;
+use serde_json::{json, Value};
+use warp::Filter;
+use token_data_provider::adapters::admin_api::create_admin_rest_api;
+use token_data_provider::adapters::rejections::handle_rejection;
+use token_data_provider::config::{load_config, load_config_with_profile, ConfigHandle, BASE_CHAIN, DEV_PROFILE};
+use token_data_provider::domain::entities::api_key::{ApiKey, ApiScope};
+use token_data_provider::domain::services::api_key_service::{ApiKeyService, ApiKeyServiceTrait};
+use token_data_provider::domain::services::data_storage_service::{DataStorageService, DataStorageTrait};
+use token_data_provider::domain::services::event_bus_service::EventBusService;
+use token_data_provider::domain::services::task_supervisor::{ShutdownSignal, TaskSupervisor};
+use token_data_provider::ports::chain::base::base_uniswap_v2_indexer::BaseUniswapV2Indexer;
+use token_data_provider::ports::chain::rpc_pool::{RpcPool, RpcPoolSettings};
+use common::mock_rpc::MockRpcServer;
+
+const ADMIN_KEY: &str = "test-admin-key";
+
+fn api_key_request(name: &str, key: &str, rate_limit_per_second: f64, burst: u32) -> Value {
+    json!({
+        "name": name,
+        "key": key,
+        "scopes": ["read"],
+        "rate_limit_per_second": rate_limit_per_second,
+        "burst": burst,
+        "daily_quota": null,
+    })
+}
+
+// Dev profile keys plus an admin key without a tight rate limit
+fn api_keys() -> ApiKeyService {
+    let api_keys = ApiKeyService::new();
+    api_keys.init(&load_config_with_profile(DEV_PROFILE).unwrap());
+    api_keys.add_api_key(ApiKey {
+        name: "test-admin".to_string(),
+        key: ADMIN_KEY.to_string(),
+        scopes: vec![ApiScope::Admin],
+        rate_limit_per_second: 100.0,
+        burst: 100,
+        daily_quota: None,
+        created_at: SystemTime::now(),
+    });
+    api_keys
+}
+
+async fn create_key<F>(admin_api: &F, request: &Value) -> (u16, Value)
+where
+    F: Filter + 'static,
+    F::Extract: warp::Reply + Send,
+{
+    let response = warp::test::request().method("POST").path("/admin/api-keys")
+        .header("x-api-key", ADMIN_KEY).json(request).reply(admin_api).await;
+    (response.status().as_u16(), serde_json::from_slice(response.body()).unwrap())
+}
+
+#[tokio::test]
+async fn api_key_create_rejects_invalid_and_duplicate_keys() {
+    let config = ConfigHandle::new(load_config().unwrap());
+    let api_keys = api_keys();
+    let supervisor = TaskSupervisor::new(config.clone(), ShutdownSignal::new(), None);
+    let admin_api = create_admin_rest_api(config, DataStorageService::new(), api_keys.clone(), EventBusService::new(), supervisor, None)
+        .recover(handle_rejection);
+
+    let mut no_scopes = api_key_request("partner", "partner-key", 5.0, 20);
+    no_scopes["scopes"] = json!([]);
+    let mut unknown_scope = api_key_request("partner", "partner-key", 5.0, 20);
+    unknown_scope["scopes"] = json!(["write"]);
+    for request in [api_key_request("partner", "partner-key", 0.0, 20), api_key_request("partner", "partner-key", 5.0, 0),
+                    api_key_request("", "partner-key", 5.0, 20), api_key_request("partner", " ", 5.0, 20), no_scopes, unknown_scope] {
+        assert_eq!(create_key(&admin_api, &request).await.0, 400, "{}", request);
+    }
+    assert!(!api_keys.get_api_keys().iter().any(|api_key| api_key.name == "partner"));
+
+    //a serve process writes no snapshots, its keys don't survive a restart
+    let (status, body) = create_key(&admin_api, &api_key_request("partner", "partner-key", 5.0, 20)).await;
+    assert_eq!(status, 201);
+    assert_eq!(body["name"], "partner");
+    assert_eq!(body["ephemeral"], true);
+    assert!(api_keys.authorize(Some("partner-key".to_string()), ApiScope::Read).is_ok());
+
+    //names and keys are unique, including the configured ones
+    assert_eq!(create_key(&admin_api, &api_key_request("partner", "other-key", 5.0, 20)).await.0, 409);
+    assert_eq!(create_key(&admin_api, &api_key_request("other", "local-read-key", 5.0, 20)).await.0, 409);
+    assert_eq!(api_keys.get_api_keys().len(), 4);
+}
+
+#[tokio::test]
+async fn api_keys_created_with_an_indexer_survive_restarts() {
+    let mock = MockRpcServer::from_fixture("base_uniswap_v2").await;
+    let mut config = load_config().unwrap();
+    config.chains.get_mut(BASE_CHAIN).unwrap().rpc_url = mock.url();
+    let pool = RpcPool::connect(&config.base_rpc_endpoints(), RpcPoolSettings::from_config(&config)).await.unwrap();
+    let config = ConfigHandle::new(config);
+    let indexer = BaseUniswapV2Indexer::new(config.clone(), DataStorageService::new(), Arc::new(Provider::new(pool)));
+    let supervisor = TaskSupervisor::new(config.clone(), ShutdownSignal::new(), None);
+    let admin_api = create_admin_rest_api(config, indexer.data_service.clone(), api_keys(), indexer.event_bus.clone(),
+                                          supervisor, Some(indexer.clone()));
+
+    let (status, body) = create_key(&admin_api, &api_key_request("partner", "partner-key", 5.0, 20)).await;
+    assert_eq!(status, 201);
+    assert_eq!(body["ephemeral"], false);
+    assert!(body.get("key").is_none());
+
+    let path = std::env::temp_dir().join(format!("token-data-provider-api-keys-{}.json", std::process::id())).to_string_lossy().to_string();
+    indexer.data_service.save_snapshot(&path).unwrap();
+    let restarted = DataStorageService::new();
+    assert!(restarted.load_snapshot(&path).unwrap());
+    std::fs::remove_file(&path).unwrap();
+    let restarted_keys = ApiKeyService::new();
+    restarted_keys.init(&load_config_with_profile(DEV_PROFILE).unwrap());
+    restarted_keys.restore_api_keys(restarted.get_api_keys());
+    assert!(restarted_keys.authorize(Some("partner-key".to_string()), ApiScope::Read).is_ok());
+
+    let response = warp::test::request().method("DELETE").path("/admin/api-keys/partner")
+        .header("x-api-key", ADMIN_KEY).reply(&admin_api).await;
+    assert_eq!(response.status(), 200);
+    assert!(indexer.data_service.get_api_keys().is_empty());
+}
@@ -0,0 +1,442 @@
+mod common;
+
+use std::sync::Arc;
+use std::time::Duration;
+use ethers::providers::Provider;
+use ethers::types::H256;
+use serde_json::json;
+use token_data_provider::config::{load_config, ConfigHandle, BASE_CHAIN};
+use token_data_provider::domain::entities::address::Address;
+use token_data_provider::domain::entities::sync_state::SyncPhase;
+use token_data_provider::domain::services::data_storage_service::{DataStorageService, DataStorageTrait};
+use token_data_provider::domain::services::pair_activity_aggregator::PairActivityAggregatorTrait;
+use token_data_provider::ports::chain::base::base_uniswap_v2_client_service::{BaseUniswapV2ClientServiceTrait, PROTOCOL_ID};
+use token_data_provider::ports::chain::base::base_uniswap_v2_indexer::BaseUniswapV2Indexer;
+use token_data_provider::ports::chain::base::base_uniswap_v2_swap_sync::BaseUniswapV2SwapSynchronizerTrait;
+use token_data_provider::ports::chain::chain_transport::ChainTransport;
+use common::mock_rpc::{Cassette, Interaction, MockRpcServer};
+
+// Addresses of tests/fixtures/base_uniswap_v2.json
+const HEAD_BLOCK: u64 = 22800100;
+const WETH: &str = "0x4200000000000000000000000000000000000006";
+const USDC: &str = "0x833589fcd6edb6e08f4c7c32d4f71b54bda02913";
+const RUG: &str = "0xe17964b8e0416e15a38a43d1aa749599e56fd256";
+// pair token whose metadata calls revert
+const ANON: &str = "0x02e9367ee3fae97214c5e3d60df77e6559825a57";
+const PAIR_WETH_USDC: &str = "0x175176ddf9971c155909bcda18f8061b93109083";
+const PAIR_WETH_RUG: &str = "0x5500b6adf1590f37ef8f7ecaef06db4cd16005a3";
+const PAIR_USDC_ANON: &str = "0x6adb06345c327df2029907fb4d72c521e7ec39ff";
+// allPairs(uint256) selector
+const ALL_PAIRS: &str = "1e3dd18b";
+// created by the SushiSwap factory of resources/config.toml
+const PAIR_SUSHISWAP: &str = "0x7f49febd71320326f97c5f6fd92053dbfd6aac43";
+// swap emitter that is no pair at all
+const UNKNOWN_EMITTER: &str = "0xd2801b64ad3faefb6f7a6ae518e514c9c4590615";
+
+type Indexer = BaseUniswapV2Indexer<Provider<ChainTransport>>;
+
+fn address(address: &str) -> Address {
+    address.parse().unwrap()
+}
+
+// Indexer over a fresh storage, polling the mock over HTTP
+async fn connect_indexer(mock: &MockRpcServer) -> Indexer {
+    let mut config = load_config().unwrap();
+    config.chains.get_mut(BASE_CHAIN).unwrap().rpc_url = mock.url();
+    config.sync.logs_range_initial = 100;
+    config.sync.logs_max_attempts = 1;
+    config.sync.data_refresh_interval = 1;
+    let transport = ChainTransport::connect(&mock.url()).await.unwrap();
+    BaseUniswapV2Indexer::new(ConfigHandle::new(config), DataStorageService::new(), Arc::new(Provider::new(transport)))
+}
+
+#[tokio::test]
+async fn update_sync_log_follows_chain_head() {
+    let mock = MockRpcServer::from_fixture("base_uniswap_v2").await;
+    let indexer = connect_indexer(&mock).await;
+
+    let created = indexer.client_service.update_sync_log().await.unwrap();
+    assert_eq!(created.start_block, 0);
+    assert_eq!(created.end_block, HEAD_BLOCK);
+
+    mock.set_block_number(HEAD_BLOCK + 20);
+    let updated = indexer.client_service.update_sync_log().await.unwrap();
+    assert_eq!(updated.start_block, 0);
+    assert_eq!(updated.end_block, HEAD_BLOCK + 20);
+    assert_eq!(updated.created_at, created.created_at);
+    assert_eq!(indexer.data_service.get_update_logs_size(), 1);
+}
+
+#[tokio::test]
+async fn fetch_token_details_reads_erc20_metadata() {
+    let mock = MockRpcServer::from_fixture("base_uniswap_v2").await;
+    let indexer = connect_indexer(&mock).await;
+
+    let weth = indexer.client_service.fetch_token_details(address(WETH), 0, HEAD_BLOCK).await.unwrap();
+    assert_eq!(weth.name, "Wrapped Ether");
+    assert_eq!(weth.symbol, "WETH");
+    assert_eq!(weth.decimals, "18");
+    assert_eq!(weth.address, address(WETH));
+    //no pairs stored yet
+    assert!(weth.pairs.is_empty());
+    assert!(weth.high_risk);
+
+    let anon = indexer.client_service.fetch_token_details(address(ANON), 0, HEAD_BLOCK).await.unwrap();
+    assert_eq!(anon.name, "No data");
+    assert_eq!(anon.symbol, "No data");
+    assert_eq!(anon.decimals, "0");
+}
+
+#[tokio::test]
+async fn extract_swap_events_splits_ranges_rejected_by_provider() {
+    let mock = MockRpcServer::from_fixture("base_uniswap_v2").await;
+    let indexer = connect_indexer(&mock).await;
+    indexer.client_service.refresh_token_pair(address(PAIR_WETH_USDC)).await.unwrap();
+
+    let to_block = indexer.swap_sync.extract_swap_events(22800000, HEAD_BLOCK).await.unwrap();
+    assert_eq!(to_block, HEAD_BLOCK);
+    assert_eq!(indexer.swap_sync.get_last_processed_block().await, HEAD_BLOCK);
+
+    //the stored pair's swaps are indexed, the other emitters wait in quarantine
+    assert_eq!(indexer.data_service.get_swap_count(address(PAIR_WETH_USDC)), 3);
+    assert_eq!(indexer.data_service.get_total_swap_logs_size(), 3);
+    assert_eq!(indexer.data_service.get_quarantined_swap_logs_size(), 3);
+
+    //the fixture allows 50 blocks per request, the initial 100 block range was halved
+    let requests = mock.requests("eth_getLogs");
+    let accepted: Vec<(u64, u64)> = requests.iter()
+        .map(|params| (block(&params[0]["fromBlock"]), block(&params[0]["toBlock"])))
+        .filter(|(from, to)| to - from < 50)
+        .collect();
+    assert_eq!(requests.len(), accepted.len() + 1);
+    assert_eq!(accepted.first().map(|range| range.0), Some(22800000));
+    assert_eq!(accepted.last().map(|range| range.1), Some(HEAD_BLOCK));
+    assert!(accepted.windows(2).all(|ranges| ranges[1].0 == ranges[0].1 + 1));
+}
+
+#[tokio::test]
+async fn init_token_list_discovers_pairs_tokens_and_releases_quarantine() {
+    let mock = MockRpcServer::from_fixture("base_uniswap_v2").await;
+    let indexer = connect_indexer(&mock).await;
+    let data_service = &indexer.data_service;
+
+    //swaps are synced before pair discovery, all of them are quarantined
+    indexer.swap_sync.extract_swap_events(22800000, HEAD_BLOCK).await.unwrap();
+    assert_eq!(data_service.get_quarantined_swap_logs_size(), 6);
+
+    indexer.client_service.init_token_list(0, HEAD_BLOCK).await.unwrap();
+
+    assert_eq!(data_service.get_token_pairs_size(), 3);
+    let weth_usdc = data_service.get_token_pair(address(PAIR_WETH_USDC)).unwrap();
+    assert_eq!(weth_usdc.base_address, address(WETH));
+    assert_eq!(weth_usdc.quote_address, address(USDC));
+    assert_eq!(weth_usdc.base_reserve, 1_520_000_000_000_000_000_000);
+    assert_eq!(weth_usdc.quote_reserve, 3_800_000_000_000);
+    assert_eq!(weth_usdc.swaps, 3);
+    assert_eq!(data_service.get_token_pair(address(PAIR_WETH_RUG)).unwrap().swaps, 1);
+
+    //pair of another registered factory keeps its protocol, the unknown emitter stays quarantined
+    assert_eq!(data_service.get_pair_protocol(address(PAIR_SUSHISWAP)).as_deref(), Some("BASE_SUSHISWAP_V2"));
+    assert_eq!(data_service.get_swap_count(address(PAIR_SUSHISWAP)), 1);
+    assert_eq!(data_service.get_quarantined_swap_log_addresses(), vec![address(UNKNOWN_EMITTER)]);
+
+    assert_eq!(data_service.get_tokens_size(), 4);
+    let usdc = data_service.get_token(address(USDC)).unwrap();
+    assert_eq!(usdc.symbol, "USDC");
+    assert_eq!(usdc.decimals, "6");
+    assert_eq!(usdc.pairs.len(), 2);
+    assert!(!usdc.high_risk);
+    //its only pair has no reserves
+    let rug = data_service.get_token(address(RUG)).unwrap();
+    assert_eq!(rug.active_pairs.len(), 0);
+    assert!(rug.high_risk);
+    assert_eq!(data_service.get_token(address(ANON)).unwrap().symbol, "No data");
+}
+
+#[tokio::test]
+async fn pair_activity_recounts_backfilled_swaps() {
+    let mock = MockRpcServer::from_fixture("base_uniswap_v2").await;
+    let indexer = connect_indexer(&mock).await;
+    let data_service = &indexer.data_service;
+    indexer.swap_sync.extract_swap_events(22800000, HEAD_BLOCK).await.unwrap();
+    indexer.client_service.init_token_list(0, HEAD_BLOCK).await.unwrap();
+    let counted_at = data_service.get_token_pair(address(PAIR_WETH_USDC)).unwrap().updated_at;
+
+    let aggregator = indexer.pair_activity_aggregator();
+    let running = tokio::spawn(async move { aggregator.run().await });
+    tokio::time::sleep(Duration::from_millis(50)).await;
+
+    //the backfill indexes the same swaps again, the count already set by init_token_list stays
+    indexer.swap_sync.backfill_swaps(22800000, HEAD_BLOCK).await.unwrap();
+    for _ in 0..50 {
+        if data_service.get_token_pair(address(PAIR_WETH_USDC)).unwrap().updated_at != counted_at {
+            break;
+        }
+        tokio::time::sleep(Duration::from_millis(20)).await;
+    }
+    let weth_usdc = data_service.get_token_pair(address(PAIR_WETH_USDC)).unwrap();
+    assert_ne!(weth_usdc.updated_at, counted_at);
+    assert_eq!(weth_usdc.swaps, 3);
+    assert_eq!(data_service.get_token_pair(address(PAIR_WETH_RUG)).unwrap().swaps, 1);
+    running.abort();
+}
+
+#[tokio::test]
+async fn swap_checkpoint_is_restored_and_logs_stored_once() {
+    let mock = MockRpcServer::from_fixture("base_uniswap_v2").await;
+    let indexer = connect_indexer(&mock).await;
+    let data_service = &indexer.data_service;
+    indexer.client_service.init_token_list(0, HEAD_BLOCK).await.unwrap();
+    indexer.swap_sync.extract_swap_events(22800000, HEAD_BLOCK).await.unwrap();
+    let (stored, quarantined) = (data_service.get_total_swap_logs_size(), data_service.get_quarantined_swap_logs_size());
+    assert_eq!(data_service.get_swap_checkpoint(PROTOCOL_ID.to_string()), HEAD_BLOCK);
+
+    //a restart over the saved storage resumes after the checkpoint
+    let restarted = Indexer::new(indexer.config.clone(), data_service.clone(), indexer.provider.clone());
+    assert_eq!(restarted.swap_sync.get_last_processed_block().await, HEAD_BLOCK);
+
+    //the same logs delivered again are not stored twice
+    restarted.swap_sync.extract_swap_events(22800000, HEAD_BLOCK).await.unwrap();
+    assert_eq!(data_service.get_total_swap_logs_size(), stored);
+    assert_eq!(data_service.get_swap_count(address(PAIR_WETH_USDC)), 3);
+    assert_eq!(data_service.get_quarantined_swap_logs_size(), quarantined);
+}
+
+#[tokio::test]
+async fn refresh_chain_data_adds_new_pairs_and_updates_only_changed_entities() {
+    let mock = MockRpcServer::from_fixture("base_uniswap_v2").await;
+    //the USDC/ANON pair is created after the initial load
+    mock.update_chain(|chain| chain.factories[0].pairs.truncate(2));
+    let indexer = connect_indexer(&mock).await;
+    let data_service = &indexer.data_service;
+    indexer.client_service.update_sync_log().await.unwrap();
+    indexer.swap_sync.extract_swap_events(22800000, HEAD_BLOCK).await.unwrap();
+    indexer.client_service.init_token_list(0, HEAD_BLOCK).await.unwrap();
+    assert_eq!(data_service.get_update_log(PROTOCOL_ID.to_string()).unwrap().pair_count, 2);
+    assert_eq!(data_service.get_tokens_size(), 3);
+    let rug = data_service.get_token(address(RUG)).unwrap();
+    let weth_rug = data_service.get_token_pair(address(PAIR_WETH_RUG)).unwrap();
+
+    //a new pair, one more WETH/USDC swap and the reserves it moved
+    mock.update_chain(|chain| {
+        chain.factories[0].pairs.push(address(PAIR_USDC_ANON).as_h160());
+        let pair = chain.pairs.iter_mut().find(|pair| pair.address == address(PAIR_WETH_USDC).as_h160()).unwrap();
+        pair.reserve0 = "1521000000000000000000".to_string();
+        let mut swap = chain.logs[0].clone();
+        swap.block_number = Some((HEAD_BLOCK + 10).into());
+        swap.transaction_hash = Some(H256::repeat_byte(0x11));
+        chain.logs.push(swap);
+        chain.block_number = HEAD_BLOCK + 20;
+    });
+    indexer.swap_sync.extract_swap_events(HEAD_BLOCK + 1, HEAD_BLOCK + 20).await.unwrap();
+    let all_pairs_calls = mock.calls(ALL_PAIRS);
+    indexer.client_service.refresh_chain_data().await.unwrap();
+
+    //only the new pair is read from the factory
+    assert_eq!(mock.calls(ALL_PAIRS) - all_pairs_calls, 1);
+    let update_log = data_service.get_update_log(PROTOCOL_ID.to_string()).unwrap();
+    assert_eq!(update_log.end_block, HEAD_BLOCK + 20);
+    assert_eq!(update_log.pair_count, 3);
+
+    assert_eq!(data_service.get_token_pairs_size(), 3);
+    let weth_usdc = data_service.get_token_pair(address(PAIR_WETH_USDC)).unwrap();
+    assert_eq!(weth_usdc.swaps, 4);
+    assert_eq!(weth_usdc.base_reserve, 1_521_000_000_000_000_000_000);
+    assert_eq!(data_service.get_tokens_size(), 4);
+    assert_eq!(data_service.get_token(address(ANON)).unwrap().pairs.len(), 1);
+    let usdc = data_service.get_token(address(USDC)).unwrap();
+    assert_eq!(usdc.pairs.len(), 2);
+    assert_eq!(usdc.active_pairs.len(), 2);
+    assert_eq!(usdc.swaps, 4);
+    assert_eq!(data_service.get_token(address(WETH)).unwrap().swaps, 5);
+
+    //untouched by the new pair and the swap
+    assert_eq!(data_service.get_token(address(RUG)).unwrap(), rug);
+    assert_eq!(data_service.get_token_pair(address(PAIR_WETH_RUG)).unwrap(), weth_rug);
+
+    //nothing changed since
+    let weth = data_service.get_token(address(WETH)).unwrap();
+    indexer.client_service.refresh_chain_data().await.unwrap();
+    assert_eq!(data_service.get_token(address(WETH)).unwrap(), weth);
+    assert_eq!(mock.calls(ALL_PAIRS) - all_pairs_calls, 1);
+}
+
+#[tokio::test]
+async fn refresh_chain_data_reads_reserves_of_pairs_swapped_since_last_refresh() {
+    let mock = MockRpcServer::from_fixture("base_uniswap_v2").await;
+    let indexer = connect_indexer(&mock).await;
+    let data_service = &indexer.data_service;
+    indexer.client_service.update_sync_log().await.unwrap();
+    indexer.swap_sync.extract_swap_events(22800000, HEAD_BLOCK).await.unwrap();
+    indexer.client_service.init_token_list(0, HEAD_BLOCK).await.unwrap();
+    indexer.client_service.refresh_chain_data().await.unwrap();
+
+    //the aggregator counts new swaps before the next refresh runs
+    let aggregator = indexer.pair_activity_aggregator();
+    let running = tokio::spawn(async move { aggregator.run().await });
+    tokio::time::sleep(Duration::from_millis(50)).await;
+
+    for (cycle, reserve0) in [(1, 1_521_000_000_000_000_000_000u128), (2, 1_522_000_000_000_000_000_000)] {
+        let swap_block = HEAD_BLOCK + cycle * 20;
+        mock.update_chain(|chain| {
+            let pair = chain.pairs.iter_mut().find(|pair| pair.address == address(PAIR_WETH_USDC).as_h160()).unwrap();
+            pair.reserve0 = reserve0.to_string();
+            let mut swap = chain.logs[0].clone();
+            swap.block_number = Some(swap_block.into());
+            swap.transaction_hash = Some(H256::from_low_u64_be(swap_block));
+            chain.logs.push(swap);
+            chain.block_number = swap_block;
+        });
+        indexer.swap_sync.extract_swap_events(swap_block - 19, swap_block).await.unwrap();
+        let swaps = 3 + cycle as i64;
+        for _ in 0..50 {
+            if data_service.get_token_pair(address(PAIR_WETH_USDC)).unwrap().swaps == swaps {
+                break;
+            }
+            tokio::time::sleep(Duration::from_millis(20)).await;
+        }
+        assert_eq!(data_service.get_token_pair(address(PAIR_WETH_USDC)).unwrap().swaps, swaps);
+
+        indexer.client_service.refresh_chain_data().await.unwrap();
+        let weth_usdc = data_service.get_token_pair(address(PAIR_WETH_USDC)).unwrap();
+        assert_eq!(weth_usdc.base_reserve, reserve0);
+        assert_eq!(weth_usdc.reserves_read_block, swap_block);
+    }
+    running.abort();
+}
+
+#[tokio::test]
+async fn synchronize_swaps_reports_phases_until_shutdown() {
+    let mock = MockRpcServer::from_fixture("base_uniswap_v2").await;
+    let indexer = connect_indexer(&mock).await;
+    let sync_state = indexer.swap_sync.get_sync_state();
+    let mut changes = sync_state.subscribe();
+    assert_eq!(sync_state.phase(), SyncPhase::Idle);
+
+    let swap_sync = indexer.swap_sync.clone();
+    let sync = tokio::spawn(async move { swap_sync.synchronize_swaps().await.map_err(|e| e.to_string()) });
+    let following = tokio::time::timeout(Duration::from_secs(10), changes.wait_for(|state| state.phase == SyncPhase::Following))
+        .await.unwrap().unwrap().clone();
+    assert_eq!(following.block, HEAD_BLOCK);
+    assert_eq!(following.target_block, HEAD_BLOCK);
+    assert!(following.caught_up_at.is_some());
+    assert!(following.last_error.is_none());
+
+    //the next poll continues after the extracted head
+    mock.set_block_number(HEAD_BLOCK + 10);
+    tokio::time::timeout(Duration::from_secs(10), changes.wait_for(|state| state.block == HEAD_BLOCK + 10))
+        .await.unwrap().unwrap();
+    let ranges: Vec<(u64, u64)> = mock.requests("eth_getLogs").iter()
+        .map(|params| (block(&params[0]["fromBlock"]), block(&params[0]["toBlock"])))
+        .filter(|(from, to)| to - from < 50)
+        .collect();
+    assert_eq!(ranges.last().map(|range| range.1), Some(HEAD_BLOCK + 10));
+    assert!(ranges.windows(2).all(|ranges| ranges[1].0 == ranges[0].1 + 1));
+
+    indexer.shutdown.trigger();
+    sync.await.unwrap().unwrap();
+    assert_eq!(sync_state.phase(), SyncPhase::Idle);
+}
+
+#[tokio::test]
+async fn init_token_list_continues_after_swap_sync_failed() {
+    let mut cassette = Cassette::load(&Cassette::fixture_path("base_uniswap_v2"));
+    cassette.interactions.push(Interaction {
+        method: "eth_getLogs".to_string(),
+        params: json!([]),
+        result: None,
+        error: Some(json!({ "code": -32000, "message": "header not found" })),
+    });
+    let mock = MockRpcServer::start(cassette, None, None).await;
+    let indexer = connect_indexer(&mock).await;
+
+    let error = indexer.swap_sync.synchronize_swaps().await.unwrap_err();
+    let failed = indexer.swap_sync.get_sync_state().get_state();
+    assert_eq!(failed.phase, SyncPhase::Failed);
+    assert_eq!(failed.last_error, Some(error.to_string()));
+    assert!(failed.last_error_at.is_some());
+
+    //the token load no longer waits for a synchronizer that stopped
+    tokio::time::timeout(Duration::from_secs(10), indexer.client_service.init_token_list(0, HEAD_BLOCK))
+        .await.unwrap().unwrap();
+    assert_eq!(indexer.data_service.get_token_pairs_size(), 3);
+    assert_eq!(indexer.data_service.get_token_pair(address(PAIR_WETH_USDC)).unwrap().swaps, 0);
+}
+
+#[tokio::test]
+async fn synchronize_swaps_follows_head_after_restart_from_failed() {
+    let mut cassette = Cassette::load(&Cassette::fixture_path("base_uniswap_v2"));
+    cassette.interactions.push(Interaction {
+        method: "eth_getLogs".to_string(),
+        params: json!([]),
+        result: None,
+        error: Some(json!({ "code": -32000, "message": "header not found" })),
+    });
+    let mock = MockRpcServer::start(cassette, None, None).await;
+    let indexer = connect_indexer(&mock).await;
+    let sync_state = indexer.swap_sync.get_sync_state();
+    let mut changes = sync_state.subscribe();
+
+    indexer.swap_sync.synchronize_swaps().await.unwrap_err();
+    assert_eq!(sync_state.phase(), SyncPhase::Failed);
+    assert!(sync_state.get_state().is_settled());
+
+    //the provider recovered, the restarted synchronizer leaves Failed
+    mock.remove_interactions("eth_getLogs");
+    let swap_sync = indexer.swap_sync.clone();
+    let sync = tokio::spawn(async move { swap_sync.synchronize_swaps().await.map_err(|e| e.to_string()) });
+    let following = tokio::time::timeout(Duration::from_secs(10), changes.wait_for(|state| state.phase == SyncPhase::Following))
+        .await.unwrap().unwrap().clone();
+    assert_eq!(following.block, HEAD_BLOCK);
+
+    indexer.shutdown.trigger();
+    sync.await.unwrap().unwrap();
+}
+
+#[tokio::test]
+async fn synchronize_swaps_keeps_following_within_poll_window() {
+    let mock = MockRpcServer::from_fixture("base_uniswap_v2").await;
+    let indexer = connect_indexer(&mock).await;
+    let sync_state = indexer.swap_sync.get_sync_state();
+    let mut changes = sync_state.subscribe();
+
+    let swap_sync = indexer.swap_sync.clone();
+    let sync = tokio::spawn(async move { swap_sync.synchronize_swaps().await.map_err(|e| e.to_string()) });
+    let following = tokio::time::timeout(Duration::from_secs(10), changes.wait_for(|state| state.phase == SyncPhase::Following))
+        .await.unwrap().unwrap().clone();
+
+    //one new block is a regular poll, not a backfill
+    mock.set_block_number(HEAD_BLOCK + 1);
+    let polled = tokio::time::timeout(Duration::from_secs(10), changes.wait_for(|state| state.block == HEAD_BLOCK + 1))
+        .await.unwrap().unwrap().clone();
+    assert_eq!(polled.phase, SyncPhase::Following);
+    assert_eq!(polled.since, following.since);
+
+    indexer.shutdown.trigger();
+    sync.await.unwrap().unwrap();
+}
+
+#[tokio::test]
+async fn recorded_cassette_replays_offline() {
+    let upstream = MockRpcServer::from_fixture("base_uniswap_v2").await;
+    let cassette = std::env::temp_dir().join(format!("token_data_provider_cassette_{}.json", std::process::id()));
+
+    let recorder = MockRpcServer::record(&upstream.url(), &cassette).await;
+    let recorded = connect_indexer(&recorder).await.client_service.fetch_token_pair(address(PAIR_WETH_USDC)).await.unwrap();
+    drop(recorder);
+    drop(upstream);
+
+    let replay = MockRpcServer::replay(&cassette).await;
+    let replayed = connect_indexer(&replay).await.client_service.fetch_token_pair(address(PAIR_WETH_USDC)).await.unwrap();
+    std::fs::remove_file(&cassette).unwrap();
+
+    assert_eq!(replay.requests("eth_call").len(), 3);
+    assert_eq!(replayed.base_address, recorded.base_address);
+    assert_eq!(replayed.quote_address, recorded.quote_address);
+    assert_eq!(replayed.base_reserve, recorded.base_reserve);
+    assert_eq!(replayed.reserve_block, recorded.reserve_block);
+}
+
+fn block(value: &serde_json::Value) -> u64 {
+    u64::from_str_radix(value.as_str().unwrap().trim_start_matches("0x"), 16).unwrap()
+}
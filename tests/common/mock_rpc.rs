@@ -0,0 +1,420 @@
+use std::env;
+use std::net::SocketAddr;
+use std::path::{Path, PathBuf};
+use std::str::FromStr;
+use std::sync::{Arc, Mutex};
+use ethers::abi::{encode, Token};
+use ethers::providers::{Http, HttpClientError, JsonRpcClient};
+use ethers::types::{Address, Log, H256, U256};
+use ethers::utils::hex;
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+use warp::Filter;
+
+// Upstream node to record unanswered requests from, e.g. MOCK_RPC_RECORD_URL=https://mainnet.base.org
+const RECORD_URL: &str = "MOCK_RPC_RECORD_URL";
+
+// Selectors of the factory, pair and ERC-20 calls the chain fixture answers
+const ALL_PAIRS_LENGTH: &str = "574f2ba3";
+const ALL_PAIRS: &str = "1e3dd18b";
+const FACTORY: &str = "c45a0155";
+const TOKEN0: &str = "0dfe1681";
+const TOKEN1: &str = "d21220a7";
+const GET_RESERVES: &str = "0902f1ac";
+const NAME: &str = "06fdde03";
+const SYMBOL: &str = "95d89b41";
+const DECIMALS: &str = "313ce567";
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct FactoryFixture {
+    pub address: Address,
+    pub pairs: Vec<Address>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PairFixture {
+    pub address: Address,
+    pub factory: Address,
+    pub token0: Address,
+    pub token1: Address,
+    // decimal strings, uint112
+    pub reserve0: String,
+    pub reserve1: String,
+    pub block_timestamp_last: u32,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct TokenFixture {
+    pub address: Address,
+    pub name: String,
+    pub symbol: String,
+    pub decimals: u8,
+}
+
+// Sender of a transaction, answered by eth_getTransactionByHash
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct TransactionFixture {
+    pub hash: H256,
+    pub from: Address,
+}
+
+// Chain state the mock derives eth_blockNumber, eth_call and eth_getLogs answers from
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct ChainFixture {
+    pub chain_id: u64,
+    pub block_number: u64,
+    // getLogs block spans above this are rejected like a public provider does
+    #[serde(default)]
+    pub max_logs_range: Option<u64>,
+    #[serde(default)]
+    pub factories: Vec<FactoryFixture>,
+    #[serde(default)]
+    pub pairs: Vec<PairFixture>,
+    #[serde(default)]
+    pub tokens: Vec<TokenFixture>,
+    #[serde(default)]
+    pub logs: Vec<Log>,
+    #[serde(default)]
+    pub transactions: Vec<TransactionFixture>,
+}
+
+// One recorded request and its answer, replayed before the chain fixture is asked
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Interaction {
+    pub method: String,
+    pub params: Value,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub result: Option<Value>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub error: Option<Value>,
+}
+
+// Cassette file: recorded interactions and an optional chain fixture
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct Cassette {
+    #[serde(default)]
+    pub chain: Option<ChainFixture>,
+    #[serde(default)]
+    pub interactions: Vec<Interaction>,
+}
+
+impl Cassette {
+    pub fn load(path: &Path) -> Cassette {
+        let json = std::fs::read_to_string(path).unwrap_or_else(|e| panic!("cassette {}: {}", path.display(), e));
+        serde_json::from_str(&json).unwrap_or_else(|e| panic!("cassette {}: {}", path.display(), e))
+    }
+
+    pub fn fixture_path(name: &str) -> PathBuf {
+        Path::new(env!("CARGO_MANIFEST_DIR")).join("tests/fixtures").join(format!("{}.json", name))
+    }
+}
+
+struct MockState {
+    cassette: Mutex<Cassette>,
+    // unanswered requests are forwarded here and recorded into the cassette
+    upstream: Option<Http>,
+    record_path: Option<PathBuf>,
+    requests: Mutex<Vec<(String, Value)>>,
+}
+
+// JSON-RPC server on a local port answering from a cassette, optionally recording from an upstream node
+pub struct MockRpcServer {
+    address: SocketAddr,
+    state: Arc<MockState>,
+}
+
+impl MockRpcServer {
+    // Replays tests/fixtures/<name>.json, re-records into it when MOCK_RPC_RECORD_URL is set
+    pub async fn from_fixture(name: &str) -> MockRpcServer {
+        let path = Cassette::fixture_path(name);
+        let upstream = env::var(RECORD_URL).ok().map(|url| Http::from_str(&url).unwrap());
+        let record_path = upstream.as_ref().map(|_| path.clone());
+        Self::start(Cassette::load(&path), upstream, record_path).await
+    }
+
+    // Replays a cassette file, nothing is forwarded
+    pub async fn replay(path: &Path) -> MockRpcServer {
+        Self::start(Cassette::load(path), None, None).await
+    }
+
+    // Records every request sent to the upstream node into a new cassette at path
+    pub async fn record(upstream_url: &str, path: &Path) -> MockRpcServer {
+        let upstream = Http::from_str(upstream_url).unwrap();
+        Self::start(Cassette::default(), Some(upstream), Some(path.to_path_buf())).await
+    }
+
+    pub async fn start(cassette: Cassette, upstream: Option<Http>, record_path: Option<PathBuf>) -> MockRpcServer {
+        let state = Arc::new(MockState {
+            cassette: Mutex::new(cassette),
+            upstream,
+            record_path,
+            requests: Mutex::new(vec![]),
+        });
+        let server_state = state.clone();
+        let route = warp::post()
+            .and(warp::body::json())
+            .and_then(move |body: Value| {
+                let state = server_state.clone();
+                async move {
+                    let reply = match body {
+                        Value::Array(batch) => {
+                            let mut replies = vec![];
+                            for request in &batch {
+                                replies.push(state.answer(request).await);
+                            }
+                            Value::Array(replies)
+                        }
+                        request => state.answer(&request).await,
+                    };
+                    Ok::<_, warp::Rejection>(warp::reply::json(&reply))
+                }
+            });
+        let (address, server) = warp::serve(route).bind_ephemeral(([127, 0, 0, 1], 0));
+        tokio::spawn(server);
+        MockRpcServer { address, state }
+    }
+
+    pub fn url(&self) -> String {
+        format!("http://{}", self.address)
+    }
+
+    // Params of every request received for method
+    pub fn requests(&self, method: &str) -> Vec<Value> {
+        self.state.requests.lock().unwrap().iter()
+            .filter(|(requested, _)| requested == method)
+            .map(|(_, params)| params.clone())
+            .collect()
+    }
+
+    pub fn set_block_number(&self, block_number: u64) {
+        if let Some(chain) = self.state.cassette.lock().unwrap().chain.as_mut() {
+            chain.block_number = block_number;
+        }
+    }
+
+    // Changes the chain fixture, e.g. new pairs, reserves or logs between two refreshes
+    pub fn update_chain(&self, update: impl FnOnce(&mut ChainFixture)) {
+        if let Some(chain) = self.state.cassette.lock().unwrap().chain.as_mut() {
+            update(chain);
+        }
+    }
+
+    // Drops the recorded interactions for method, e.g. an error that went away
+    pub fn remove_interactions(&self, method: &str) {
+        self.state.cassette.lock().unwrap().interactions.retain(|interaction| interaction.method != method);
+    }
+
+    // eth_call requests to a contract function, selector without 0x
+    pub fn calls(&self, selector: &str) -> usize {
+        self.requests("eth_call").iter()
+            .filter_map(|params| params[0].get("input").or_else(|| params[0].get("data")).and_then(|data| data.as_str()))
+            .filter(|data| data.trim_start_matches("0x").starts_with(selector))
+            .count()
+    }
+
+    // Writes the recorded interactions, a no-op unless recording
+    pub fn save(&self) {
+        if let Some(path) = &self.state.record_path {
+            let json = serde_json::to_string_pretty(&*self.state.cassette.lock().unwrap()).unwrap();
+            std::fs::write(path, json + "\n").unwrap_or_else(|e| panic!("cassette {}: {}", path.display(), e));
+        }
+    }
+}
+
+impl Drop for MockRpcServer {
+    fn drop(&mut self) {
+        self.save();
+    }
+}
+
+impl MockState {
+    async fn answer(&self, request: &Value) -> Value {
+        let method = request["method"].as_str().unwrap_or_default().to_string();
+        let params = request.get("params").cloned().unwrap_or(Value::Null);
+        self.requests.lock().unwrap().push((method.clone(), params.clone()));
+
+        let answer = self.replay(&method, &params)
+            .or_else(|| self.cassette.lock().unwrap().chain.as_ref().and_then(|chain| chain.answer(&method, &params)));
+        let answer = match answer {
+            Some(answer) => answer,
+            None => self.forward(&method, &params).await,
+        };
+
+        match answer {
+            Ok(result) => json!({ "jsonrpc": "2.0", "id": request["id"], "result": result }),
+            Err(error) => json!({ "jsonrpc": "2.0", "id": request["id"], "error": error }),
+        }
+    }
+
+    fn replay(&self, method: &str, params: &Value) -> Option<Result<Value, Value>> {
+        let cassette = self.cassette.lock().unwrap();
+        let interaction = cassette.interactions.iter()
+            .find(|interaction| interaction.method == method && params_match(&interaction.params, params))?;
+        Some(match &interaction.error {
+            Some(error) => Err(error.clone()),
+            None => Ok(interaction.result.clone().unwrap_or(Value::Null)),
+        })
+    }
+
+    async fn forward(&self, method: &str, params: &Value) -> Result<Value, Value> {
+        let Some(upstream) = &self.upstream else {
+            return Err(json!({ "code": -32601, "message": format!("mock: no fixture for {} {}", method, params) }));
+        };
+        let answer = match upstream.request::<_, Value>(method, params.clone()).await {
+            Ok(result) => Ok(result),
+            Err(HttpClientError::JsonRpcError(e)) => Err(json!({ "code": e.code, "message": e.message, "data": e.data })),
+            //transport errors are not recorded
+            Err(e) => return Err(json!({ "code": -32603, "message": format!("mock: upstream {}", e) })),
+        };
+        self.cassette.lock().unwrap().interactions.push(Interaction {
+            method: method.to_string(),
+            params: params.clone(),
+            result: answer.as_ref().ok().cloned(),
+            error: answer.as_ref().err().cloned(),
+        });
+        answer
+    }
+}
+
+// Recorded params match when every recorded field equals the request's, hex strings compared case-insensitively
+fn params_match(recorded: &Value, actual: &Value) -> bool {
+    match (recorded, actual) {
+        (Value::Object(recorded), Value::Object(actual)) => recorded.iter()
+            .all(|(key, value)| actual.get(key).is_some_and(|actual| params_match(value, actual))),
+        (Value::Array(recorded), Value::Array(actual)) => recorded.len() <= actual.len()
+            && recorded.iter().zip(actual).all(|(recorded, actual)| params_match(recorded, actual)),
+        (Value::String(recorded), Value::String(actual)) => recorded.eq_ignore_ascii_case(actual),
+        (recorded, actual) => recorded == actual,
+    }
+}
+
+fn revert() -> Value {
+    json!({ "code": 3, "message": "execution reverted" })
+}
+
+fn parse_block(value: &Value, head: u64) -> u64 {
+    match value.as_str() {
+        Some("earliest") => 0,
+        Some(hex) if hex.starts_with("0x") => u64::from_str_radix(&hex[2..], 16).unwrap_or(head),
+        _ => head,
+    }
+}
+
+// null matches everything, a list any of its entries
+fn value_matches(filter: &Value, value: &str) -> bool {
+    match filter {
+        Value::Null => true,
+        Value::String(expected) => expected.eq_ignore_ascii_case(value),
+        Value::Array(any) => any.iter().any(|expected| value_matches(expected, value)),
+        _ => false,
+    }
+}
+
+impl ChainFixture {
+    fn answer(&self, method: &str, params: &Value) -> Option<Result<Value, Value>> {
+        match method {
+            "eth_blockNumber" => Some(Ok(json!(format!("{:#x}", self.block_number)))),
+            "eth_chainId" => Some(Ok(json!(format!("{:#x}", self.chain_id)))),
+            "eth_call" => Some(self.call(&params[0])),
+            "eth_getLogs" => Some(self.get_logs(&params[0])),
+            "eth_getTransactionByHash" => Some(Ok(self.get_transaction(&params[0]))),
+            _ => None,
+        }
+    }
+
+    fn call(&self, transaction: &Value) -> Result<Value, Value> {
+        let to: Address = transaction["to"].as_str().and_then(|to| to.parse().ok()).ok_or_else(revert)?;
+        let data = transaction.get("input").or_else(|| transaction.get("data"))
+            .and_then(|data| data.as_str())
+            .map(|data| data.trim_start_matches("0x").to_lowercase())
+            .ok_or_else(revert)?;
+        if data.len() < 8 {
+            return Err(revert());
+        }
+        let (selector, arguments) = data.split_at(8);
+
+        let tokens = if let Some(factory) = self.factories.iter().find(|factory| factory.address == to) {
+            match selector {
+                ALL_PAIRS_LENGTH => vec![Token::Uint(factory.pairs.len().into())],
+                ALL_PAIRS => {
+                    let index = U256::from_str_radix(arguments, 16).map_err(|_| revert())?;
+                    let pair = factory.pairs.get(index.as_usize()).ok_or_else(revert)?;
+                    vec![Token::Address(*pair)]
+                }
+                _ => return Err(revert()),
+            }
+        } else if let Some(pair) = self.pairs.iter().find(|pair| pair.address == to) {
+            match selector {
+                FACTORY => vec![Token::Address(pair.factory)],
+                TOKEN0 => vec![Token::Address(pair.token0)],
+                TOKEN1 => vec![Token::Address(pair.token1)],
+                GET_RESERVES => vec![
+                    Token::Uint(U256::from_dec_str(&pair.reserve0).unwrap()),
+                    Token::Uint(U256::from_dec_str(&pair.reserve1).unwrap()),
+                    Token::Uint(pair.block_timestamp_last.into()),
+                ],
+                _ => return Err(revert()),
+            }
+        } else if let Some(token) = self.tokens.iter().find(|token| token.address == to) {
+            match selector {
+                NAME => vec![Token::String(token.name.clone())],
+                SYMBOL => vec![Token::String(token.symbol.clone())],
+                DECIMALS => vec![Token::Uint(token.decimals.into())],
+                _ => return Err(revert()),
+            }
+        } else {
+            //no code at the address
+            return Ok(json!("0x"));
+        };
+
+        Ok(json!(format!("0x{}", hex::encode(encode(&tokens)))))
+    }
+
+    // Pending-like transaction with the fixture's sender, null for unknown hashes
+    fn get_transaction(&self, hash: &Value) -> Value {
+        let Some(transaction) = self.transactions.iter()
+            .find(|transaction| hash.as_str().is_some_and(|hash| hash.eq_ignore_ascii_case(&format!("{:?}", transaction.hash)))) else {
+            return Value::Null;
+        };
+        json!({
+            "hash": transaction.hash,
+            "nonce": "0x0",
+            "blockHash": null,
+            "blockNumber": null,
+            "transactionIndex": null,
+            "from": transaction.from,
+            "to": null,
+            "value": "0x0",
+            "gasPrice": "0x0",
+            "gas": "0x0",
+            "input": "0x",
+            "v": "0x0",
+            "r": "0x0",
+            "s": "0x0",
+        })
+    }
+
+    fn get_logs(&self, filter: &Value) -> Result<Value, Value> {
+        let from_block = parse_block(&filter["fromBlock"], self.block_number);
+        let to_block = parse_block(&filter["toBlock"], self.block_number);
+        if let Some(max) = self.max_logs_range {
+            if to_block.saturating_sub(from_block) + 1 > max {
+                return Err(json!({ "code": -32005, "message": format!("eth_getLogs exceeds max block range {}", max) }));
+            }
+        }
+        let topics = filter["topics"].as_array().cloned().unwrap_or_default();
+
+        let logs: Vec<&Log> = self.logs.iter()
+            //nothing above the node's own head
+            .filter(|log| log.block_number.is_some_and(|block| (from_block..=to_block.min(self.block_number)).contains(&block.as_u64())))
+            .filter(|log| value_matches(&filter["address"], &format!("{:?}", log.address)))
+            .filter(|log| topics.iter().enumerate().all(|(index, topic)| {
+                match log.topics.get(index) {
+                    Some(log_topic) => value_matches(topic, &format!("{:?}", log_topic)),
+                    None => topic.is_null(),
+                }
+            }))
+            .collect();
+        Ok(json!(logs))
+    }
+}
@@ -0,0 +1,4 @@
+// shared by several test crates, each using part of it
+#![allow(dead_code)]
+
+pub mod mock_rpc;
@@ -0,0 +1,207 @@
+use std::env;
+use std::sync::Mutex;
+use ethers::types::H160;
+use token_data_provider::adapters::pair_api::create_pair_rest_api;
+use token_data_provider::config::{
+    load_config, load_config_from_env_or_file, load_config_with_profile, validate_config, ConfigHandle, IngestionOverrides,
+    RpcEndpointConfig, BASE_CHAIN, DEV_PROFILE, PROFILE_ENV_VAR,
+};
+use token_data_provider::domain::entities::address::Address;
+use token_data_provider::domain::entities::token_pair::TokenPair;
+use token_data_provider::domain::services::api_key_service::{ApiKeyService, ApiKeyServiceTrait};
+use token_data_provider::domain::services::data_storage_service::{DataStorageService, DataStorageTrait};
+use token_data_provider::ports::chain::log_range::AdaptiveLogRange;
+
+// Held by the tests changing the environment, the others read config.toml alone
+static ENVIRONMENT: Mutex<()> = Mutex::new(());
+
+#[test]
+fn validate_config_reports_invalid_keys_by_path() {
+    let config = load_config().unwrap();
+    assert_eq!(validate_config(&config), Vec::<String>::new());
+
+    let mut invalid = config.clone();
+    invalid.sync.logs_range_min = 0;
+    invalid.risk.min_active_pair_ratio = 1.5;
+    invalid.chains.get_mut(BASE_CHAIN).unwrap().rpc_quorum_size = 2;
+    invalid.protocols[1].factory = "0x1234".to_string();
+    invalid.protocols.retain(|protocol| protocol.id != "BASE_UNISWAP_V2");
+
+    let errors = validate_config(&invalid);
+    assert!(errors.contains(&"sync.logs_range_min must be between 1 and logs_range_max".to_string()));
+    assert!(errors.contains(&"risk.min_active_pair_ratio must be between 0 and 1".to_string()));
+    assert!(errors.contains(&"chains.base.rpc_quorum_size 2 exceeds the 1 configured rpc_endpoints".to_string()));
+    assert!(errors.iter().any(|error| error.starts_with("protocols.BASE_UNISWAP_V3: factory is not a valid address")));
+    assert!(errors.contains(&"protocols.BASE_UNISWAP_V2 must be configured, its pairs are indexed".to_string()));
+
+    //the public sample admin key of the dev profile is refused in any other profile
+    let mut sample_keys = config.clone();
+    sample_keys.api.keys = load_config_with_profile(DEV_PROFILE).unwrap().api.keys;
+    assert_eq!(validate_config(&sample_keys), vec!["api.keys.local-admin: admin key is the public sample key of the dev profile"]);
+    sample_keys.profile = Some(DEV_PROFILE.to_string());
+    assert_eq!(validate_config(&sample_keys), Vec::<String>::new());
+}
+
+#[test]
+fn protocol_ingestion_overrides_fall_back_to_sync_settings() {
+    let mut config = load_config().unwrap();
+    config.protocols[0].ingestion = IngestionOverrides {
+        logs_range_max: Some(2000),
+        logs_max_attempts: Some(8),
+        ..IngestionOverrides::default()
+    };
+
+    let ingestion = config.ingestion("BASE_UNISWAP_V2");
+    assert_eq!(ingestion.logs_range_initial, config.sync.logs_range_initial);
+    assert_eq!(ingestion.logs_range_max, 2000);
+    assert_eq!(ingestion.logs_max_attempts, 8);
+    assert_eq!(config.ingestion("BASE_SUSHISWAP_V2").logs_range_max, config.sync.logs_range_max);
+    assert_eq!(validate_config(&config), Vec::<String>::new());
+
+    config.protocols[0].ingestion.logs_range_min = Some(5000);
+    config.protocols[0].ingestion.logs_max_attempts = Some(0);
+    let errors = validate_config(&config);
+    assert_eq!(errors, vec![
+        "protocols.BASE_UNISWAP_V2.ingestion.logs_range_min must be between 1 and logs_range_max",
+        "protocols.BASE_UNISWAP_V2.ingestion.logs_range_initial must be between logs_range_min and logs_range_max",
+        "protocols.BASE_UNISWAP_V2.ingestion.logs_max_attempts must be greater than 0",
+    ]);
+}
+
+#[test]
+fn endpoint_max_logs_range_below_logs_range_min_is_rejected() {
+    let mut config = load_config().unwrap();
+    config.sync.logs_range_min = 100;
+    config.chains.get_mut(BASE_CHAIN).unwrap().rpc_endpoints = vec![RpcEndpointConfig {
+        url: "https://mainnet.base.org".to_string(),
+        weight: 1,
+        max_logs_range: Some(50),
+    }];
+
+    let errors = validate_config(&config);
+    assert!(errors.contains(&"chains.base.rpc_endpoints.https://mainnet.base.org: max_logs_range must not be below \
+        the logs_range_min 100 of protocol BASE_UNISWAP_V2".to_string()));
+    //the range stays within the provider limit instead of panicking
+    let range = AdaptiveLogRange::from_config(&config, "BASE_UNISWAP_V2");
+    assert_eq!(range.size(), 50);
+}
+
+#[test]
+fn profile_is_layered_between_config_file_and_environment() {
+    let _environment = ENVIRONMENT.lock().unwrap_or_else(|e| e.into_inner());
+    let file = load_config().unwrap();
+
+    env::set_var(PROFILE_ENV_VAR, "dev");
+    env::set_var("TDP_SYNC__DATA_REFRESH_INTERVAL", "7");
+    let dev = load_config_from_env_or_file();
+    env::set_var(PROFILE_ENV_VAR, "missing");
+    let missing = load_config_from_env_or_file().map(|_| ()).map_err(|e| e.to_string());
+    env::remove_var(PROFILE_ENV_VAR);
+    env::remove_var("TDP_SYNC__DATA_REFRESH_INTERVAL");
+
+    let dev = dev.unwrap();
+    assert_eq!(dev.profile.as_deref(), Some("dev"));
+    assert_eq!(dev.sync.data_refresh_interval, 7);
+    assert_eq!(dev.sync.logs_range_initial, 500);
+    assert_eq!(dev.storage.snapshot_path, "data/storage.dev.json");
+    assert_eq!(dev.base_chain().rpc_url, "ws://127.0.0.1:8546");
+    //keys the profile doesn't set keep the config.toml values
+    assert_eq!(dev.sync.logs_range_max, file.sync.logs_range_max);
+    assert_eq!(dev.base_chain().chain_id, file.base_chain().chain_id);
+    assert_eq!(dev.protocols, file.protocols);
+    assert_eq!(validate_config(&dev), Vec::<String>::new());
+
+    assert_eq!(missing.unwrap_err(), "profile missing: resources/config.missing.toml not found");
+}
+
+#[test]
+fn environment_overrides_any_key_and_reload_keeps_structural_settings() {
+    let _environment = ENVIRONMENT.lock().unwrap_or_else(|e| e.into_inner());
+    let handle = ConfigHandle::new(load_config().unwrap());
+
+    env::set_var("TDP_SYNC__DATA_REFRESH_INTERVAL", "3");
+    env::set_var("TDP_RISK__MIN_ACTIVE_PAIR_RATIO", "0.25");
+    env::set_var("TDP_CHAINS__BASE__RPC_URL", "ws://127.0.0.1:8546");
+    let overridden = load_config_from_env_or_file();
+    let reload = handle.reload();
+    env::set_var("TDP_SYNC__DATA_REFRESH_INTERVAL", "not a number");
+    let invalid = load_config_from_env_or_file().map(|_| ()).map_err(|e| e.to_string());
+    env::set_var("TDP_SYNC__DATA_REFRESH_INTERVAL", "0");
+    let rejected = handle.reload().map(|_| ()).map_err(|e| e.to_string());
+    for variable in ["TDP_SYNC__DATA_REFRESH_INTERVAL", "TDP_RISK__MIN_ACTIVE_PAIR_RATIO", "TDP_CHAINS__BASE__RPC_URL"] {
+        env::remove_var(variable);
+    }
+
+    let overridden = overridden.unwrap();
+    assert_eq!(overridden.sync.data_refresh_interval, 3);
+    assert_eq!(overridden.risk.min_active_pair_ratio, 0.25);
+    assert_eq!(overridden.base_chain().rpc_url, "ws://127.0.0.1:8546");
+
+    //thresholds and intervals apply at once, the RPC endpoint on restart
+    let reload = reload.unwrap();
+    assert_eq!(reload.applied, vec!["risk.min_active_pair_ratio", "sync.data_refresh_interval"]);
+    assert_eq!(reload.restart_required, vec!["chains.base.rpc_url"]);
+    let current = handle.get();
+    assert_eq!(current.sync.data_refresh_interval, 3);
+    assert_eq!(current.risk.min_active_pair_ratio, 0.25);
+    assert_eq!(current.base_chain().rpc_url, "https://mainnet.base.org");
+
+    assert!(invalid.unwrap_err().contains("sync.data_refresh_interval"));
+    assert!(rejected.unwrap_err().contains("sync.data_refresh_interval must be greater than 0"));
+    assert_eq!(handle.get().sync.data_refresh_interval, 3);
+}
+
+#[test]
+fn legacy_factory_variables_set_protocol_factories() {
+    let _environment = ENVIRONMENT.lock().unwrap_or_else(|e| e.into_inner());
+    let factory = "0x0000000000000000000000000000000000000001";
+
+    env::set_var("CHAIN_BASE_UNISWAP_V2_FACTORY_ADDRESS", factory);
+    let config = load_config_from_env_or_file();
+    env::remove_var("CHAIN_BASE_UNISWAP_V2_FACTORY_ADDRESS");
+
+    let config = config.unwrap();
+    let factories: Vec<(&str, &str)> = config.protocols.iter()
+        .map(|protocol| (protocol.id.as_str(), protocol.factory.as_str()))
+        .collect();
+    assert!(factories.contains(&("BASE_UNISWAP_V2", factory)));
+    //the other protocols keep the config.toml factories
+    assert_eq!(factories.iter().filter(|(_, configured)| *configured == factory).count(), 1);
+}
+
+#[tokio::test]
+async fn reload_drops_cached_responses() {
+    let config = load_config().unwrap();
+    let api_keys = ApiKeyService::new();
+    api_keys.init(&load_config_with_profile(DEV_PROFILE).unwrap());
+    let handle = ConfigHandle::new(config);
+    let pair = Address::from(H160::from_low_u64_be(1));
+    let data_service = DataStorageService::new();
+    data_service.add_token_pair(pair, TokenPair {
+        token_pair_address: pair,
+        protocol_id: "BASE_UNISWAP_V2".to_string(),
+        base_reserve: 1_000_000,
+        quote_reserve: 1_000_000,
+        ..TokenPair::default()
+    });
+    let api = create_pair_rest_api(handle.clone(), data_service, api_keys);
+    let path = format!("/pairs/{:?}/depth", H160::from_low_u64_be(1));
+    let response = warp::test::request().path(&path).header("x-api-key", "local-read-key").reply(&api).await;
+    let etag = response.headers()["etag"].to_str().unwrap().to_string();
+
+    let reload = {
+        let _environment = ENVIRONMENT.lock().unwrap_or_else(|e| e.into_inner());
+        env::set_var("TDP_DEPTH__CURVE_POINTS", "3");
+        let reload = handle.reload();
+        env::remove_var("TDP_DEPTH__CURVE_POINTS");
+        reload
+    };
+    assert_eq!(reload.unwrap().applied, vec!["depth.curve_points"]);
+
+    //unchanged data, the depth follows the reloaded configuration
+    let response = warp::test::request().path(&path).header("x-api-key", "local-read-key").header("if-none-match", &etag)
+        .reply(&api).await;
+    assert_eq!(response.status(), 200);
+    let depth: serde_json::Value = serde_json::from_slice(response.body()).unwrap();
+    assert_eq!(depth["sell_base"]["curve"].as_array().unwrap().len(), 3);
+}
@@ -0,0 +1,213 @@
+use std::collections::HashMap;
+use std::time::SystemTime;
+use ethers::types::{H160, U256};
+use token_data_provider::adapters::pair_api::create_pair_rest_api;
+use token_data_provider::adapters::token_api::create_token_rest_api;
+use token_data_provider::config::{load_config, load_config_with_profile, ConfigHandle, DEV_PROFILE};
+use token_data_provider::domain::entities::address::Address;
+use token_data_provider::domain::entities::token::Token;
+use token_data_provider::domain::entities::token_pair::TokenPair;
+use token_data_provider::domain::entities::update_log::UpdateLog;
+use token_data_provider::domain::services::api_key_service::{ApiKeyService, ApiKeyServiceTrait};
+use token_data_provider::domain::services::data_storage_service::{DataStorageService, DataStorageTrait};
+use token_data_provider::domain::services::depth_service::{depth_at_impact, DepthService, DepthServiceTrait};
+use token_data_provider::domain::services::quote_service::{QuoteService, QuoteServiceTrait};
+
+const WETH: &str = "0x4200000000000000000000000000000000000006";
+const USDC: &str = "0x833589fcd6edb6e08f4c7c32d4f71b54bda02913";
+const DAI: &str = "0x50c5725949a6f0c72e6c4a641f24049a917db0cb";
+const ETHER: u128 = 1_000_000_000_000_000_000;
+
+fn address(address: &str) -> Address {
+    address.parse().unwrap()
+}
+
+fn pair(pair: u64, protocol_id: &str, base: (&str, u128), quote: (&str, u128), block: u32) -> TokenPair {
+    TokenPair {
+        token_pair_address: Address::from(H160::from_low_u64_be(pair)),
+        protocol_id: protocol_id.to_string(),
+        base_address: address(base.0),
+        base_reserve: base.1,
+        quote_address: address(quote.0),
+        quote_reserve: quote.1,
+        reserve_block: block,
+        ..TokenPair::default()
+    }
+}
+
+// WETH is base of one pair and quote of the other
+fn storage() -> DataStorageService {
+    let data_service = DataStorageService::new();
+    let pairs = [
+        pair(1, "BASE_UNISWAP_V2", (WETH, 100 * ETHER), (USDC, 300_000 * ETHER), 100),
+        pair(2, "BASE_BASESWAP_V2", (DAI, 600_000 * ETHER), (WETH, 200 * ETHER), 90),
+    ];
+    let active_pairs: HashMap<Address, TokenPair> = pairs.iter().map(|pair| (pair.token_pair_address, pair.clone())).collect();
+    for pair in pairs {
+        data_service.add_token_pair(pair.token_pair_address, pair);
+    }
+    data_service.add_token(address(WETH), Token {
+        address: address(WETH),
+        pairs: active_pairs.clone(),
+        active_pairs,
+        ..Token::default()
+    });
+    data_service
+}
+
+#[test]
+fn depth_moves_the_price_by_the_impact() {
+    let level = depth_at_impact(1_000_000, 1_000_000, 30, 100);
+    assert_eq!(level.amount_in, U256::from(10_131));
+
+    //a quote of the depth at 1% shows a 1% price impact
+    let config = ConfigHandle::new(load_config().unwrap());
+    let data_service = storage();
+    let weth_usdc = data_service.get_token_pair(Address::from(H160::from_low_u64_be(1))).unwrap();
+    let depth = DepthService::new(config.clone()).pair_depth(&weth_usdc).unwrap();
+    let level = &depth.sell_base.levels[0];
+    assert_eq!(level.impact_bps, 100);
+    let quote = QuoteService::new(config)
+        .quote(&data_service.get_snapshot(), address(WETH), address(USDC), level.amount_in).unwrap();
+    assert_eq!(quote.amount_out, level.amount_out);
+    assert!((quote.price_impact - 0.01).abs() < 1e-9);
+    //and buys 1% of the other reserve
+    assert_eq!(level.amount_out / U256::from(ETHER), U256::from(2_999));
+    assert!(depth.sell_quote.levels[2].amount_in > depth.sell_quote.levels[1].amount_in);
+}
+
+#[test]
+fn token_depth_sums_active_pairs() {
+    let config = ConfigHandle::new(load_config().unwrap());
+    let data_service = storage();
+    let depth_service = DepthService::new(config);
+    let weth = data_service.get_token(address(WETH)).unwrap();
+    let depth = depth_service.token_depth(&data_service.get_snapshot(), &weth);
+    assert_eq!(depth.pairs, 2);
+    assert_eq!(depth.reserve_block, 90);
+
+    let weth_usdc = depth_service.pair_depth(&data_service.get_token_pair(Address::from(H160::from_low_u64_be(1))).unwrap()).unwrap();
+    let dai_weth = depth_service.pair_depth(&data_service.get_token_pair(Address::from(H160::from_low_u64_be(2))).unwrap()).unwrap();
+    for (i, level) in depth.sell.levels.iter().enumerate() {
+        assert_eq!(level.amount, weth_usdc.sell_base.levels[i].amount_in + dai_weth.sell_quote.levels[i].amount_in);
+    }
+    for (i, level) in depth.buy.levels.iter().enumerate() {
+        assert_eq!(level.amount, weth_usdc.sell_quote.levels[i].amount_out + dai_weth.sell_base.levels[i].amount_out);
+    }
+
+    let impacts: Vec<u32> = depth.sell.curve.iter().map(|point| point.impact_bps).collect();
+    assert_eq!(impacts.len(), 10);
+    assert_eq!((impacts[0], impacts[9]), (10, 5000));
+    assert!(depth.buy.curve.windows(2).all(|points| points[0].amount < points[1].amount));
+
+    //pairs stored after the token's pair lists were computed count, drained ones don't
+    for pair in [
+        pair(3, "BASE_SUSHISWAP_V2", (WETH, 50 * ETHER), (DAI, 150_000 * ETHER), 95),
+        pair(1, "BASE_UNISWAP_V2", (WETH, 0), (USDC, 0), 110),
+    ] {
+        data_service.add_token_pair(pair.token_pair_address, pair);
+    }
+    let depth = depth_service.token_depth(&data_service.get_snapshot(), &weth);
+    assert_eq!(weth.active_pairs.len(), 2);
+    assert_eq!(depth.pairs, 2);
+    assert_eq!(depth.reserve_block, 90);
+    let weth_dai = data_service.get_token_pair(Address::from(H160::from_low_u64_be(3))).unwrap();
+    let weth_dai = depth_service.pair_depth(&weth_dai).unwrap();
+    assert_eq!(depth.sell.levels[0].amount, weth_dai.sell_base.levels[0].amount_in + dai_weth.sell_quote.levels[0].amount_in);
+}
+
+#[tokio::test]
+async fn pair_and_token_endpoints_serve_depth() {
+    let config = load_config().unwrap();
+    let api_keys = ApiKeyService::new();
+    api_keys.init(&load_config_with_profile(DEV_PROFILE).unwrap());
+    let config = ConfigHandle::new(config);
+    let data_service = storage();
+    let pair_api = create_pair_rest_api(config.clone(), data_service.clone(), api_keys.clone());
+    let token_api = create_token_rest_api(config, data_service, api_keys);
+
+    let path = format!("/pairs/{:?}/depth", H160::from_low_u64_be(1));
+    let response = warp::test::request().path(&path).header("x-api-key", "local-read-key").reply(&pair_api).await;
+    assert_eq!(response.status(), 200);
+    let depth: serde_json::Value = serde_json::from_slice(response.body()).unwrap();
+    assert_eq!(depth["fee_bps"], 30);
+    assert_eq!(depth["sell_base"]["token_in"], address(WETH).to_string());
+    assert_eq!(depth["sell_base"]["levels"].as_array().unwrap().len(), 3);
+
+    let response = warp::test::request().path("/pairs/0x1234/depth").header("x-api-key", "local-read-key").reply(&pair_api).await;
+    assert_eq!(response.status(), 400);
+    let path = format!("/pairs/{:?}/depth", H160::from_low_u64_be(3));
+    let response = warp::test::request().path(&path).header("x-api-key", "local-read-key").reply(&pair_api).await;
+    assert_eq!(response.status(), 404);
+
+    let path = format!("/token/{}/depth", WETH.to_uppercase().replace("0X", "0x"));
+    let response = warp::test::request().path(&path).header("x-api-key", "local-read-key").reply(&token_api).await;
+    assert_eq!(response.status(), 200);
+    let depth: serde_json::Value = serde_json::from_slice(response.body()).unwrap();
+    assert_eq!(depth["pairs"], 2);
+    assert_eq!(depth["sell"]["levels"][0]["impact_bps"], 100);
+
+    let path = format!("/token/{}/depth", USDC);
+    let response = warp::test::request().path(&path).header("x-api-key", "local-read-key").reply(&token_api).await;
+    assert_eq!(response.status(), 404);
+}
+
+#[tokio::test]
+async fn restarted_process_does_not_validate_earlier_etags() {
+    let config = load_config().unwrap();
+    let api_keys = ApiKeyService::new();
+    api_keys.init(&load_config_with_profile(DEV_PROFILE).unwrap());
+    let config = ConfigHandle::new(config);
+    let path = format!("/pairs/{:?}/depth", H160::from_low_u64_be(1));
+
+    let response = warp::test::request().path(&path).header("x-api-key", "local-read-key")
+        .reply(&create_pair_rest_api(config.clone(), storage(), api_keys.clone())).await;
+    let etag = response.headers()["etag"].to_str().unwrap().to_string();
+
+    //same data, same storage version, another process
+    let restarted = create_pair_rest_api(config, storage(), api_keys);
+    let response = warp::test::request().path(&path).header("x-api-key", "local-read-key").header("if-none-match", &etag)
+        .reply(&restarted).await;
+    assert_eq!(response.status(), 200);
+    let etag = response.headers()["etag"].to_str().unwrap().to_string();
+    let response = warp::test::request().path(&path).header("x-api-key", "local-read-key").header("if-none-match", &etag)
+        .reply(&restarted).await;
+    assert_eq!(response.status(), 304);
+}
+
+#[tokio::test]
+async fn unrelated_writes_keep_the_etag() {
+    let config = load_config().unwrap();
+    let api_keys = ApiKeyService::new();
+    api_keys.init(&load_config_with_profile(DEV_PROFILE).unwrap());
+    let data_service = storage();
+    let pair_api = create_pair_rest_api(ConfigHandle::new(config), data_service.clone(), api_keys);
+    let path = format!("/pairs/{:?}/depth", H160::from_low_u64_be(1));
+
+    let response = warp::test::request().path(&path).header("x-api-key", "local-read-key").reply(&pair_api).await;
+    let etag = response.headers()["etag"].to_str().unwrap().to_string();
+    let last_modified = response.headers()["last-modified"].to_str().unwrap().to_string();
+
+    //a new head bumps the storage version, the depth of the pair is unchanged
+    let now = SystemTime::now();
+    data_service.add_update_log("BASE_UNISWAP_V2".to_string(), UpdateLog {
+        protocol_id: "BASE_UNISWAP_V2".to_string(),
+        created_at: now,
+        last_update_at: now,
+        start_block: 100,
+        end_block: 101,
+        pair_count: 2,
+    });
+    let response = warp::test::request().path(&path).header("x-api-key", "local-read-key").header("if-none-match", &etag)
+        .reply(&pair_api).await;
+    assert_eq!(response.status(), 304);
+    assert_eq!(response.headers()["last-modified"], last_modified.as_str());
+
+    let mut weth_usdc = data_service.get_token_pair(Address::from(H160::from_low_u64_be(1))).unwrap();
+    weth_usdc.base_reserve *= 2;
+    data_service.add_token_pair(weth_usdc.token_pair_address, weth_usdc);
+    let response = warp::test::request().path(&path).header("x-api-key", "local-read-key").header("if-none-match", &etag)
+        .reply(&pair_api).await;
+    assert_eq!(response.status(), 200);
+    assert_ne!(response.headers()["etag"], etag.as_str());
+}
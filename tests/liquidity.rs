@@ -0,0 +1,281 @@
+mod common;
+
+use std::sync::Arc;
+use ethers::abi::{encode, Token as AbiToken};
+use ethers::providers::Provider;
+use ethers::types::{Log, H160, H256, U256};
+use ethers::utils::keccak256;
+use token_data_provider::adapters::pair_api::create_pair_rest_api;
+use token_data_provider::config::{load_config, load_config_with_profile, ConfigHandle, BASE_CHAIN, DEV_PROFILE};
+use token_data_provider::domain::entities::address::Address;
+use token_data_provider::domain::entities::liquidity_event::{LiquidityEvent, LiquidityEventKind, RugPullReason};
+use token_data_provider::domain::entities::token_pair::TokenPair;
+use token_data_provider::domain::services::api_key_service::{ApiKeyService, ApiKeyServiceTrait};
+use token_data_provider::domain::services::data_storage_service::{DataStorageService, DataStorageTrait};
+use token_data_provider::domain::services::liquidity_service::{LiquidityService, LiquidityServiceTrait};
+use token_data_provider::ports::chain::base::base_uniswap_v2_client_service::BaseUniswapV2ClientServiceTrait;
+use token_data_provider::ports::chain::base::base_uniswap_v2_indexer::BaseUniswapV2Indexer;
+use token_data_provider::ports::chain::base::base_uniswap_v2_swap_sync::BaseUniswapV2SwapSynchronizerTrait;
+use token_data_provider::ports::chain::chain_transport::ChainTransport;
+use common::mock_rpc::{MockRpcServer, TransactionFixture};
+
+// Addresses of tests/fixtures/base_uniswap_v2.json
+const HEAD_BLOCK: u64 = 22800100;
+const USDC: &str = "0x833589fcd6edb6e08f4c7c32d4f71b54bda02913";
+const PAIR_WETH_USDC: &str = "0x175176ddf9971c155909bcda18f8061b93109083";
+const ROUTER: u64 = 0x4752;
+const DEPLOYER: u64 = 0xde9;
+const LP: u64 = 0x11b;
+
+fn address(address: &str) -> Address {
+    address.parse().unwrap()
+}
+
+fn account(id: u64) -> Address {
+    Address::from(H160::from_low_u64_be(id))
+}
+
+fn event(kind: LiquidityEventKind, origin: u64, amounts: (u128, u128), block_number: u64, log_index: u64) -> LiquidityEvent {
+    LiquidityEvent {
+        pair_address: address(PAIR_WETH_USDC),
+        kind,
+        sender: account(ROUTER),
+        to: (kind == LiquidityEventKind::Burn).then(|| account(origin)),
+        origin: Some(account(origin)),
+        amount0: amounts.0,
+        amount1: amounts.1,
+        block_number,
+        transaction_hash: Some(H256::from_low_u64_be(block_number * 100 + log_index)),
+        log_index: Some(log_index.into()),
+    }
+}
+
+// Pair whose reserves were read after its last liquidity event
+fn storage(reserves: (u128, u128), events: Vec<LiquidityEvent>) -> DataStorageService {
+    let data_service = DataStorageService::new();
+    data_service.add_token_pair(address(PAIR_WETH_USDC), TokenPair {
+        token_pair_address: address(PAIR_WETH_USDC),
+        protocol_id: "BASE_UNISWAP_V2".to_string(),
+        base_reserve: reserves.0,
+        quote_reserve: reserves.1,
+        reserves_read_block: u64::MAX,
+        ..TokenPair::default()
+    });
+    data_service.write_batch(|storage| {
+        storage.liquidity_events.insert(address(PAIR_WETH_USDC), events.into_iter().collect());
+    });
+    data_service
+}
+
+fn liquidity_service() -> LiquidityService {
+    LiquidityService::new(ConfigHandle::new(load_config().unwrap()))
+}
+
+#[test]
+fn detects_liquidity_drop_within_window() {
+    let liquidity_service = liquidity_service();
+    let mint = event(LiquidityEventKind::Mint, DEPLOYER, (1000, 2000), 100, 0);
+
+    //400 of 1000 removed, below the 0.5 drop share
+    let data_service = storage((600, 1200), vec![mint.clone(), event(LiquidityEventKind::Burn, LP, (400, 800), 200, 0)]);
+    assert!(liquidity_service.detect_rug_pull(&data_service.get_snapshot(), address(PAIR_WETH_USDC)).is_none());
+
+    //two burns in 20 minutes take 700 of 1000
+    let data_service = storage((300, 600), vec![
+        mint.clone(),
+        event(LiquidityEventKind::Burn, LP, (400, 800), 200, 0),
+        event(LiquidityEventKind::Burn, LP, (300, 600), 800, 0),
+    ]);
+    let rug_pull = liquidity_service.detect_rug_pull(&data_service.get_snapshot(), address(PAIR_WETH_USDC)).unwrap();
+    assert_eq!(rug_pull.reason, RugPullReason::LiquidityDrop);
+    assert_eq!(rug_pull.block_number, 800);
+    assert!((rug_pull.share - 0.7).abs() < 1e-9);
+
+    //600 of 1000 a day apart, the second burn takes a third of what was left
+    let data_service = storage((400, 800), vec![
+        mint.clone(),
+        event(LiquidityEventKind::Burn, LP, (400, 800), 200, 0),
+        event(LiquidityEventKind::Burn, LP, (200, 400), 200 + 43_200, 0),
+    ]);
+    assert!(liquidity_service.detect_rug_pull(&data_service.get_snapshot(), address(PAIR_WETH_USDC)).is_none());
+
+    //not judged on reserves read before the last burn
+    let data_service = storage((1000, 2000), vec![
+        mint,
+        event(LiquidityEventKind::Burn, LP, (400, 800), 200, 0),
+        event(LiquidityEventKind::Burn, LP, (300, 600), 800, 0),
+    ]);
+    data_service.write_batch(|storage| storage.token_pairs.get_mut(&address(PAIR_WETH_USDC)).unwrap().reserves_read_block = 0);
+    assert!(liquidity_service.detect_rug_pull(&data_service.get_snapshot(), address(PAIR_WETH_USDC)).is_none());
+}
+
+#[test]
+fn detects_removal_by_deployer() {
+    let liquidity_service = liquidity_service();
+    let events = vec![
+        event(LiquidityEventKind::Mint, DEPLOYER, (1000, 2000), 100, 0),
+        event(LiquidityEventKind::Mint, LP, (1000, 2000), 110, 0),
+        //5% of 2000 by another LP, then 15% by the deployer
+        event(LiquidityEventKind::Burn, LP, (100, 200), 200, 0),
+        event(LiquidityEventKind::Burn, DEPLOYER, (285, 570), 300, 0),
+    ];
+    let data_service = storage((1615, 3230), events);
+    let storage = data_service.get_snapshot();
+    let rug_pull = liquidity_service.detect_rug_pull(&storage, address(PAIR_WETH_USDC)).unwrap();
+    assert_eq!(rug_pull.reason, RugPullReason::DeployerRemoval);
+    assert_eq!(rug_pull.block_number, 300);
+    assert!((rug_pull.share - 0.15).abs() < 1e-9);
+
+    let history = liquidity_service.history(&storage, address(PAIR_WETH_USDC)).unwrap();
+    assert_eq!(history.deployer, Some(account(DEPLOYER)));
+    assert_eq!((history.minted0, history.burned0, history.net0), (2000, 385, 1615));
+    assert_eq!(history.net1, 3230);
+    assert_eq!(history.events.len(), 4);
+    assert!(liquidity_service.history(&storage, account(LP)).is_none());
+}
+
+#[test]
+fn events_past_window_fold_into_totals() {
+    let liquidity_service = liquidity_service();
+    let data_service = storage((1600, 3200), vec![
+        event(LiquidityEventKind::Mint, DEPLOYER, (1000, 2000), 100, 0),
+        event(LiquidityEventKind::Mint, LP, (1000, 2000), 110, 0),
+        event(LiquidityEventKind::Burn, LP, (400, 800), 5000, 0),
+    ]);
+    //an emitter never discovered as a pair
+    let unknown = LiquidityEvent { pair_address: account(0xbad), ..event(LiquidityEventKind::Mint, LP, (1, 1), 120, 0) };
+    data_service.write_batch(|storage| storage.liquidity_events.insert(unknown.pair_address, vec![unknown].into_iter().collect()));
+
+    //the 3600s window is 1800 blocks, the mints are past it
+    let dropped = data_service.write_batch(|storage| liquidity_service.prune_liquidity_events(storage, 5100));
+    assert_eq!(dropped, 1);
+    let storage = data_service.get_snapshot();
+    assert_eq!(storage.liquidity_events.len(), 1);
+    assert_eq!(storage.liquidity_totals.get(&address(PAIR_WETH_USDC)).unwrap().last_block, 110);
+    let history = liquidity_service.history(&storage, address(PAIR_WETH_USDC)).unwrap();
+    assert_eq!(history.deployer, Some(account(DEPLOYER)));
+    assert_eq!((history.minted0, history.burned0, history.net1), (2000, 400, 3200));
+    assert_eq!(history.events.len(), 1);
+}
+
+// Logs of one block share the transaction
+fn liquidity_log(kind: LiquidityEventKind, origin: u64, amounts: (u128, u128), block_number: u64, log_index: u64) -> (Log, TransactionFixture) {
+    let hash = H256::from_low_u64_be(block_number);
+    let (signature, mut topics) = match kind {
+        LiquidityEventKind::Mint => ("Mint(address,uint256,uint256)", vec![]),
+        LiquidityEventKind::Burn => ("Burn(address,uint256,uint256,address)", vec![H256::from(account(origin).as_h160())]),
+    };
+    topics.insert(0, H256::from(account(ROUTER).as_h160()));
+    topics.insert(0, H256::from(keccak256(signature)));
+    let log = Log {
+        address: address(PAIR_WETH_USDC).as_h160(),
+        topics,
+        data: encode(&[AbiToken::Uint(U256::from(amounts.0)), AbiToken::Uint(U256::from(amounts.1))]).into(),
+        block_number: Some(block_number.into()),
+        transaction_hash: Some(hash),
+        transaction_index: Some(0.into()),
+        log_index: Some(log_index.into()),
+        removed: Some(false),
+        ..Log::default()
+    };
+    (log, TransactionFixture { hash, from: account(origin).as_h160() })
+}
+
+#[tokio::test]
+async fn indexed_burns_flag_pair_and_tokens() {
+    let mock = MockRpcServer::from_fixture("base_uniswap_v2").await;
+    //the deployer takes 80% of the WETH/USDC liquidity back, the reserves left are those of the fixture
+    mock.update_chain(|chain| {
+        for (kind, origin, amounts, block) in [
+            (LiquidityEventKind::Mint, DEPLOYER, (7_600_000_000_000_000_000_000, 19_000_000_000_000), HEAD_BLOCK - 90),
+            (LiquidityEventKind::Burn, DEPLOYER, (6_080_000_000_000_000_000_000, 15_200_000_000_000), HEAD_BLOCK - 10),
+        ] {
+            let (log, transaction) = liquidity_log(kind, origin, amounts, block, 0);
+            chain.logs.push(log);
+            chain.transactions.push(transaction);
+        }
+    });
+    let mut config = load_config().unwrap();
+    config.chains.get_mut(BASE_CHAIN).unwrap().rpc_url = mock.url();
+    config.sync.logs_range_initial = 100;
+    config.sync.logs_max_attempts = 1;
+    config.rug_pull.flag_expiry_secs = 3600;
+    let api_keys = ApiKeyService::new();
+    api_keys.init(&load_config_with_profile(DEV_PROFILE).unwrap());
+    let config = ConfigHandle::new(config);
+    let transport = ChainTransport::connect(&mock.url()).await.unwrap();
+    let indexer = BaseUniswapV2Indexer::new(config.clone(), DataStorageService::new(), Arc::new(Provider::new(transport)));
+    let data_service = &indexer.data_service;
+
+    indexer.client_service.update_sync_log().await.unwrap();
+    indexer.swap_sync.extract_swap_events(22800000, HEAD_BLOCK).await.unwrap();
+    let events = data_service.get_liquidity_events(address(PAIR_WETH_USDC));
+    assert_eq!(events.len(), 2);
+    assert_eq!(events[0].kind, LiquidityEventKind::Mint);
+    assert_eq!(events[1].to, Some(account(DEPLOYER)));
+    assert!(events.iter().all(|event| event.origin == Some(account(DEPLOYER))));
+    assert_eq!(mock.requests("eth_getTransactionByHash").len(), 2);
+    //liquidity logs are no swaps, the fixture's 6 swaps wait for pair discovery
+    assert_eq!(data_service.get_quarantined_swap_logs_size(), 6);
+
+    //indexed again by a backfill
+    indexer.swap_sync.backfill_swaps(22800000, HEAD_BLOCK).await.unwrap();
+    assert_eq!(data_service.get_total_liquidity_events_size(), 2);
+
+    indexer.client_service.init_token_list(0, HEAD_BLOCK).await.unwrap();
+    let rug_pull = data_service.get_rug_pull(address(PAIR_WETH_USDC)).unwrap();
+    assert_eq!(rug_pull.reason, RugPullReason::DeployerRemoval);
+    assert!((rug_pull.share - 0.8).abs() < 1e-9);
+    let usdc = data_service.get_token(address(USDC)).unwrap();
+    assert!(usdc.high_risk);
+
+    let api = create_pair_rest_api(config.clone(), data_service.clone(), api_keys);
+    let path = format!("/pairs/{}/liquidity", PAIR_WETH_USDC.to_uppercase().replace("0X", "0x"));
+    let response = warp::test::request().path(&path).header("x-api-key", "local-read-key").reply(&api).await;
+    assert_eq!(response.status(), 200);
+    let history: serde_json::Value = serde_json::from_slice(response.body()).unwrap();
+    assert_eq!(history["deployer"], account(DEPLOYER).to_string());
+    assert_eq!(history["rug_pull"]["reason"], "deployer_removal");
+    assert_eq!(history["events"][1]["kind"], "burn");
+    assert_eq!(history["net1"], 3_800_000_000_000u64);
+
+    let response = warp::test::request().path("/pairs/0x1234/liquidity").header("x-api-key", "local-read-key").reply(&api).await;
+    assert_eq!(response.status(), 400);
+
+    //an hour of blocks after the burn the flag expires, the events are folded into the totals
+    mock.set_block_number(HEAD_BLOCK + 1800);
+    indexer.swap_sync.extract_swap_events(HEAD_BLOCK + 1, HEAD_BLOCK + 1800).await.unwrap();
+    indexer.client_service.refresh_chain_data().await.unwrap();
+    assert!(data_service.get_rug_pull(address(PAIR_WETH_USDC)).is_none());
+    assert_eq!(data_service.get_total_liquidity_events_size(), 0);
+    let history = LiquidityService::new(config.clone()).history(&data_service.get_snapshot(), address(PAIR_WETH_USDC)).unwrap();
+    assert_eq!(history.deployer, Some(account(DEPLOYER)));
+    assert_eq!(history.net1, 3_800_000_000_000);
+}
+
+#[tokio::test]
+async fn origins_are_read_once_per_transaction() {
+    let mock = MockRpcServer::from_fixture("base_uniswap_v2").await;
+    //two burns of one transaction after the first mint
+    mock.update_chain(|chain| {
+        for (kind, amounts, block, log_index) in [
+            (LiquidityEventKind::Mint, (1000, 2000), HEAD_BLOCK - 90, 0),
+            (LiquidityEventKind::Burn, (100, 200), HEAD_BLOCK - 10, 0),
+            (LiquidityEventKind::Burn, (100, 200), HEAD_BLOCK - 10, 1),
+        ] {
+            let (log, transaction) = liquidity_log(kind, DEPLOYER, amounts, block, log_index);
+            chain.logs.push(log);
+            chain.transactions.push(transaction);
+        }
+    });
+    let mut config = load_config().unwrap();
+    config.chains.get_mut(BASE_CHAIN).unwrap().rpc_url = mock.url();
+    let transport = ChainTransport::connect(&mock.url()).await.unwrap();
+    let indexer = BaseUniswapV2Indexer::new(ConfigHandle::new(config), DataStorageService::new(), Arc::new(Provider::new(transport)));
+
+    indexer.swap_sync.extract_swap_events(22800000, HEAD_BLOCK).await.unwrap();
+    let events = indexer.data_service.get_liquidity_events(address(PAIR_WETH_USDC));
+    assert_eq!(events.len(), 3);
+    assert!(events.iter().all(|event| event.origin == Some(account(DEPLOYER))));
+    assert_eq!(mock.requests("eth_getTransactionByHash").len(), 2);
+}
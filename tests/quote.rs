@@ -0,0 +1,107 @@
+use ethers::types::U256;
+use token_data_provider::adapters::quote_api::create_quote_rest_api;
+use token_data_provider::config::{load_config, load_config_with_profile, ConfigHandle, DEV_PROFILE};
+use token_data_provider::domain::entities::address::Address;
+use token_data_provider::domain::entities::token_pair::TokenPair;
+use token_data_provider::domain::services::api_key_service::{ApiKeyService, ApiKeyServiceTrait};
+use token_data_provider::domain::services::data_storage_service::{DataStorageService, DataStorageTrait};
+use token_data_provider::domain::services::quote_service::{get_amount_out, QuoteService, QuoteServiceTrait};
+
+const WETH: &str = "0x4200000000000000000000000000000000000006";
+const USDC: &str = "0x833589fcd6edb6e08f4c7c32d4f71b54bda02913";
+const DAI: &str = "0x50c5725949a6f0c72e6c4a641f24049a917db0cb";
+const RUG: &str = "0xe17964b8e0416e15a38a43d1aa749599e56fd256";
+const ETHER: u128 = 1_000_000_000_000_000_000;
+
+fn address(address: &str) -> Address {
+    address.parse().unwrap()
+}
+
+fn add_pair(data_service: &DataStorageService, pair: u64, protocol_id: &str, base: (&str, u128), quote: (&str, u128), block: u32) {
+    let pair_address = Address::from(ethers::types::H160::from_low_u64_be(pair));
+    data_service.add_token_pair(pair_address, TokenPair {
+        token_pair_address: pair_address,
+        protocol_id: protocol_id.to_string(),
+        base_address: address(base.0),
+        base_reserve: base.1,
+        quote_address: address(quote.0),
+        quote_reserve: quote.1,
+        reserve_block: block,
+        ..TokenPair::default()
+    });
+}
+
+// WETH/USDC is shallow, the deep WETH/DAI and DAI/USDC pools pay more for large swaps
+fn storage() -> DataStorageService {
+    let data_service = DataStorageService::new();
+    add_pair(&data_service, 1, "BASE_UNISWAP_V2", (WETH, 10 * ETHER), (USDC, 30_000 * ETHER), 100);
+    add_pair(&data_service, 2, "BASE_UNISWAP_V2", (WETH, 1_000 * ETHER), (DAI, 3_000_000 * ETHER), 101);
+    add_pair(&data_service, 3, "BASE_BASESWAP_V2", (USDC, 5_000_000 * ETHER), (DAI, 5_000_000 * ETHER), 102);
+    //no reserves
+    add_pair(&data_service, 4, "BASE_UNISWAP_V2", (RUG, 0), (WETH, 0), 103);
+    data_service
+}
+
+#[test]
+fn amount_out_follows_uniswap_v2() {
+    let amount_out = get_amount_out(U256::from(1000), U256::from(10_000), U256::from(10_000), 30);
+    assert_eq!(amount_out, U256::from(906));
+    assert!(get_amount_out(U256::from(1000), U256::from(10_000), U256::from(10_000), 25) > amount_out);
+    assert!(get_amount_out(U256::from(1000), U256::zero(), U256::from(10_000), 30).is_zero());
+}
+
+#[test]
+fn quote_picks_best_route_within_max_hops() {
+    let mut config = load_config().unwrap();
+    let data_service = storage();
+    let storage = data_service.get_snapshot();
+    let amount_in = U256::from(ETHER);
+
+    let quote = QuoteService::new(ConfigHandle::new(config.clone()))
+        .quote(&storage, address(WETH), address(USDC), amount_in).unwrap();
+    let route: Vec<Address> = quote.route.iter().map(|step| step.token_out).collect();
+    assert_eq!(route, vec![address(DAI), address(USDC)]);
+    assert_eq!(quote.route[1].fee_bps, 25);
+    assert_eq!(quote.route[0].amount_out, quote.route[1].amount_in);
+    assert_eq!(quote.amount_out, quote.route[1].amount_out);
+    assert_eq!(quote.reserve_block, 101);
+    assert!(quote.price_impact > 0.0 && quote.price_impact < 0.01);
+
+    //a single hop only reaches the shallow pair
+    config.quote.max_hops = 1;
+    let direct = QuoteService::new(ConfigHandle::new(config))
+        .quote(&storage, address(WETH), address(USDC), amount_in).unwrap();
+    assert_eq!(direct.route.len(), 1);
+    assert_eq!(direct.reserve_block, 100);
+    assert!(direct.amount_out < quote.amount_out);
+    assert!(direct.price_impact > 0.09);
+
+    let quotes = QuoteService::new(ConfigHandle::new(load_config().unwrap()));
+    assert!(quotes.quote(&storage, address(RUG), address(WETH), amount_in).is_none());
+    assert!(quotes.quote(&storage, address(WETH), address(WETH), amount_in).is_none());
+}
+
+#[tokio::test]
+async fn quote_endpoint_serves_route() {
+    let config = load_config().unwrap();
+    let api_keys = ApiKeyService::new();
+    api_keys.init(&load_config_with_profile(DEV_PROFILE).unwrap());
+    let api = create_quote_rest_api(ConfigHandle::new(config), storage(), api_keys);
+
+    let path = format!("/quote?in={}&out={}&amount={}", WETH, USDC.to_uppercase().replace("0X", "0x"), ETHER);
+    let response = warp::test::request().path(&path).header("x-api-key", "local-read-key").reply(&api).await;
+    assert_eq!(response.status(), 200);
+    let quote: serde_json::Value = serde_json::from_slice(response.body()).unwrap();
+    assert_eq!(quote["token_out"], address(USDC).to_string());
+    assert_eq!(quote["amount_in"], ETHER.to_string());
+    assert_eq!(quote["route"].as_array().unwrap().len(), 2);
+    assert_eq!(quote["reserve_block"], 101);
+
+    let path = format!("/quote?in={}&out={}&amount=1.5", WETH, USDC);
+    let response = warp::test::request().path(&path).header("x-api-key", "local-read-key").reply(&api).await;
+    assert_eq!(response.status(), 400);
+
+    let path = format!("/quote?in={}&out={}&amount=1000", RUG, USDC);
+    let response = warp::test::request().path(&path).header("x-api-key", "local-read-key").reply(&api).await;
+    assert_eq!(response.status(), 404);
+}
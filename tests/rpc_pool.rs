@@ -0,0 +1,195 @@
+mod common;
+
+use std::sync::Arc;
+use std::time::Duration;
+use ethers::providers::{Middleware, Provider};
+use token_data_provider::config::{load_config, load_config_with_profile, ConfigHandle, RpcEndpointConfig, BASE_CHAIN, DEV_PROFILE};
+use token_data_provider::domain::entities::address::Address;
+use token_data_provider::domain::entities::api_key::ApiScope;
+use token_data_provider::domain::entities::job::JobKind;
+use token_data_provider::domain::entities::supervised_task::{ShutdownMode, TaskState};
+use token_data_provider::domain::entities::token_pair::TokenPair;
+use token_data_provider::domain::services::api_key_service::{ApiKeyService, ApiKeyServiceTrait};
+use token_data_provider::domain::services::data_storage_service::{DataStorageService, DataStorageTrait};
+use token_data_provider::domain::services::job_service::JobServiceTrait;
+use token_data_provider::domain::services::task_supervisor::{ShutdownSignal, TaskSupervisor};
+use token_data_provider::ports::chain::base::base_uniswap_v2_client_service::BaseUniswapV2ClientServiceTrait;
+use token_data_provider::ports::chain::base::base_uniswap_v2_indexer::BaseUniswapV2Indexer;
+use token_data_provider::ports::chain::base::base_uniswap_v2_job_runner::BaseUniswapV2JobRunnerTrait;
+use token_data_provider::ports::chain::base::base_uniswap_v2_swap_sync::BaseUniswapV2SwapSynchronizerTrait;
+use token_data_provider::ports::chain::quorum::{find_quorum, normalize_response, QuorumService, QuorumServiceTrait};
+use token_data_provider::ports::chain::rpc_pool::{RpcPool, RpcPoolSettings};
+use common::mock_rpc::MockRpcServer;
+
+// Addresses of tests/fixtures/base_uniswap_v2.json
+const PAIR_WETH_USDC: &str = "0x175176ddf9971c155909bcda18f8061b93109083";
+const HEAD_BLOCK: u64 = 22800100;
+
+type Indexer = BaseUniswapV2Indexer<Provider<RpcPool>>;
+
+fn address(address: &str) -> Address {
+    address.parse().unwrap()
+}
+
+// Indexer reading every eth_call and eth_getLogs from all the mocks, which have to agree
+async fn quorum_indexer(mocks: &[&MockRpcServer]) -> Indexer {
+    let mut config = load_config().unwrap();
+    let chain = config.chains.get_mut(BASE_CHAIN).unwrap();
+    chain.rpc_url = mocks[0].url();
+    chain.rpc_endpoints = mocks.iter()
+        .map(|mock| RpcEndpointConfig { url: mock.url(), weight: 1, max_logs_range: None })
+        .collect();
+    chain.rpc_quorum_size = mocks.len();
+    chain.rpc_quorum_threshold = mocks.len();
+    let pool = RpcPool::connect(&config.base_rpc_endpoints(), RpcPoolSettings::from_config(&config)).await.unwrap();
+    BaseUniswapV2Indexer::new(ConfigHandle::new(config), DataStorageService::new(), Arc::new(Provider::new(pool)))
+}
+
+#[tokio::test]
+async fn indexers_keep_their_own_disputes_and_jobs() {
+    let (honest, other_honest) = (MockRpcServer::from_fixture("base_uniswap_v2").await, MockRpcServer::from_fixture("base_uniswap_v2").await);
+    let lying = MockRpcServer::from_fixture("base_uniswap_v2").await;
+    lying.update_chain(|chain| chain.pairs.iter_mut().for_each(|pair| pair.reserve0 = "1".to_string()));
+    let disputed = quorum_indexer(&[&honest, &lying]).await;
+    let agreeing = quorum_indexer(&[&other_honest, &honest]).await;
+
+    assert!(disputed.client_service.refresh_token_pair(address(PAIR_WETH_USDC)).await.is_err());
+    agreeing.client_service.refresh_token_pair(address(PAIR_WETH_USDC)).await.unwrap();
+    assert!(disputed.quorum.is_disputed(address(PAIR_WETH_USDC)));
+    assert!(!agreeing.quorum.is_disputed(address(PAIR_WETH_USDC)));
+    assert_eq!(agreeing.quorum.get_metrics().disagreements, 0);
+
+    let supervisor = TaskSupervisor::new(disputed.config.clone(), disputed.shutdown.clone(), None);
+    let job = disputed.job_runner.submit(&supervisor, JobKind::SyncPause { synchronizer: "swaps".to_string() });
+    assert!(disputed.jobs.get_job(job.id.clone()).is_some());
+    assert!(agreeing.jobs.get_job(job.id).is_none());
+    assert!(agreeing.jobs.get_jobs().is_empty());
+}
+
+#[test]
+fn api_key_services_authorize_their_own_keys() {
+    let config = load_config_with_profile(DEV_PROFILE).unwrap();
+    let (api_keys, other_api_keys) = (ApiKeyService::new(), ApiKeyService::new());
+    api_keys.init(&config);
+
+    assert!(api_keys.authorize(Some("local-read-key".to_string()), ApiScope::Read).is_ok());
+    assert!(other_api_keys.authorize(Some("local-read-key".to_string()), ApiScope::Read).is_err());
+    assert!(other_api_keys.get_api_keys().is_empty());
+}
+
+#[tokio::test]
+async fn head_lag_is_no_quorum_disagreement() {
+    let (honest, lagging) = (MockRpcServer::from_fixture("base_uniswap_v2").await, MockRpcServer::from_fixture("base_uniswap_v2").await);
+    //the last swap, at block 22800090, is above the lagging head
+    lagging.set_block_number(HEAD_BLOCK - 20);
+    let indexer = quorum_indexer(&[&honest, &lagging]).await;
+
+    assert_eq!(indexer.provider.get_block_number().await.unwrap().as_u64(), HEAD_BLOCK - 20);
+    indexer.swap_sync.extract_swap_events(22800000, HEAD_BLOCK).await.unwrap();
+    assert_eq!(indexer.quorum.get_metrics().disagreements, 0);
+    assert!(indexer.quorum.disputed_keys().is_empty());
+    //the answer reaching above the common head is kept
+    assert_eq!(indexer.data_service.get_quarantined_swap_logs_size(), 6);
+}
+
+#[test]
+fn disputes_expire() {
+    let pair = address(PAIR_WETH_USDC);
+    let quorum = QuorumService::new(1);
+    quorum.record_disagreement("eth_call", &[pair]);
+    let data_service = DataStorageService::new();
+    data_service.add_token_pair(pair, TokenPair { token_pair_address: pair, ..TokenPair::default() });
+    assert_eq!(data_service.set_quorum_disputed(quorum.disputed_keys()), 1);
+    assert!(data_service.get_token_pair(pair).unwrap().quorum_disputed);
+
+    std::thread::sleep(Duration::from_millis(1100));
+    assert!(!quorum.is_disputed(pair));
+    assert!(quorum.get_metrics().disputes.is_empty());
+    assert_eq!(data_service.set_quorum_disputed(quorum.disputed_keys()), 1);
+    assert!(!data_service.get_token_pair(pair).unwrap().quorum_disputed);
+}
+
+#[tokio::test]
+async fn health_check_runs_as_supervised_task() {
+    let mock = MockRpcServer::from_fixture("base_uniswap_v2").await;
+    let indexer = quorum_indexer(&[&mock]).await;
+    let supervisor = TaskSupervisor::new(indexer.config.clone(), ShutdownSignal::new(), None);
+    let pool: &RpcPool = indexer.provider.as_ref().as_ref();
+    pool.supervise_health_check(&supervisor, 1);
+
+    let task = supervisor.get_tasks().into_iter().find(|task| task.name == "rpc_health_check").unwrap();
+    assert_eq!(task.shutdown_mode, ShutdownMode::Cancel);
+    for _ in 0..50 {
+        if pool.get_status()[0].head_block == HEAD_BLOCK {
+            break;
+        }
+        tokio::time::sleep(Duration::from_millis(20)).await;
+    }
+    assert_eq!(pool.get_status()[0].head_block, HEAD_BLOCK);
+
+    //the check loop never returns by itself, shutdown cancels it
+    supervisor.shutdown().await;
+    let task = supervisor.get_tasks().into_iter().find(|task| task.name == "rpc_health_check").unwrap();
+    assert_eq!(task.state, TaskState::Stopped);
+}
+
+#[tokio::test]
+async fn calls_are_spread_over_healthy_endpoints_by_weight() {
+    let (light, heavy) = (MockRpcServer::from_fixture("base_uniswap_v2").await, MockRpcServer::from_fixture("base_uniswap_v2").await);
+    let endpoints = [
+        RpcEndpointConfig { url: light.url(), weight: 1, max_logs_range: None },
+        RpcEndpointConfig { url: heavy.url(), weight: 3, max_logs_range: None },
+    ];
+    let pool = RpcPool::connect(&endpoints, RpcPoolSettings::from_config(&load_config().unwrap())).await.unwrap();
+    let provider = Provider::new(pool);
+    for _ in 0..40 {
+        assert_eq!(provider.get_block_number().await.unwrap().as_u64(), HEAD_BLOCK);
+    }
+
+    //not only the best scored endpoint, the heavier one takes the larger share
+    let (light_calls, heavy_calls) = (light.requests("eth_blockNumber").len(), heavy.requests("eth_blockNumber").len());
+    assert_eq!(light_calls + heavy_calls, 40);
+    assert!(light_calls >= 4, "light endpoint got {} calls", light_calls);
+    assert!(heavy_calls > light_calls, "heavy endpoint got {} calls", heavy_calls);
+}
+
+#[test]
+fn equal_results_in_other_encodings_agree() {
+    let log = serde_json::json!({
+        "address": PAIR_WETH_USDC,
+        "topics": ["0xd78ad95fa46c994b6551d0da85fc275fe613ce37657fb8d5e3d130840159d822"],
+        "data": "0xabcd",
+        "blockNumber": "0x15be9e4",
+        "transactionHash": "0x1111111111111111111111111111111111111111111111111111111111111111",
+        "transactionIndex": "0x1",
+        "blockHash": "0x2222222222222222222222222222222222222222222222222222222222222222",
+        "logIndex": "0x2",
+        "removed": false
+    });
+    //upper case hex, padded quantities and a field the other provider doesn't send
+    let other = serde_json::json!({
+        "removed": false,
+        "logIndex": "0x02",
+        "blockHash": "0x2222222222222222222222222222222222222222222222222222222222222222",
+        "transactionIndex": "0x01",
+        "transactionHash": "0x1111111111111111111111111111111111111111111111111111111111111111",
+        "blockNumber": "0x015BE9E4",
+        "data": "0xABCD",
+        "topics": ["0xD78AD95FA46C994B6551D0DA85FC275FE613CE37657FB8D5E3D130840159D822"],
+        "address": PAIR_WETH_USDC.to_uppercase().replace("0X", "0x"),
+        "blockTimestamp": "0x6650f3a1"
+    });
+    let responses = [serde_json::json!([log]), serde_json::json!([other])];
+    let normalized: Vec<_> = responses.iter().map(|response| normalize_response("eth_getLogs", response)).collect();
+    assert_eq!(find_quorum(&normalized, 2), Some(0));
+
+    let calls = [serde_json::json!("0x00000000000000000000000000000000000000000000000000000000000000ff"),
+        serde_json::json!("0x00000000000000000000000000000000000000000000000000000000000000FF")];
+    let normalized: Vec<_> = calls.iter().map(|response| normalize_response("eth_call", response)).collect();
+    assert_eq!(find_quorum(&normalized, 2), Some(0));
+
+    //a different result still disagrees
+    let changed = [serde_json::json!("0x01"), serde_json::json!("0x02")];
+    let normalized: Vec<_> = changed.iter().map(|response| normalize_response("eth_call", response)).collect();
+    assert_eq!(find_quorum(&normalized, 2), None);
+}
@@ -0,0 +1,38 @@
+use std::fs;
+use token_data_provider::commands::load_snapshot;
+use token_data_provider::domain::entities::address::Address;
+use token_data_provider::domain::entities::token_pair::TokenPair;
+use token_data_provider::domain::services::data_storage_service::{DataStorageService, DataStorageTrait};
+
+const PAIR: &str = "0x175176ddf9971c155909bcda18f8061b93109083";
+
+fn snapshot_path(name: &str) -> String {
+    std::env::temp_dir().join(format!("token-data-provider-{}-{}.json", name, std::process::id())).to_string_lossy().to_string()
+}
+
+#[test]
+fn restart_resumes_from_saved_snapshot() {
+    let path = snapshot_path("resume");
+    let pair: Address = PAIR.parse().unwrap();
+    let data_service = DataStorageService::new();
+    data_service.add_token_pair(pair, TokenPair { token_pair_address: pair, ..TokenPair::default() });
+    data_service.save_snapshot(&path).unwrap();
+
+    let restarted = DataStorageService::new();
+    assert!(load_snapshot(&restarted, &path).unwrap());
+    assert!(restarted.contains_token_pair(pair));
+
+    //first start, nothing to load
+    fs::remove_file(&path).unwrap();
+    assert!(!load_snapshot(&DataStorageService::new(), &path).unwrap());
+}
+
+#[test]
+fn unreadable_snapshot_is_an_error() {
+    let path = snapshot_path("corrupt");
+    fs::write(&path, "{\"tokens\": ").unwrap();
+    assert!(load_snapshot(&DataStorageService::new(), &path).is_err());
+    //left as it was for inspection
+    assert_eq!(fs::read_to_string(&path).unwrap(), "{\"tokens\": ");
+    fs::remove_file(&path).unwrap();
+}
@@ -0,0 +1,96 @@
+use ethers::types::H256;
+use token_data_provider::config::{load_config, ConfigHandle};
+use token_data_provider::domain::entities::address::Address;
+use token_data_provider::domain::entities::swap_aggregate::AggregateInterval;
+use token_data_provider::domain::entities::swap_log::SwapLog;
+use token_data_provider::domain::services::data_storage_service::{DataStorageService, DataStorageTrait};
+use token_data_provider::domain::services::swap_compaction_service::{SwapCompactionService, SwapCompactionServiceTrait};
+
+const PAIR: &str = "0x175176ddf9971c155909bcda18f8061b93109083";
+const OTHER_PAIR: &str = "0x88a43bbdf9d098eec7bceda4e2494615dfd9bb9c";
+
+fn swap_log(pair: Address, block: u64, removed: bool) -> SwapLog {
+    SwapLog {
+        address: pair,
+        protocol_id: "BASE_UNISWAP_V2".to_string(),
+        topics: vec![],
+        data: Default::default(),
+        block_hash: None,
+        block_number: Some(block.into()),
+        transaction_hash: Some(H256::from_low_u64_be(block)),
+        transaction_index: Some(0.into()),
+        log_index: Some(0.into()),
+        removed: Some(removed),
+    }
+}
+
+#[test]
+fn swap_count_keeps_compacted_swaps_per_pair() {
+    let (pair, other_pair): (Address, Address) = (PAIR.parse().unwrap(), OTHER_PAIR.parse().unwrap());
+    let mut config = load_config().unwrap();
+    config.storage.swap_log_retention_secs = 0;
+    config.storage.swap_log_retention_blocks = 0;
+    config.storage.swap_log_retention_per_pair = 2;
+    config.storage.swap_aggregate_hourly_retention_secs = 0;
+    let data_service = DataStorageService::new();
+    data_service.write_batch(|storage| {
+        //the reorged swap at block 2 is dropped, not counted
+        let logs = (1..=5).map(|block| swap_log(pair, block, block == 2)).collect();
+        storage.swap_logs.insert(pair, logs);
+        storage.swap_logs.insert(other_pair, (1..=3).map(|block| swap_log(other_pair, block, false)).collect());
+    });
+    let compaction = SwapCompactionService::new(ConfigHandle::new(config), data_service.clone());
+
+    let result = compaction.compact_swap_logs(10);
+    assert_eq!(result.compacted_swap_logs, 4);
+    assert_eq!(data_service.get_swap_count(pair), 2 + 2);
+    assert_eq!(data_service.get_swap_count(other_pair), 3);
+    assert_eq!(data_service.get_snapshot().compacted_swaps.get(&pair), Some(&2));
+
+    //snapshots without the counts rebuild them from the daily aggregates
+    let path = std::env::temp_dir().join(format!("token-data-provider-compaction-{}.json", std::process::id())).to_string_lossy().to_string();
+    data_service.write_batch(|storage| storage.compacted_swaps.clear());
+    data_service.save_snapshot(&path).unwrap();
+    let restarted = DataStorageService::new();
+    assert!(restarted.load_snapshot(&path).unwrap());
+    assert_eq!(restarted.get_swap_count(pair), 4);
+    assert_eq!(restarted.get_swap_count(other_pair), 3);
+    std::fs::remove_file(&path).unwrap();
+}
+
+#[test]
+fn aggregates_are_read_per_pair() {
+    let (pair, other_pair): (Address, Address) = (PAIR.parse().unwrap(), OTHER_PAIR.parse().unwrap());
+    let mut config = load_config().unwrap();
+    config.storage.swap_log_retention_secs = 0;
+    config.storage.swap_log_retention_blocks = 0;
+    config.storage.swap_log_retention_per_pair = 1;
+    config.storage.swap_aggregate_hourly_retention_secs = 0;
+    let data_service = DataStorageService::new();
+    data_service.write_batch(|storage| {
+        storage.swap_logs.insert(pair, (1..=3).map(|block| swap_log(pair, block, false)).collect());
+        storage.swap_logs.insert(other_pair, (1..=3).map(|block| swap_log(other_pair, block, false)).collect());
+    });
+    SwapCompactionService::new(ConfigHandle::new(config.clone()), data_service.clone()).compact_swap_logs(10);
+
+    let daily = data_service.get_swap_aggregates(pair, AggregateInterval::Daily);
+    assert_eq!(daily.len(), 1);
+    assert!(daily.iter().all(|aggregate| aggregate.pair_address == pair));
+    assert_eq!(daily[0].swaps, 2);
+    assert_eq!(data_service.get_swap_aggregates(pair, AggregateInterval::Hourly).iter().map(|aggregate| aggregate.swaps).sum::<i64>(), 2);
+
+    //the index is not in the snapshot, loading rebuilds it
+    let path = std::env::temp_dir().join(format!("token-data-provider-aggregates-{}.json", std::process::id())).to_string_lossy().to_string();
+    data_service.save_snapshot(&path).unwrap();
+    let restarted = DataStorageService::new();
+    assert!(restarted.load_snapshot(&path).unwrap());
+    std::fs::remove_file(&path).unwrap();
+    assert_eq!(restarted.get_swap_aggregates(other_pair, AggregateInterval::Daily)[0].swaps, 2);
+
+    //dropped hourly aggregates leave the index as well
+    config.storage.swap_aggregate_hourly_retention_secs = 1;
+    let result = SwapCompactionService::new(ConfigHandle::new(config), restarted.clone()).compact_swap_logs(10);
+    assert!(result.dropped_hourly_aggregates >= 2);
+    assert!(restarted.get_swap_aggregates(pair, AggregateInterval::Hourly).is_empty());
+    assert_eq!(restarted.get_swap_aggregates(pair, AggregateInterval::Daily).len(), 1);
+}
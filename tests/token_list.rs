@@ -0,0 +1,154 @@
+use std::collections::HashMap;
+use ethers::types::H160;
+use token_data_provider::adapters::token_list_api::create_token_list_rest_api;
+use token_data_provider::config::{load_config, load_config_with_profile, ConfigHandle, DEV_PROFILE};
+use token_data_provider::domain::entities::address::Address;
+use token_data_provider::domain::entities::token::Token;
+use token_data_provider::domain::entities::token_list::TokenList;
+use token_data_provider::domain::entities::token_pair::TokenPair;
+use token_data_provider::domain::services::api_key_service::{ApiKeyService, ApiKeyServiceTrait};
+use token_data_provider::domain::services::data_storage_service::{DataStorageService, DataStorageTrait};
+use token_data_provider::domain::services::token_list_service::{TokenListService, TokenListServiceTrait};
+
+const WETH: &str = "0x4200000000000000000000000000000000000006";
+const USDC: &str = "0x833589fcd6edb6e08f4c7c32d4f71b54bda02913";
+const RUG: &str = "0xe17964b8e0416e15a38a43d1aa749599e56fd256";
+const DUST: &str = "0x0000000000000000000000000000000000000d57";
+// metadata calls reverted
+const ANON: &str = "0x02e9367ee3fae97214c5e3d60df77e6559825a57";
+const FIXTURE: &str = "tests/fixtures/tokenlist.json";
+
+fn address(address: &str) -> Address {
+    address.parse().unwrap()
+}
+
+fn token(token_address: &str, symbol: &str, active_pairs: usize, high_risk: bool) -> Token {
+    let active_pairs: HashMap<Address, TokenPair> = (0..active_pairs as u64)
+        .map(|i| (Address::from(H160::from_low_u64_be(i + 1)), TokenPair {
+            token_pair_address: Address::from(H160::from_low_u64_be(i + 1)),
+            protocol_id: "BASE_UNISWAP_V2".to_string(),
+            base_address: address(token_address),
+            base_reserve: 1,
+            quote_address: Address::default(),
+            quote_reserve: 1,
+            reserve_block: 0,
+            reserves_read_block: 0,
+            swaps: 0,
+            retrieved_at: std::time::SystemTime::now(),
+            updated_at: std::time::SystemTime::now(),
+            quorum_disputed: false,
+        }))
+        .collect();
+    Token {
+        address: address(token_address),
+        protocol_id: "BASE_UNISWAP_V2".to_string(),
+        symbol: symbol.to_string(),
+        name: if symbol == "No data" { symbol.to_string() } else { format!("{} Token", symbol) },
+        decimals: if symbol == "No data" { "0" } else { "18" }.to_string(),
+        pairs: active_pairs.clone(),
+        active_pairs,
+        high_risk,
+        ..Token::default()
+    }
+}
+
+fn token_list_service() -> (TokenListService, DataStorageService) {
+    let data_service = DataStorageService::new();
+    let config = ConfigHandle::new(load_config().unwrap());
+    (TokenListService::new(config, data_service.clone()), data_service)
+}
+
+#[test]
+fn import_overrides_metadata_of_listed_tokens_also_after_refresh() {
+    let (token_lists, data_service) = token_list_service();
+    data_service.add_token(address(WETH), token(WETH, "WETH", 2, false));
+
+    let import = token_lists.import_file(FIXTURE).unwrap();
+    assert_eq!(import.list, "Test Curated");
+    //the mainnet entry and the invalid address are skipped
+    assert_eq!(import.entries, 2);
+    assert_eq!(import.skipped, 2);
+    assert_eq!(import.updated_tokens, 1);
+
+    let weth = data_service.get_token(address(WETH)).unwrap();
+    assert!(weth.verified);
+    assert_eq!(weth.name, "Wrapped Ether (Base)");
+    assert_eq!(weth.symbol, "wETH");
+    assert_eq!(weth.logo_uri.as_deref(), Some("https://example.org/weth.png"));
+    assert_eq!(weth.tags, vec!["wrapped"]);
+
+    //stored later or refreshed from the chain, the list metadata stays
+    data_service.add_token(address(USDC), token(USDC, "USDC.e", 1, false));
+    data_service.add_token(address(WETH), token(WETH, "WETH", 2, false));
+    assert_eq!(data_service.get_token(address(WETH)).unwrap().symbol, "wETH");
+    let usdc = data_service.get_token(address(USDC)).unwrap();
+    assert!(usdc.verified);
+    assert_eq!(usdc.symbol, "USDC");
+    assert_eq!(usdc.logo_uri, None);
+
+    data_service.add_token(address(RUG), token(RUG, "RUG", 0, true));
+    assert!(!data_service.get_token(address(RUG)).unwrap().verified);
+
+    assert!(token_lists.import_file("tests/fixtures/missing.json").is_err());
+}
+
+#[test]
+fn export_filters_tokens_by_risk_and_liquidity() {
+    let (token_lists, data_service) = token_list_service();
+    data_service.add_token(address(WETH), token(WETH, "WETH", 2, false));
+    data_service.add_token(address(USDC), token(USDC, "USDC", 1, false));
+    data_service.add_token(address(RUG), token(RUG, "RUG", 1, true));
+    data_service.add_token(address(DUST), token(DUST, "DUST", 0, false));
+    data_service.add_token(address(ANON), token(ANON, "No data", 3, false));
+    token_lists.import_file(FIXTURE).unwrap();
+
+    let storage = data_service.get_snapshot();
+    let filter = token_lists.default_filter();
+    let list = token_lists.export(&storage, &filter);
+    assert_eq!(list.name, "Token Data Provider");
+    assert_eq!(list.version.patch, storage.version);
+    let symbols: Vec<&str> = list.tokens.iter().map(|token| token.symbol.as_str()).collect();
+    assert_eq!(symbols, vec!["USDC", "wETH"]);
+    //EIP-55 checksummed
+    assert_eq!(list.tokens[0].address, "0x833589fCD6eDb6E08f4c7C32D4f71b54bdA02913");
+    assert_eq!(list.tokens[0].chain_id, 8453);
+    assert_eq!(list.tokens[1].tags, vec!["wrapped"]);
+
+    let mut all = filter;
+    all.include_high_risk = true;
+    all.min_active_pairs = 0;
+    let symbols: Vec<String> = token_lists.export(&storage, &all).tokens.into_iter().map(|token| token.symbol).collect();
+    //tokens without metadata never qualify
+    assert_eq!(symbols, vec!["DUST", "RUG", "USDC", "wETH"]);
+
+    let mut liquid = filter;
+    liquid.min_active_pairs = 2;
+    assert_eq!(token_lists.export(&storage, &liquid).tokens.len(), 1);
+}
+
+#[tokio::test]
+async fn tokenlist_json_serves_filtered_list() {
+    let config = load_config().unwrap();
+    let api_keys = ApiKeyService::new();
+    api_keys.init(&load_config_with_profile(DEV_PROFILE).unwrap());
+    let (token_lists, data_service) = token_list_service();
+    data_service.add_token(address(WETH), token(WETH, "WETH", 2, false));
+    data_service.add_token(address(RUG), token(RUG, "RUG", 1, true));
+    data_service.add_token(address(DUST), token(DUST, "DUST", 1, false));
+    token_lists.import_file(FIXTURE).unwrap();
+    let api = create_token_list_rest_api(ConfigHandle::new(config), data_service, api_keys);
+
+    let response = warp::test::request().path("/tokenlist.json").header("x-api-key", "local-read-key").reply(&api).await;
+    assert_eq!(response.status(), 200);
+    let list: TokenList = serde_json::from_slice(response.body()).unwrap();
+    assert_eq!(list.tokens.iter().map(|token| token.symbol.as_str()).collect::<Vec<_>>(), vec!["DUST", "wETH"]);
+
+    let response = warp::test::request().path("/tokenlist.json?include_high_risk=true&verified=true")
+        .header("x-api-key", "local-read-key").reply(&api).await;
+    let list: TokenList = serde_json::from_slice(response.body()).unwrap();
+    assert_eq!(list.tokens.iter().map(|token| token.symbol.as_str()).collect::<Vec<_>>(), vec!["wETH"]);
+
+    //without a key the request is rejected
+    let response = warp::test::request().path("/tokenlist.json").reply(&api).await;
+    assert_ne!(response.status(), 200);
+}